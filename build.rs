@@ -0,0 +1,18 @@
+use std::fs;
+
+/// Exposes the zkevm-circuits commit the crate is built against as `ZKEVM_CIRCUITS_COMMIT`, it
+/// is recorded next to the circuit keys since another commit may change the circuit. The commit
+/// is the `rev` the fork is patched in with in Cargo.toml, Cargo.lock is not checked in.
+fn main() {
+    println!("cargo:rerun-if-changed=Cargo.toml");
+    let manifest = fs::read_to_string("Cargo.toml").expect("cannot read Cargo.toml");
+    let commit = manifest
+        .lines()
+        .filter(|line| line.starts_with("zkevm-circuits = "))
+        .find_map(|line| line.split("rev = \"").nth(1)?.split('"').next())
+        .expect("Cargo.toml does not pin zkevm-circuits to a rev");
+    println!("cargo:rustc-env=ZKEVM_CIRCUITS_COMMIT={commit}");
+
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/prover.proto").expect("cannot compile proto/prover.proto");
+}
@@ -1,14 +1,38 @@
+#[cfg(feature = "dev-circuits")]
+use crate::utils::halo2::sub_circuit::CircuitSelection;
 use crate::{
+    constants::{
+        DEFAULT_MAX_BYTECODE, DEFAULT_MAX_COPY_ROWS, DEFAULT_MAX_EVM_ROWS, DEFAULT_MAX_EXP_STEPS,
+        DEFAULT_MAX_KECCAK_ROWS, DEFAULT_MAX_RWS, DEFAULT_PARAMS_MARGIN, MAX_CALLDATA, MAX_TXS,
+    },
     env::Env,
+    error::Error,
     utils::{
-        anvil::types::zkevm_types::Bytes, halo2::proof::Proof, huff::compile_huff, ipfs, solidity,
+        anvil::{tx_builder::TxType, types::zkevm_types::Bytes},
+        bench::BenchCase,
+        eip3155::{Eip3155Trace, StateSnapshot},
+        forge::ForgeBroadcast,
+        halo2::{
+            aggregation::{AggregatedProof, InstanceEncoding, DEFAULT_AGGREGATION_DEGREE},
+            preset::CircuitPreset,
+            proof::{PcsScheme, Proof, ProofBackend, ProofTranscript},
+            real_prover::ProverOptions,
+            srs::ArtifactChecks,
+            vk_registry::parse_vk_digest,
+        },
+        hardfork::Hardfork,
+        ipfs,
+        logger::{self, LogFormat},
+        solidity::{self, compile_exploit, compile_exploit_creation},
     },
 };
+use bus_mapping::circuit_input_builder::FixedCParams;
 use clap::{arg, command, ArgMatches, Command};
-use eth_types::U256;
-use ethers::utils::parse_ether;
+use eth_types::{H256, U256};
+use ethers::{signers::LocalWallet, utils::parse_ether};
 use home::home_dir;
-use std::{fs::create_dir_all, path::PathBuf, str::FromStr};
+use std::{fs::create_dir_all, net::SocketAddr, path::PathBuf, str::FromStr, time::Duration};
+use tracing_subscriber::filter::LevelFilter;
 
 pub const EXPLOIT: &str = "exploit";
 pub const TEST: &str = "test";
@@ -16,9 +40,20 @@ pub const PROVE: &str = "prove";
 pub const VERIFY: &str = "verify";
 pub const PUBLISH: &str = "publish";
 pub const SCAFFOLD: &str = "scaffold";
+pub const AUDIT: &str = "audit";
+pub const AGGREGATE: &str = "aggregate";
+pub const VK: &str = "vk";
+pub const CORPUS: &str = "corpus";
+pub const REPRO: &str = "repro";
+pub const SETUP: &str = "setup";
+pub const GEN_VERIFIER: &str = "gen-verifier";
+pub const SERVE: &str = "serve";
+pub const BENCH: &str = "bench";
+#[cfg(feature = "grpc")]
+pub const SERVE_GRPC: &str = "serve-grpc";
 
 pub fn exploit_command() -> Command {
-    command!(EXPLOIT)
+    let command = command!(EXPLOIT)
         .name("Proof of Exploit")
         .about("Generate and verify zk proof of exploits for ethereum smart contracts")
         .version("v0.1.0")
@@ -30,21 +65,127 @@ pub fn exploit_command() -> Command {
             PublishArgs::apply(command!(PUBLISH)).about("Publish proof to IPFS"),
             ScaffoldArgs::apply(command!(SCAFFOLD))
                 .about("Scaffold new project for writing exploit"),
+            AuditArgs::apply(command!(AUDIT)).about("Export a signed audit archive of a run"),
+            AggregateArgs::apply(command!(AGGREGATE))
+                .about("Aggregate several proofs into a single proof"),
+            VkArgs::apply(command!(VK)).about("Manage the approved verifying keys"),
+            CorpusArgs::apply(command!(CORPUS))
+                .about("Run every exploit scenario of a directory and report the outcomes"),
+            ReproArgs::apply(command!(REPRO))
+                .about("Export or replay an offline fixture of a failing exploit block"),
+            SetupArgs::apply(command!(SETUP))
+                .about("Generate the params and keys of a circuit size ahead of proving"),
+            GenVerifierArgs::apply(command!(GEN_VERIFIER))
+                .about("Generate the solidity verifier of a circuit size set up with setup"),
+            ServeArgs::apply(command!(SERVE))
+                .about("Serve proofs over HTTP with the keys of a circuit size loaded once"),
+            BenchArgs::apply(command!(BENCH))
+                .about("Prove an exploit across circuit params and degrees and report the costs"),
         ])
         .subcommand_required(true)
+        .args(LogArgs::args());
+    #[cfg(feature = "grpc")]
+    let command = command.subcommand(
+        ServeGrpcArgs::apply(command!(SERVE_GRPC))
+            .about("Prove witness files submitted over gRPC by repro --remote"),
+    );
+    command
+}
+
+pub struct LogArgs {
+    /// Console level, `--log-level` or else the level of the `-v` flags.
+    pub level: LevelFilter,
+    pub format: LogFormat,
+    pub log_file: Option<PathBuf>,
+}
+
+impl LogArgs {
+    pub fn args() -> [clap::Arg; 4] {
+        [
+            arg!(-v --verbose ... "Increase console verbosity (-v, -vv)").global(true),
+            arg!(--"log-level" <LEVEL> "Console log level: error, warn, info, debug or trace")
+                .global(true),
+            arg!(--"log-format" <FORMAT> "Console log format: text or json").global(true),
+            arg!(--"log-file" <PATH> "Write full logs to a file").global(true),
+        ]
+    }
+
+    pub fn from(arg_matches: Option<&ArgMatches>, env: &Env) -> Result<Self, Error> {
+        let arg_matches = arg_matches.ok_or(Error::InternalError("missing arg matches"))?;
+        let level =
+            match parse_optional::<String>(arg_matches, "log-level")?.or(env.log_level.clone()) {
+                Some(level) => level
+                    .parse()
+                    .map_err(|err| Error::InvalidArgument(format!("--log-level: {err}")))?,
+                None => logger::console_level(arg_matches.get_count("verbose")),
+            };
+        let format = parse_optional::<String>(arg_matches, "log-format")?
+            .or(env.log_format.clone())
+            .map(|format| format.parse())
+            .transpose()?
+            .unwrap_or_default();
+        let log_file = parse_optional::<String>(arg_matches, "log-file")?
+            .or(env.log_file.clone())
+            .map(PathBuf::from);
+        Ok(Self {
+            level,
+            format,
+            log_file,
+        })
+    }
 }
 
 pub struct ProveArgs {
     pub rpc: String,
     pub geth_rpc: Option<String>,
+    pub rpc_timeout: Duration,
     pub block: Option<usize>,
     pub challenge_artifact: solidity::Artifact,
     pub exploit_bytecode: Bytes,
+    /// Creation bytecode of the exploit followed by its constructor args, which is deployed at
+    /// the exploit address with `deploy_at` instead of setting `exploit_bytecode` as its code.
+    pub exploit_init_code: Option<Bytes>,
     pub exploit_balance: U256,
+    /// Least balance the exploit must gain over `exploit_balance`, checked on anvil before the
+    /// witness is generated.
+    pub min_balance_gain: Option<U256>,
     pub gas: Option<usize>,
+    /// Envelope of the exploit tx, legacy unless set.
+    pub tx_type: TxType,
+    /// Prove the exploit tx even if it reverts, instead of failing with its revert reason.
+    pub allow_revert: bool,
+    /// Timestamp of the block of the exploit tx, the clock of anvil unless set.
+    pub block_timestamp: Option<u64>,
+    /// Degree to prove at, the degree the witness needs unless set.
+    pub degree: Option<u32>,
     pub srs_path: PathBuf,
     pub proof_out_path: Option<String>,
     pub ipfs: bool,
+    pub regenerate_keys: bool,
+    /// Prove even if the srs dir manifest has a proof of the same witness and verifying key.
+    pub force: bool,
+    pub skip_sanity_check: bool,
+    /// Prove even if the estimated memory of the proof exceeds the available memory.
+    pub skip_memory_check: bool,
+    pub diff_trace: bool,
+    pub trace_out: Option<PathBuf>,
+    pub repro_out: Option<PathBuf>,
+    pub imported_trace: Option<Eip3155Trace>,
+    pub state_snapshot: Option<StateSnapshot>,
+    /// Hardfork of the forked chain, detected from the fork block if there is none.
+    pub hardfork: Option<Hardfork>,
+    pub transcript: ProofTranscript,
+    pub wrap: bool,
+    pub instance_encoding: InstanceEncoding,
+    pub backend: ProofBackend,
+    pub pcs_scheme: PcsScheme,
+    /// Sub-circuit to prove instead of the SuperCircuit, for iterating on the exploit.
+    #[cfg(feature = "dev-circuits")]
+    pub circuit: CircuitSelection,
+    pub prover_options: ProverOptions,
+    /// Margin in percent of the circuit params measured from the exploit block, which replace the
+    /// `max_*` params if it is set.
+    pub params_margin: Option<usize>,
     pub max_rws: usize,
     pub max_copy_rows: usize,
     pub max_exp_steps: usize,
@@ -57,114 +198,359 @@ impl ProveArgs {
     pub fn apply(c: clap::Command) -> clap::Command {
         c.arg(arg!(--rpc <URL> "Enter ethereum archive node RPC url" ))
             .arg(arg!(--"geth-rpc" <URL> "Use geth node for generating traces" ))
+            .arg(arg!(--"rpc-timeout" <SECONDS> "Fail an RPC call not answered in time (default 300)" ))
             .arg(arg!(--block <NUMBER> "Enter the fork block number" ))
             .arg(arg!(--challenge <CONTRACT> "Enter hex bytecode or file path" ))
-            .arg(arg!(--exploit <CONTRACT> "Enter hex bytecode or file path" ))
+            .arg(arg!(--exploit <CONTRACT> "Enter hex bytecode or file path, a .sol file is compiled with solc and its Exploit contract is used" ))
+            .arg(arg!(--"forge-broadcast" <PATH> "Replay the transactions of a forge script broadcast file as the exploit" ))
+            .arg(arg!(--"exploit-constructor-args" <HEX> "Deploy the exploit by running its constructor with these ABI encoded args" ))
             .arg(arg!(--"exploit-balance" <NUMBER> "Enter ether amount to fund 0xbada55 address" ))
+            .arg(arg!(--"min-balance-gain" <NUMBER> "Fail before proving unless the exploit ends with at least this much ether more than --exploit-balance" ))
             .arg(arg!(--gas <NUMBER> "Enter amount of gas for exploit tx" ))
+            .arg(arg!(--"tx-type" <TYPE> "Send the exploit tx as a legacy, eip2930 or eip1559 transaction (default legacy)" ))
+            .arg(arg!(--"allow-revert" "Prove the exploit tx even if it reverts" ))
             .arg(arg!(--srs <PATH> "Enter the dir for srs params" ))
             .arg(arg!(--out <PATH> "Path for output proof.json file" ))
             .arg(arg!(--ipfs "Publish the proof to IPFS" ))
-            .arg(arg!(--"max-rws" <NUMBER>))
-            .arg(arg!(--"max-copy-rows" <NUMBER>))
-            .arg(arg!(--"max-exp-steps" <NUMBER>))
-            .arg(arg!(--"max-bytecode" <NUMBER>))
-            .arg(arg!(--"max-evm-rows" <NUMBER>))
-            .arg(arg!(--"max-keccak-rows" <NUMBER>))
+            .arg(arg!(--"regenerate-keys" "Delete cached circuit keys and generate them again" ))
+            .arg(arg!(--force "Prove again even if a proof of the same witness and verifying key was created before" ))
+            .arg(arg!(--"skip-sanity-check" "Skip validating the witness before proving" ))
+            .arg(arg!(--"skip-memory-check" "Prove even if the machine seems to lack the memory for the degree" ))
+            .arg(arg!(--"diff-trace" "Compare the circuit inputs step by step with a geth trace of the exploit" ))
+            .arg(arg!(--"trace-out" <PATH> "Write the trace of the exploit tx in the EIP-3155 format" ))
+            .arg(arg!(--"repro-out" <PATH> "Write the inputs of the exploit block to a fixture which replays without RPC access" ))
+            .arg(arg!(--"import-trace" <PATH> "Build the witness from an EIP-3155 trace of the exploit tx instead of tracing it" ))
+            .arg(arg!(--state <PATH> "Accounts to set on the fork before the exploit runs, in the t8n alloc format" ))
+            .arg(arg!(--hardfork <NAME> "Hardfork of the forked chain, detected from the fork block by default, only shanghai is supported" ))
+            .arg(arg!(--transcript <NAME> "Transcript of the proof, blake2b, poseidon for proofs verified in another circuit or keccak for the solidity verifier" ))
+            .arg(arg!(--"for-aggregation" "Use a Poseidon transcript so that the proof can be aggregated" ))
+            .arg(arg!(--wrap "Wrap the proof in a small outer proof which is cheaper to verify on-chain" ))
+            .arg(arg!(--"hash-instances" "Expose only a Poseidon digest of the instances of the wrapped proof" ))
+            .arg(arg!(--ipa "Use the IPA backend which needs no trusted setup, for local development only" ))
+            .arg(arg!(--mock "Only run the MockProver and write a stub proof, for iterating on the exploit" ))
+            .arg(arg!(--pcs <SCHEME> "Multiopen scheme of the proof, shplonk or gwc, the solidity verifier must be generated with the same one" ))
+            .arg(arg!(--threads <NUMBER> "Generate the keys and the proof with this many threads (default all cores)" ))
+            .arg(arg!(--"low-memory" "Release the witness once the proof is created, before a wrapper proof is generated" ))
+            .arg(arg!(--"no-persist-pk" "Generate a missing proving key in memory only instead of caching it in the srs dir" ))
+            .arg(arg!(--"mock-check" "Check the constraints with the MockProver before keygen and proving" ))
+            .arg(arg!(--"unchecked-artifacts" "Read the params and keys of the srs dir without checking their points, only for a dir nobody else writes to" ))
+            .args(circuit_params_args())
+            .args(sub_circuit_args())
+            .arg(arg!(--degree <NUMBER> "Prove at this degree instead of the one the exploit block needs, e.g. to match the keys of a setup" ))
+            .arg(arg!(--"auto-params" "Measure the exploit block and use the smallest circuit params it fits in instead of the --max-* options" ))
+            .arg(arg!(--"params-margin" <PERCENT> "Grow the params measured by --auto-params by this percentage (default 10)" ))
     }
 
-    pub fn from(arg_matches: Option<&ArgMatches>, env: &Env) -> Self {
-        let arg_matches = arg_matches.unwrap();
-        let rpc = parse_optional(arg_matches, "rpc")
+    pub fn from(arg_matches: Option<&ArgMatches>, env: &Env) -> Result<Self, Error> {
+        let arg_matches = arg_matches.ok_or(Error::InternalError("missing arg matches"))?;
+        let rpc = parse_optional(arg_matches, "rpc")?
             .or(env.eth_rpc_url.clone())
-            .expect("please provide --rpc or ETH_RPC_URL");
-        let geth_rpc = parse_optional(arg_matches, "geth-rpc").or(env.geth_rpc_url.clone());
-        let block = parse_optional(arg_matches, "block").or(env.fork_block_number);
-        let challenge_input = parse_optional(arg_matches, "challenge")
+            .ok_or(Error::InvalidArgument(
+                "please provide --rpc or ETH_RPC_URL".to_string(),
+            ))?;
+        let geth_rpc = parse_optional(arg_matches, "geth-rpc")?.or(env.geth_rpc_url.clone());
+        let rpc_timeout = Duration::from_secs(
+            parse_optional(arg_matches, "rpc-timeout")?
+                .or(env.rpc_timeout)
+                .unwrap_or(300) as u64,
+        );
+        let block = parse_optional(arg_matches, "block")?.or(env.fork_block_number);
+        let challenge_input = parse_optional(arg_matches, "challenge")?
             .or(env.challenge_path.clone())
             .unwrap_or("./src/Challenge.sol".to_string());
-        let challenge_artifact = solidity::Artifact::from_source(challenge_input);
-        let exploit_input = parse_optional(arg_matches, "exploit")
+        let challenge_artifact = solidity::Artifact::from_source(challenge_input)?;
+        let exploit_input = parse_optional(arg_matches, "exploit")?
             .or(env.exploit_path.clone())
             .unwrap_or("./src/Exploit.huff".to_string());
-        let exploit_bytecode = compile_huff(exploit_input);
+        let forge_broadcast = parse_optional::<String>(arg_matches, "forge-broadcast")?;
+        let exploit_constructor_args =
+            parse_optional::<Bytes>(arg_matches, "exploit-constructor-args")?;
+        let exploit_init_code = match (&forge_broadcast, exploit_constructor_args) {
+            (Some(_), Some(_)) => return Err(Error::InvalidArgument(
+                "--exploit-constructor-args needs an --exploit source instead of --forge-broadcast"
+                    .to_string(),
+            )),
+            (None, Some(constructor_args)) => Some(Bytes::from(
+                [
+                    compile_exploit_creation(exploit_input.clone())?.as_ref(),
+                    constructor_args.as_ref(),
+                ]
+                .concat(),
+            )),
+            _ => None,
+        };
+        let exploit_bytecode = match forge_broadcast {
+            Some(path) => {
+                ForgeBroadcast::read_from_file(&PathBuf::from(path))?.exploit_bytecode()?
+            }
+            None => compile_exploit(exploit_input)?,
+        };
         let exploit_balance = parse_ether(
-            parse_optional(arg_matches, "exploit-balance")
+            parse_optional(arg_matches, "exploit-balance")?
                 .or(env.exploit_balance.clone())
                 .unwrap_or("0".to_string()),
         )
-        .expect("please provide ether amount correctly for --exploit-balance");
-        let gas = parse_optional(arg_matches, "gas");
-        let srs_path = parse_srs_path(arg_matches, env);
-        let proof_out_path = parse_optional(arg_matches, "out");
+        .map_err(|_| {
+            Error::InvalidArgument(
+                "please provide ether amount correctly for --exploit-balance".to_string(),
+            )
+        })?;
+        let min_balance_gain = parse_optional::<String>(arg_matches, "min-balance-gain")?
+            .map(|amount| {
+                parse_ether(&amount).map_err(|_| {
+                    Error::InvalidArgument(format!(
+                        "invalid ether amount {amount} for --min-balance-gain"
+                    ))
+                })
+            })
+            .transpose()?;
+        let gas = parse_optional(arg_matches, "gas")?;
+        let tx_type = parse_optional(arg_matches, "tx-type")?.unwrap_or_default();
+        let allow_revert = arg_matches.get_flag("allow-revert");
+        let srs_path = parse_srs_path(arg_matches, env)?;
+        let proof_out_path = parse_optional(arg_matches, "out")?;
         let ipfs = arg_matches.get_flag("ipfs");
-        let max_rws = parse_optional(arg_matches, "max-rws").unwrap_or(env.max_rws.unwrap_or(1000));
-        let max_copy_rows = parse_optional(arg_matches, "max-copy-rows")
-            .unwrap_or(env.max_copy_rows.unwrap_or(1000));
-        let max_exp_steps = parse_optional(arg_matches, "max-exp-steps")
-            .unwrap_or(env.max_exp_steps.unwrap_or(1000));
-        let max_bytecode =
-            parse_optional(arg_matches, "max-bytecode").unwrap_or(env.max_bytecode.unwrap_or(512));
-        let max_evm_rows =
-            parse_optional(arg_matches, "max-evm-rows").unwrap_or(env.max_evm_rows.unwrap_or(1000));
-        let max_keccak_rows = parse_optional(arg_matches, "max-keccak-rows")
-            .unwrap_or(env.max_keccak_rows.unwrap_or(1000));
-
-        Self {
+        let regenerate_keys = arg_matches.get_flag("regenerate-keys");
+        let force = arg_matches.get_flag("force");
+        let skip_sanity_check = arg_matches.get_flag("skip-sanity-check");
+        let skip_memory_check = arg_matches.get_flag("skip-memory-check");
+        let diff_trace = arg_matches.get_flag("diff-trace");
+        let trace_out = parse_optional::<String>(arg_matches, "trace-out")?.map(PathBuf::from);
+        let repro_out = parse_optional::<String>(arg_matches, "repro-out")?.map(PathBuf::from);
+        let imported_trace = parse_optional::<String>(arg_matches, "import-trace")?
+            .map(|path| Eip3155Trace::read_from_file(&PathBuf::from(path)))
+            .transpose()?;
+        let state_snapshot = parse_optional::<String>(arg_matches, "state")?
+            .map(|path| StateSnapshot::read_from_file(&PathBuf::from(path)))
+            .transpose()?;
+        let hardfork = parse_optional::<Hardfork>(arg_matches, "hardfork")?.or(env.hardfork);
+        if let Some(hardfork) = hardfork {
+            hardfork.check_supported(None)?;
+        }
+        let transcript = parse_optional(arg_matches, "transcript")?;
+        let transcript = match (arg_matches.get_flag("for-aggregation"), transcript) {
+            (true, Some(_)) => {
+                return Err(Error::InvalidArgument(
+                    "--for-aggregation sets the poseidon transcript and cannot be used with --transcript".to_string(),
+                ))
+            }
+            (true, None) => ProofTranscript::Poseidon,
+            (false, transcript) => transcript.unwrap_or_default(),
+        };
+        let wrap = arg_matches.get_flag("wrap");
+        let instance_encoding = parse_instance_encoding(arg_matches);
+        if instance_encoding == InstanceEncoding::Poseidon && !wrap {
+            return Err(Error::InvalidArgument(
+                "--hash-instances is only supported with --wrap".to_string(),
+            ));
+        }
+        if wrap && ipfs {
+            return Err(Error::InvalidArgument(
+                "--ipfs does not support wrapped proofs yet".to_string(),
+            ));
+        }
+        let backend = if arg_matches.get_flag("mock") {
+            ProofBackend::Mock
+        } else if arg_matches.get_flag("ipa") {
+            ProofBackend::Ipa
+        } else {
+            ProofBackend::Kzg
+        };
+        if backend != ProofBackend::Kzg && (transcript != ProofTranscript::Blake2b || wrap || ipfs)
+        {
+            return Err(Error::InvalidArgument(
+                "--ipa and --mock proofs are for local development and cannot be aggregated, wrapped or published".to_string(),
+            ));
+        }
+        let pcs_scheme = parse_optional(arg_matches, "pcs")?.unwrap_or_default();
+        if pcs_scheme != PcsScheme::Shplonk
+            && (backend != ProofBackend::Kzg || transcript == ProofTranscript::Poseidon || wrap)
+        {
+            return Err(Error::InvalidArgument(
+                "--pcs gwc is only supported for KZG proofs of the SuperCircuit, the aggregation circuit verifies SHPLONK proofs".to_string(),
+            ));
+        }
+        #[cfg(feature = "dev-circuits")]
+        let circuit = parse_optional(arg_matches, "circuit")?.unwrap_or_default();
+        #[cfg(feature = "dev-circuits")]
+        if circuit != CircuitSelection::Super
+            && (backend != ProofBackend::Kzg
+                || transcript != ProofTranscript::Blake2b
+                || pcs_scheme != PcsScheme::Shplonk
+                || wrap
+                || ipfs)
+        {
+            return Err(Error::InvalidArgument(
+                "--circuit proofs are for local development and cannot use another backend, transcript or --pcs, be wrapped or published".to_string(),
+            ));
+        }
+        let prover_options = ProverOptions {
+            num_threads: parse_optional(arg_matches, "threads")?,
+            low_memory: arg_matches.get_flag("low-memory"),
+            persist_proving_key: !arg_matches.get_flag("no-persist-pk"),
+            artifact_checks: parse_artifact_checks(arg_matches),
+            mock_check: arg_matches.get_flag("mock-check"),
+        };
+        let (_, circuits_params) = parse_circuit_params(arg_matches, env)?;
+        let degree = parse_optional(arg_matches, "degree")?.or(env.degree);
+        let params_margin = parse_optional(arg_matches, "params-margin")?;
+        let params_margin = if arg_matches.get_flag("auto-params") || params_margin.is_some() {
+            Some(params_margin.unwrap_or(DEFAULT_PARAMS_MARGIN))
+        } else {
+            None
+        };
+
+        Ok(Self {
             rpc,
             geth_rpc,
+            rpc_timeout,
             block,
             challenge_artifact,
             exploit_bytecode,
+            exploit_init_code,
             exploit_balance,
+            min_balance_gain,
             gas,
+            tx_type,
+            allow_revert,
+            block_timestamp: None,
+            degree,
             srs_path,
             proof_out_path,
             ipfs,
-            max_rws,
-            max_copy_rows,
-            max_exp_steps,
-            max_bytecode,
-            max_evm_rows,
-            max_keccak_rows,
-        }
+            regenerate_keys,
+            force,
+            skip_sanity_check,
+            skip_memory_check,
+            diff_trace,
+            trace_out,
+            repro_out,
+            imported_trace,
+            state_snapshot,
+            hardfork,
+            transcript,
+            wrap,
+            instance_encoding,
+            backend,
+            pcs_scheme,
+            #[cfg(feature = "dev-circuits")]
+            circuit,
+            prover_options,
+            params_margin,
+            max_rws: circuits_params.max_rws,
+            max_copy_rows: circuits_params.max_copy_rows,
+            max_exp_steps: circuits_params.max_exp_steps,
+            max_bytecode: circuits_params.max_bytecode,
+            max_evm_rows: circuits_params.max_evm_rows,
+            max_keccak_rows: circuits_params.max_keccak_rows,
+        })
     }
 }
 
 pub struct VerifyArgs {
     pub srs_path: PathBuf,
     pub proof: Proof,
+    pub batch: Vec<Proof>,
+    pub trajectory: bool,
+    pub anchor: Option<(String, ethers::types::H256)>,
+    /// Solidity verifier written by `gen-verifier` which has to accept the proof as well.
+    pub evm_verifier: Option<PathBuf>,
     pub unpack_dir: Option<String>,
+    pub artifact_checks: ArtifactChecks,
+    /// Verify with the light verifier key instead of the params and the verifying key.
+    pub light: bool,
+    /// Verify with the params and verifying key published on IPFS before the ceremony params.
+    pub ipfs_setup: bool,
 }
 
 impl VerifyArgs {
     pub fn apply(c: clap::Command) -> clap::Command {
         c.arg(arg!(--srs <PATH> "Enter the path for storing SRS parameters" ))
             .arg(arg!(--proof <PATH> "Enter the proof path or IPFS hash" ))
+            .arg(arg!(--batch <PATHS> "Comma separated proofs of the same circuit to verify along with --proof" ))
+            .arg(arg!(--trajectory "Check that --proof and --batch prove consecutive blocks, each starting from the state root of the previous one" ))
+            .arg(arg!(--anchor <BLOCKHASH> "Check that the proven block is an ancestor of this block" ))
+            .arg(arg!(--rpc <URL> "Ethereum RPC url to fetch the headers for --anchor" ))
+            .arg(arg!(--"evm-verifier" <PATH> "Also run the proof through a solidity verifier of gen-verifier in a local EVM" ))
             .arg(arg!(--unpack <PATH> "Enter path to unpack challenge solidity code" ))
+            .arg(arg!(--"unchecked-artifacts" "Read the params and verifying key of the srs dir without checking their points" ))
+            .arg(arg!(--light "Verify with the light verifier key of the srs dir, without reading the params" ))
+            .arg(arg!(--"ipfs-setup" "Verify a proof published before the ceremony params were used with the forgeable setup of its time, downloaded from IPFS" ))
     }
 
-    pub async fn from(arg_matches: Option<&ArgMatches>, env: &Env) -> Self {
-        let arg_matches = arg_matches.unwrap();
-        let srs_path = parse_srs_path(arg_matches, env);
+    pub async fn from(arg_matches: Option<&ArgMatches>, env: &Env) -> Result<Self, Error> {
+        let arg_matches = arg_matches.ok_or(Error::InternalError("missing arg matches"))?;
+        let srs_path = parse_srs_path(arg_matches, env)?;
 
-        let proof_input: String = parse_optional(arg_matches, "proof")
-            .expect("please provide the path to proof json file using --proof");
-        let proof = if let Ok(proof) =
-            Proof::read_from_file(&PathBuf::from_str(proof_input.as_str()).unwrap())
-        {
+        let proof_input: String =
+            parse_optional(arg_matches, "proof")?.ok_or(Error::InvalidArgument(
+                "please provide the path to proof json file using --proof".to_string(),
+            ))?;
+        let proof = if let Ok(proof) = Proof::read_from_file(&PathBuf::from(proof_input.as_str())) {
             proof
         } else {
-            ipfs::get(proof_input).await.unwrap()
+            ipfs::get(proof_input).await?
         };
 
-        let unpack_dir: Option<String> = parse_optional(arg_matches, "unpack");
+        let batch = parse_optional::<String>(arg_matches, "batch")?
+            .map(|paths| {
+                paths
+                    .split(',')
+                    .map(|path| Proof::read_from_file(&PathBuf::from(path.trim())))
+                    .collect::<Result<Vec<_>, Error>>()
+            })
+            .transpose()?
+            .unwrap_or_default();
+
+        let trajectory = arg_matches.get_flag("trajectory");
+        if trajectory && batch.is_empty() {
+            return Err(Error::InvalidArgument(
+                "--trajectory needs the following blocks' proofs in --batch".to_string(),
+            ));
+        }
+
+        let anchor = parse_optional::<ethers::types::H256>(arg_matches, "anchor")?
+            .map(|anchor| {
+                let rpc = parse_optional(arg_matches, "rpc")?
+                    .or(env.eth_rpc_url.clone())
+                    .ok_or(Error::InvalidArgument(
+                        "please provide --rpc or ETH_RPC_URL to check --anchor".to_string(),
+                    ))?;
+                Ok::<_, Error>((rpc, anchor))
+            })
+            .transpose()?;
+
+        let evm_verifier =
+            parse_optional::<String>(arg_matches, "evm-verifier")?.map(PathBuf::from);
+        let unpack_dir: Option<String> = parse_optional(arg_matches, "unpack")?;
+        let artifact_checks = parse_artifact_checks(arg_matches);
+
+        let light = arg_matches.get_flag("light");
+        if light && !batch.is_empty() {
+            return Err(Error::InvalidArgument(
+                "--batch needs the params, it cannot be combined with --light".to_string(),
+            ));
+        }
+
+        let ipfs_setup = arg_matches.get_flag("ipfs-setup");
+        if ipfs_setup && light {
+            return Err(Error::InvalidArgument(
+                "the IPFS setup has no light verifier key, --ipfs-setup cannot be combined with --light".to_string(),
+            ));
+        }
 
-        Self {
+        Ok(Self {
             srs_path,
             proof,
+            batch,
+            trajectory,
+            anchor,
+            evm_verifier,
             unpack_dir,
-        }
+            artifact_checks,
+            light,
+            ipfs_setup,
+        })
     }
 }
 
@@ -177,11 +563,12 @@ impl PublishArgs {
         c.arg(arg!(--file <PATH> "Enter the file path" ))
     }
 
-    pub fn from(arg_matches: Option<&ArgMatches>) -> Self {
-        let arg_matches = arg_matches.unwrap();
-        let file_path: String = parse_optional(arg_matches, "file")
-            .expect("please provide the path to file using --file");
-        Self { file_path }
+    pub fn from(arg_matches: Option<&ArgMatches>) -> Result<Self, Error> {
+        let arg_matches = arg_matches.ok_or(Error::InternalError("missing arg matches"))?;
+        let file_path: String = parse_optional(arg_matches, "file")?.ok_or(
+            Error::InvalidArgument("please provide the path to file using --file".to_string()),
+        )?;
+        Ok(Self { file_path })
     }
 }
 
@@ -195,28 +582,662 @@ impl ScaffoldArgs {
             .arg_required_else_help(true)
     }
 
-    pub fn from(arg_matches: Option<&ArgMatches>) -> Self {
-        let arg_matches = arg_matches.unwrap();
-        let project_name =
-            parse_optional(arg_matches, "name").expect("please provide project name using --name");
-        Self { project_name }
+    pub fn from(arg_matches: Option<&ArgMatches>) -> Result<Self, Error> {
+        let arg_matches = arg_matches.ok_or(Error::InternalError("missing arg matches"))?;
+        let project_name = parse_optional(arg_matches, "name")?.ok_or(Error::InvalidArgument(
+            "please provide project name using --name".to_string(),
+        ))?;
+        Ok(Self { project_name })
+    }
+}
+
+pub struct AuditArgs {
+    pub srs_path: PathBuf,
+    pub run_id: Option<String>,
+    pub proof_path: Option<PathBuf>,
+    pub signer: Option<LocalWallet>,
+    pub out_path: Option<PathBuf>,
+    pub check_path: Option<PathBuf>,
+}
+
+impl AuditArgs {
+    pub fn apply(c: clap::Command) -> clap::Command {
+        c.arg(arg!(--srs <PATH> "Enter the dir for srs params" ))
+            .arg(arg!(--run <ID> "Run to export, defaults to the latest prove run" ))
+            .arg(arg!(--proof <PATH> "Proof of the run, defaults to the one recorded in the run" ))
+            .arg(arg!(--key <PRIVATE_KEY> "Key to sign the archive with" ))
+            .arg(arg!(--out <PATH> "Path for output audit archive" ))
+            .arg(arg!(--check <PATH> "Verify the signature of an audit archive instead" ))
+    }
+
+    pub fn from(arg_matches: Option<&ArgMatches>, env: &Env) -> Result<Self, Error> {
+        let arg_matches = arg_matches.ok_or(Error::InternalError("missing arg matches"))?;
+        let srs_path = parse_srs_path(arg_matches, env)?;
+        let run_id = parse_optional(arg_matches, "run")?;
+        let proof_path = parse_optional::<String>(arg_matches, "proof")?.map(PathBuf::from);
+        let signer = parse_optional::<String>(arg_matches, "key")?
+            .or(env.audit_signer_key.clone())
+            .map(|key| {
+                LocalWallet::from_str(&key)
+                    .map_err(|_| Error::InvalidArgument("--key: invalid private key".to_string()))
+            })
+            .transpose()?;
+        let out_path = parse_optional::<String>(arg_matches, "out")?.map(PathBuf::from);
+        let check_path = parse_optional::<String>(arg_matches, "check")?.map(PathBuf::from);
+        if signer.is_none() && check_path.is_none() {
+            return Err(Error::InvalidArgument(
+                "please provide --key or AUDIT_SIGNER_KEY to sign the archive".to_string(),
+            ));
+        }
+        Ok(Self {
+            srs_path,
+            run_id,
+            proof_path,
+            signer,
+            out_path,
+            check_path,
+        })
+    }
+}
+
+pub struct AggregateArgs {
+    pub srs_path: PathBuf,
+    pub proofs: Vec<Proof>,
+    pub degree: u32,
+    pub out_path: Option<String>,
+    pub verify_paths: Vec<PathBuf>,
+    pub instance_encoding: InstanceEncoding,
+    pub transcript: ProofTranscript,
+    /// Solidity verifier written by `gen-verifier --aggregated` which has to accept the verified
+    /// aggregated proofs as well.
+    pub evm_verifier: Option<PathBuf>,
+}
+
+impl AggregateArgs {
+    pub fn apply(c: clap::Command) -> clap::Command {
+        c.arg(arg!(--srs <PATH> "Enter the dir for srs params" ))
+            .arg(arg!(--proofs <PATHS> "Comma separated paths of proofs generated with --for-aggregation" ))
+            .arg(arg!(--degree <NUMBER> "Degree of the aggregation circuit" ))
+            .arg(arg!(--out <PATH> "Path for output aggregated proof file" ))
+            .arg(arg!(--verify <PATHS> "Verify comma separated aggregated proofs instead" ))
+            .arg(arg!(--"hash-instances" "Expose only a Poseidon digest of the aggregated instances" ))
+            .arg(arg!(--transcript <NAME> "Transcript of the aggregated proof, blake2b, poseidon or keccak for the solidity verifier" ))
+            .arg(arg!(--"evm-verifier" <PATH> "Also call the solidity verifier of gen-verifier --aggregated with the --verify proofs" ))
+    }
+
+    pub fn from(arg_matches: Option<&ArgMatches>, env: &Env) -> Result<Self, Error> {
+        let arg_matches = arg_matches.ok_or(Error::InternalError("missing arg matches"))?;
+        let srs_path = parse_srs_path(arg_matches, env)?;
+        let verify_paths = parse_optional::<String>(arg_matches, "verify")?
+            .map(|paths| {
+                paths
+                    .split(',')
+                    .map(|path| PathBuf::from(path.trim()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let proofs = parse_optional::<String>(arg_matches, "proofs")?
+            .map(|paths| {
+                paths
+                    .split(',')
+                    .map(|path| Proof::read_from_file(&PathBuf::from(path.trim())))
+                    .collect::<Result<Vec<_>, Error>>()
+            })
+            .transpose()?
+            .unwrap_or_default();
+        if proofs.is_empty() && verify_paths.is_empty() {
+            return Err(Error::InvalidArgument(
+                "please provide the proofs to aggregate using --proofs".to_string(),
+            ));
+        }
+        let degree = parse_optional(arg_matches, "degree")?.unwrap_or(DEFAULT_AGGREGATION_DEGREE);
+        let out_path = parse_optional(arg_matches, "out")?;
+        let instance_encoding = parse_instance_encoding(arg_matches);
+        let transcript = parse_optional(arg_matches, "transcript")?.unwrap_or_default();
+        let evm_verifier =
+            parse_optional::<String>(arg_matches, "evm-verifier")?.map(PathBuf::from);
+        if evm_verifier.is_some() && verify_paths.is_empty() {
+            return Err(Error::InvalidArgument(
+                "--evm-verifier is only supported with --verify".to_string(),
+            ));
+        }
+        Ok(Self {
+            srs_path,
+            proofs,
+            degree,
+            out_path,
+            verify_paths,
+            instance_encoding,
+            transcript,
+            evm_verifier,
+        })
+    }
+}
+
+pub struct CorpusArgs {
+    pub dir: PathBuf,
+    pub rpc: String,
+    pub srs_path: PathBuf,
+    pub prove: bool,
+    pub report_path: Option<PathBuf>,
+}
+
+impl CorpusArgs {
+    pub fn apply(c: clap::Command) -> clap::Command {
+        c.arg(arg!(--dir <PATH> "Directory with a subdirectory per scenario" ))
+            .arg(arg!(--rpc <URL> "Enter ethereum archive node RPC url" ))
+            .arg(arg!(--srs <PATH> "Enter the dir for srs params" ))
+            .arg(arg!(--prove "Generate real proofs instead of running the MockProver only" ))
+            .arg(arg!(--report <PATH> "Write the report as json" ))
+    }
+
+    pub fn from(arg_matches: Option<&ArgMatches>, env: &Env) -> Result<Self, Error> {
+        let arg_matches = arg_matches.ok_or(Error::InternalError("missing arg matches"))?;
+        let dir = parse_optional::<String>(arg_matches, "dir")?
+            .map(PathBuf::from)
+            .ok_or(Error::InvalidArgument(
+                "please provide the scenarios directory using --dir".to_string(),
+            ))?;
+        let rpc = parse_optional(arg_matches, "rpc")?
+            .or(env.eth_rpc_url.clone())
+            .ok_or(Error::InvalidArgument(
+                "please provide --rpc or ETH_RPC_URL".to_string(),
+            ))?;
+        let srs_path = parse_srs_path(arg_matches, env)?;
+        let prove = arg_matches.get_flag("prove");
+        let report_path = parse_optional::<String>(arg_matches, "report")?.map(PathBuf::from);
+        Ok(Self {
+            dir,
+            rpc,
+            srs_path,
+            prove,
+            report_path,
+        })
+    }
+}
+
+pub struct BenchArgs {
+    /// Exploit to benchmark, its transcript is Keccak unless --transcript is given so that the
+    /// gas of its verifier is measured.
+    pub prove_args: ProveArgs,
+    pub cases: Vec<BenchCase>,
+    /// Degrees to prove each case at, the degree of its witness if there are none.
+    pub degrees: Vec<u32>,
+    pub report_path: Option<PathBuf>,
+}
+
+impl BenchArgs {
+    pub fn apply(c: clap::Command) -> clap::Command {
+        ProveArgs::apply(c)
+            .arg(arg!(--presets <NAMES> "Comma separated presets to benchmark instead of the --preset and --max-* params" ))
+            .arg(arg!(--degrees <DEGREES> "Comma separated degrees to prove each params at (default the degree of the witness)" ))
+            .arg(arg!(--report <PATH> "Write the report as json" ))
+    }
+
+    pub fn from(arg_matches: Option<&ArgMatches>, env: &Env) -> Result<Self, Error> {
+        let mut prove_args = ProveArgs::from(arg_matches, env)?;
+        let arg_matches = arg_matches.ok_or(Error::InternalError("missing arg matches"))?;
+        if prove_args.params_margin.is_some() || prove_args.wrap {
+            return Err(Error::InvalidArgument(
+                "bench proves the given params unwrapped, --auto-params and --wrap are not supported"
+                    .to_string(),
+            ));
+        }
+        if prove_args.backend != ProofBackend::Kzg {
+            return Err(Error::InvalidArgument(
+                "bench measures the KZG prover, --ipa and --mock are not supported".to_string(),
+            ));
+        }
+        if parse_optional::<String>(arg_matches, "transcript")?.is_none()
+            && !arg_matches.get_flag("for-aggregation")
+        {
+            prove_args.transcript = ProofTranscript::Keccak;
+        }
+        let cases = match parse_optional::<String>(arg_matches, "presets")? {
+            Some(presets) => presets
+                .split(',')
+                .map(|preset| {
+                    let preset = preset.trim().parse::<CircuitPreset>()?;
+                    Ok(BenchCase {
+                        preset: Some(preset),
+                        circuits_params: preset.params(),
+                    })
+                })
+                .collect::<Result<Vec<_>, Error>>()?,
+            None => {
+                let (preset, circuits_params) = parse_circuit_params(arg_matches, env)?;
+                vec![BenchCase {
+                    preset,
+                    circuits_params,
+                }]
+            }
+        };
+        let degrees = parse_optional::<String>(arg_matches, "degrees")?
+            .map(|degrees| {
+                degrees
+                    .split(',')
+                    .map(|degree| {
+                        degree.trim().parse().map_err(|_| {
+                            Error::InvalidArgument(format!("invalid degree {degree} in --degrees"))
+                        })
+                    })
+                    .collect::<Result<Vec<_>, Error>>()
+            })
+            .transpose()?
+            .unwrap_or_default();
+        let report_path = parse_optional::<String>(arg_matches, "report")?.map(PathBuf::from);
+        Ok(Self {
+            prove_args,
+            cases,
+            degrees,
+            report_path,
+        })
+    }
+}
+
+pub struct ReproArgs {
+    /// Fixture to replay, otherwise the exploit runs like `test` and its fixture is written.
+    pub replay: Option<PathBuf>,
+    /// Proves the replayed fixture instead of only checking its constraints.
+    pub replay_prove: Option<ReplayProveArgs>,
+    pub prove_args: Option<ProveArgs>,
+}
+
+pub struct ReplayProveArgs {
+    pub srs_path: PathBuf,
+    pub transcript: ProofTranscript,
+    pub out_path: PathBuf,
+    /// `serve-grpc` server which proves the fixture instead of this machine.
+    #[cfg(feature = "grpc")]
+    pub remote: Option<String>,
+}
+
+impl ReproArgs {
+    pub fn apply(c: clap::Command) -> clap::Command {
+        let c = ProveArgs::apply(c)
+            .arg(arg!(--replay <PATH> "Replay a fixture offline instead of exporting one" ))
+            .arg(
+                arg!(--prove "Generate a real proof of the --replay fixture, which needs no RPC" ),
+            );
+        #[cfg(feature = "grpc")]
+        let c = c.arg(arg!(--remote <URL> "Prove the --replay fixture on a serve-grpc server" ));
+        c
+    }
+
+    pub fn from(arg_matches: Option<&ArgMatches>, env: &Env) -> Result<Self, Error> {
+        let am = arg_matches.ok_or(Error::InternalError("missing arg matches"))?;
+        if let Some(replay) = parse_optional::<String>(am, "replay")? {
+            let replay_prove = if am.get_flag("prove") {
+                Some(ReplayProveArgs {
+                    srs_path: parse_srs_path(am, env)?,
+                    transcript: parse_optional(am, "transcript")?.unwrap_or_default(),
+                    out_path: parse_optional::<String>(am, "out")?
+                        .map_or(PathBuf::from("proof.json"), PathBuf::from),
+                    #[cfg(feature = "grpc")]
+                    remote: parse_optional(am, "remote")?,
+                })
+            } else {
+                None
+            };
+            return Ok(Self {
+                replay: Some(PathBuf::from(replay)),
+                replay_prove,
+                prove_args: None,
+            });
+        }
+        if am.get_flag("prove") {
+            return Err(Error::InvalidArgument(
+                "--prove is only supported with --replay, use the prove command otherwise"
+                    .to_string(),
+            ));
+        }
+        let mut prove_args = ProveArgs::from(arg_matches, env)?;
+        prove_args
+            .repro_out
+            .get_or_insert(PathBuf::from("repro.json"));
+        Ok(Self {
+            replay: None,
+            replay_prove: None,
+            prove_args: Some(prove_args),
+        })
+    }
+}
+
+pub struct SetupArgs {
+    pub srs_path: PathBuf,
+    pub degree: u32,
+    pub circuits_params: FixedCParams,
+}
+
+impl SetupArgs {
+    pub fn apply(c: clap::Command) -> clap::Command {
+        c.arg(arg!(--srs <PATH> "Enter the dir for srs params" ))
+            .arg(arg!(--degree <NUMBER> "Degree of the circuit, defaults to the degree of --preset" ))
+            .args(circuit_params_args())
+    }
+
+    pub fn from(arg_matches: Option<&ArgMatches>, env: &Env) -> Result<Self, Error> {
+        let arg_matches = arg_matches.ok_or(Error::InternalError("missing arg matches"))?;
+        let srs_path = parse_srs_path(arg_matches, env)?;
+        let (preset, circuits_params) = parse_circuit_params(arg_matches, env)?;
+        let cli_preset = parse_optional::<CircuitPreset>(arg_matches, "preset")?;
+        let degree = parse_optional(arg_matches, "degree")?
+            .or(cli_preset.map(|preset| preset.degree()))
+            .or(env.degree)
+            .or(preset.map(|preset| preset.degree()))
+            .ok_or(Error::InvalidArgument(
+                "please provide the circuit degree using --degree or --preset".to_string(),
+            ))?;
+        Ok(Self {
+            srs_path,
+            degree,
+            circuits_params,
+        })
+    }
+}
+
+/// Address `serve` listens on when there is no --listen.
+pub const DEFAULT_LISTEN_ADDR: &str = "127.0.0.1:3030";
+
+pub struct ServeArgs {
+    /// Circuit size whose keys are loaded, every exploit is proven at its degree.
+    pub setup: SetupArgs,
+    pub listen: SocketAddr,
+    pub rpc: String,
+    pub geth_rpc: Option<String>,
+    pub rpc_timeout: Duration,
+    pub prover_options: ProverOptions,
+}
+
+impl ServeArgs {
+    pub fn apply(c: clap::Command) -> clap::Command {
+        SetupArgs::apply(c)
+            .arg(arg!(--listen <ADDR> "Address to serve on (default 127.0.0.1:3030)" ))
+            .arg(arg!(--rpc <URL> "Enter ethereum archive node RPC url" ))
+            .arg(arg!(--"geth-rpc" <URL> "Use geth node for generating traces" ))
+            .arg(arg!(--"rpc-timeout" <SECONDS> "Fail an RPC call not answered in time (default 300)" ))
+            .arg(arg!(--threads <NUMBER> "Generate the keys and the proofs with this many threads (default all cores)" ))
+            .arg(arg!(--"unchecked-artifacts" "Read the params and keys of the srs dir without checking their points, only for a dir nobody else writes to" ))
+    }
+
+    pub fn from(arg_matches: Option<&ArgMatches>, env: &Env) -> Result<Self, Error> {
+        let setup = SetupArgs::from(arg_matches, env)?;
+        let arg_matches = arg_matches.ok_or(Error::InternalError("missing arg matches"))?;
+        let listen = parse_optional::<String>(arg_matches, "listen")?
+            .unwrap_or(DEFAULT_LISTEN_ADDR.to_string())
+            .parse()
+            .map_err(|err| Error::InvalidArgument(format!("--listen: {err}")))?;
+        let rpc = parse_optional(arg_matches, "rpc")?
+            .or(env.eth_rpc_url.clone())
+            .ok_or(Error::InvalidArgument(
+                "please provide --rpc or ETH_RPC_URL".to_string(),
+            ))?;
+        let geth_rpc = parse_optional(arg_matches, "geth-rpc")?.or(env.geth_rpc_url.clone());
+        let rpc_timeout = Duration::from_secs(
+            parse_optional(arg_matches, "rpc-timeout")?
+                .or(env.rpc_timeout)
+                .unwrap_or(300) as u64,
+        );
+        let prover_options = ProverOptions {
+            num_threads: parse_optional(arg_matches, "threads")?,
+            artifact_checks: parse_artifact_checks(arg_matches),
+            ..ProverOptions::default()
+        };
+        Ok(Self {
+            setup,
+            listen,
+            rpc,
+            geth_rpc,
+            rpc_timeout,
+            prover_options,
+        })
+    }
+}
+
+/// Address `serve-grpc` listens on when there is no --listen.
+#[cfg(feature = "grpc")]
+pub const DEFAULT_GRPC_LISTEN_ADDR: &str = "127.0.0.1:50051";
+
+#[cfg(feature = "grpc")]
+pub struct ServeGrpcArgs {
+    pub srs_path: PathBuf,
+    pub listen: SocketAddr,
+    pub prover_options: ProverOptions,
+}
+
+#[cfg(feature = "grpc")]
+impl ServeGrpcArgs {
+    pub fn apply(c: clap::Command) -> clap::Command {
+        c.arg(arg!(--srs <PATH> "Enter the dir for srs params" ))
+            .arg(arg!(--listen <ADDR> "Address to serve on (default 127.0.0.1:50051)" ))
+            .arg(arg!(--threads <NUMBER> "Generate the keys and the proofs with this many threads (default all cores)" ))
+            .arg(arg!(--"unchecked-artifacts" "Read the params and keys of the srs dir without checking their points, only for a dir nobody else writes to" ))
+    }
+
+    pub fn from(arg_matches: Option<&ArgMatches>, env: &Env) -> Result<Self, Error> {
+        let arg_matches = arg_matches.ok_or(Error::InternalError("missing arg matches"))?;
+        let listen = parse_optional::<String>(arg_matches, "listen")?
+            .unwrap_or(DEFAULT_GRPC_LISTEN_ADDR.to_string())
+            .parse()
+            .map_err(|err| Error::InvalidArgument(format!("--listen: {err}")))?;
+        Ok(Self {
+            srs_path: parse_srs_path(arg_matches, env)?,
+            listen,
+            prover_options: ProverOptions {
+                num_threads: parse_optional(arg_matches, "threads")?,
+                artifact_checks: parse_artifact_checks(arg_matches),
+                ..ProverOptions::default()
+            },
+        })
+    }
+}
+
+/// Circuit whose proofs the solidity verifier of `gen-verifier` verifies.
+pub enum VerifierCircuit {
+    Super(SetupArgs),
+    /// The aggregation circuit of an aggregated proof, which fixes the aggregated circuits.
+    Aggregation {
+        srs_path: PathBuf,
+        aggregated: AggregatedProof,
+    },
+}
+
+pub struct GenVerifierArgs {
+    pub circuit: VerifierCircuit,
+    pub out_path: PathBuf,
+    /// Multiopen scheme of the proofs of the SuperCircuit verifier.
+    pub pcs_scheme: PcsScheme,
+    /// Proof of the circuit, an aggregated one with `--aggregated`, run through the verifier to
+    /// report its deployment and verification gas.
+    pub gas_report: Option<PathBuf>,
+    /// Split a verifier above the contract size limit into parts which fit it.
+    pub split: bool,
+}
+
+impl GenVerifierArgs {
+    pub fn apply(c: clap::Command) -> clap::Command {
+        SetupArgs::apply(c)
+            .arg(arg!(--aggregated <PATH> "Generate the verifier of the aggregation circuit of this aggregated proof instead" ))
+            .arg(arg!(--out <PATH> "Path for output solidity verifier, Verifier.sol by default" ))
+            .arg(arg!(--pcs <SCHEME> "Multiopen scheme of the proofs, shplonk or gwc, the one they were proven with" ))
+            .arg(arg!(--split "Split a verifier above the contract size limit into contracts which fit it and a dispatcher" ))
+            .arg(arg!(--"gas-report" <PROOF> "Run this Keccak transcript proof through the verifier in a local anvil and print the gas of deploying it and of verifying" ))
+    }
+
+    pub fn from(arg_matches: Option<&ArgMatches>, env: &Env) -> Result<Self, Error> {
+        let am = arg_matches.ok_or(Error::InternalError("missing arg matches"))?;
+        let circuit = match parse_optional::<String>(am, "aggregated")? {
+            Some(path) => VerifierCircuit::Aggregation {
+                srs_path: parse_srs_path(am, env)?,
+                aggregated: AggregatedProof::read_from_file(&PathBuf::from(path))?,
+            },
+            None => VerifierCircuit::Super(SetupArgs::from(arg_matches, env)?),
+        };
+        let pcs_scheme = parse_optional(am, "pcs")?.unwrap_or_default();
+        if pcs_scheme != PcsScheme::Shplonk
+            && matches!(circuit, VerifierCircuit::Aggregation { .. })
+        {
+            return Err(Error::InvalidArgument(
+                "aggregated proofs are SHPLONK proofs, --pcs gwc is only supported for the SuperCircuit verifier".to_string(),
+            ));
+        }
+        let out_path = parse_optional::<String>(am, "out")?
+            .map_or(PathBuf::from("Verifier.sol"), PathBuf::from);
+        let gas_report = parse_optional::<String>(am, "gas-report")?.map(PathBuf::from);
+        Ok(Self {
+            circuit,
+            out_path,
+            pcs_scheme,
+            gas_report,
+            split: am.get_flag("split"),
+        })
+    }
+}
+
+pub struct VkArgs {
+    pub srs_path: PathBuf,
+    pub approve: Option<H256>,
+    pub revoke: Option<H256>,
+    pub membership_proof: Option<H256>,
+}
+
+impl VkArgs {
+    pub fn apply(c: clap::Command) -> clap::Command {
+        c.arg(arg!(--srs <PATH> "Enter the dir for srs params" ))
+            .arg(arg!(--approve <VK> "Approve a verifying key, given as its file path or 0x digest" ))
+            .arg(arg!(--revoke <VK> "Revoke a verifying key, given as its file path or 0x digest" ))
+            .arg(arg!(--proof <VK> "Print the Merkle proof that a verifying key is approved" ))
+    }
+
+    pub fn from(arg_matches: Option<&ArgMatches>, env: &Env) -> Result<Self, Error> {
+        let arg_matches = arg_matches.ok_or(Error::InternalError("missing arg matches"))?;
+        let srs_path = parse_srs_path(arg_matches, env)?;
+        let parse_vk = |id: &str| {
+            parse_optional::<String>(arg_matches, id)?
+                .map(|input| parse_vk_digest(&input))
+                .transpose()
+        };
+        Ok(Self {
+            srs_path,
+            approve: parse_vk("approve")?,
+            revoke: parse_vk("revoke")?,
+            membership_proof: parse_vk("proof")?,
+        })
+    }
+}
+
+fn circuit_params_args() -> [clap::Arg; 7] {
+    [
+        arg!(--preset <NAME> "Circuit size to start from, small, medium or large, the --max-* options override it" ),
+        arg!(--"max-rws" <NUMBER>),
+        arg!(--"max-copy-rows" <NUMBER>),
+        arg!(--"max-exp-steps" <NUMBER>),
+        arg!(--"max-bytecode" <NUMBER>),
+        arg!(--"max-evm-rows" <NUMBER>),
+        arg!(--"max-keccak-rows" <NUMBER>),
+    ]
+}
+
+/// `--circuit` of the dev mode which proves a single sub-circuit, see the `dev-circuits` feature.
+#[cfg(feature = "dev-circuits")]
+fn sub_circuit_args() -> Vec<clap::Arg> {
+    vec![
+        arg!(--circuit <NAME> "Prove only this sub-circuit of the witness, evm, state, copy, keccak, exp or tx, for iterating on the exploit" ),
+    ]
+}
+
+#[cfg(not(feature = "dev-circuits"))]
+fn sub_circuit_args() -> Vec<clap::Arg> {
+    Vec::new()
+}
+
+fn parse_circuit_params(
+    arg_matches: &ArgMatches,
+    env: &Env,
+) -> Result<(Option<CircuitPreset>, FixedCParams), Error> {
+    // the flags take precedence over the env, a --preset over the --max-* vars of the env too
+    let cli_preset = parse_optional::<CircuitPreset>(arg_matches, "preset")?;
+    let cli_params = cli_preset.map(|preset| preset.params());
+    let preset = cli_preset.or(env.preset);
+    let params = preset.map(|preset| preset.params());
+    let max_rws = parse_optional(arg_matches, "max-rws")?
+        .or(cli_params.map(|p| p.max_rws))
+        .or(env.max_rws)
+        .unwrap_or(params.map_or(DEFAULT_MAX_RWS, |p| p.max_rws));
+    let max_copy_rows = parse_optional(arg_matches, "max-copy-rows")?
+        .or(cli_params.map(|p| p.max_copy_rows))
+        .or(env.max_copy_rows)
+        .unwrap_or(params.map_or(DEFAULT_MAX_COPY_ROWS, |p| p.max_copy_rows));
+    let max_exp_steps = parse_optional(arg_matches, "max-exp-steps")?
+        .or(cli_params.map(|p| p.max_exp_steps))
+        .or(env.max_exp_steps)
+        .unwrap_or(params.map_or(DEFAULT_MAX_EXP_STEPS, |p| p.max_exp_steps));
+    let max_bytecode = parse_optional(arg_matches, "max-bytecode")?
+        .or(cli_params.map(|p| p.max_bytecode))
+        .or(env.max_bytecode)
+        .unwrap_or(params.map_or(DEFAULT_MAX_BYTECODE, |p| p.max_bytecode));
+    let max_evm_rows = parse_optional(arg_matches, "max-evm-rows")?
+        .or(cli_params.map(|p| p.max_evm_rows))
+        .or(env.max_evm_rows)
+        .unwrap_or(params.map_or(DEFAULT_MAX_EVM_ROWS, |p| p.max_evm_rows));
+    let max_keccak_rows = parse_optional(arg_matches, "max-keccak-rows")?
+        .or(cli_params.map(|p| p.max_keccak_rows))
+        .or(env.max_keccak_rows)
+        .unwrap_or(params.map_or(DEFAULT_MAX_KECCAK_ROWS, |p| p.max_keccak_rows));
+    Ok((
+        preset,
+        FixedCParams {
+            max_rws,
+            max_txs: MAX_TXS,
+            max_calldata: MAX_CALLDATA,
+            max_copy_rows,
+            max_exp_steps,
+            max_bytecode,
+            max_evm_rows,
+            max_keccak_rows,
+        },
+    ))
+}
+
+fn parse_instance_encoding(arg_matches: &ArgMatches) -> InstanceEncoding {
+    if arg_matches.get_flag("hash-instances") {
+        InstanceEncoding::Poseidon
+    } else {
+        InstanceEncoding::Plain
+    }
+}
+
+fn parse_artifact_checks(arg_matches: &ArgMatches) -> ArtifactChecks {
+    if arg_matches.get_flag("unchecked-artifacts") {
+        ArtifactChecks::Unchecked
+    } else {
+        ArtifactChecks::Checked
     }
 }
 
-fn parse_srs_path(arg_matches: &ArgMatches, env: &Env) -> PathBuf {
-    let srs_input = parse_optional(arg_matches, "srs").or(env.srs_path.clone());
+fn parse_srs_path(arg_matches: &ArgMatches, env: &Env) -> Result<PathBuf, Error> {
+    let srs_input = parse_optional(arg_matches, "srs")?.or(env.srs_path.clone());
     let srs_path = if let Some(srs_input) = srs_input {
         PathBuf::from(srs_input)
     } else {
-        home_dir().unwrap().join(".proof-of-exploit-srs")
+        home_dir()
+            .ok_or(Error::InvalidArgument(
+                "could not find home dir, please provide --srs".to_string(),
+            ))?
+            .join(".proof-of-exploit-srs")
     };
-    create_dir_all(srs_path.clone()).unwrap();
-    srs_path
+    create_dir_all(srs_path.clone())?;
+    Ok(srs_path)
 }
 
-pub fn parse_optional<T: FromStr>(am: &ArgMatches, id: &str) -> Option<T>
+pub fn parse_optional<T: FromStr>(am: &ArgMatches, id: &str) -> Result<Option<T>, Error>
 where
     <T as std::str::FromStr>::Err: std::fmt::Debug,
 {
-    am.get_one::<String>(id).map(|val| val.parse().unwrap())
+    am.get_one::<String>(id)
+        .map(|val| {
+            val.parse()
+                .map_err(|err| Error::InvalidArgument(format!("--{id}: {err:?}")))
+        })
+        .transpose()
 }
@@ -0,0 +1,185 @@
+use crate::{
+    constants::{
+        DEFAULT_MAX_BYTECODE, DEFAULT_MAX_COPY_ROWS, DEFAULT_MAX_EVM_ROWS, DEFAULT_MAX_EXP_STEPS,
+        DEFAULT_MAX_KECCAK_ROWS, DEFAULT_MAX_RWS, MAX_CALLDATA, MAX_TXS,
+    },
+    env::Env,
+    error::Error,
+    utils::{halo2::preset::CircuitPreset, hardfork::Hardfork},
+};
+use bus_mapping::circuit_input_builder::FixedCParams;
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// File read when the `CONFIG` env var does not point to another one.
+pub const DEFAULT_CONFIG_FILE: &str = "exploit.toml";
+
+/// Parameter profile an exploit ships next to its sources: the circuit size, the degree, the dir
+/// of the params and keys, and the chain anvil forks. Every field is optional, env vars override
+/// the file and command line options override both.
+///
+/// ```toml
+/// preset = "medium"
+/// degree = 19
+/// srs_path = "./srs"
+///
+/// [circuit]
+/// max_rws = 50000
+///
+/// [anvil]
+/// rpc = "https://eth.llamarpc.com"
+/// block = 18000000
+/// hardfork = "shanghai"
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub preset: Option<CircuitPreset>,
+    pub degree: Option<u32>,
+    pub srs_path: Option<PathBuf>,
+    pub circuit: CircuitParamsConfig,
+    pub anvil: AnvilConfig,
+}
+
+/// The `FixedCParams` which are not fixed by the PoX circuit, unset ones come from the preset.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CircuitParamsConfig {
+    pub max_rws: Option<usize>,
+    pub max_copy_rows: Option<usize>,
+    pub max_exp_steps: Option<usize>,
+    pub max_bytecode: Option<usize>,
+    pub max_evm_rows: Option<usize>,
+    pub max_keccak_rows: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnvilConfig {
+    pub rpc: Option<String>,
+    pub geth_rpc: Option<String>,
+    pub block: Option<usize>,
+    /// Seconds after which an RPC call is given up.
+    pub rpc_timeout: Option<usize>,
+    pub hardfork: Option<Hardfork>,
+    /// Chain id of a chain which is not forked, a fork keeps the one of its chain.
+    pub chain_id: Option<u64>,
+    pub block_gas_limit: Option<u64>,
+    /// JSON-RPC url of an anvil node already running, which is used instead of starting one.
+    pub attach: Option<String>,
+}
+
+impl Config {
+    pub fn read_from_file(path: &Path) -> Result<Self, Error> {
+        toml::from_str(&fs::read_to_string(path)?)
+            .map_err(|err| Error::InvalidArgument(format!("{}: {err}", path.display())))
+    }
+
+    /// Reads the file the `CONFIG` env var points to, otherwise `exploit.toml` in the working dir
+    /// if there is one.
+    pub fn find() -> Result<Self, Error> {
+        match env::var("CONFIG") {
+            Ok(path) => Self::read_from_file(Path::new(&path)),
+            Err(_) if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                Self::read_from_file(Path::new(DEFAULT_CONFIG_FILE))
+            }
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    /// Profile with the env vars applied, `Env::load` already falls back to the config file for
+    /// the vars which are not set.
+    pub fn from_env(env: &Env) -> Self {
+        Self {
+            preset: env.preset,
+            degree: env.degree,
+            srs_path: env.srs_path.clone().map(PathBuf::from),
+            circuit: CircuitParamsConfig {
+                max_rws: env.max_rws,
+                max_copy_rows: env.max_copy_rows,
+                max_exp_steps: env.max_exp_steps,
+                max_bytecode: env.max_bytecode,
+                max_evm_rows: env.max_evm_rows,
+                max_keccak_rows: env.max_keccak_rows,
+            },
+            anvil: AnvilConfig {
+                rpc: env.eth_rpc_url.clone(),
+                geth_rpc: env.geth_rpc_url.clone(),
+                block: env.fork_block_number,
+                rpc_timeout: env.rpc_timeout,
+                hardfork: env.hardfork,
+                chain_id: env.chain_id,
+                block_gas_limit: env.block_gas_limit,
+                attach: env.anvil_url.clone(),
+            },
+        }
+    }
+
+    pub fn circuits_params(&self) -> FixedCParams {
+        let preset = self.preset.map(|preset| preset.params());
+        let c = &self.circuit;
+        FixedCParams {
+            max_rws: c
+                .max_rws
+                .unwrap_or(preset.map_or(DEFAULT_MAX_RWS, |p| p.max_rws)),
+            max_txs: MAX_TXS,
+            max_calldata: MAX_CALLDATA,
+            max_copy_rows: c
+                .max_copy_rows
+                .unwrap_or(preset.map_or(DEFAULT_MAX_COPY_ROWS, |p| p.max_copy_rows)),
+            max_exp_steps: c
+                .max_exp_steps
+                .unwrap_or(preset.map_or(DEFAULT_MAX_EXP_STEPS, |p| p.max_exp_steps)),
+            max_bytecode: c
+                .max_bytecode
+                .unwrap_or(preset.map_or(DEFAULT_MAX_BYTECODE, |p| p.max_bytecode)),
+            max_evm_rows: c
+                .max_evm_rows
+                .unwrap_or(preset.map_or(DEFAULT_MAX_EVM_ROWS, |p| p.max_evm_rows)),
+            max_keccak_rows: c
+                .max_keccak_rows
+                .unwrap_or(preset.map_or(DEFAULT_MAX_KECCAK_ROWS, |p| p.max_keccak_rows)),
+        }
+    }
+
+    /// Degree of the config, the preset's degree if there is none.
+    pub fn degree(&self) -> Option<u32> {
+        self.degree.or(self.preset.map(|preset| preset.degree()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Config;
+    use crate::{constants::DEFAULT_MAX_COPY_ROWS, utils::halo2::preset::CircuitPreset};
+
+    #[test]
+    fn test_config_from_toml() {
+        let config: Config = toml::from_str(
+            r#"
+            preset = "small"
+            [circuit]
+            max_rws = 357
+            [anvil]
+            block = 18000000
+            hardfork = "shanghai"
+            "#,
+        )
+        .unwrap();
+        let params = config.circuits_params();
+        assert_eq!(params.max_rws, 357);
+        assert_eq!(
+            params.max_copy_rows,
+            CircuitPreset::Small.params().max_copy_rows
+        );
+        assert_eq!(config.degree(), Some(CircuitPreset::Small.degree()));
+        assert_eq!(
+            Config::default().circuits_params().max_copy_rows,
+            DEFAULT_MAX_COPY_ROWS
+        );
+        assert!(toml::from_str::<Config>("max_rws = 1").is_err());
+    }
+}
@@ -1,4 +1,16 @@
 pub const MAX_TXS: usize = 1;
 pub const MAX_CALLDATA: usize = 256;
+/// Randomness of every circuit, with or without a witness. The keys and the verifiers are
+/// generated with it, so a witness with another one does not verify against them.
 pub const RANDOMNESS: u64 = 0x100;
+pub const DEFAULT_MAX_RWS: usize = 1000;
+pub const DEFAULT_MAX_COPY_ROWS: usize = 1000;
+pub const DEFAULT_MAX_EXP_STEPS: usize = 1000;
+pub const DEFAULT_MAX_BYTECODE: usize = 512;
+pub const DEFAULT_MAX_EVM_ROWS: usize = 1000;
+pub const DEFAULT_MAX_KECCAK_ROWS: usize = 1000;
+/// Percent by which `--auto-params` grows the params measured from the exploit block.
+pub const DEFAULT_PARAMS_MARGIN: usize = 10;
+/// Commit of zkevm-circuits in Cargo.lock, set by the build script.
+pub const ZKEVM_CIRCUITS_COMMIT: &str = env!("ZKEVM_CIRCUITS_COMMIT");
 pub use bus_mapping::{POX_CHALLENGE_ADDRESS, POX_EXPLOIT_ADDRESS};
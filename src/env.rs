@@ -1,113 +1,153 @@
+use crate::{
+    config::Config,
+    error::Error,
+    utils::{halo2::preset::CircuitPreset, hardfork::Hardfork},
+};
 use dotenv::dotenv;
 use ethers::types::U64;
-use std::env;
+use std::{env, str::FromStr};
 
 #[allow(dead_code)]
 pub struct Env {
     pub eth_rpc_url: Option<String>,
     pub fork_block_number: Option<usize>,
+    pub hardfork: Option<Hardfork>,
+    pub chain_id: Option<u64>,
+    pub block_gas_limit: Option<u64>,
+    pub anvil_url: Option<String>,
     pub geth_rpc_url: Option<String>,
+    pub rpc_timeout: Option<usize>,
     pub challenge_path: Option<String>,
     pub exploit_path: Option<String>,
     pub exploit_balance: Option<String>,
     pub srs_path: Option<String>,
+    pub preset: Option<CircuitPreset>,
+    pub degree: Option<u32>,
     pub max_rws: Option<usize>,
     pub max_copy_rows: Option<usize>,
     pub max_exp_steps: Option<usize>,
     pub max_bytecode: Option<usize>,
     pub max_evm_rows: Option<usize>,
     pub max_keccak_rows: Option<usize>,
+    pub log_level: Option<String>,
+    pub log_format: Option<String>,
+    pub log_file: Option<String>,
+    pub audit_signer_key: Option<String>,
+    /// Why the config file could not be read, reported by `check_config` for the commands
+    /// which use it. The other commands run with the env vars alone.
+    config_error: Option<String>,
 }
 
 #[allow(dead_code)]
 impl Env {
-    pub fn load() -> Env {
+    pub fn load() -> Result<Env, Error> {
         dotenv().ok();
-        // TODO refactor - remove duplicate code
 
-        // anvil params
-        let eth_rpc_url = match env::var("ETH_RPC_URL") {
-            Ok(val) => Some(val),
-            Err(_) => None,
-        };
-        let fork_block_number = match env::var("FORK_BLOCK_NUMBER") {
-            Ok(val) => Some(U64::from_str_radix(&val, 10).unwrap().as_usize()),
-            Err(_) => None,
+        // the config file fills in the vars which are not set
+        let (config, config_error) = match Config::find() {
+            Ok(config) => (config, None),
+            Err(error) => (Config::default(), Some(error.message())),
         };
 
+        // anvil params
+        let eth_rpc_url = var("ETH_RPC_URL").or(config.anvil.rpc);
+        let fork_block_number = var_usize("FORK_BLOCK_NUMBER")?.or(config.anvil.block);
+        let hardfork = var_parse("HARDFORK")?.or(config.anvil.hardfork);
+        let chain_id = var_parse("CHAIN_ID")?.or(config.anvil.chain_id);
+        let block_gas_limit = var_parse("BLOCK_GAS_LIMIT")?.or(config.anvil.block_gas_limit);
+        let anvil_url = var("ANVIL_URL").or(config.anvil.attach);
+
         // temp params
-        let geth_rpc_url = match env::var("GETH_RPC_URL") {
-            Ok(val) => Some(val),
-            Err(_) => None,
-        };
+        let geth_rpc_url = var("GETH_RPC_URL").or(config.anvil.geth_rpc);
+        let rpc_timeout = var_usize("RPC_TIMEOUT")?.or(config.anvil.rpc_timeout);
 
         // PoX params
-        let challenge_path = match env::var("CHALLENGE") {
-            Ok(val) => Some(val),
-            Err(_) => match env::var("CHALLENGE_PATH") {
-                Ok(val) => Some(val),
-                Err(_) => None,
-            },
-        };
-        let exploit_path = match env::var("EXPLOIT") {
-            Ok(val) => Some(val),
-            Err(_) => match env::var("EXPLOIT_PATH") {
-                Ok(val) => Some(val),
-                Err(_) => None,
-            },
-        };
-        let exploit_balance = match env::var("EXPLOIT_BALANCE") {
-            Ok(val) => Some(val),
-            Err(_) => None,
-        };
+        let challenge_path = var("CHALLENGE").or(var("CHALLENGE_PATH"));
+        let exploit_path = var("EXPLOIT").or(var("EXPLOIT_PATH"));
+        let exploit_balance = var("EXPLOIT_BALANCE");
 
         // zkEVM params
-        let srs_path = match env::var("SRS") {
-            Ok(val) => Some(val),
-            Err(_) => match env::var("SRS_PATH") {
-                Ok(val) => Some(val),
-                Err(_) => None,
-            },
-        };
-        let max_rws = match env::var("MAX_ROWS") {
-            Ok(val) => Some(U64::from_str_radix(&val, 10).unwrap().as_usize()),
-            Err(_) => None,
-        };
-        let max_copy_rows = match env::var("MAX_COPY_ROWS") {
-            Ok(val) => Some(U64::from_str_radix(&val, 10).unwrap().as_usize()),
-            Err(_) => None,
-        };
-        let max_exp_steps = match env::var("MAX_EXP_ROWS") {
-            Ok(val) => Some(U64::from_str_radix(&val, 10).unwrap().as_usize()),
-            Err(_) => None,
-        };
-        let max_bytecode = match env::var("MAX_BYTECODE") {
-            Ok(val) => Some(U64::from_str_radix(&val, 10).unwrap().as_usize()),
-            Err(_) => None,
-        };
-        let max_evm_rows = match env::var("MAX_EVM_ROWS") {
-            Ok(val) => Some(U64::from_str_radix(&val, 10).unwrap().as_usize()),
-            Err(_) => None,
-        };
-        let max_keccak_rows = match env::var("MAX_KECCAK_ROWS") {
-            Ok(val) => Some(U64::from_str_radix(&val, 10).unwrap().as_usize()),
-            Err(_) => None,
-        };
+        let srs_path = var("SRS")
+            .or(var("SRS_PATH"))
+            .or(var("POX_ARTIFACT_DIR"))
+            .or(config
+                .srs_path
+                .map(|path| path.to_string_lossy().to_string()));
+        let preset = var_parse("PRESET")?.or(config.preset);
+        let degree = var_usize("DEGREE")?
+            .map(|degree| degree as u32)
+            .or(config.degree);
+        let max_rws = var_usize("MAX_ROWS")?.or(config.circuit.max_rws);
+        let max_copy_rows = var_usize("MAX_COPY_ROWS")?.or(config.circuit.max_copy_rows);
+        let max_exp_steps = var_usize("MAX_EXP_ROWS")?.or(config.circuit.max_exp_steps);
+        let max_bytecode = var_usize("MAX_BYTECODE")?.or(config.circuit.max_bytecode);
+        let max_evm_rows = var_usize("MAX_EVM_ROWS")?.or(config.circuit.max_evm_rows);
+        let max_keccak_rows = var_usize("MAX_KECCAK_ROWS")?.or(config.circuit.max_keccak_rows);
+
+        // logging params
+        let log_level = var("LOG_LEVEL");
+        let log_format = var("LOG_FORMAT");
+        let log_file = var("LOG_FILE");
+
+        // audit params
+        let audit_signer_key = var("AUDIT_SIGNER_KEY");
 
-        Env {
+        Ok(Env {
             eth_rpc_url,
             fork_block_number,
+            hardfork,
+            chain_id,
+            block_gas_limit,
+            anvil_url,
             geth_rpc_url,
+            rpc_timeout,
             challenge_path,
             exploit_path,
             exploit_balance,
             srs_path,
+            preset,
+            degree,
             max_rws,
             max_copy_rows,
             max_exp_steps,
             max_bytecode,
             max_evm_rows,
             max_keccak_rows,
+            log_level,
+            log_format,
+            log_file,
+            audit_signer_key,
+            config_error,
+        })
+    }
+
+    /// Fails if the config file could not be read, for the commands which take their parameter
+    /// profile from it.
+    pub fn check_config(&self) -> Result<(), Error> {
+        match &self.config_error {
+            Some(error) => Err(Error::InvalidArgument(error.clone())),
+            None => Ok(()),
         }
     }
 }
+
+fn var(key: &str) -> Option<String> {
+    env::var(key).ok()
+}
+
+fn var_usize(key: &'static str) -> Result<Option<usize>, Error> {
+    match var(key) {
+        Some(val) => Ok(Some(
+            U64::from_str_radix(&val, 10)
+                .map_err(|_| Error::InvalidEnvVar(key))?
+                .as_usize(),
+        )),
+        None => Ok(None),
+    }
+}
+
+fn var_parse<T: FromStr>(key: &'static str) -> Result<Option<T>, Error> {
+    var(key)
+        .map(|val| val.parse().map_err(|_| Error::InvalidEnvVar(key)))
+        .transpose()
+}
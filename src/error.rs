@@ -1,23 +1,182 @@
+#[cfg(feature = "nowasm")]
 use anvil::eth::error::BlockchainError;
 use ethers_core::utils::rlp;
 use halo2_proofs::plonk;
+#[cfg(feature = "nowasm")]
 use partial_mpt;
+use serde::{Deserialize, Serialize};
 
 #[allow(dead_code)]
 #[derive(Debug)]
 pub enum Error {
+    #[cfg(feature = "nowasm")]
     AnvilError(Box<BlockchainError>),
     RlpDecoderError(Box<rlp::DecoderError>),
     BusMappingError(Box<bus_mapping::Error>),
     Halo2Error(Box<plonk::Error>),
     StdError(Box<std::io::Error>),
+    #[cfg(feature = "nowasm")]
     PartialMptError(Box<partial_mpt::Error>),
     SerdeJsonError(Box<serde_json::Error>),
+    #[cfg(feature = "nowasm")]
     PinataError(Box<pinata_sdk::ApiError>),
     EthersProviderError(Box<ethers::providers::ProviderError>),
+    #[cfg(feature = "nowasm")]
+    ReqwestError(Box<reqwest::Error>),
+    #[cfg(feature = "nowasm")]
+    SvmError(Box<svm_lib::SolcVmError>),
+    SnarkVerifierError(Box<snark_verifier::Error>),
+    #[cfg(feature = "grpc")]
+    GrpcError(Box<tonic::Status>),
+    #[cfg(feature = "grpc")]
+    GrpcTransportError(Box<tonic::transport::Error>),
+    /// Failure of a remote prover, with the code it was reported with.
+    RemoteError(ErrorBody),
+    CompilationError(String),
+    InvalidEnvVar(&'static str),
+    InvalidArgument(String),
+    WitnessOverflow(String),
+    VkMismatch(String),
+    SrsMissing(String),
+    SrsInvalid(String),
+    ExploitFailedSimulation(String),
+    PreflightFailed(String),
+    InvalidWitness(String),
+    UnsupportedTxType(String),
+    UnsupportedOpcode(String),
+    UnsupportedRpcMethod(String),
+    Timeout(String),
     InternalError(&'static str),
 }
 
+/// Stable identifiers for failures, clients of the service mode should branch on these rather
+/// than on the error messages which may change between versions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    WitnessOverflow,
+    VkMismatch,
+    SrsMissing,
+    SrsInvalid,
+    ExploitFailedSimulation,
+    PreflightFailed,
+    InvalidWitness,
+    UnsupportedTxType,
+    UnsupportedOpcode,
+    UnsupportedRpcMethod,
+    Timeout,
+    InvalidInput,
+    CompilationFailed,
+    RpcError,
+    ProvingFailed,
+    IoError,
+    Internal,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::WitnessOverflow => "WITNESS_OVERFLOW",
+            ErrorCode::VkMismatch => "VK_MISMATCH",
+            ErrorCode::SrsMissing => "SRS_MISSING",
+            ErrorCode::SrsInvalid => "SRS_INVALID",
+            ErrorCode::ExploitFailedSimulation => "EXPLOIT_FAILED_SIMULATION",
+            ErrorCode::PreflightFailed => "PREFLIGHT_FAILED",
+            ErrorCode::InvalidWitness => "INVALID_WITNESS",
+            ErrorCode::UnsupportedTxType => "UNSUPPORTED_TX_TYPE",
+            ErrorCode::UnsupportedOpcode => "UNSUPPORTED_OPCODE",
+            ErrorCode::UnsupportedRpcMethod => "UNSUPPORTED_RPC_METHOD",
+            ErrorCode::Timeout => "TIMEOUT",
+            ErrorCode::InvalidInput => "INVALID_INPUT",
+            ErrorCode::CompilationFailed => "COMPILATION_FAILED",
+            ErrorCode::RpcError => "RPC_ERROR",
+            ErrorCode::ProvingFailed => "PROVING_FAILED",
+            ErrorCode::IoError => "IO_ERROR",
+            ErrorCode::Internal => "INTERNAL",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ErrorBody {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl Error {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Error::WitnessOverflow(_) => ErrorCode::WitnessOverflow,
+            Error::VkMismatch(_) => ErrorCode::VkMismatch,
+            Error::SrsMissing(_) => ErrorCode::SrsMissing,
+            Error::SrsInvalid(_) => ErrorCode::SrsInvalid,
+            Error::ExploitFailedSimulation(_) => ErrorCode::ExploitFailedSimulation,
+            Error::PreflightFailed(_) => ErrorCode::PreflightFailed,
+            Error::InvalidWitness(_) => ErrorCode::InvalidWitness,
+            Error::UnsupportedTxType(_) => ErrorCode::UnsupportedTxType,
+            Error::UnsupportedOpcode(_) => ErrorCode::UnsupportedOpcode,
+            Error::UnsupportedRpcMethod(_) => ErrorCode::UnsupportedRpcMethod,
+            Error::Timeout(_) => ErrorCode::Timeout,
+            Error::InvalidEnvVar(_) | Error::InvalidArgument(_) | Error::RlpDecoderError(_) => {
+                ErrorCode::InvalidInput
+            }
+            Error::CompilationError(_) => ErrorCode::CompilationFailed,
+            #[cfg(feature = "nowasm")]
+            Error::SvmError(_) => ErrorCode::CompilationFailed,
+            #[cfg(feature = "nowasm")]
+            Error::AnvilError(_) | Error::PinataError(_) | Error::ReqwestError(_) => {
+                ErrorCode::RpcError
+            }
+            Error::EthersProviderError(_) => ErrorCode::RpcError,
+            #[cfg(feature = "grpc")]
+            Error::GrpcError(_) | Error::GrpcTransportError(_) => ErrorCode::RpcError,
+            Error::RemoteError(body) => body.code,
+            #[cfg(feature = "nowasm")]
+            Error::PartialMptError(_) => ErrorCode::ProvingFailed,
+            Error::BusMappingError(_) | Error::Halo2Error(_) | Error::SnarkVerifierError(_) => {
+                ErrorCode::ProvingFailed
+            }
+            Error::StdError(_) | Error::SerdeJsonError(_) => ErrorCode::IoError,
+            Error::InternalError(_) => ErrorCode::Internal,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            Error::InvalidEnvVar(var) => format!("invalid value for env var {var}"),
+            Error::CompilationError(message)
+            | Error::InvalidArgument(message)
+            | Error::WitnessOverflow(message)
+            | Error::VkMismatch(message)
+            | Error::SrsMissing(message)
+            | Error::SrsInvalid(message)
+            | Error::ExploitFailedSimulation(message)
+            | Error::PreflightFailed(message)
+            | Error::InvalidWitness(message)
+            | Error::UnsupportedTxType(message)
+            | Error::UnsupportedOpcode(message)
+            | Error::UnsupportedRpcMethod(message)
+            | Error::Timeout(message) => message.clone(),
+            Error::InternalError(message) => message.to_string(),
+            Error::RemoteError(body) => body.message.clone(),
+            error => format!("{error:?}"),
+        }
+    }
+
+    pub fn to_body(&self) -> ErrorBody {
+        ErrorBody {
+            code: self.code(),
+            message: self.message(),
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        // ErrorBody only contains strings, serializing it cannot fail
+        serde_json::to_value(self.to_body()).unwrap()
+    }
+}
+
+#[cfg(feature = "nowasm")]
 impl From<BlockchainError> for Error {
     fn from(err: BlockchainError) -> Self {
         Error::AnvilError(Box::new(err))
@@ -42,12 +201,33 @@ impl From<plonk::Error> for Error {
     }
 }
 
+impl From<snark_verifier::Error> for Error {
+    fn from(err: snark_verifier::Error) -> Self {
+        Error::SnarkVerifierError(Box::new(err))
+    }
+}
+
+#[cfg(feature = "nowasm")]
 impl From<partial_mpt::Error> for Error {
     fn from(err: partial_mpt::Error) -> Self {
         Error::PartialMptError(Box::new(err))
     }
 }
 
+#[cfg(feature = "grpc")]
+impl From<tonic::Status> for Error {
+    fn from(err: tonic::Status) -> Self {
+        Error::GrpcError(Box::new(err))
+    }
+}
+
+#[cfg(feature = "grpc")]
+impl From<tonic::transport::Error> for Error {
+    fn from(err: tonic::transport::Error) -> Self {
+        Error::GrpcTransportError(Box::new(err))
+    }
+}
+
 impl From<std::io::Error> for Error {
     fn from(err: std::io::Error) -> Self {
         Error::StdError(Box::new(err))
@@ -66,8 +246,45 @@ impl From<ethers::providers::ProviderError> for Error {
     }
 }
 
+#[cfg(feature = "nowasm")]
 impl From<pinata_sdk::ApiError> for Error {
     fn from(err: pinata_sdk::ApiError) -> Self {
         Error::PinataError(Box::new(err))
     }
 }
+
+#[cfg(feature = "nowasm")]
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Error::ReqwestError(Box::new(err))
+    }
+}
+
+#[cfg(feature = "nowasm")]
+impl From<svm_lib::SolcVmError> for Error {
+    fn from(err: svm_lib::SolcVmError) -> Self {
+        Error::SvmError(Box::new(err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, ErrorCode};
+
+    #[test]
+    fn test_error_body() {
+        let error = Error::SrsMissing("kzg_general_params_19 not found".to_string());
+        assert_eq!(error.code(), ErrorCode::SrsMissing);
+        assert_eq!(
+            error.to_json(),
+            serde_json::json!({
+                "code": "SRS_MISSING",
+                "message": "kzg_general_params_19 not found",
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(ErrorCode::ExploitFailedSimulation).unwrap(),
+            ErrorCode::ExploitFailedSimulation.as_str()
+        );
+    }
+}
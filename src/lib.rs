@@ -1,27 +1,42 @@
 #![feature(let_chains)]
 #![feature(slice_pattern)]
 
-#[cfg(all(feature = "wasm", feature = "nowasm"))]
+#[cfg(all(feature = "wasm", feature = "verifier"))]
 compile_error!(
-    "proof-of-exploit: both wasm & nowasm are enabled, just one of them must be enabled"
+    "proof-of-exploit: both wasm & nowasm (or verifier) are enabled, just one of them must be enabled"
+);
+#[cfg(all(not(feature = "wasm"), not(feature = "verifier")))]
+compile_error!(
+    "proof-of-exploit: none of wasm, nowasm & verifier are enabled, one of them must be enabled"
 );
-#[cfg(all(not(feature = "wasm"), not(feature = "nowasm")))]
-compile_error!("proof-of-exploit: none of wasm & nowasm are enabled, one of them must be enabled");
 
-#[cfg(not(feature = "wasm"))]
+#[cfg(feature = "nowasm")]
 pub mod cli;
-#[cfg(not(feature = "wasm"))]
+#[cfg(feature = "nowasm")]
+pub mod config;
+#[cfg(any(feature = "verifier", feature = "wasm"))]
 pub mod constants;
-#[cfg(not(feature = "wasm"))]
+#[cfg(feature = "nowasm")]
 pub mod env;
-#[cfg(not(feature = "wasm"))]
+#[cfg(feature = "verifier")]
 pub mod error;
-#[cfg(not(feature = "wasm"))]
+#[cfg(feature = "grpc")]
+pub mod remote;
+#[cfg(feature = "nowasm")]
+pub mod service;
+#[cfg(feature = "nowasm")]
+pub mod testing;
+#[cfg(feature = "verifier")]
 pub mod utils;
-#[cfg(not(feature = "wasm"))]
+#[cfg(feature = "nowasm")]
 pub mod verification;
-#[cfg(not(feature = "wasm"))]
+#[cfg(feature = "nowasm")]
 pub mod witness;
+/// The verifier only needs the circuit of the witness, to read the vk and recompute the instances.
+#[cfg(all(feature = "verifier", not(feature = "nowasm")))]
+pub mod witness {
+    pub mod circuit;
+}
 
 #[cfg(feature = "wasm")]
 pub mod wasm;
@@ -1,51 +1,481 @@
 #[cfg(not(feature = "dep_wasm"))]
 use proof_of_exploit::{
     cli::{
-        exploit_command, ProveArgs, PublishArgs, ScaffoldArgs, VerifyArgs, PROVE, PUBLISH,
-        SCAFFOLD, TEST, VERIFY,
+        exploit_command, AggregateArgs, AuditArgs, BenchArgs, CorpusArgs, GenVerifierArgs, LogArgs,
+        ProveArgs, PublishArgs, ReproArgs, ScaffoldArgs, ServeArgs, SetupArgs, VerifierCircuit,
+        VerifyArgs, VkArgs, AGGREGATE, AUDIT, BENCH, CORPUS, GEN_VERIFIER, PROVE, PUBLISH, REPRO,
+        SCAFFOLD, SERVE, SETUP, TEST, VERIFY, VK,
     },
     env::Env,
-    utils::{ipfs, scaffold},
+    error::Error,
+    service,
+    utils::{
+        audit::{self, AuditArchive, AuditBundle},
+        bench, corpus,
+        halo2::{
+            aggregation::{self, AggregatedProof, AggregationProver},
+            ceremony,
+            proof::Proof,
+            real_prover::RealProver,
+            real_verifier::{
+                self, EvmGasReport, RealVerifier, CONTRACT_SIZE_LIMIT, SOLIDITY_VERIFIER_CONTRACT,
+            },
+            split_verifier,
+            srs::{self, SRS},
+            vk_registry::VkRegistry,
+        },
+        ipfs, logger,
+        run_manifest::{RunInputs, RunManifest, RunOutcome},
+        scaffold, shutdown,
+    },
     verification::handle_verify,
-    witness::Witness,
+    witness::{repro::Repro, Witness},
+};
+#[cfg(feature = "grpc")]
+use proof_of_exploit::{
+    cli::{ReplayProveArgs, ServeGrpcArgs, SERVE_GRPC},
+    remote::{RemoteProver, RemoteProverService},
+};
+#[cfg(not(feature = "dep_wasm"))]
+use std::{
+    path::{Path, PathBuf},
+    time::{Instant, SystemTime, UNIX_EPOCH},
 };
 
 #[cfg(not(feature = "dep_wasm"))]
 #[tokio::main]
 async fn main() {
-    let env = Env::load();
+    if let Err(error) = run().await {
+        let code = error.code().as_str();
+        if tracing::dispatcher::has_been_set() {
+            tracing::error!("[{code}] {}", error.message());
+        } else {
+            println!("Error [{code}]: {}", error.message());
+        }
+        std::process::exit(1);
+    }
+}
 
+#[cfg(not(feature = "dep_wasm"))]
+async fn run() -> Result<(), Error> {
     let matches = exploit_command().get_matches();
     let subcommand_name = matches.subcommand_name();
     let arg_matches = subcommand_name.and_then(|name| matches.subcommand_matches(name));
 
+    let env = Env::load()?;
+    // only the commands which size a circuit or fork a chain take the profile of the config file
+    if matches!(
+        subcommand_name,
+        Some(TEST | PROVE | SETUP | GEN_VERIFIER | SERVE | BENCH | CORPUS)
+    ) {
+        env.check_config()?;
+    }
+
+    let log_args = LogArgs::from(arg_matches, &env)?;
+    logger::init(log_args.level, log_args.format, log_args.log_file)?;
+    shutdown::install()?;
+
     match subcommand_name {
         Some(TEST) => {
-            let r = ProveArgs::from(arg_matches, &env);
-            let w = Witness::gen(&r).await;
-            w.assert();
+            let r = ProveArgs::from(arg_matches, &env)?;
+            let srs_path = r.srs_path.clone();
+            let mut manifest = RunManifest::new(TEST);
+            let result = run_test(r, &srs_path, &mut manifest).await;
+            shutdown::untrack_run();
+            manifest.finish(&result);
+            manifest.write(&srs_path)?;
+            result?;
         }
         Some(PROVE) => {
-            let r = ProveArgs::from(arg_matches, &env);
-            let w = Witness::gen(&r).await;
-            w.prove(r).await;
+            let r = ProveArgs::from(arg_matches, &env)?;
+            let srs_path = r.srs_path.clone();
+            let mut manifest = RunManifest::new(PROVE);
+            let result = run_prove(r, &srs_path, &mut manifest).await;
+            shutdown::untrack_run();
+            manifest.set_keys(&srs_path);
+            manifest.finish(&result);
+            manifest.write(&srs_path)?;
+            result?;
         }
         Some(VERIFY) => {
-            let r = VerifyArgs::from(arg_matches, &env).await;
-            handle_verify(r).await;
+            let r = VerifyArgs::from(arg_matches, &env).await?;
+            handle_verify(r).await?;
         }
         Some(PUBLISH) => {
-            let r = PublishArgs::from(arg_matches);
-            let hash = ipfs::publish_file(r.file_path).await.unwrap();
+            let r = PublishArgs::from(arg_matches)?;
+            let hash = ipfs::publish_file(r.file_path).await?;
             println!("Published file to ipfs: {}", hash);
         }
         Some(SCAFFOLD) => {
-            let r = ScaffoldArgs::from(arg_matches);
-            scaffold::huff_template(r.project_name.as_str());
+            let r = ScaffoldArgs::from(arg_matches)?;
+            scaffold::huff_template(r.project_name.as_str())?;
             println!("\nGet started:\ncd {}", r.project_name);
         }
+        Some(AUDIT) => {
+            let r = AuditArgs::from(arg_matches, &env)?;
+            run_audit(r).await?;
+        }
+        Some(AGGREGATE) => {
+            let r = AggregateArgs::from(arg_matches, &env)?;
+            run_aggregate(r).await?;
+        }
+        Some(VK) => {
+            let r = VkArgs::from(arg_matches, &env)?;
+            run_vk(r)?;
+        }
+        Some(CORPUS) => {
+            let r = CorpusArgs::from(arg_matches, &env)?;
+            run_corpus(r).await?;
+        }
+        Some(REPRO) => {
+            let r = ReproArgs::from(arg_matches, &env)?;
+            run_repro(r).await?;
+        }
+        Some(SETUP) => {
+            let r = SetupArgs::from(arg_matches, &env)?;
+            run_setup(r).await?;
+        }
+        Some(GEN_VERIFIER) => {
+            let r = GenVerifierArgs::from(arg_matches, &env)?;
+            run_gen_verifier(r).await?;
+        }
+        Some(SERVE) => {
+            let r = ServeArgs::from(arg_matches, &env)?;
+            service::serve(r).await?;
+        }
+        Some(BENCH) => {
+            let r = BenchArgs::from(arg_matches, &env)?;
+            run_bench(r).await?;
+        }
+        #[cfg(feature = "grpc")]
+        Some(SERVE_GRPC) => {
+            env.check_config()?;
+            let r = ServeGrpcArgs::from(arg_matches, &env)?;
+            RemoteProverService::new(r.srs_path, r.prover_options)
+                .serve(r.listen)
+                .await?;
+        }
         _ => unreachable!("command not found"),
     }
+    Ok(())
+}
+
+#[cfg(not(feature = "dep_wasm"))]
+async fn run_test(r: ProveArgs, srs_path: &Path, manifest: &mut RunManifest) -> Result<(), Error> {
+    manifest.inputs = Some(RunInputs::from(&r)?);
+    shutdown::track_run(srs_path, manifest);
+    let start = Instant::now();
+    let w = Witness::gen(&r).await?;
+    manifest.record_timing("witness", start.elapsed());
+    manifest.set_circuit(w.circuits_params(), w.degree())?;
+    shutdown::track_run(srs_path, manifest);
+    w.row_report().print();
+
+    let start = Instant::now();
+    let result = w.assert();
+    manifest.record_timing("mock_prover", start.elapsed());
+    result
+}
+
+#[cfg(not(feature = "dep_wasm"))]
+async fn run_prove(r: ProveArgs, srs_path: &Path, manifest: &mut RunManifest) -> Result<(), Error> {
+    manifest.inputs = Some(RunInputs::from(&r)?);
+    shutdown::track_run(srs_path, manifest);
+    let start = Instant::now();
+    let w = Witness::gen(&r).await?;
+    manifest.record_timing("witness", start.elapsed());
+    manifest.set_circuit(w.circuits_params(), w.degree())?;
+    shutdown::track_run(srs_path, manifest);
+
+    let start = Instant::now();
+    let proof_path = w.prove(r).await?;
+    manifest.record_timing("prove", start.elapsed());
+    manifest.artifacts.push(proof_path);
+    Ok(())
+}
+
+#[cfg(not(feature = "dep_wasm"))]
+async fn run_audit(r: AuditArgs) -> Result<(), Error> {
+    if let Some(check_path) = r.check_path {
+        let archive = AuditArchive::read_from_file(&check_path)?;
+        archive.verify()?;
+        println!(
+            "Audit archive of run {} is signed by {:?}",
+            archive.bundle.manifest.id, archive.signer
+        );
+        return Ok(());
+    }
+
+    let manifest = match r.run_id {
+        Some(id) => RunManifest::read(&r.srs_path, &id)?,
+        None => RunManifest::list(&r.srs_path)?
+            .into_iter()
+            .rev()
+            .find(|run| run.command == PROVE && run.outcome == RunOutcome::Success)
+            .ok_or(Error::InvalidArgument(
+                "no successful prove run found, please provide --run".to_string(),
+            ))?,
+    };
+    let proof_path = r.proof_path.or(manifest
+        .artifacts
+        .iter()
+        .find(|path| path.extension().map_or(false, |ext| ext == "json"))
+        .cloned());
+    let proof = match proof_path {
+        Some(path) => Some(Proof::read_from_file(&path)?),
+        None => None,
+    };
+
+    let signer = r.signer.ok_or(Error::InvalidArgument(
+        "missing signer for audit archive".to_string(),
+    ))?;
+    let out_path = r
+        .out_path
+        .unwrap_or(audit::default_path(&r.srs_path, &manifest.id));
+    let archive = AuditBundle::from(&r.srs_path, manifest, proof.as_ref())?
+        .sign(&signer)
+        .await?;
+    archive.write_to_file(&out_path)?;
+    println!(
+        "Audit archive signed by {:?} written to {}",
+        archive.signer,
+        out_path.display()
+    );
+    Ok(())
+}
+
+#[cfg(not(feature = "dep_wasm"))]
+fn run_vk(r: VkArgs) -> Result<(), Error> {
+    let mut registry = VkRegistry::load(&r.srs_path)?;
+    if let Some(digest) = r.approve {
+        if registry.approve(digest) {
+            println!("Approved {digest:?}");
+        }
+    }
+    if let Some(digest) = r.revoke {
+        if !registry.revoke(digest) {
+            return Err(Error::InvalidArgument(format!(
+                "{digest:?} is not an approved verifying key"
+            )));
+        }
+        println!("Revoked {digest:?}");
+    }
+    registry.save(&r.srs_path)?;
+
+    if let Some(digest) = r.membership_proof {
+        let proof = registry
+            .membership_proof(&digest)
+            .ok_or(Error::InvalidArgument(format!(
+                "{digest:?} is not an approved verifying key"
+            )))?;
+        println!("{}", serde_json::to_string_pretty(&proof)?);
+        return Ok(());
+    }
+
+    println!("Approved verifying keys:");
+    for digest in &registry.approved {
+        println!("  {digest:?}");
+    }
+    println!("Root: {:?}", registry.root());
+    Ok(())
+}
+
+#[cfg(not(feature = "dep_wasm"))]
+async fn run_corpus(r: CorpusArgs) -> Result<(), Error> {
+    let report = corpus::run(&r.dir, &r.rpc, r.srs_path, r.prove).await?;
+    report.print();
+    if let Some(report_path) = r.report_path {
+        report.write_to_file(&report_path)?;
+        println!("Report written to {}", report_path.display());
+    }
+    if !report.passed() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "dep_wasm"))]
+async fn run_bench(r: BenchArgs) -> Result<(), Error> {
+    let report = bench::run(r.prove_args, r.cases, &r.degrees).await?;
+    report.print();
+    if let Some(report_path) = r.report_path {
+        report.write_to_file(&report_path)?;
+        println!("Report written to {}", report_path.display());
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "dep_wasm"))]
+async fn run_repro(r: ReproArgs) -> Result<(), Error> {
+    if let (Some(replay), Some(p)) = (&r.replay, &r.replay_prove) {
+        #[cfg(feature = "grpc")]
+        if let Some(remote) = &p.remote {
+            return run_remote_prove(replay, p, remote).await;
+        }
+        println!("Running RealProver on {}", replay.display());
+        let mut prover = RealProver::from_witness_file(replay, p.srs_path.clone()).await?;
+        prover.set_transcript(p.transcript);
+        println!("Generating proof...");
+        let proof = prover.prove()?;
+        println!("Writing proof to {}", p.out_path.display());
+        proof.write_to_file(&p.out_path)?;
+
+        // sanity check
+        prover.verifier().verify(&proof).await?;
+        println!("Success!");
+        return Ok(());
+    }
+
+    let witness = match (r.replay, r.prove_args) {
+        (Some(replay), _) => Repro::read_from_file(&replay)?.replay()?,
+        (None, Some(prove_args)) => Witness::gen(&prove_args).await?,
+        (None, None) => {
+            return Err(Error::InvalidArgument(
+                "nothing to export or replay".to_string(),
+            ))
+        }
+    };
+    witness.verify()?;
+    println!("Constraints are satisfied, the fixture does not reproduce a failure");
+    Ok(())
+}
+
+#[cfg(feature = "grpc")]
+async fn run_remote_prove(replay: &Path, p: &ReplayProveArgs, url: &str) -> Result<(), Error> {
+    let repro = Repro::read_from_file(replay)?;
+    println!("Submitting {} to {url}", replay.display());
+    let mut prover = RemoteProver::connect(url.to_string()).await?;
+    let proof = prover
+        .prove(&repro, p.transcript, |_, percent, message| {
+            println!("[{percent:>3}%] {message}")
+        })
+        .await?;
+    println!("Writing proof to {}", p.out_path.display());
+    proof.write_to_file(&p.out_path)?;
+    println!("Success!");
+    Ok(())
+}
+
+#[cfg(not(feature = "dep_wasm"))]
+async fn run_setup(r: SetupArgs) -> Result<(), Error> {
+    println!(
+        "Generating params and keys of degree {} for {:?}",
+        r.degree, r.circuits_params
+    );
+    SRS::setup(r.srs_path.clone(), r.degree, r.circuits_params).await?;
+    println!("Params and keys are in {}", r.srs_path.display());
+    Ok(())
+}
+
+#[cfg(not(feature = "dep_wasm"))]
+async fn run_gen_verifier(r: GenVerifierArgs) -> Result<(), Error> {
+    let aggregated_circuit = matches!(r.circuit, VerifierCircuit::Aggregation { .. });
+    let source = match r.circuit {
+        VerifierCircuit::Super(SetupArgs {
+            srs_path,
+            degree,
+            circuits_params,
+        }) => {
+            RealVerifier::gen_evm_verifier(srs_path, degree, circuits_params, r.pcs_scheme).await?
+        }
+        VerifierCircuit::Aggregation {
+            srs_path,
+            aggregated,
+        } => aggregation::generate_solidity(srs_path, &aggregated).await?,
+    };
+    std::fs::write(&r.out_path, source)?;
+    println!(
+        "Solidity verifier written to {}, it accepts proofs created with --transcript keccak",
+        r.out_path.display()
+    );
+    if r.split {
+        let parts = split_verifier::split_to_fit(&r.out_path)?;
+        if parts > 0 {
+            println!(
+                "The verifier exceeds the contract size limit of {CONTRACT_SIZE_LIMIT} bytes, it is split in {parts} parts which are deployed before the {SOLIDITY_VERIFIER_CONTRACT} dispatcher, given their addresses in order"
+            );
+        }
+    } else {
+        let size = split_verifier::code_sizes(&r.out_path)?
+            .get(SOLIDITY_VERIFIER_CONTRACT)
+            .copied()
+            .unwrap_or_default();
+        if size > CONTRACT_SIZE_LIMIT {
+            println!(
+                "The verifier is {size} bytes, above the contract size limit of {CONTRACT_SIZE_LIMIT} bytes, deploying it fails on chains with the limit unless it is split with --split"
+            );
+        }
+    }
+    if let Some(sample_path) = r.gas_report {
+        let verification_gas = match aggregated_circuit {
+            true => {
+                let aggregated = AggregatedProof::read_from_file(&sample_path)?;
+                aggregation::verify_on_evm(&r.out_path, &aggregated).await?
+            }
+            false => {
+                let proof = Proof::read_from_file(&sample_path)?;
+                real_verifier::verify_on_evm(&r.out_path, &proof).await?
+            }
+        };
+        println!(
+            "{}",
+            EvmGasReport::new(&r.out_path, verification_gas).await?
+        );
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "dep_wasm"))]
+async fn run_aggregate(r: AggregateArgs) -> Result<(), Error> {
+    if !r.verify_paths.is_empty() {
+        // the pairings of all the aggregated proofs are checked at once
+        let mut accumulators = vec![];
+        let mut degree = 0;
+        for verify_path in &r.verify_paths {
+            let aggregated = AggregatedProof::read_from_file(verify_path)?;
+            accumulators.push(aggregation::verify_deferred(r.srs_path.clone(), &aggregated).await?);
+            degree = aggregated.degree;
+            if let Some(evm_verifier) = &r.evm_verifier {
+                let gas = aggregation::verify_on_evm(evm_verifier, &aggregated).await?;
+                println!(
+                    "Solidity verifier accepts {}, verifying it costs {gas} gas",
+                    verify_path.display()
+                );
+            }
+        }
+        ceremony::provision(&r.srs_path, degree).await?;
+        let general_params = srs::load_general_params(r.srs_path, degree)?;
+        aggregation::decide_batch(&general_params, &accumulators)?;
+        println!(
+            "Verification of {} aggregated proofs success!",
+            accumulators.len()
+        );
+        return Ok(());
+    }
+
+    let num_proofs = r.proofs.len();
+    println!("Aggregating {num_proofs} proofs");
+    let mut prover =
+        AggregationProver::from(r.srs_path.clone(), r.proofs, r.degree, r.instance_encoding)
+            .await?;
+    prover.set_transcript(r.transcript);
+    println!("Generating aggregated proof...");
+    let aggregated = prover.prove()?;
+
+    let out_path = PathBuf::from(r.out_path.unwrap_or(format!(
+        "aggregated_proof_{}.json",
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+    )));
+    println!("Writing aggregated proof to {}", out_path.display());
+    aggregated.write_to_file(&out_path)?;
+
+    // sanity check
+    aggregation::verify(r.srs_path, &aggregated).await?;
+    println!("Success!");
+    Ok(())
 }
 
 #[cfg(feature = "dep_wasm")]
@@ -0,0 +1,247 @@
+//! gRPC proving of witness files (`proto/prover.proto`), for setups where the witness is
+//! generated on a machine with chain access and proven on a large machine elsewhere.
+//! `RemoteProverService` is the server of `serve-grpc`, it proves the submitted witnesses one
+//! after the other and streams the phase of each proof. `RemoteProver` is the client.
+//!
+//! The phases are the ones `RealProver` reports to its `ProverProgress`, `create_proof` does not
+//! report the progress of its own rounds so the proving phase is a single event.
+
+use crate::{
+    error::{Error, ErrorBody, ErrorCode},
+    utils::halo2::{
+        ceremony,
+        proof::{Proof, ProofTranscript},
+        real_prover::{ProverOptions, RealProver},
+    },
+    utils::progress::{ProofPhase, ProverProgress},
+    witness::repro::Repro,
+};
+use proto::{
+    prove_response::Event,
+    prover_client::ProverClient,
+    prover_server::{Prover, ProverServer},
+    Failure, Phase, Progress, ProveRequest, ProveResponse,
+};
+use std::{
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{Arc, Mutex, PoisonError},
+    thread,
+};
+use tokio::{runtime::Handle, sync::mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{
+    transport::{Channel, Server},
+    Request, Response, Status,
+};
+use tracing::info;
+
+pub mod proto {
+    tonic::include_proto!("pox.prover");
+}
+
+/// Limit of the messages of both sides, witness files of large blocks exceed the 4MB of tonic.
+const MAX_MESSAGE_SIZE: usize = 256 * 1024 * 1024;
+
+pub struct RemoteProverService {
+    srs_path: PathBuf,
+    options: ProverOptions,
+    /// Held by the job which is proving, a machine only has the memory for one proof at a time.
+    proving: Arc<Mutex<()>>,
+}
+
+impl RemoteProverService {
+    pub fn new(srs_path: PathBuf, options: ProverOptions) -> Self {
+        Self {
+            srs_path,
+            options,
+            proving: Arc::default(),
+        }
+    }
+
+    /// Serves on `listen` until the process exits.
+    pub async fn serve(self, listen: SocketAddr) -> Result<(), Error> {
+        info!("Serving remote proofs on {listen}");
+        Server::builder()
+            .add_service(
+                ProverServer::new(self)
+                    .max_decoding_message_size(MAX_MESSAGE_SIZE)
+                    .max_encoding_message_size(MAX_MESSAGE_SIZE),
+            )
+            .serve(listen)
+            .await?;
+        Ok(())
+    }
+}
+
+#[tonic::async_trait]
+impl Prover for RemoteProverService {
+    type ProveStream = ReceiverStream<Result<ProveResponse, Status>>;
+
+    async fn prove(
+        &self,
+        request: Request<ProveRequest>,
+    ) -> Result<Response<Self::ProveStream>, Status> {
+        let request = request.into_inner();
+        let repro: Repro = serde_json::from_slice(&request.witness)
+            .map_err(|err| Status::invalid_argument(format!("invalid witness file: {err}")))?;
+        let transcript = match request.transcript.as_str() {
+            "" => ProofTranscript::default(),
+            transcript => transcript
+                .parse()
+                .map_err(|err: Error| Status::invalid_argument(err.message()))?,
+        };
+
+        let (events, receiver) = mpsc::channel(16);
+        let job = Job {
+            srs_path: self.srs_path.clone(),
+            options: self.options,
+            runtime: Handle::current(),
+            events: Arc::new(Events(events)),
+        };
+        let proving = Arc::clone(&self.proving);
+        // keygen and proving block for minutes, they run outside of the runtime
+        thread::spawn(move || {
+            let event = match job.run(repro, transcript, &proving) {
+                Ok(proof) => serde_json::to_vec(&proof)
+                    .map(Event::Proof)
+                    .unwrap_or_else(|err| failure(&err.into())),
+                Err(error) => failure(&error),
+            };
+            job.events.send(event);
+        });
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+}
+
+struct Job {
+    srs_path: PathBuf,
+    options: ProverOptions,
+    runtime: Handle,
+    events: Arc<Events>,
+}
+
+impl Job {
+    fn run(
+        &self,
+        repro: Repro,
+        transcript: ProofTranscript,
+        proving: &Mutex<()>,
+    ) -> Result<Proof, Error> {
+        self.events.progress(
+            Phase::Queued,
+            0,
+            "Waiting for the proofs submitted before".to_string(),
+        );
+        let _proving = proving.lock().unwrap_or_else(PoisonError::into_inner);
+
+        self.events
+            .on_phase(ProofPhase::Witness, ProofPhase::Witness.percent());
+        let witness = repro.replay()?;
+        let degree = witness.degree();
+
+        self.runtime
+            .block_on(ceremony::provision(&self.srs_path, degree))?;
+        let mut prover = RealProver::from_with_progress(
+            witness.into_circuit(),
+            degree,
+            self.srs_path.clone(),
+            self.options,
+            self.events.clone(),
+        )?;
+        prover.set_transcript(transcript);
+        prover.prove()
+    }
+}
+
+/// Stream of the events of a job.
+struct Events(mpsc::Sender<Result<ProveResponse, Status>>);
+
+impl Events {
+    fn progress(&self, phase: Phase, percent: u8, message: String) {
+        self.send(Event::Progress(Progress {
+            phase: phase as i32,
+            percent: percent.into(),
+            message,
+        }));
+    }
+
+    /// A client which went away is not an error of the job, its events are dropped.
+    fn send(&self, event: Event) {
+        let _ = self
+            .0
+            .blocking_send(Ok(ProveResponse { event: Some(event) }));
+    }
+}
+
+impl ProverProgress for Events {
+    fn on_phase(&self, phase: ProofPhase, percent: u8) {
+        let grpc_phase = match phase {
+            ProofPhase::Trace | ProofPhase::State | ProofPhase::Witness => Phase::Witness,
+            ProofPhase::Keygen => Phase::Keygen,
+            ProofPhase::Proving => Phase::Proving,
+            ProofPhase::Done => Phase::Done,
+        };
+        self.progress(grpc_phase, percent, phase.description().to_string());
+    }
+}
+
+fn failure(error: &Error) -> Event {
+    Event::Failure(Failure {
+        code: error.code().as_str().to_string(),
+        message: error.message(),
+    })
+}
+
+/// Client of a `serve-grpc` server.
+pub struct RemoteProver {
+    client: ProverClient<Channel>,
+}
+
+impl RemoteProver {
+    pub async fn connect(url: String) -> Result<Self, Error> {
+        let client = ProverClient::connect(url)
+            .await?
+            .max_decoding_message_size(MAX_MESSAGE_SIZE)
+            .max_encoding_message_size(MAX_MESSAGE_SIZE);
+        Ok(Self { client })
+    }
+
+    /// Proves the witness on the server, `on_progress` is called with the phases of the proof,
+    /// their estimated percentage and message as the server streams them. A failure of the server
+    /// is returned with its error code.
+    pub async fn prove(
+        &mut self,
+        repro: &Repro,
+        transcript: ProofTranscript,
+        mut on_progress: impl FnMut(Phase, u32, &str),
+    ) -> Result<Proof, Error> {
+        let request = ProveRequest {
+            witness: serde_json::to_vec(repro)?,
+            transcript: serde_json::to_value(transcript)?
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+        };
+        let mut events = self.client.prove(request).await?.into_inner();
+        while let Some(response) = events.message().await? {
+            match response.event {
+                Some(Event::Progress(progress)) => {
+                    on_progress(progress.phase(), progress.percent, &progress.message)
+                }
+                Some(Event::Proof(proof)) => return Ok(serde_json::from_slice(&proof)?),
+                Some(Event::Failure(failure)) => {
+                    return Err(Error::RemoteError(ErrorBody {
+                        code: serde_json::from_value(serde_json::Value::String(failure.code))
+                            .unwrap_or(ErrorCode::Internal),
+                        message: failure.message,
+                    }))
+                }
+                None => {}
+            }
+        }
+        Err(Error::InternalError(
+            "the remote prover closed the stream without a proof",
+        ))
+    }
+}
@@ -0,0 +1,377 @@
+//! `serve`: a long-running prover which loads the params and keys of a circuit size once and
+//! proves the exploits its clients send, one after the other, at the degree of the keys. A
+//! client no longer waits for the proving key to load for every proof.
+//!
+//! `POST /prove` queues a `ProveRequest` and answers the id of its job, `GET /proof/{id}` answers
+//! the job, which carries the proof once it is done or the error body of a failure, and
+//! `DELETE /proof/{id}` cancels a queued job. `GET /jobs` lists the jobs. The jobs are kept in the
+//! `JobStore` of the srs dir, a restarted server proves the ones which were still queued.
+
+#[cfg(feature = "dev-circuits")]
+use crate::utils::halo2::sub_circuit::CircuitSelection;
+use crate::{
+    cli::{ProveArgs, ServeArgs},
+    error::Error,
+    utils::{
+        anvil::types::zkevm_types::Bytes,
+        halo2::{
+            aggregation::InstanceEncoding,
+            ceremony,
+            proof::{PcsScheme, Proof, ProofBackend, ProofTranscript},
+            real_prover::RealProver,
+            srs::SRS,
+        },
+        job_store::{JobRecord, JobStore},
+        progress::{ProofPhase, ProverProgress},
+        solidity,
+    },
+    witness::{circuit::ExploitCircuit, Witness},
+};
+use eth_types::U256;
+use halo2_proofs::halo2curves::bn256::Fr;
+use hyper::{
+    header::{HeaderValue, CONTENT_TYPE},
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    convert::Infallible,
+    io,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+use tokio::runtime::Runtime;
+use tracing::{error, info, warn};
+use zkevm_circuits::super_circuit::SuperCircuit;
+
+/// Exploit of a `POST /prove` request, the fields of `prove` which are not fixed by the server.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProveRequest {
+    /// Fork block, the latest block of the rpc when there is none.
+    pub block: Option<usize>,
+    /// Solidity source of the `Challenge` contract, compiled by the server so that the proof
+    /// carries its artifact.
+    pub challenge_source: String,
+    /// Runtime bytecode of the exploit.
+    pub exploit_bytecode: Bytes,
+    #[serde(default)]
+    pub exploit_balance: U256,
+    pub gas: Option<usize>,
+    #[serde(default)]
+    pub transcript: ProofTranscript,
+}
+
+/// Answer of `GET /proof/{id}`, the job with its proof once it is done.
+#[derive(Serialize)]
+struct JobResponse {
+    #[serde(flatten)]
+    job: JobRecord,
+    proof: Option<Proof>,
+}
+
+#[derive(Clone)]
+struct Jobs {
+    store: Arc<JobStore>,
+    queue: Arc<Mutex<mpsc::Sender<u64>>>,
+}
+
+impl Jobs {
+    fn push(&self, request: ProveRequest) -> Result<JobRecord, Error> {
+        let job = self.store.enqueue(request)?;
+        self.queue
+            .lock()
+            .unwrap()
+            .send(job.id)
+            .map_err(|_| Error::InternalError("the prover thread has stopped"))?;
+        Ok(job)
+    }
+}
+
+/// Loads the keys of `args.setup` and serves proofs on `args.listen` until the process exits.
+pub async fn serve(args: ServeArgs) -> Result<(), Error> {
+    let degree = args.setup.degree;
+    ceremony::provision(&args.setup.srs_path, degree).await?;
+    info!(
+        "Loading the params and keys of degree {degree} for {:?}",
+        args.setup.circuits_params
+    );
+    let circuit = SuperCircuit::<Fr>::without_witness(args.setup.circuits_params);
+    let srs = Arc::new(args.prover_options.install(|| {
+        SRS::load_with(
+            &circuit,
+            degree,
+            args.setup.srs_path.clone(),
+            true,
+            args.prover_options.artifact_checks,
+        )
+    })?);
+
+    let store = Arc::new(JobStore::open(&args.setup.srs_path)?);
+    let (sender, receiver) = mpsc::channel();
+    let pending = store.pending()?;
+    if !pending.is_empty() {
+        info!("Resuming {} queued jobs", pending.len());
+    }
+    for id in pending {
+        // the receiver is alive until the prover thread starts
+        sender.send(id).unwrap();
+    }
+    let jobs = Jobs {
+        store,
+        queue: Arc::new(Mutex::new(sender)),
+    };
+    let listen = args.listen;
+    let worker_jobs = jobs.clone();
+    // the witness generation drives anvil, which is not Send, so the jobs run on a thread of
+    // their own instead of the tasks of the server
+    thread::spawn(move || work(&args, &srs, &worker_jobs, receiver));
+
+    let make_service = make_service_fn(move |_| {
+        let jobs = jobs.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let jobs = jobs.clone();
+                async move { Ok::<_, Infallible>(handle(&jobs, request).await) }
+            }))
+        }
+    });
+    let server = Server::try_bind(&listen)
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?
+        .serve(make_service);
+    info!("Serving proofs on http://{listen}");
+    server
+        .await
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+    Ok(())
+}
+
+fn work(args: &ServeArgs, srs: &Arc<SRS>, jobs: &Jobs, receiver: mpsc::Receiver<u64>) {
+    let runtime = match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime,
+        Err(err) => {
+            error!("Cannot start the prover thread: {err}");
+            return;
+        }
+    };
+    for id in receiver {
+        if let Err(error) = run_job(args, srs, &jobs.store, &runtime, id) {
+            warn!("Cannot record job {id}: {}", error.message());
+        }
+    }
+}
+
+/// Records the phase of a job in the store, for `GET /proof/{id}`.
+struct JobProgress {
+    store: Arc<JobStore>,
+    id: u64,
+}
+
+impl ProverProgress for JobProgress {
+    fn on_phase(&self, phase: ProofPhase, percent: u8) {
+        if let Err(error) = self.store.set_phase(self.id, phase, percent) {
+            warn!(
+                "cannot record the phase of job {}: {}",
+                self.id,
+                error.message()
+            );
+        }
+    }
+}
+
+fn run_job(
+    args: &ServeArgs,
+    srs: &Arc<SRS>,
+    store: &Arc<JobStore>,
+    runtime: &Runtime,
+    id: u64,
+) -> Result<(), Error> {
+    // a job cancelled while it was queued is skipped
+    if !store.start(id)? {
+        return Ok(());
+    }
+    let Some(job) = store.get(id)? else {
+        return Ok(());
+    };
+    info!("Proving job {id}");
+    let progress = Arc::new(JobProgress {
+        store: Arc::clone(store),
+        id,
+    });
+    let result = runtime.block_on(prove(args, srs, job.request, progress));
+    if let Err(error) = &result {
+        warn!("Job {id} failed: {}", error.message());
+    }
+    store.finish(id, result)?;
+    Ok(())
+}
+
+async fn prove(
+    args: &ServeArgs,
+    srs: &Arc<SRS>,
+    request: ProveRequest,
+    progress: Arc<dyn ProverProgress>,
+) -> Result<Proof, Error> {
+    let prove_args = prove_args(args, request)?;
+    let witness = Witness::gen_with_progress(&prove_args, progress.clone()).await?;
+    let degree = args.setup.degree;
+    if witness.degree() > degree {
+        return Err(Error::WitnessOverflow(format!(
+            "the exploit block needs degree {} but the server proves at degree {degree}",
+            witness.degree()
+        )));
+    }
+    let mut prover = RealProver::from_srs(
+        witness.into_circuit(),
+        degree,
+        args.setup.srs_path.clone(),
+        Arc::clone(srs),
+        args.prover_options,
+    );
+    prover.set_transcript(prove_args.transcript);
+    prover.set_pcs_scheme(prove_args.pcs_scheme);
+    prover.set_progress(progress);
+    let mut proof = prover.prove()?;
+    proof.challenge_artifact = Some(prove_args.challenge_artifact);
+    Ok(proof)
+}
+
+/// Arguments of `prove` for a request, with the rpc, srs dir and circuit params of the server.
+fn prove_args(args: &ServeArgs, request: ProveRequest) -> Result<ProveArgs, Error> {
+    let circuits_params = args.setup.circuits_params;
+    Ok(ProveArgs {
+        rpc: args.rpc.clone(),
+        geth_rpc: args.geth_rpc.clone(),
+        rpc_timeout: args.rpc_timeout,
+        block: request.block,
+        challenge_artifact: solidity::Artifact::from_source_code(
+            "Challenge.sol".to_string(),
+            request.challenge_source,
+        )?,
+        exploit_bytecode: request.exploit_bytecode,
+        exploit_init_code: None,
+        exploit_balance: request.exploit_balance,
+        min_balance_gain: None,
+        gas: request.gas,
+        tx_type: Default::default(),
+        allow_revert: false,
+        block_timestamp: None,
+        degree: None,
+        srs_path: args.setup.srs_path.clone(),
+        proof_out_path: None,
+        ipfs: false,
+        regenerate_keys: false,
+        force: false,
+        skip_sanity_check: false,
+        skip_memory_check: true,
+        diff_trace: false,
+        trace_out: None,
+        repro_out: None,
+        imported_trace: None,
+        state_snapshot: None,
+        hardfork: None,
+        transcript: request.transcript,
+        wrap: false,
+        instance_encoding: InstanceEncoding::Plain,
+        backend: ProofBackend::Kzg,
+        pcs_scheme: PcsScheme::Shplonk,
+        #[cfg(feature = "dev-circuits")]
+        circuit: CircuitSelection::Super,
+        prover_options: args.prover_options,
+        params_margin: None,
+        max_rws: circuits_params.max_rws,
+        max_copy_rows: circuits_params.max_copy_rows,
+        max_exp_steps: circuits_params.max_exp_steps,
+        max_bytecode: circuits_params.max_bytecode,
+        max_evm_rows: circuits_params.max_evm_rows,
+        max_keccak_rows: circuits_params.max_keccak_rows,
+    })
+}
+
+async fn handle(jobs: &Jobs, request: Request<Body>) -> Response<Body> {
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    if method == Method::POST && path == "/prove" {
+        let body = match hyper::body::to_bytes(request.into_body()).await {
+            Ok(body) => body,
+            Err(err) => {
+                let error = Error::InvalidArgument(format!("cannot read the request: {err}"));
+                return error_response(StatusCode::BAD_REQUEST, &error);
+            }
+        };
+        let prove_request = match serde_json::from_slice::<ProveRequest>(&body) {
+            Ok(prove_request) => prove_request,
+            Err(err) => {
+                let error = Error::InvalidArgument(format!("invalid prove request: {err}"));
+                return error_response(StatusCode::BAD_REQUEST, &error);
+            }
+        };
+        return match jobs.push(prove_request) {
+            Ok(job) => json_response(
+                StatusCode::ACCEPTED,
+                &serde_json::json!({ "id": job.id, "inputs_hash": job.inputs_hash }),
+            ),
+            Err(error) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &error),
+        };
+    }
+    if method == Method::GET && path == "/jobs" {
+        return match jobs.store.list() {
+            Ok(list) => json_response(StatusCode::OK, &list),
+            Err(error) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &error),
+        };
+    }
+    if method == Method::GET || method == Method::DELETE {
+        if let Some(id) = path.strip_prefix("/proof/") {
+            let id = id.parse().ok();
+            return match id.map(|id| handle_job(&jobs.store, &method, id)) {
+                Some(Ok(response)) => response,
+                Some(Err(error)) => error_response(StatusCode::INTERNAL_SERVER_ERROR, &error),
+                None => error_response(
+                    StatusCode::NOT_FOUND,
+                    &Error::InvalidArgument(format!("no endpoint {method} {path}")),
+                ),
+            };
+        }
+    }
+    error_response(
+        StatusCode::NOT_FOUND,
+        &Error::InvalidArgument(format!("no endpoint {method} {path}")),
+    )
+}
+
+fn handle_job(store: &JobStore, method: &Method, id: u64) -> Result<Response<Body>, Error> {
+    let Some(job) = store.get(id)? else {
+        return Ok(error_response(
+            StatusCode::NOT_FOUND,
+            &Error::InvalidArgument(format!("no job {id}")),
+        ));
+    };
+    if method == Method::DELETE {
+        return Ok(match store.cancel(id) {
+            Ok(job) => json_response(StatusCode::OK, &job),
+            Err(error) => error_response(StatusCode::CONFLICT, &error),
+        });
+    }
+    let proof = job
+        .proof_path
+        .as_ref()
+        .map(Proof::read_from_file)
+        .transpose()?;
+    Ok(json_response(StatusCode::OK, &JobResponse { job, proof }))
+}
+
+fn error_response(status: StatusCode, error: &Error) -> Response<Body> {
+    json_response(status, &error.to_body())
+}
+
+fn json_response(status: StatusCode, body: &impl Serialize) -> Response<Body> {
+    let mut response = Response::new(Body::from(serde_json::to_vec(body).unwrap_or_default()));
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    response
+}
@@ -0,0 +1,163 @@
+//! Harness for end-to-end tests of challenges. A test forks the chain in anvil, deploys the
+//! challenge and the exploit, mines the exploit transaction and checks the witness with the
+//! MockProver, the same as `exploit test`:
+//!
+//! ```ignore
+//! let mut test = ExploitTest::from(rpc, "src/Challenge.sol".into(), "src/Exploit.huff".into())?;
+//! test.set_block(4405541);
+//! test.run().await?;
+//! ```
+
+use crate::{
+    cli::ProveArgs,
+    constants::{
+        DEFAULT_MAX_BYTECODE, DEFAULT_MAX_COPY_ROWS, DEFAULT_MAX_EVM_ROWS, DEFAULT_MAX_EXP_STEPS,
+        DEFAULT_MAX_KECCAK_ROWS, DEFAULT_MAX_RWS,
+    },
+    error::Error,
+    utils::{
+        anvil::tx_builder::TxType,
+        solidity::{compile_exploit, Artifact},
+        watchdog::DEFAULT_RPC_TIMEOUT,
+    },
+    witness::Witness,
+};
+use bus_mapping::circuit_input_builder::FixedCParams;
+use eth_types::{Bytes, U256};
+use std::{env, path::PathBuf};
+
+pub struct ExploitTest {
+    args: ProveArgs,
+}
+
+impl ExploitTest {
+    /// `challenge` and `exploit` take the same inputs as `--challenge` and `--exploit`, a source
+    /// file path or hex bytecode.
+    pub fn from(rpc: String, challenge: String, exploit: String) -> Result<Self, Error> {
+        Ok(Self::from_compiled(
+            rpc,
+            Artifact::from_source(challenge)?,
+            compile_exploit(exploit)?,
+        ))
+    }
+
+    pub fn from_compiled(
+        rpc: String,
+        challenge_artifact: Artifact,
+        exploit_bytecode: Bytes,
+    ) -> Self {
+        Self {
+            args: ProveArgs {
+                rpc,
+                geth_rpc: None,
+                rpc_timeout: DEFAULT_RPC_TIMEOUT,
+                block: None,
+                challenge_artifact,
+                exploit_bytecode,
+                exploit_init_code: None,
+                exploit_balance: U256::zero(),
+                min_balance_gain: None,
+                gas: None,
+                tx_type: TxType::default(),
+                allow_revert: false,
+                block_timestamp: None,
+                degree: None,
+                // nothing is read from the srs dir when only the MockProver runs
+                srs_path: env::temp_dir(),
+                proof_out_path: None,
+                ipfs: false,
+                regenerate_keys: false,
+                force: false,
+                skip_sanity_check: false,
+                skip_memory_check: false,
+                diff_trace: false,
+                trace_out: None,
+                repro_out: None,
+                imported_trace: None,
+                state_snapshot: None,
+                hardfork: None,
+                transcript: Default::default(),
+                wrap: false,
+                instance_encoding: Default::default(),
+                backend: Default::default(),
+                pcs_scheme: Default::default(),
+                #[cfg(feature = "dev-circuits")]
+                circuit: Default::default(),
+                prover_options: Default::default(),
+                params_margin: None,
+                max_rws: DEFAULT_MAX_RWS,
+                max_copy_rows: DEFAULT_MAX_COPY_ROWS,
+                max_exp_steps: DEFAULT_MAX_EXP_STEPS,
+                max_bytecode: DEFAULT_MAX_BYTECODE,
+                max_evm_rows: DEFAULT_MAX_EVM_ROWS,
+                max_keccak_rows: DEFAULT_MAX_KECCAK_ROWS,
+            },
+        }
+    }
+
+    /// Block to fork from, the latest block by default.
+    pub fn set_block(&mut self, block: usize) {
+        self.args.block = Some(block);
+    }
+
+    pub fn set_exploit_balance(&mut self, exploit_balance: U256) {
+        self.args.exploit_balance = exploit_balance;
+    }
+
+    /// Fails the test before the witness is generated unless the exploit gains at least
+    /// `min_balance_gain`.
+    pub fn set_min_balance_gain(&mut self, min_balance_gain: U256) {
+        self.args.min_balance_gain = Some(min_balance_gain);
+    }
+
+    pub fn set_block_timestamp(&mut self, timestamp: u64) {
+        self.args.block_timestamp = Some(timestamp);
+    }
+
+    pub fn set_gas(&mut self, gas: usize) {
+        self.args.gas = Some(gas);
+    }
+
+    pub fn set_tx_type(&mut self, tx_type: TxType) {
+        self.args.tx_type = tx_type;
+    }
+
+    /// Runs the exploit through the circuits even if it reverts.
+    pub fn set_allow_revert(&mut self, allow_revert: bool) {
+        self.args.allow_revert = allow_revert;
+    }
+
+    pub fn set_circuit_params(&mut self, params: FixedCParams) {
+        self.args.max_rws = params.max_rws;
+        self.args.max_copy_rows = params.max_copy_rows;
+        self.args.max_exp_steps = params.max_exp_steps;
+        self.args.max_bytecode = params.max_bytecode;
+        self.args.max_evm_rows = params.max_evm_rows;
+        self.args.max_keccak_rows = params.max_keccak_rows;
+    }
+
+    pub fn args(&self) -> &ProveArgs {
+        &self.args
+    }
+
+    /// The arguments the witness is generated with, for the settings which have no setter.
+    pub fn args_mut(&mut self) -> &mut ProveArgs {
+        &mut self.args
+    }
+
+    pub async fn witness(&self) -> Result<Witness, Error> {
+        Witness::gen(&self.args).await
+    }
+
+    /// Generates the witness and runs the MockProver on it, which panics with a report of the
+    /// unsatisfied constraints if the circuit is not satisfied.
+    pub async fn run(&self) -> Result<(), Error> {
+        self.witness().await?.assert()
+    }
+
+    /// Generates the witness and the proof with the settings of `args`, and returns the path the
+    /// proof was written to.
+    pub async fn prove(self) -> Result<PathBuf, Error> {
+        self.witness().await?.prove(self.args).await
+    }
+}
@@ -0,0 +1,133 @@
+//! Conversions between the alloy-rs primitives and the types of the circuit input builder, for
+//! integrators whose stack moved from ethers-rs to alloy. Enabled by the `alloy` feature.
+
+use super::anvil::{conversion::Conversion, types::zkevm_types};
+use crate::{error::Error, witness::inputs_builder::BuilderClient};
+use alloy_primitives::{Address, Bytes, B256, U256};
+use bus_mapping::circuit_input_builder::PoxInputs;
+use eth_types::{keccak256, Fr};
+use zkevm_circuits::witness::Block;
+
+// Conversion from zkevm types to alloy types, the reverse of `Conversion` for alloy types
+pub trait AlloyConversion<T> {
+    fn to_alloy_type(&self) -> T;
+}
+
+impl Conversion<zkevm_types::Address> for Address {
+    fn to_zkevm_type(&self) -> zkevm_types::Address {
+        zkevm_types::Address::from_slice(self.as_slice())
+    }
+}
+
+impl AlloyConversion<Address> for zkevm_types::Address {
+    fn to_alloy_type(&self) -> Address {
+        Address::from_slice(self.as_bytes())
+    }
+}
+
+impl Conversion<zkevm_types::H256> for B256 {
+    fn to_zkevm_type(&self) -> zkevm_types::H256 {
+        zkevm_types::H256::from_slice(self.as_slice())
+    }
+}
+
+impl AlloyConversion<B256> for zkevm_types::H256 {
+    fn to_alloy_type(&self) -> B256 {
+        B256::from_slice(self.as_bytes())
+    }
+}
+
+impl Conversion<zkevm_types::U256> for U256 {
+    fn to_zkevm_type(&self) -> zkevm_types::U256 {
+        zkevm_types::U256::from_big_endian(&self.to_be_bytes::<32>())
+    }
+}
+
+impl AlloyConversion<U256> for zkevm_types::U256 {
+    fn to_alloy_type(&self) -> U256 {
+        let mut bytes = [0u8; 32];
+        self.to_big_endian(&mut bytes);
+        U256::from_be_bytes(bytes)
+    }
+}
+
+impl Conversion<zkevm_types::Bytes> for Bytes {
+    fn to_zkevm_type(&self) -> zkevm_types::Bytes {
+        zkevm_types::Bytes::from(self.to_vec())
+    }
+}
+
+impl AlloyConversion<Bytes> for zkevm_types::Bytes {
+    fn to_alloy_type(&self) -> Bytes {
+        Bytes::from(self.to_vec())
+    }
+}
+
+/// The inputs of the proof of exploit in alloy types, the codehashes are computed from the
+/// bytecode when converting to `PoxInputs`.
+#[derive(Clone, Debug, Default)]
+pub struct AlloyPoxInputs {
+    pub challenge_bytecode: Bytes,
+    pub exploit_bytecode: Bytes,
+    pub exploit_balance: U256,
+    pub exploit_balance_before: U256,
+}
+
+impl Conversion<PoxInputs> for AlloyPoxInputs {
+    fn to_zkevm_type(&self) -> PoxInputs {
+        PoxInputs {
+            challenge_codehash: keccak256(&self.challenge_bytecode).into(),
+            challenge_bytecode: self.challenge_bytecode.to_zkevm_type(),
+            exploit_codehash: keccak256(&self.exploit_bytecode).into(),
+            exploit_bytecode: self.exploit_bytecode.to_zkevm_type(),
+            exploit_balance: self.exploit_balance.to_zkevm_type(),
+            exploit_balance_before: self.exploit_balance_before.to_zkevm_type(),
+        }
+    }
+}
+
+impl AlloyConversion<AlloyPoxInputs> for PoxInputs {
+    fn to_alloy_type(&self) -> AlloyPoxInputs {
+        AlloyPoxInputs {
+            challenge_bytecode: self.challenge_bytecode.to_alloy_type(),
+            exploit_bytecode: self.exploit_bytecode.to_alloy_type(),
+            exploit_balance: self.exploit_balance.to_alloy_type(),
+            exploit_balance_before: self.exploit_balance_before.to_alloy_type(),
+        }
+    }
+}
+
+/// Witness generation taking alloy types, see the methods of the same name without `_alloy`.
+impl BuilderClient {
+    pub async fn gen_witness_alloy(
+        &self,
+        block_number: usize,
+        pox_inputs: AlloyPoxInputs,
+        use_geth_trace: bool,
+    ) -> Result<Block<Fr>, Error> {
+        self.gen_witness(block_number, pox_inputs.to_zkevm_type(), use_geth_trace)
+            .await
+    }
+
+    pub async fn gen_witness_for_tx_alloy(
+        &self,
+        tx_hash: B256,
+        pox_inputs: AlloyPoxInputs,
+    ) -> Result<Block<Fr>, Error> {
+        self.gen_witness_for_tx(tx_hash.to_zkevm_type(), pox_inputs.to_zkevm_type())
+            .await
+    }
+
+    pub async fn gen_witness_for_txs_alloy(
+        &self,
+        tx_hashes: &[B256],
+        pox_inputs: AlloyPoxInputs,
+    ) -> Result<Block<Fr>, Error> {
+        let tx_hashes = tx_hashes
+            .iter()
+            .map(|tx_hash| tx_hash.to_zkevm_type())
+            .collect::<Vec<_>>();
+        self.gen_witness_for_txs(&tx_hashes, pox_inputs.to_zkevm_type())
+            .await
+    }
+}
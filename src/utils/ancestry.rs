@@ -0,0 +1,110 @@
+use super::{
+    halo2::proof::Proof,
+    watchdog::{watch, DEFAULT_RPC_TIMEOUT},
+};
+use crate::error::Error;
+use ethers::{
+    providers::{Http, Middleware, Provider},
+    types::{Block, BlockId, H256},
+    utils::{keccak256, rlp::RlpStream},
+};
+
+/// Furthest ancestor which is looked up, the anchor is usually one of the last 256 blocks whose
+/// hash the `BLOCKHASH` opcode returns.
+pub const MAX_ANCESTRY_DEPTH: u64 = 8192;
+
+/// Checks natively that the block the proof forked from is an ancestor of the block `anchor`.
+/// Headers are fetched from `rpc` but every one of them is hashed again, so the RPC is not
+/// trusted: the chain of parent hashes leads from the anchor to a header whose state root is the
+/// state root in the public inputs.
+pub async fn check_ancestry(rpc: &str, proof: &Proof, anchor: H256) -> Result<u64, Error> {
+    let provider = Provider::<Http>::try_from(rpc)
+        .map_err(|_| Error::InvalidArgument(format!("invalid rpc url {rpc}")))?;
+    // the proof is for a block mined on top of the block it forked from
+    let fork_number = (proof.public_data.block_constants.number - 1).as_u64();
+    // the public data uses the types of the zkevm-circuits fork, compared as bytes
+    let prev_state_root = H256::from_slice(proof.public_data.prev_state_root.as_bytes());
+
+    let mut hash = anchor;
+    for depth in 0..=MAX_ANCESTRY_DEPTH {
+        let header = watch(
+            "eth_getBlockByHash",
+            hash,
+            DEFAULT_RPC_TIMEOUT,
+            provider.get_block(BlockId::Hash(hash)),
+        )
+        .await?
+        .ok_or(Error::InvalidArgument(format!("block {hash:?} not found")))?;
+        if header_hash(&header) != hash {
+            return Err(Error::InvalidArgument(format!(
+                "rpc returned a header which does not hash to {hash:?}"
+            )));
+        }
+
+        let number = header
+            .number
+            .ok_or(Error::InternalError("header is missing its number"))?;
+        if number.as_u64() < fork_number {
+            break;
+        }
+        if number.as_u64() == fork_number {
+            if header.state_root != prev_state_root {
+                return Err(Error::InvalidArgument(format!(
+                    "block {number} has state root {:?} but the proof is for {prev_state_root:?}",
+                    header.state_root
+                )));
+            }
+            return Ok(depth);
+        }
+        hash = header.parent_hash;
+    }
+    Err(Error::InvalidArgument(format!(
+        "block {fork_number} is not among the {MAX_ANCESTRY_DEPTH} ancestors of {anchor:?}"
+    )))
+}
+
+/// Hash of a header, the keccak of its RLP encoding with the fields of every fork so far.
+pub fn header_hash<T>(header: &Block<T>) -> H256 {
+    let optional_fields = [
+        header.base_fee_per_gas.is_some(),
+        header.withdrawals_root.is_some(),
+        header.blob_gas_used.is_some(),
+        header.excess_blob_gas.is_some(),
+        header.parent_beacon_block_root.is_some(),
+    ];
+    let num_optional = optional_fields.iter().filter(|present| **present).count();
+
+    let mut stream = RlpStream::new_list(15 + num_optional);
+    stream
+        .append(&header.parent_hash)
+        .append(&header.uncles_hash)
+        .append(&header.author.unwrap_or_default())
+        .append(&header.state_root)
+        .append(&header.transactions_root)
+        .append(&header.receipts_root)
+        .append(&header.logs_bloom.unwrap_or_default())
+        .append(&header.difficulty)
+        .append(&header.number.unwrap_or_default())
+        .append(&header.gas_limit)
+        .append(&header.gas_used)
+        .append(&header.timestamp)
+        .append(&header.extra_data.as_ref())
+        .append(&header.mix_hash.unwrap_or_default())
+        .append(&header.nonce.unwrap_or_default());
+    if let Some(base_fee_per_gas) = header.base_fee_per_gas {
+        stream.append(&base_fee_per_gas);
+    }
+    if let Some(withdrawals_root) = header.withdrawals_root {
+        stream.append(&withdrawals_root);
+    }
+    if let Some(blob_gas_used) = header.blob_gas_used {
+        stream.append(&blob_gas_used);
+    }
+    if let Some(excess_blob_gas) = header.excess_blob_gas {
+        stream.append(&excess_blob_gas);
+    }
+    if let Some(parent_beacon_block_root) = header.parent_beacon_block_root {
+        stream.append(&parent_beacon_block_root);
+    }
+    H256::from(keccak256(stream.out()))
+}
@@ -1,47 +1,208 @@
 use super::{
-    conversion::{convert_option, ConversionReverse},
+    conversion::{convert_option, to_zkevm_trace, ConversionReverse},
     types::{anvil_types, zkevm_types},
 };
-use crate::{env::Env, error::Error};
-use anvil::{eth::EthApi, spawn, NodeConfig};
-use ethers::utils::parse_ether;
-use std::{thread::sleep, time::Duration};
+use crate::{
+    config::{AnvilConfig, Config},
+    env::Env,
+    error::Error,
+    utils::{
+        hardfork::Hardfork,
+        watchdog::{watch, DEFAULT_RPC_TIMEOUT},
+    },
+};
+use anvil::{
+    eth::{error::BlockchainError, EthApi},
+    spawn, NodeConfig, NodeHandle,
+};
+use ethers::{
+    providers::{Http, Provider},
+    utils::parse_ether,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fmt::Debug, future::Future, time::Duration};
 
 use super::conversion::Conversion;
+/// Anvil node, either running in this process or attached to over JSON-RPC.
 pub struct AnvilClient {
-    eth_api: EthApi,
+    node: Node,
+    timeout: Duration,
+}
+
+enum Node {
+    /// Serves on a free port so that several nodes can run side by side, and is shut down when the
+    /// client is dropped.
+    Spawned { eth_api: EthApi, handle: NodeHandle },
+    /// Started elsewhere and left running, its chain id does not change.
+    Attached {
+        provider: Provider<Http>,
+        url: String,
+        chain_id: anvil_types::U64,
+    },
 }
 
 #[allow(dead_code)]
 impl AnvilClient {
-    pub async fn default() -> Self {
-        let env = Env::load();
-        Self::setup(env.eth_rpc_url, env.fork_block_number).await
+    pub async fn default() -> Result<Self, Error> {
+        let env = Env::load()?;
+        env.check_config()?;
+        Self::from_config(&Config::from_env(&env).anvil).await
+    }
+
+    pub async fn setup(
+        eth_rpc_url: Option<String>,
+        fork_block_number: Option<usize>,
+        hardfork: Hardfork,
+    ) -> Result<Self, Error> {
+        Self::spawn(&AnvilConfig {
+            rpc: eth_rpc_url,
+            block: fork_block_number,
+            hardfork: Some(hardfork),
+            ..Default::default()
+        })
+        .await
+    }
+
+    /// Node of the anvil settings of a parameter profile. With `attach` it is the anvil node
+    /// running at that url, otherwise one is started with the fork, chain id, block gas limit and
+    /// hardfork of the settings. The geth rpc is not used by anvil.
+    pub async fn from_config(config: &AnvilConfig) -> Result<Self, Error> {
+        let mut client = match &config.attach {
+            Some(url) => Self::attach(url.clone()).await?,
+            None => Self::spawn(config).await?,
+        };
+        if let Some(rpc_timeout) = config.rpc_timeout {
+            client.set_timeout(Duration::from_secs(rpc_timeout as u64));
+        }
+        Ok(client)
+    }
+
+    /// Client of the anvil node already running at `url`, which keeps the fork, chain id, block gas
+    /// limit and hardfork it was started with and is not stopped with the client. Fails unless the
+    /// node is an anvil, the state is overridden with its `anvil_*` methods.
+    pub async fn attach(url: String) -> Result<Self, Error> {
+        let provider = Provider::<Http>::try_from(&url)
+            .map_err(|_| Error::InvalidArgument(format!("invalid anvil url {url}")))?;
+        let version: String = watch(
+            "web3_clientVersion",
+            &url,
+            DEFAULT_RPC_TIMEOUT,
+            provider.request("web3_clientVersion", ()),
+        )
+        .await?;
+        if !version.starts_with("anvil") {
+            return Err(Error::InvalidArgument(format!(
+                "{url} is a {version} node, only an anvil node can be attached to"
+            )));
+        }
+        let chain_id = watch(
+            "eth_chainId",
+            &url,
+            DEFAULT_RPC_TIMEOUT,
+            provider.request("eth_chainId", ()),
+        )
+        .await?;
+        Ok(Self {
+            node: Node::Attached {
+                provider,
+                url,
+                chain_id,
+            },
+            timeout: DEFAULT_RPC_TIMEOUT,
+        })
     }
 
-    pub async fn setup(eth_rpc_url: Option<String>, fork_block_number: Option<usize>) -> Self {
+    /// Starts a node, after checking that the fork rpc answers, since anvil panics when it cannot
+    /// fork.
+    async fn spawn(config: &AnvilConfig) -> Result<Self, Error> {
+        if let Some(url) = &config.rpc {
+            let provider = Provider::<Http>::try_from(url)
+                .map_err(|_| Error::InvalidArgument(format!("invalid fork rpc url {url}")))?;
+            let _: anvil_types::U64 = watch(
+                "eth_chainId",
+                url,
+                DEFAULT_RPC_TIMEOUT,
+                provider.request("eth_chainId", ()),
+            )
+            .await?;
+        }
+        let hardfork = config.hardfork.unwrap_or_default();
         let node_config = NodeConfig::default()
-            .with_eth_rpc_url(eth_rpc_url)
-            .with_fork_block_number(fork_block_number.map(|v| v as u64))
-            .with_port(8548)
+            .with_eth_rpc_url(config.rpc.clone())
+            .with_fork_block_number(config.block.map(|v| v as u64))
+            .with_hardfork(Some(hardfork.anvil_hardfork()))
+            .with_chain_id(config.chain_id)
+            .with_gas_limit(config.block_gas_limit)
+            .with_port(0)
             .silent()
             .with_steps_tracing(true)
             .with_gas_price(Some(0))
             .with_base_fee(Some(0));
 
-        let (eth_api, _) = spawn(node_config).await;
-        Self { eth_api }
+        let (eth_api, handle) = spawn(node_config).await;
+        Ok(Self {
+            node: Node::Spawned { eth_api, handle },
+            timeout: DEFAULT_RPC_TIMEOUT,
+        })
     }
 
-    pub fn eth_chain_id(&self) -> Result<Option<zkevm_types::Word>, Error> {
-        match self.eth_api.eth_chain_id()? {
-            Some(chain_id) => Ok(Some(zkevm_types::Word::from(chain_id.as_usize()))),
-            None => Ok(None),
+    /// Http endpoint of the node, for tools which talk to it over JSON-RPC.
+    pub fn endpoint(&self) -> String {
+        match &self.node {
+            Node::Spawned { handle, .. } => handle.http_endpoint(),
+            Node::Attached { url, .. } => url.clone(),
+        }
+    }
+
+    /// Sets the time after which a pending call to anvil fails with `Error::Timeout`.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Answer to `method`, by `spawned` from a node of this process and by a JSON-RPC request with
+    /// `params` from an attached one.
+    async fn request<'a, T, P, F>(
+        &'a self,
+        method: &str,
+        params: P,
+        spawned: impl FnOnce(&'a EthApi) -> F,
+    ) -> Result<T, Error>
+    where
+        T: Serialize + DeserializeOwned + Debug + Send,
+        P: Serialize + Debug + Send + Sync,
+        F: Future<Output = Result<T, BlockchainError>> + 'a,
+    {
+        match &self.node {
+            Node::Spawned { eth_api, .. } => {
+                watch(method, &params, self.timeout, spawned(eth_api)).await
+            }
+            Node::Attached { provider, .. } => {
+                watch(
+                    method,
+                    &params,
+                    self.timeout,
+                    provider.request(method, &params),
+                )
+                .await
+            }
         }
     }
 
-    pub fn block_number(&self) -> Result<usize, Error> {
-        Ok(self.eth_api.block_number()?.as_usize())
+    pub fn eth_chain_id(&self) -> Result<Option<zkevm_types::Word>, Error> {
+        let chain_id = match &self.node {
+            Node::Spawned { eth_api, .. } => eth_api.eth_chain_id()?,
+            Node::Attached { chain_id, .. } => Some(*chain_id),
+        };
+        Ok(chain_id.map(|chain_id| zkevm_types::Word::from(chain_id.as_usize())))
+    }
+
+    pub async fn block_number(&self) -> Result<usize, Error> {
+        let block_number: anvil_types::U256 = self
+            .request("eth_blockNumber", (), |eth_api| async move {
+                eth_api.block_number()
+            })
+            .await?;
+        Ok(block_number.as_usize())
     }
 
     pub async fn block_by_number(
@@ -49,7 +210,12 @@ impl AnvilClient {
         block_number: usize,
     ) -> Result<Option<zkevm_types::EthBlockHeader>, Error> {
         let b = anvil_types::BlockNumber::Number(anvil_types::U64::from(block_number));
-        match self.eth_api.block_by_number(b).await? {
+        match self
+            .request("eth_getBlockByNumber", (b, false), |eth_api| {
+                eth_api.block_by_number(b)
+            })
+            .await?
+        {
             Some(block) => Ok(Some(block.to_zkevm_type())),
             None => Ok(None),
         }
@@ -59,11 +225,11 @@ impl AnvilClient {
         &self,
         block_number: usize,
     ) -> Result<Option<zkevm_types::EthBlockFull>, Error> {
+        let b = anvil_types::BlockNumber::Number(anvil_types::U64::from(block_number));
         match self
-            .eth_api
-            .block_by_number_full(anvil_types::BlockNumber::Number(anvil_types::U64::from(
-                block_number,
-            )))
+            .request("eth_getBlockByNumber", (b, true), |eth_api| {
+                eth_api.block_by_number_full(b)
+            })
             .await?
         {
             Some(block) => {
@@ -83,9 +249,11 @@ impl AnvilClient {
         &self,
         hash: zkevm_types::H256,
     ) -> Result<Option<zkevm_types::Transaction>, Error> {
+        let hash = hash.to_anvil_type();
         match self
-            .eth_api
-            .transaction_by_hash(hash.to_anvil_type())
+            .request("eth_getTransactionByHash", [hash], |eth_api| {
+                eth_api.transaction_by_hash(hash)
+            })
             .await?
         {
             Some(tx) => Ok(Some(patch_transaction(tx.to_zkevm_type()))),
@@ -97,9 +265,11 @@ impl AnvilClient {
         &self,
         hash: zkevm_types::H256,
     ) -> Result<Option<zkevm_types::TransactionReceipt>, Error> {
+        let hash = hash.to_anvil_type();
         match self
-            .eth_api
-            .transaction_receipt(hash.to_anvil_type())
+            .request("eth_getTransactionReceipt", [hash], |eth_api| {
+                eth_api.transaction_receipt(hash)
+            })
             .await?
         {
             Some(rc) => Ok(Some(rc.to_zkevm_type())),
@@ -113,17 +283,13 @@ impl AnvilClient {
         keys: Vec<zkevm_types::U256>,
         block_number: Option<usize>,
     ) -> Result<zkevm_types::EIP1186ProofResponse, Error> {
+        let address = address.to_anvil_type();
+        let keys: Vec<anvil_types::H256> = keys.iter().map(|key| key.to_anvil_type()).collect();
+        let block = block_id(block_number);
         Ok(self
-            .eth_api
-            .get_proof(
-                address.to_anvil_type(),
-                keys.iter().map(|key| key.to_anvil_type()).collect(),
-                block_number.map(|_block_number| {
-                    anvil_types::BlockId::Number(anvil_types::BlockNumber::Number(
-                        anvil_types::U64::from(_block_number),
-                    ))
-                }),
-            )
+            .request("eth_getProof", (address, &keys, block), |eth_api| {
+                eth_api.get_proof(address, keys.clone(), block)
+            })
             .await?
             .to_zkevm_type())
     }
@@ -132,8 +298,12 @@ impl AnvilClient {
         &self,
         hash: zkevm_types::Hash,
     ) -> Result<Option<zkevm_types::EthBlockHeader>, Error> {
+        let hash = hash.to_anvil_type();
         Ok(convert_option(
-            self.eth_api.block_by_hash(hash.to_anvil_type()).await?,
+            self.request("eth_getBlockByHash", (hash, false), |eth_api| {
+                eth_api.block_by_hash(hash)
+            })
+            .await?,
         ))
     }
 
@@ -142,12 +312,13 @@ impl AnvilClient {
         hash: zkevm_types::Hash,
         options: anvil_types::GethDebugTracingOptions,
     ) -> Result<zkevm_types::GethExecTrace, Error> {
+        let hash = hash.to_anvil_type();
         let trace = self
-            .eth_api
-            .debug_trace_transaction(hash.to_anvil_type(), options)
-            .await?
-            .to_zkevm_type();
-        Ok(patch_trace(trace))
+            .request("debug_traceTransaction", (hash, &options), |eth_api| {
+                eth_api.debug_trace_transaction(hash, options.clone())
+            })
+            .await?;
+        Ok(patch_trace(to_zkevm_trace(&trace)?))
     }
 
     pub async fn get_code(
@@ -155,16 +326,12 @@ impl AnvilClient {
         address: zkevm_types::Address,
         block_number: Option<usize>,
     ) -> Result<zkevm_types::Bytes, Error> {
+        let address = address.to_anvil_type();
+        let block = block_id(block_number);
         Ok(self
-            .eth_api
-            .get_code(
-                address.to_anvil_type(),
-                block_number.map(|_block_number| {
-                    anvil_types::BlockId::Number(anvil_types::BlockNumber::Number(
-                        anvil_types::U64::from(_block_number),
-                    ))
-                }),
-            )
+            .request("eth_getCode", (address, block), |eth_api| {
+                eth_api.get_code(address, block)
+            })
             .await?
             .to_zkevm_type())
     }
@@ -174,10 +341,11 @@ impl AnvilClient {
         address: zkevm_types::Address,
         balance: zkevm_types::U256,
     ) -> Result<(), Error> {
-        Ok(self
-            .eth_api
-            .anvil_set_balance(address.to_anvil_type(), balance.to_anvil_type())
-            .await?)
+        let (address, balance) = (address.to_anvil_type(), balance.to_anvil_type());
+        self.request("anvil_setBalance", (address, balance), |eth_api| {
+            eth_api.anvil_set_balance(address, balance)
+        })
+        .await
     }
 
     pub async fn set_code(
@@ -185,10 +353,41 @@ impl AnvilClient {
         address: zkevm_types::Address,
         code: zkevm_types::Bytes,
     ) -> Result<(), Error> {
-        Ok(self
-            .eth_api
-            .anvil_set_code(address.to_anvil_type(), code.to_anvil_type())
-            .await?)
+        let (address, code) = (address.to_anvil_type(), code.to_anvil_type());
+        self.request("anvil_setCode", (address, &code), |eth_api| {
+            eth_api.anvil_set_code(address, code.clone())
+        })
+        .await
+    }
+
+    pub async fn set_nonce(
+        &self,
+        address: zkevm_types::Address,
+        nonce: zkevm_types::U256,
+    ) -> Result<(), Error> {
+        let (address, nonce) = (address.to_anvil_type(), nonce.to_anvil_type());
+        self.request("anvil_setNonce", (address, nonce), |eth_api| {
+            eth_api.anvil_set_nonce(address, nonce)
+        })
+        .await
+    }
+
+    pub async fn set_storage_at(
+        &self,
+        address: zkevm_types::Address,
+        slot: zkevm_types::U256,
+        value: zkevm_types::H256,
+    ) -> Result<(), Error> {
+        let (address, slot, value): (_, anvil_types::U256, _) = (
+            address.to_anvil_type(),
+            slot.to_anvil_type(),
+            value.to_anvil_type(),
+        );
+        self.request("anvil_setStorageAt", (address, slot, value), |eth_api| {
+            eth_api.anvil_set_storage_at(address, slot, value)
+        })
+        .await?;
+        Ok(())
     }
 
     pub async fn get_balance(
@@ -196,16 +395,12 @@ impl AnvilClient {
         address: zkevm_types::Address,
         block_number: Option<usize>,
     ) -> Result<zkevm_types::U256, Error> {
+        let address = address.to_anvil_type();
+        let block = block_id(block_number);
         Ok(self
-            .eth_api
-            .balance(
-                address.to_anvil_type(),
-                block_number.map(|_block_number| {
-                    anvil_types::BlockId::Number(anvil_types::BlockNumber::Number(
-                        anvil_types::U64::from(_block_number),
-                    ))
-                }),
-            )
+            .request("eth_getBalance", (address, block), |eth_api| {
+                eth_api.balance(address, block)
+            })
             .await?
             .to_zkevm_type())
     }
@@ -215,16 +410,12 @@ impl AnvilClient {
         address: zkevm_types::Address,
         block_number: Option<usize>,
     ) -> Result<zkevm_types::U256, Error> {
+        let address = address.to_anvil_type();
+        let block = block_id(block_number);
         Ok(self
-            .eth_api
-            .transaction_count(
-                address.to_anvil_type(),
-                block_number.map(|_block_number| {
-                    anvil_types::BlockId::Number(anvil_types::BlockNumber::Number(
-                        anvil_types::U64::from(_block_number),
-                    ))
-                }),
-            )
+            .request("eth_getTransactionCount", (address, block), |eth_api| {
+                eth_api.transaction_count(address, block)
+            })
             .await?
             .to_zkevm_type())
     }
@@ -235,17 +426,13 @@ impl AnvilClient {
         index: zkevm_types::U256,
         block_number: Option<usize>,
     ) -> Result<zkevm_types::H256, Error> {
+        let (address, index): (_, anvil_types::U256) =
+            (address.to_anvil_type(), index.to_anvil_type());
+        let block = block_id(block_number);
         Ok(self
-            .eth_api
-            .storage_at(
-                address.to_anvil_type(),
-                index.to_anvil_type(),
-                block_number.map(|_block_number| {
-                    anvil_types::BlockId::Number(anvil_types::BlockNumber::Number(
-                        anvil_types::U64::from(_block_number),
-                    ))
-                }),
-            )
+            .request("eth_getStorageAt", (address, index, block), |eth_api| {
+                eth_api.storage_at(address, index, block)
+            })
             .await?
             .to_zkevm_type())
     }
@@ -255,27 +442,40 @@ impl AnvilClient {
         request: anvil_types::EthTransactionRequest,
         block_number: Option<usize>,
     ) -> Result<zkevm_types::U256, Error> {
+        let block = block_id(block_number);
         Ok(self
-            .eth_api
-            .estimate_gas(
-                request,
-                block_number.map(|n| {
-                    anvil_types::BlockId::Number(anvil_types::BlockNumber::Number(
-                        anvil_types::U64::from(n),
-                    ))
-                }),
-            )
-            .await
-            .map(|v| v.to_zkevm_type())?)
+            .request("eth_estimateGas", (&request, block), |eth_api| {
+                eth_api.estimate_gas(request.clone(), block)
+            })
+            .await?
+            .to_zkevm_type())
+    }
+
+    /// Output of `request` executed on the state of `block_number`, the latest block if there is
+    /// none, without a transaction.
+    pub async fn call(
+        &self,
+        request: anvil_types::EthTransactionRequest,
+        block_number: Option<usize>,
+    ) -> Result<zkevm_types::Bytes, Error> {
+        let block = block_id(block_number);
+        Ok(self
+            .request("eth_call", (&request, block), |eth_api| {
+                eth_api.call(request.clone(), block, None)
+            })
+            .await?
+            .to_zkevm_type())
     }
 
     pub async fn send_raw_transaction(
         &self,
         raw_tx: zkevm_types::Bytes,
     ) -> Result<zkevm_types::Hash, Error> {
+        let raw_tx = raw_tx.to_anvil_type();
         Ok(self
-            .eth_api
-            .send_raw_transaction(raw_tx.to_anvil_type())
+            .request("eth_sendRawTransaction", [&raw_tx], |eth_api| {
+                eth_api.send_raw_transaction(raw_tx.clone())
+            })
             .await?
             .to_zkevm_type())
     }
@@ -284,42 +484,93 @@ impl AnvilClient {
         &self,
         address: zkevm_types::Address,
     ) -> Result<zkevm_types::Hash, Error> {
-        let accounts = self.eth_api.accounts().unwrap();
+        let accounts: Vec<anvil_types::Address> = self
+            .request(
+                "eth_accounts",
+                (),
+                |eth_api| async move { eth_api.accounts() },
+            )
+            .await?;
+        let request = anvil_types::EthTransactionRequest {
+            from: Some(accounts[0]),
+            to: Some(address.to_anvil_type()),
+            gas_price: None,
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+            gas: None,
+            value: Some(parse_ether("1").unwrap()), // literal amount, cannot fail
+            data: None,
+            nonce: None,
+            chain_id: None,
+            access_list: None,
+            transaction_type: None,
+        };
         Ok(self
-            .eth_api
-            .send_transaction(anvil_types::EthTransactionRequest {
-                from: Some(accounts[0]),
-                to: Some(address.to_anvil_type()),
-                gas_price: None,
-                max_fee_per_gas: None,
-                max_priority_fee_per_gas: None,
-                gas: None,
-                value: Some(parse_ether("1").unwrap()),
-                data: None,
-                nonce: None,
-                chain_id: None,
-                access_list: None,
-                transaction_type: None,
+            .request("eth_sendTransaction", [&request], |eth_api| {
+                eth_api.send_transaction(request.clone())
             })
             .await?
             .to_zkevm_type())
     }
 
-    pub async fn mine_one(&self) {
-        self.eth_api.mine_one().await;
+    /// Whether every transaction is mined in a block of its own as soon as it is sent, the
+    /// default. Without automine the pending transactions are mined by `mine_one`.
+    pub async fn set_automine(&self, enabled: bool) -> Result<(), Error> {
+        self.request("anvil_setAutomine", [enabled], |eth_api| {
+            eth_api.anvil_set_auto_mine(enabled)
+        })
+        .await
     }
 
-    pub async fn wait_for_transaction(&self, hash: zkevm_types::Hash) -> Result<(), Error> {
-        loop {
-            let rc = self.transaction_receipt(hash).await.unwrap();
-            if rc.is_some() {
-                return Ok(());
+    /// Sets the timestamp of the next block anvil mines.
+    pub async fn set_next_block_timestamp(&self, timestamp: u64) -> Result<(), Error> {
+        self.request(
+            "evm_setNextBlockTimestamp",
+            [timestamp],
+            |eth_api| async move { eth_api.evm_set_next_block_timestamp(timestamp) },
+        )
+        .await
+    }
+
+    pub async fn mine_one(&self) -> Result<(), Error> {
+        match &self.node {
+            Node::Spawned { eth_api, .. } => eth_api.mine_one().await,
+            Node::Attached { provider, .. } => {
+                // answers "0x0"
+                watch(
+                    "evm_mine",
+                    (),
+                    self.timeout,
+                    provider.request::<_, String>("evm_mine", ()),
+                )
+                .await?;
             }
-            sleep(Duration::from_secs(1))
         }
+        Ok(())
+    }
+
+    pub async fn wait_for_transaction(&self, hash: zkevm_types::Hash) -> Result<(), Error> {
+        watch("wait_for_transaction", hash, self.timeout, async {
+            loop {
+                let rc = self.transaction_receipt(hash).await?;
+                if rc.is_some() {
+                    return Ok::<_, Error>(());
+                }
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        })
+        .await
     }
 }
 
+fn block_id(block_number: Option<usize>) -> Option<anvil_types::BlockId> {
+    block_number.map(|block_number| {
+        anvil_types::BlockId::Number(anvil_types::BlockNumber::Number(anvil_types::U64::from(
+            block_number,
+        )))
+    })
+}
+
 pub fn patch_transaction(mut tx: zkevm_types::Transaction) -> zkevm_types::Transaction {
     if tx.transaction_type.is_none() {
         tx.transaction_type = Some(zkevm_types::U64::from(0));
@@ -339,13 +590,16 @@ pub fn patch_trace(mut trace: zkevm_types::GethExecTrace) -> zkevm_types::GethEx
 #[cfg(test)]
 mod tests {
     use super::AnvilClient;
+    use crate::utils::hardfork::Hardfork;
 
     // ignored because cannot run anvil again in other test
     #[ignore]
     #[tokio::test]
     async fn test() {
-        let cli = AnvilClient::setup(None, None).await;
-        let bn = cli.block_number().unwrap();
+        let cli = AnvilClient::setup(None, None, Hardfork::default())
+            .await
+            .unwrap();
+        let bn = cli.block_number().await.unwrap();
         assert_eq!(bn, 0);
     }
 }
@@ -1,4 +1,5 @@
 use super::types::{anvil_types, zkevm_types};
+use crate::error::Error;
 use ethers::{types::BigEndianHash, utils::hex};
 use std::{collections::HashMap, str::FromStr};
 
@@ -197,52 +198,69 @@ impl Conversion<zkevm_types::Log> for anvil_types::Log {
     }
 }
 
-impl Conversion<zkevm_types::GethExecTrace> for anvil_types::GethTrace {
-    fn to_zkevm_type(&self) -> zkevm_types::GethExecTrace {
-        if let ethers::types::GethTrace::Known(ethers::types::GethTraceFrame::Default(
-            anvil_trace,
-        )) = self.to_owned()
-        {
-            zkevm_types::GethExecTrace {
-                gas: anvil_trace.gas.as_u64(),
-                failed: anvil_trace.failed,
-                return_value: hex::encode(anvil_trace.return_value.as_ref()), // TODO see if 0x adjustment is needed
-                struct_logs: anvil_trace
-                    .struct_logs
-                    .into_iter()
-                    .map(|step| {
-                        zkevm_types::GethExecStep {
-                            pc: step.pc,
-                            op: zkevm_types::OpcodeId::from_str(step.op.as_str()).unwrap(),
-                            gas: step.gas,
-                            gas_cost: step.gas_cost,
-                            refund: step.refund_counter.unwrap_or(0),
-                            depth: u16::try_from(step.depth).expect("error converting depth"),
-                            error: step.error,
-                            stack: zkevm_types::Stack(
-                                step.stack
-                                    .unwrap_or(Vec::new())
-                                    .into_iter()
-                                    .map(|w| w.to_zkevm_type())
-                                    .collect(),
-                            ),
-                            memory: zkevm_types::Memory::default(), // memory is not enabled
-                            storage: {
-                                let tree = step.storage.unwrap_or_default();
-                                let mut hash_map =
-                                    HashMap::<zkevm_types::Word, zkevm_types::Word>::new();
-                                for (key, value) in &tree {
-                                    hash_map.insert(key.to_zkevm_type(), value.to_zkevm_type());
-                                }
-                                zkevm_types::Storage(hash_map)
-                            },
-                        }
+/// Converts a struct logger trace of anvil, which is not infallible like the other conversions
+/// since anvil may return another tracer's frame or an opcode the zkevm does not know.
+pub fn to_zkevm_trace(trace: &anvil_types::GethTrace) -> Result<zkevm_types::GethExecTrace, Error> {
+    if let ethers::types::GethTrace::Known(ethers::types::GethTraceFrame::Default(anvil_trace)) =
+        trace.to_owned()
+    {
+        Ok(zkevm_types::GethExecTrace {
+            gas: anvil_trace.gas.as_u64(),
+            failed: anvil_trace.failed,
+            return_value: hex::encode(anvil_trace.return_value.as_ref()), // TODO see if 0x adjustment is needed
+            struct_logs: anvil_trace
+                .struct_logs
+                .into_iter()
+                .map(|step| {
+                    Ok(zkevm_types::GethExecStep {
+                        pc: step.pc,
+                        op: zkevm_types::OpcodeId::from_str(step.op.as_str()).map_err(|_| {
+                            Error::UnsupportedOpcode(format!(
+                                "anvil traced the unknown opcode {} at pc {}",
+                                step.op, step.pc
+                            ))
+                        })?,
+                        gas: step.gas,
+                        gas_cost: step.gas_cost,
+                        refund: step.refund_counter.unwrap_or(0),
+                        depth: u16::try_from(step.depth).map_err(|_| {
+                            Error::InternalError("call depth of the trace exceeds u16")
+                        })?,
+                        error: step.error,
+                        stack: zkevm_types::Stack(
+                            step.stack
+                                .unwrap_or(Vec::new())
+                                .into_iter()
+                                .map(|w| w.to_zkevm_type())
+                                .collect(),
+                        ),
+                        // empty unless memory is enabled in the tracing options
+                        memory: zkevm_types::Memory(
+                            step.memory
+                                .unwrap_or_default()
+                                .iter()
+                                .flat_map(|word| {
+                                    hex::decode(word.trim_start_matches("0x")).unwrap_or_default()
+                                })
+                                .collect(),
+                        ),
+                        storage: {
+                            let tree = step.storage.unwrap_or_default();
+                            let mut hash_map =
+                                HashMap::<zkevm_types::Word, zkevm_types::Word>::new();
+                            for (key, value) in &tree {
+                                hash_map.insert(key.to_zkevm_type(), value.to_zkevm_type());
+                            }
+                            zkevm_types::Storage(hash_map)
+                        },
                     })
-                    .collect(),
-            }
-        } else {
-            panic!("unknown value in trace")
-        }
+                })
+                .collect::<Result<_, Error>>()?,
+        })
+    } else {
+        Err(Error::InternalError(
+            "anvil returned a trace which is not a struct log trace",
+        ))
     }
 }
 
@@ -366,6 +384,24 @@ impl ConversionReverse<anvil_types::H256> for zkevm_types::U256 {
     }
 }
 
+impl ConversionReverse<anvil_types::AccessList> for zkevm_types::AccessList {
+    fn to_anvil_type(&self) -> anvil_types::AccessList {
+        anvil_types::AccessList(
+            self.0
+                .iter()
+                .map(|item| anvil_types::AccessListItem {
+                    address: item.address.to_anvil_type(),
+                    storage_keys: item
+                        .storage_keys
+                        .iter()
+                        .map(|key| key.to_anvil_type())
+                        .collect(),
+                })
+                .collect(),
+        )
+    }
+}
+
 impl ConversionReverse<anvil_types::Bytes> for zkevm_types::Bytes {
     fn to_anvil_type(&self) -> anvil_types::Bytes {
         anvil_types::Bytes::from(self.to_vec())
@@ -1,4 +1,5 @@
 pub mod client;
 pub mod conversion;
+pub mod tx_builder;
 pub mod types;
 pub use client::AnvilClient;
@@ -0,0 +1,350 @@
+use super::{
+    conversion::{Conversion, ConversionReverse},
+    types::{anvil_types, zkevm_types},
+    AnvilClient,
+};
+use crate::{error::Error, utils::signer::default_signer};
+use anvil::eth::error::{BlockchainError, InvalidTransactionError};
+use ethers::{
+    abi::{decode, ParamType, Token},
+    signers::{LocalWallet, Signer},
+    types::{
+        transaction::{eip2718::TypedTransaction, eip2930::Eip2930TransactionRequest},
+        Eip1559TransactionRequest, NameOrAddress, TransactionRequest,
+    },
+    utils::hex,
+};
+use std::{collections::HashMap, str::FromStr};
+
+/// Gas the transaction is estimated with when it has no gas limit.
+const ESTIMATE_GAS_CAP: u64 = 1_000_000;
+
+/// Selector of `Error(string)`, the revert data of `require` and `revert` with a message.
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// Selector of `Panic(uint256)`, the revert data of failed asserts and checked arithmetic.
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// Envelope of a transaction, the ones the circuits can prove.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TxType {
+    #[default]
+    Legacy,
+    /// EIP-2930 transaction with an access list.
+    AccessList,
+    /// EIP-1559 transaction with a max fee and a priority fee, and an access list.
+    DynamicFee,
+}
+
+impl FromStr for TxType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "legacy" => Ok(TxType::Legacy),
+            "eip2930" => Ok(TxType::AccessList),
+            "eip1559" => Ok(TxType::DynamicFee),
+            _ => Err(Error::InvalidArgument(format!(
+                "unknown tx type {s}, expected legacy, eip2930 or eip1559"
+            ))),
+        }
+    }
+}
+
+/// Transaction which is signed locally and sent to anvil, with the chain id and the nonce of
+/// anvil and no gas price, anvil has no base fee. The gas limit is estimated unless set, and the
+/// estimate fails if the transaction reverts.
+pub struct TxBuilder {
+    signer: LocalWallet,
+    to: zkevm_types::Address,
+    value: zkevm_types::U256,
+    data: zkevm_types::Bytes,
+    gas: Option<u64>,
+    tx_type: TxType,
+    access_list: zkevm_types::AccessList,
+    allow_revert: bool,
+}
+
+impl TxBuilder {
+    /// Transaction to `to` signed by `default_signer`.
+    pub fn new(to: zkevm_types::Address) -> Self {
+        Self {
+            signer: default_signer(),
+            to,
+            value: zkevm_types::U256::zero(),
+            data: zkevm_types::Bytes::default(),
+            gas: None,
+            tx_type: TxType::default(),
+            access_list: zkevm_types::AccessList::default(),
+            allow_revert: false,
+        }
+    }
+
+    pub fn set_signer(&mut self, signer: LocalWallet) {
+        self.signer = signer;
+    }
+
+    pub fn set_value(&mut self, value: zkevm_types::U256) {
+        self.value = value;
+    }
+
+    pub fn set_data(&mut self, data: zkevm_types::Bytes) {
+        self.data = data;
+    }
+
+    pub fn set_gas(&mut self, gas: Option<u64>) {
+        self.gas = gas;
+    }
+
+    pub fn set_tx_type(&mut self, tx_type: TxType) {
+        self.tx_type = tx_type;
+    }
+
+    /// Accounts and storage slots the transaction warms up front, it is ignored by legacy
+    /// transactions.
+    pub fn set_access_list(&mut self, access_list: zkevm_types::AccessList) {
+        self.access_list = access_list;
+    }
+
+    /// Mines the transaction even if it reverts, its gas limit is then not estimated.
+    pub fn set_allow_revert(&mut self, allow_revert: bool) {
+        self.allow_revert = allow_revert;
+    }
+
+    /// Executes the transaction with `eth_call` on the latest block and returns the decoded revert
+    /// reason if it reverts.
+    pub async fn revert_reason(&self, anvil: &AnvilClient) -> Result<Option<String>, Error> {
+        let nonce = anvil
+            .get_nonce(self.signer.address(), Some(anvil.block_number().await?))
+            .await?;
+        match anvil
+            .call(self.request(chain_id(anvil)?, nonce), None)
+            .await
+        {
+            Ok(_) => Ok(None),
+            Err(Error::AnvilError(err)) => match *err {
+                BlockchainError::InvalidTransaction(InvalidTransactionError::Revert(data)) => {
+                    Ok(Some(decode_revert_reason(&data.unwrap_or_default())))
+                }
+                err => Err(err.into()),
+            },
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Signs and sends the transaction, and returns its hash once it is mined.
+    pub async fn send(&self, anvil: &AnvilClient) -> Result<zkevm_types::H256, Error> {
+        let chain_id = chain_id(anvil)?;
+        let nonce = anvil
+            .get_nonce(self.signer.address(), Some(anvil.block_number().await?))
+            .await?;
+        let mut request = self.request(chain_id, nonce);
+        if !self.allow_revert {
+            // check for reverts and fail out
+            let gas_estimate = anvil.estimate_gas(request.clone(), None).await?;
+            request.gas = Some(
+                self.gas
+                    .map(zkevm_types::U256::from)
+                    .unwrap_or(gas_estimate),
+            );
+        }
+        let hash = anvil.send_raw_transaction(self.sign(&request)?).await?;
+        anvil.wait_for_transaction(hash).await?;
+        Ok(hash)
+    }
+
+    /// Signs and sends the transactions in this order into a single block, and returns their
+    /// hashes once it is mined. A transaction may depend on the ones before it, so the gas is not
+    /// estimated and a revert only shows in the receipt.
+    pub async fn send_in_one_block(
+        txs: &[TxBuilder],
+        anvil: &AnvilClient,
+    ) -> Result<Vec<zkevm_types::H256>, Error> {
+        let chain_id = chain_id(anvil)?;
+        let block_number = anvil.block_number().await?;
+        let mut nonces = HashMap::new();
+        let mut raw_txs = vec![];
+        for tx in txs {
+            let address = tx.signer.address();
+            let nonce = match nonces.get(&address) {
+                Some(nonce) => *nonce,
+                None => anvil.get_nonce(address, Some(block_number)).await?,
+            };
+            nonces.insert(address, nonce + 1);
+            raw_txs.push(tx.sign(&tx.request(chain_id, nonce))?);
+        }
+
+        anvil.set_automine(false).await?;
+        let mut hashes = vec![];
+        for raw_tx in raw_txs {
+            match anvil.send_raw_transaction(raw_tx).await {
+                Ok(hash) => hashes.push(hash),
+                Err(err) => {
+                    anvil.set_automine(true).await?;
+                    return Err(err);
+                }
+            }
+        }
+        anvil.mine_one().await?;
+        anvil.set_automine(true).await?;
+        for hash in &hashes {
+            anvil.wait_for_transaction(*hash).await?;
+        }
+        Ok(hashes)
+    }
+
+    fn request(
+        &self,
+        chain_id: u64,
+        nonce: zkevm_types::U256,
+    ) -> anvil_types::EthTransactionRequest {
+        let zero = Some(zkevm_types::U256::zero());
+        let (gas_price, max_fee_per_gas, transaction_type) = match self.tx_type {
+            TxType::Legacy => (zero, None, 0u64),
+            TxType::AccessList => (zero, None, 1),
+            TxType::DynamicFee => (None, zero, 2),
+        };
+        anvil_types::EthTransactionRequest {
+            from: Some(self.signer.address()),
+            to: Some(self.to),
+            gas_price,
+            max_fee_per_gas,
+            max_priority_fee_per_gas: max_fee_per_gas,
+            gas: Some(self.gas.unwrap_or(ESTIMATE_GAS_CAP).into()),
+            value: Some(self.value),
+            data: Some(self.data.clone().to_anvil_type()),
+            nonce: Some(nonce),
+            chain_id: Some(chain_id.into()),
+            access_list: (self.tx_type != TxType::Legacy)
+                .then(|| self.access_list.to_anvil_type().0),
+            transaction_type: Some(transaction_type.into()),
+        }
+    }
+
+    fn sign(
+        &self,
+        request: &anvil_types::EthTransactionRequest,
+    ) -> Result<zkevm_types::Bytes, Error> {
+        let legacy = TransactionRequest {
+            from: request.from,
+            to: request.to.map(NameOrAddress::Address),
+            gas_price: request.gas_price,
+            gas: request.gas,
+            value: request.value,
+            data: request.data.clone(),
+            nonce: request.nonce,
+            chain_id: request.chain_id,
+        };
+        let access_list = anvil_types::AccessList(request.access_list.clone().unwrap_or_default());
+        let tx = match self.tx_type {
+            TxType::Legacy => TypedTransaction::Legacy(legacy),
+            TxType::AccessList => {
+                TypedTransaction::Eip2930(Eip2930TransactionRequest::new(legacy, access_list))
+            }
+            TxType::DynamicFee => TypedTransaction::Eip1559(Eip1559TransactionRequest {
+                from: legacy.from,
+                to: legacy.to,
+                gas: legacy.gas,
+                value: legacy.value,
+                data: legacy.data,
+                nonce: legacy.nonce,
+                access_list,
+                max_priority_fee_per_gas: request.max_priority_fee_per_gas,
+                max_fee_per_gas: request.max_fee_per_gas,
+                chain_id: legacy.chain_id,
+            }),
+        };
+        // the chain id of the transaction is signed in
+        let signature = self
+            .signer
+            .sign_transaction_sync(&tx)
+            .map_err(|_| Error::InternalError("failed to sign the transaction"))?;
+        Ok(tx.rlp_signed(&signature).to_zkevm_type())
+    }
+}
+
+/// Reason of a revert, the message of `Error(string)`, the code of `Panic(uint256)` or the hex of
+/// any other revert data such as custom errors.
+pub fn decode_revert_reason(data: &[u8]) -> String {
+    if data.is_empty() {
+        return "no revert data".to_string();
+    }
+    let (selector, args) = data.split_at(data.len().min(4));
+    let reason = if selector == ERROR_SELECTOR {
+        decode(&[ParamType::String], args)
+            .ok()
+            .and_then(|tokens| tokens.into_iter().next())
+            .and_then(Token::into_string)
+    } else if selector == PANIC_SELECTOR {
+        decode(&[ParamType::Uint(256)], args)
+            .ok()
+            .and_then(|tokens| tokens.into_iter().next())
+            .and_then(Token::into_uint)
+            .map(|code| format!("panic 0x{code:02x}"))
+    } else {
+        None
+    };
+    reason.unwrap_or_else(|| hex::encode_prefixed(data))
+}
+
+fn chain_id(anvil: &AnvilClient) -> Result<u64, Error> {
+    Ok(anvil
+        .eth_chain_id()?
+        .ok_or(Error::InternalError("anvil has no chain id"))?
+        .as_u64())
+}
+
+/// Runs `init_code`, creation bytecode followed by its ABI encoded constructor args, as the code
+/// of `address` so that the constructor writes the storage of `address`, then sets the runtime
+/// code the constructor returns, immutables included, as the code of `address` and returns it.
+/// Unlike a deployment the address is not derived from a sender, and the constructor runs in a
+/// block of its own.
+pub async fn deploy_at(
+    anvil: &AnvilClient,
+    address: zkevm_types::Address,
+    init_code: zkevm_types::Bytes,
+) -> Result<zkevm_types::Bytes, Error> {
+    anvil.set_code(address, init_code).await?;
+    let runtime_code = anvil
+        .call(
+            anvil_types::EthTransactionRequest {
+                from: Some(default_signer().address()),
+                to: Some(address),
+                gas_price: Some(zkevm_types::U256::zero()),
+                max_fee_per_gas: None,
+                max_priority_fee_per_gas: None,
+                gas: None,
+                value: None,
+                data: None,
+                nonce: None,
+                chain_id: None,
+                access_list: None,
+                transaction_type: None,
+            },
+            None,
+        )
+        .await?;
+    TxBuilder::new(address).send(anvil).await?;
+    anvil.set_code(address, runtime_code.clone()).await?;
+    Ok(runtime_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_revert_reason, ERROR_SELECTOR, PANIC_SELECTOR};
+    use ethers::abi::{encode, Token};
+
+    #[test]
+    fn test_decode_revert_reason() {
+        let error = [
+            &ERROR_SELECTOR[..],
+            &encode(&[Token::String("not solved".to_string())]),
+        ]
+        .concat();
+        assert_eq!(decode_revert_reason(&error), "not solved");
+        let panic = [&PANIC_SELECTOR[..], &encode(&[Token::Uint(0x11.into())])].concat();
+        assert_eq!(decode_revert_reason(&panic), "panic 0x11");
+        assert_eq!(decode_revert_reason(&[0xde, 0xad]), "0xdead");
+        assert_eq!(decode_revert_reason(&[]), "no revert data");
+    }
+}
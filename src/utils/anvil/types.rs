@@ -25,9 +25,10 @@ pub mod zkevm_types {
 pub mod anvil_types {
     pub use anvil_core::eth::transaction::EthTransactionRequest;
     pub use ethers::types::{
-        transaction::eip2930::AccessList, Address, Block, BlockId, BlockNumber, Bloom, Bytes,
-        EIP1186ProofResponse, GethDebugTracingOptions, GethTrace, Log, OtherFields, StorageProof,
-        Transaction, TransactionReceipt, TxHash, Withdrawal, H160, H256, H64, U256, U64,
+        transaction::eip2930::{AccessList, AccessListItem},
+        Address, Block, BlockId, BlockNumber, Bloom, Bytes, EIP1186ProofResponse,
+        GethDebugTracingOptions, GethTrace, Log, OtherFields, StorageProof, Transaction,
+        TransactionReceipt, TxHash, Withdrawal, H160, H256, H64, U256, U64,
     };
     pub type EthBlockFull = Block<Transaction>;
     pub type EthBlockHeader = Block<TxHash>;
@@ -0,0 +1,218 @@
+use super::{
+    halo2::{proof::Proof, srs},
+    run_manifest::{RunInputs, RunManifest},
+};
+use crate::error::Error;
+use bus_mapping::circuit_input_builder::FixedCParams;
+use eth_types::{keccak256, H256, U256};
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::{Address, Signature},
+};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Directory of the srs path the archives are written to by default. They are kept out of the
+/// runs dir, whose json files are all run manifests.
+pub(crate) const AUDITS_DIR: &str = "audits";
+
+/// Metadata of a proof which lets an auditor match it to a bounty claim without the proof bytes.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProofMetadata {
+    pub version: Version,
+    pub degree: u32,
+    pub fixed_circuit_params: FixedCParams,
+    pub proof_digest: H256,
+    pub instances_digest: H256,
+    pub chain_id: U256,
+    pub block_number: U256,
+    pub prev_state_root: H256,
+    pub challenge_codehash: H256,
+    pub exploit_balance: U256,
+}
+
+impl ProofMetadata {
+    pub fn from(proof: &Proof) -> Result<Self, Error> {
+        let instances = proof
+            .instances()
+            .iter()
+            .flat_map(|column| column.iter().map(|element| element.to_bytes()))
+            .flatten()
+            .collect::<Vec<u8>>();
+        Ok(Self {
+            version: proof.version.clone(),
+            degree: proof.degree,
+            fixed_circuit_params: proof.fixed_circuit_params,
+            proof_digest: H256::from(keccak256(&proof.data)),
+            instances_digest: H256::from(keccak256(instances)),
+            chain_id: proof.public_data.chain_id,
+            block_number: proof.public_data.block_constants.number,
+            prev_state_root: proof.public_data.prev_state_root,
+            challenge_codehash: proof.public_data.pox_challenge_codehash,
+            exploit_balance: proof.public_data.pox_exploit_balance,
+        })
+    }
+}
+
+/// Everything an auditor needs to reproduce a run: the run manifest, digests of the params and
+/// verifying key which were used, hashes of the PoX inputs and the metadata of the proof.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AuditBundle {
+    pub version: Version,
+    pub created_at: u64,
+    pub manifest: RunManifest,
+    pub general_params_digest: Option<H256>,
+    pub verifying_key_digest: Option<H256>,
+    pub pox_inputs: Option<RunInputs>,
+    pub proof: Option<ProofMetadata>,
+}
+
+impl AuditBundle {
+    pub fn from(
+        srs_path: &Path,
+        manifest: RunManifest,
+        proof: Option<&Proof>,
+    ) -> Result<Self, Error> {
+        let (general_params_digest, verifying_key_digest) =
+            match (manifest.degree, manifest.circuit_params) {
+                (Some(degree), Some(circuit_params)) => (
+                    file_digest(&srs_path.join(srs::general_params_file_name(degree)))?,
+                    file_digest(
+                        &srs_path
+                            .join(srs::circuit_verifying_key_file_name(degree, circuit_params)),
+                    )?,
+                ),
+                _ => (None, None),
+            };
+        if let (Some(recorded), Some(current)) =
+            (manifest.verifying_key_digest, verifying_key_digest)
+        {
+            if recorded != current {
+                return Err(Error::VkMismatch(format!(
+                    "verifying key in {} changed since run {}",
+                    srs_path.display(),
+                    manifest.id
+                )));
+            }
+        }
+        Ok(Self {
+            // cargo guarantees that the package version is valid semver
+            version: Version::from_str(env!("CARGO_PKG_VERSION")).unwrap(),
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            pox_inputs: manifest.inputs.clone(),
+            manifest,
+            general_params_digest,
+            verifying_key_digest,
+            proof: proof.map(ProofMetadata::from).transpose()?,
+        })
+    }
+
+    pub fn digest(&self) -> Result<H256, Error> {
+        Ok(H256::from(keccak256(serde_json::to_vec(self)?)))
+    }
+
+    /// Signs the digest of the bundle as an EIP-191 personal message.
+    pub async fn sign(self, signer: &LocalWallet) -> Result<AuditArchive, Error> {
+        let digest = self.digest()?;
+        let signature = signer
+            .sign_message(digest.as_bytes())
+            .await
+            .map_err(|_| Error::InternalError("failed to sign audit bundle"))?;
+        Ok(AuditArchive {
+            bundle: self,
+            digest,
+            signer: signer.address(),
+            signature,
+        })
+    }
+}
+
+/// An audit bundle along with the signature of whoever exported it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AuditArchive {
+    pub bundle: AuditBundle,
+    pub digest: H256,
+    pub signer: Address,
+    pub signature: Signature,
+}
+
+impl AuditArchive {
+    /// Checks that the bundle was not modified after it was signed by `signer`.
+    pub fn verify(&self) -> Result<(), Error> {
+        if self.bundle.digest()? != self.digest {
+            return Err(Error::InvalidArgument(
+                "audit bundle does not match its digest".to_string(),
+            ));
+        }
+        self.signature
+            .verify(self.digest.as_bytes(), self.signer)
+            .map_err(|_| {
+                Error::InvalidArgument(format!("audit bundle is not signed by {:?}", self.signer))
+            })
+    }
+
+    pub fn write_to_file(&self, path: &Path) -> Result<(), Error> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = File::create(path)?;
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn read_from_file(path: &Path) -> Result<Self, Error> {
+        let mut contents = String::new();
+        File::open(path)?.read_to_string(&mut contents)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+}
+
+/// Default location of the archive of a run.
+pub fn default_path(srs_path: &Path, run_id: &str) -> PathBuf {
+    srs_path
+        .join(AUDITS_DIR)
+        .join(format!("{run_id}.audit.json"))
+}
+
+fn file_digest(path: &Path) -> Result<Option<H256>, Error> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(H256::from(keccak256(fs::read(path)?))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AuditArchive, AuditBundle};
+    use crate::utils::run_manifest::RunManifest;
+    use ethers::signers::LocalWallet;
+    use std::str::FromStr;
+
+    #[tokio::test]
+    async fn test_sign_and_verify() {
+        let signer = LocalWallet::from_str(
+            "0x0000000000000000000000000000000000000000000000000000000000000001",
+        )
+        .unwrap();
+        let dir = std::env::temp_dir().join(format!("pox_audit_test_{}", std::process::id()));
+        let bundle = AuditBundle::from(&dir, RunManifest::new("prove"), None).unwrap();
+        let archive = bundle.sign(&signer).await.unwrap();
+        assert!(archive.verify().is_ok());
+
+        let json = serde_json::to_string(&archive).unwrap();
+        let mut tampered: AuditArchive = serde_json::from_str(&json).unwrap();
+        assert!(tampered.verify().is_ok());
+        tampered.bundle.manifest.error = Some("tampered".to_string());
+        assert!(tampered.verify().is_err());
+    }
+}
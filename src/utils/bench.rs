@@ -0,0 +1,235 @@
+//! `bench`: witness generation and proving of one exploit across circuit params and degrees, to
+//! pick the cheapest params an exploit fits in before proving it for real. Every case records its
+//! wall times, the peak memory of its keygen and proof, the size of the proof and, for Keccak
+//! transcript proofs, the gas of verifying it with the solidity verifier.
+
+use crate::{
+    cli::ProveArgs,
+    error::{Error, ErrorBody},
+    utils::halo2::{
+        ceremony, preflight, preset::CircuitPreset, proof::ProofTranscript, real_prover::RealProver,
+    },
+    witness::Witness,
+};
+use bus_mapping::circuit_input_builder::FixedCParams;
+use eth_types::U256;
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Ordering,
+    fs::File,
+    io::Write,
+    path::Path,
+    time::{Duration, Instant},
+};
+use tracing::info;
+
+/// Circuit params to benchmark, named after their preset if they have one.
+#[derive(Clone, Copy, Debug)]
+pub struct BenchCase {
+    pub preset: Option<CircuitPreset>,
+    pub circuits_params: FixedCParams,
+}
+
+impl BenchCase {
+    fn name(&self) -> String {
+        match self.preset {
+            Some(preset) => format!("{preset:?}").to_lowercase(),
+            None => "custom".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BenchResult {
+    /// Preset of the params, none for the `--max-*` params.
+    pub preset: Option<CircuitPreset>,
+    pub circuits_params: FixedCParams,
+    /// Degree the proof was created at, none if the witness failed.
+    pub degree: Option<u32>,
+    pub witness_seconds: f64,
+    pub keygen_seconds: Option<f64>,
+    pub proving_seconds: Option<f64>,
+    /// Peak resident memory of the keygen and the proof in bytes, on Linux only.
+    pub peak_memory: Option<u64>,
+    /// Size of the proof data in bytes.
+    pub proof_size: Option<usize>,
+    /// Gas of the solidity verifier, for Keccak transcript proofs only.
+    pub verification_gas: Option<U256>,
+    pub error: Option<ErrorBody>,
+}
+
+impl BenchResult {
+    fn new(case: &BenchCase, degree: Option<u32>, witness_time: Duration) -> Self {
+        Self {
+            preset: case.preset,
+            circuits_params: case.circuits_params,
+            degree,
+            witness_seconds: witness_time.as_secs_f64(),
+            keygen_seconds: None,
+            proving_seconds: None,
+            peak_memory: None,
+            proof_size: None,
+            verification_gas: None,
+            error: None,
+        }
+    }
+
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BenchReport {
+    pub transcript: ProofTranscript,
+    pub results: Vec<BenchResult>,
+}
+
+impl BenchReport {
+    /// The passing case with the shortest proving time.
+    pub fn fastest(&self) -> Option<&BenchResult> {
+        self.results
+            .iter()
+            .filter(|result| result.passed())
+            .min_by(|a, b| {
+                a.proving_seconds
+                    .partial_cmp(&b.proving_seconds)
+                    .unwrap_or(Ordering::Equal)
+            })
+    }
+
+    pub fn print(&self) {
+        for result in &self.results {
+            let name = BenchCase {
+                preset: result.preset,
+                circuits_params: result.circuits_params,
+            }
+            .name();
+            let degree = result
+                .degree
+                .map_or(String::new(), |degree| format!(" at degree {degree}"));
+            match &result.error {
+                Some(error) => println!(
+                    "FAIL {name}{degree} (witness {:.1}s): {} {}",
+                    result.witness_seconds,
+                    error.code.as_str(),
+                    error.message
+                ),
+                None => println!(
+                    "PASS {name}{degree}: witness {:.1}s, keygen {:.1}s, proving {:.1}s, peak memory {}, proof {} bytes, verification gas {}",
+                    result.witness_seconds,
+                    result.keygen_seconds.unwrap_or_default(),
+                    result.proving_seconds.unwrap_or_default(),
+                    result
+                        .peak_memory
+                        .map_or("unknown".to_string(), |bytes| format!("{} MB", bytes / 1_000_000)),
+                    result.proof_size.unwrap_or_default(),
+                    result
+                        .verification_gas
+                        .map_or("not measured".to_string(), |gas| gas.to_string()),
+                ),
+            }
+        }
+        if let Some(fastest) = self.fastest() {
+            println!(
+                "Fastest passing params: {:?} at degree {}",
+                fastest.circuits_params,
+                fastest.degree.unwrap_or_default()
+            );
+        }
+    }
+
+    pub fn write_to_file(&self, path: &Path) -> Result<(), Error> {
+        let mut file = File::create(path)?;
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Generates the witness of the exploit of `args` with the params of each case and proves it at
+/// each of `degrees`, or at the degree of the witness if there are none. A failing case does not
+/// stop the run, its error is recorded.
+pub async fn run(
+    mut args: ProveArgs,
+    cases: Vec<BenchCase>,
+    degrees: &[u32],
+) -> Result<BenchReport, Error> {
+    let mut results = vec![];
+    for case in cases {
+        info!("Benchmarking the {} params", case.name());
+        args.max_rws = case.circuits_params.max_rws;
+        args.max_copy_rows = case.circuits_params.max_copy_rows;
+        args.max_exp_steps = case.circuits_params.max_exp_steps;
+        args.max_bytecode = case.circuits_params.max_bytecode;
+        args.max_evm_rows = case.circuits_params.max_evm_rows;
+        args.max_keccak_rows = case.circuits_params.max_keccak_rows;
+
+        let start = Instant::now();
+        let witness = Witness::gen(&args).await;
+        let witness_time = start.elapsed();
+        let witness = match witness {
+            Ok(witness) => witness,
+            Err(error) => {
+                let mut result = BenchResult::new(&case, None, witness_time);
+                result.error = Some(error.to_body());
+                results.push(result);
+                continue;
+            }
+        };
+
+        let case_degrees = match degrees {
+            [] => vec![witness.degree()],
+            degrees => degrees.to_vec(),
+        };
+        for degree in case_degrees {
+            let mut result = BenchResult::new(&case, Some(degree), witness_time);
+            if let Err(error) = prove(&args, &witness, degree, &mut result).await {
+                result.error = Some(error.to_body());
+            }
+            results.push(result);
+        }
+    }
+    Ok(BenchReport {
+        transcript: args.transcript,
+        results,
+    })
+}
+
+async fn prove(
+    args: &ProveArgs,
+    witness: &Witness,
+    degree: u32,
+    result: &mut BenchResult,
+) -> Result<(), Error> {
+    if degree < witness.degree() {
+        return Err(Error::WitnessOverflow(format!(
+            "the exploit block needs degree {} with these params",
+            witness.degree()
+        )));
+    }
+    let block = witness
+        .block()
+        .ok_or(Error::InternalError("witness is missing its block"))?;
+    let circuit = Witness::from_block(block).into_circuit();
+    ceremony::provision(&args.srs_path, degree).await?;
+
+    preflight::reset_peak_memory();
+    let start = Instant::now();
+    let mut prover =
+        RealProver::from_with_options(circuit, degree, args.srs_path.clone(), args.prover_options)?;
+    prover.set_transcript(args.transcript);
+    prover.set_pcs_scheme(args.pcs_scheme);
+    result.keygen_seconds = Some(start.elapsed().as_secs_f64());
+
+    let start = Instant::now();
+    let proof = prover.prove()?;
+    result.proving_seconds = Some(start.elapsed().as_secs_f64());
+    result.peak_memory = preflight::peak_memory();
+    result.proof_size = Some(proof.data.len());
+
+    if proof.transcript == ProofTranscript::Keccak {
+        let source_path = args.srs_path.join(format!("bench_verifier_{degree}.sol"));
+        result.verification_gas = Some(prover.verifier().check_on_evm(&proof, &source_path).await?);
+    }
+    Ok(())
+}
@@ -0,0 +1,180 @@
+use crate::{
+    error::{Error, ErrorCode},
+    testing::ExploitTest,
+};
+use ethers::utils::parse_ether;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+use tracing::info;
+
+pub const SCENARIO_FILE_NAME: &str = "scenario.json";
+
+/// Definition of an exploit in the corpus, read from the `scenario.json` of its directory. Paths
+/// are relative to that directory, and like `--challenge` and `--exploit` hex bytecode can be
+/// given instead.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Scenario {
+    /// Overrides the rpc of the corpus run, for scenarios forking another chain.
+    #[serde(default)]
+    pub rpc: Option<String>,
+    #[serde(default)]
+    pub block: Option<usize>,
+    pub challenge: String,
+    pub exploit: String,
+    /// Ether amount funding the exploit address.
+    #[serde(default)]
+    pub exploit_balance: Option<String>,
+    #[serde(default)]
+    pub gas: Option<usize>,
+    /// Error the run is expected to fail with, the run is expected to succeed if there is none.
+    #[serde(default)]
+    pub expected_error: Option<ErrorCode>,
+}
+
+impl Scenario {
+    pub fn read_from_dir(dir: &Path) -> Result<Self, Error> {
+        Ok(serde_json::from_slice(&fs::read(
+            dir.join(SCENARIO_FILE_NAME),
+        )?)?)
+    }
+
+    fn test(&self, dir: &Path, rpc: &str) -> Result<ExploitTest, Error> {
+        let resolve = |input: &String| {
+            if input.starts_with("0x") {
+                input.clone()
+            } else {
+                dir.join(input).to_string_lossy().to_string()
+            }
+        };
+        let mut test = ExploitTest::from(
+            self.rpc.clone().unwrap_or(rpc.to_string()),
+            resolve(&self.challenge),
+            resolve(&self.exploit),
+        )?;
+        if let Some(block) = self.block {
+            test.set_block(block);
+        }
+        if let Some(exploit_balance) = &self.exploit_balance {
+            test.set_exploit_balance(parse_ether(exploit_balance).map_err(|_| {
+                Error::InvalidArgument(format!("invalid exploit_balance {exploit_balance}"))
+            })?);
+        }
+        if let Some(gas) = self.gas {
+            test.set_gas(gas);
+        }
+        Ok(test)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScenarioResult {
+    pub name: String,
+    pub expected_error: Option<ErrorCode>,
+    pub error: Option<ErrorCode>,
+    pub message: Option<String>,
+    pub seconds: u64,
+}
+
+impl ScenarioResult {
+    pub fn passed(&self) -> bool {
+        self.error == self.expected_error
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CorpusReport {
+    /// Whether real proofs were generated, otherwise only the MockProver ran.
+    pub prove: bool,
+    pub results: Vec<ScenarioResult>,
+}
+
+impl CorpusReport {
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(ScenarioResult::passed)
+    }
+
+    pub fn print(&self) {
+        for result in &self.results {
+            let outcome = |error: Option<ErrorCode>| error.map_or("SUCCESS", |code| code.as_str());
+            println!(
+                "{} {} ({}s): {}{}",
+                if result.passed() { "PASS" } else { "FAIL" },
+                result.name,
+                result.seconds,
+                outcome(result.error),
+                if result.passed() {
+                    String::new()
+                } else {
+                    format!(", expected {}", outcome(result.expected_error))
+                }
+            );
+        }
+        let passed = self.results.iter().filter(|result| result.passed()).count();
+        println!("{passed} of {} scenarios passed", self.results.len());
+    }
+
+    pub fn write_to_file(&self, path: &Path) -> Result<(), Error> {
+        let mut file = File::create(path)?;
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Runs every scenario in the subdirectories of `dir`, in the order of their names. Only the
+/// MockProver runs unless `prove` is set, in which case the proof of each scenario is written
+/// next to its definition. A failing scenario does not stop the run, its error is recorded.
+pub async fn run(
+    dir: &Path,
+    rpc: &str,
+    srs_path: PathBuf,
+    prove: bool,
+) -> Result<CorpusReport, Error> {
+    let mut scenario_dirs = fs::read_dir(dir)?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<_>, Error>>()?
+        .into_iter()
+        .filter(|path| path.join(SCENARIO_FILE_NAME).exists())
+        .collect::<Vec<_>>();
+    scenario_dirs.sort();
+
+    let mut results = vec![];
+    for scenario_dir in scenario_dirs {
+        let name = scenario_dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        info!("Running scenario {name}");
+        let start = Instant::now();
+        let scenario = Scenario::read_from_dir(&scenario_dir)?;
+        let result = match scenario.test(&scenario_dir, rpc) {
+            Ok(mut test) if prove => {
+                test.args_mut().srs_path = srs_path.clone();
+                test.args_mut().proof_out_path = Some(
+                    scenario_dir
+                        .join("proof.json")
+                        .to_string_lossy()
+                        .to_string(),
+                );
+                test.prove().await.map(|_| ())
+            }
+            Ok(test) => match test.witness().await {
+                Ok(witness) => witness.verify(),
+                Err(error) => Err(error),
+            },
+            Err(error) => Err(error),
+        };
+        results.push(ScenarioResult {
+            name,
+            expected_error: scenario.expected_error,
+            error: result.as_ref().err().map(Error::code),
+            message: result.err().map(|error| error.message()),
+            seconds: start.elapsed().as_secs(),
+        });
+    }
+    Ok(CorpusReport { prove, results })
+}
@@ -0,0 +1,235 @@
+//! Traces in the format of EIP-3155, one JSON object per executed opcode followed by a summary,
+//! which is what `evm --json` of geth and the tracers of other EVM implementations output.
+
+use crate::{
+    error::Error,
+    utils::anvil::{types::zkevm_types::*, AnvilClient},
+};
+use ethers::utils::hex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File},
+    io::Write,
+    path::Path,
+};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Eip3155Step {
+    pub pc: u64,
+    pub op: u8,
+    pub gas: U64,
+    pub gas_cost: U64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<Bytes>,
+    pub mem_size: u64,
+    pub stack: Vec<Word>,
+    pub depth: u64,
+    pub refund: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub op_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Eip3155Summary {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_root: Option<H256>,
+    pub output: Bytes,
+    pub gas_used: U64,
+    pub pass: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Eip3155Trace {
+    pub steps: Vec<Eip3155Step>,
+    pub summary: Eip3155Summary,
+}
+
+impl Eip3155Trace {
+    /// `state_root` is the state root after the transaction, which the format allows to omit.
+    pub fn from(trace: &GethExecTrace, state_root: Option<H256>) -> Self {
+        let steps = trace
+            .struct_logs
+            .iter()
+            .map(|step| Eip3155Step {
+                pc: step.pc,
+                op: step.op.as_u8(),
+                gas: U64::from(step.gas),
+                gas_cost: U64::from(step.gas_cost),
+                memory: (!step.memory.0.is_empty()).then(|| Bytes::from(step.memory.0.clone())),
+                mem_size: step.memory.0.len() as u64,
+                stack: step.stack.0.clone(),
+                depth: u64::from(step.depth),
+                refund: step.refund,
+                op_name: Some(format!("{:?}", step.op)),
+                error: step.error.clone(),
+            })
+            .collect();
+        Self {
+            steps,
+            summary: Eip3155Summary {
+                state_root,
+                output: Bytes::from(
+                    hex::decode(trace.return_value.trim_start_matches("0x")).unwrap_or_default(),
+                ),
+                gas_used: U64::from(trace.gas),
+                pass: !trace.failed,
+            },
+        }
+    }
+
+    /// Converts back to the trace format of the circuit input builder. EIP-3155 has no storage
+    /// field, bus_mapping takes the storage values from the stack and the state instead.
+    pub fn to_geth_trace(&self) -> GethExecTrace {
+        GethExecTrace {
+            gas: self.summary.gas_used.as_u64(),
+            failed: !self.summary.pass,
+            return_value: hex::encode(self.summary.output.as_ref()),
+            struct_logs: self
+                .steps
+                .iter()
+                .map(|step| GethExecStep {
+                    pc: step.pc,
+                    op: OpcodeId::from(step.op),
+                    gas: step.gas.as_u64(),
+                    gas_cost: step.gas_cost.as_u64(),
+                    refund: step.refund,
+                    depth: step.depth as u16,
+                    error: step.error.clone(),
+                    stack: Stack(step.stack.clone()),
+                    memory: Memory(
+                        step.memory
+                            .as_ref()
+                            .map(|memory| memory.to_vec())
+                            .unwrap_or_default(),
+                    ),
+                    storage: Storage(HashMap::new()),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn read_from_file(path: &Path) -> Result<Self, Error> {
+        let contents = fs::read_to_string(path)?;
+        let mut steps = vec![];
+        let mut summary = None;
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            // the summary is the only line without a pc
+            match serde_json::from_str::<Eip3155Step>(line) {
+                Ok(step) => steps.push(step),
+                Err(_) => summary = Some(serde_json::from_str::<Eip3155Summary>(line)?),
+            }
+        }
+        let summary = summary.ok_or(Error::InvalidArgument(format!(
+            "{} has no summary line",
+            path.display()
+        )))?;
+        Ok(Self { steps, summary })
+    }
+
+    pub fn write_to_file(&self, path: &Path) -> Result<(), Error> {
+        let mut file = File::create(path)?;
+        for step in &self.steps {
+            writeln!(file, "{}", serde_json::to_string(step)?)?;
+        }
+        writeln!(file, "{}", serde_json::to_string(&self.summary)?)?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AccountState {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balance: Option<U256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<U256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<Bytes>,
+    #[serde(default)]
+    pub storage: BTreeMap<U256, H256>,
+}
+
+/// Accounts to set on the fork before the exploit runs, in the `alloc` format of the t8n tools
+/// which usually produce EIP-3155 traces. Fields which are missing keep their forked value.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct StateSnapshot {
+    pub accounts: BTreeMap<Address, AccountState>,
+}
+
+impl StateSnapshot {
+    pub fn read_from_file(path: &Path) -> Result<Self, Error> {
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    pub async fn apply(&self, anvil: &AnvilClient) -> Result<(), Error> {
+        for (address, account) in &self.accounts {
+            if let Some(balance) = account.balance {
+                anvil.set_balance(*address, balance).await?;
+            }
+            if let Some(nonce) = account.nonce {
+                anvil.set_nonce(*address, nonce).await?;
+            }
+            if let Some(code) = &account.code {
+                anvil.set_code(*address, code.clone()).await?;
+            }
+            for (slot, value) in &account.storage {
+                anvil.set_storage_at(*address, *slot, *value).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Eip3155Trace;
+    use crate::utils::anvil::types::zkevm_types::*;
+    use std::collections::HashMap;
+
+    fn step(pc: u64, op: OpcodeId, stack: Vec<u64>, memory: Vec<u8>) -> GethExecStep {
+        GethExecStep {
+            pc,
+            op,
+            gas: 100_000 - pc,
+            gas_cost: 3,
+            refund: 0,
+            depth: 1,
+            error: None,
+            stack: Stack(stack.into_iter().map(Word::from).collect()),
+            memory: Memory(memory),
+            storage: Storage(HashMap::new()),
+        }
+    }
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let trace = GethExecTrace {
+            gas: 21_018,
+            failed: false,
+            return_value: "2a".to_string(),
+            struct_logs: vec![
+                step(0, OpcodeId::PUSH1, vec![], vec![]),
+                step(2, OpcodeId::PUSH1, vec![0x2a], vec![]),
+                step(4, OpcodeId::MSTORE, vec![0x2a, 0], vec![]),
+                step(5, OpcodeId::STOP, vec![], vec![0; 32]),
+            ],
+        };
+        let path =
+            std::env::temp_dir().join(format!("pox_eip3155_test_{}.jsonl", std::process::id()));
+        let state_root = Some(H256::repeat_byte(1));
+        Eip3155Trace::from(&trace, state_root)
+            .write_to_file(&path)
+            .unwrap();
+
+        let imported = Eip3155Trace::read_from_file(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(imported.steps.len(), trace.struct_logs.len());
+        assert_eq!(imported.summary.state_root, state_root);
+        assert_eq!(imported.to_geth_trace(), trace);
+    }
+}
@@ -0,0 +1,202 @@
+use super::{
+    halo2::{
+        helpers::FrWrapper,
+        ipa::IpaVerifier,
+        proof::{Proof, ProofBackend},
+        real_verifier::RealVerifier,
+    },
+    solidity::Artifact,
+};
+use crate::{
+    error::Error,
+    testing::ExploitTest,
+    witness::{circuit::ExploitCircuit, Witness},
+};
+use bus_mapping::{circuit_input_builder::FixedCParams, operation::Target};
+use eth_types::{keccak256, Bytes, Fr, H256, U256};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+use zkevm_circuits::{super_circuit::SuperCircuit, witness::Block};
+
+/// Shape of a witness block and a digest of its rw table, enough to tell whether witness
+/// generation still produces the same witness without storing the whole block.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WitnessSummary {
+    pub num_txs: usize,
+    pub num_rws: BTreeMap<String, usize>,
+    /// Keccak of the debug representation of every rw, in rw counter order.
+    pub rws_digest: H256,
+}
+
+impl WitnessSummary {
+    pub fn from(block: &Block<Fr>) -> Self {
+        let num_rws = block
+            .rws
+            .0
+            .iter()
+            .map(|(target, rws): (&Target, _)| (format!("{target:?}"), rws.len()))
+            .collect();
+        let mut rws = block.rws.0.values().flatten().collect::<Vec<_>>();
+        rws.sort_by_key(|rw| rw.rw_counter());
+        let rws_digest = H256::from(keccak256(
+            rws.iter()
+                .map(|rw| format!("{rw:?}"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ));
+        Self {
+            num_txs: block.txs.len(),
+            num_rws,
+            rws_digest,
+        }
+    }
+}
+
+/// Recorded pipeline run: the inputs it was generated from, the witness it produced and
+/// optionally its proof. Replaying it generates the witness again from the same inputs and
+/// reports any difference, which catches behavior changes of witness generation between
+/// versions of the crate or of zkevm-circuits.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Fixture {
+    pub block: usize,
+    /// Timestamp of the witness block, which is pinned when replaying so that the same block is
+    /// mined. Fixtures recorded before it was kept are replayed with the clock of anvil.
+    #[serde(default)]
+    pub timestamp: Option<u64>,
+    pub challenge_artifact: Artifact,
+    pub exploit_bytecode: Bytes,
+    pub exploit_balance: U256,
+    pub gas: Option<usize>,
+    pub fixed_circuit_params: FixedCParams,
+    pub degree: u32,
+    pub witness: WitnessSummary,
+    instances: Vec<Vec<FrWrapper>>,
+    pub proof: Option<Proof>,
+}
+
+impl Fixture {
+    pub fn record(test: &ExploitTest, witness: &Witness) -> Result<Self, Error> {
+        let block = witness
+            .block()
+            .ok_or(Error::InternalError("witness is missing its block"))?;
+        let args = test.args();
+        let public_data = SuperCircuit::<Fr>::public_data(block);
+        Ok(Self {
+            // the witness block is mined on top of the block the chain was forked from
+            block: public_data.block_constants.number.as_usize() - 1,
+            timestamp: Some(public_data.block_constants.timestamp.as_u64()),
+            challenge_artifact: args.challenge_artifact.clone(),
+            exploit_bytecode: args.exploit_bytecode.clone(),
+            exploit_balance: args.exploit_balance,
+            gas: args.gas,
+            fixed_circuit_params: witness.circuits_params(),
+            degree: witness.degree(),
+            witness: WitnessSummary::from(block),
+            instances: witness
+                .instances()
+                .iter()
+                .map(|column| column.iter().map(|element| FrWrapper(*element)).collect())
+                .collect(),
+            proof: None,
+        })
+    }
+
+    pub fn set_proof(&mut self, proof: Proof) {
+        self.proof = Some(proof);
+    }
+
+    pub fn instances(&self) -> Vec<Vec<Fr>> {
+        self.instances
+            .iter()
+            .map(|column| column.iter().map(|element| element.0).collect())
+            .collect()
+    }
+
+    /// Generates the witness again by forking `rpc` at the recorded block and compares it with
+    /// the recorded one. The recorded proof, if any, is verified with the keys in `srs_path`.
+    pub async fn replay(&self, rpc: String, srs_path: Option<PathBuf>) -> Result<(), Error> {
+        let mut test = ExploitTest::from_compiled(
+            rpc,
+            self.challenge_artifact.clone(),
+            self.exploit_bytecode.clone(),
+        );
+        test.set_block(self.block);
+        if let Some(timestamp) = self.timestamp {
+            test.set_block_timestamp(timestamp);
+        }
+        test.set_exploit_balance(self.exploit_balance);
+        if let Some(gas) = self.gas {
+            test.set_gas(gas);
+        }
+        test.set_circuit_params(self.fixed_circuit_params);
+        let witness = test.witness().await?;
+
+        let mut differences = vec![];
+        if witness.degree() != self.degree {
+            differences.push(format!(
+                "degree is {} but {} was recorded",
+                witness.degree(),
+                self.degree
+            ));
+        }
+        let block = witness
+            .block()
+            .ok_or(Error::InternalError("witness is missing its block"))?;
+        let summary = WitnessSummary::from(block);
+        if summary != self.witness {
+            differences.push(format!(
+                "witness is {summary:?} but {:?} was recorded",
+                self.witness
+            ));
+        }
+        if witness.instances() != &self.instances() {
+            differences.push("instances differ from the recorded ones".to_string());
+        }
+        if !differences.is_empty() {
+            return Err(Error::InvalidWitness(differences.join("\n")));
+        }
+
+        if let (Some(proof), Some(srs_path)) = (&self.proof, srs_path) {
+            match proof.backend {
+                ProofBackend::Kzg => {
+                    RealVerifier::load_srs(srs_path, proof)
+                        .await?
+                        .verify(proof)
+                        .await?
+                }
+                ProofBackend::Ipa => {
+                    IpaVerifier::load_srs(srs_path, proof)
+                        .await?
+                        .verify(proof)
+                        .await?
+                }
+                // a stub has nothing to verify but its instances
+                ProofBackend::Mock => {
+                    if proof.instances() != self.instances() {
+                        return Err(Error::InvalidWitness(
+                            "instances of the stub proof differ from the recorded ones".to_string(),
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write_to_file(&self, path: &Path) -> Result<(), Error> {
+        let mut file = File::create(path)?;
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn read_from_file(path: &Path) -> Result<Self, Error> {
+        let mut contents = String::new();
+        File::open(path)?.read_to_string(&mut contents)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+}
@@ -0,0 +1,218 @@
+use super::anvil::types::zkevm_types::{Address, Bytes, U256};
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+const STOP: u8 = 0x00;
+const ISZERO: u8 = 0x15;
+const CODECOPY: u8 = 0x39;
+const MLOAD: u8 = 0x51;
+const MSTORE: u8 = 0x52;
+const JUMPI: u8 = 0x57;
+const GAS: u8 = 0x5a;
+const JUMPDEST: u8 = 0x5b;
+const PUSH1: u8 = 0x60;
+const PUSH4: u8 = 0x63;
+const PUSH20: u8 = 0x73;
+const PUSH32: u8 = 0x7f;
+const DUP1: u8 = 0x80;
+const CREATE: u8 = 0xf0;
+const CALL: u8 = 0xf1;
+const REVERT: u8 = 0xfd;
+
+/// Size of the block the program jumps to when a call fails: JUMPDEST PUSH1 0 DUP1 REVERT.
+const REVERT_BLOCK_SIZE: u32 = 5;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BroadcastTransaction {
+    #[serde(default)]
+    pub to: Option<Address>,
+    #[serde(default)]
+    pub value: Option<U256>,
+    /// Renamed to `input` by later forge versions.
+    #[serde(default, alias = "input")]
+    pub data: Bytes,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BroadcastEntry {
+    #[serde(default)]
+    pub contract_address: Option<Address>,
+    pub transaction: BroadcastTransaction,
+}
+
+/// A `broadcast/<script>/<chain>/run-latest.json` file written by `forge script`. The proven
+/// block has a single transaction, so the transactions of the script are compiled into exploit
+/// bytecode which performs them one after the other as calls from the exploit address.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ForgeBroadcast {
+    pub transactions: Vec<BroadcastEntry>,
+}
+
+impl ForgeBroadcast {
+    pub fn read_from_file(path: &Path) -> Result<Self, Error> {
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    /// Bytecode which replays the transactions and reverts if any of them fails. Contracts the
+    /// script created get other addresses when created by the exploit, so calls to them and
+    /// ABI encoded arguments holding their address are redirected to the new addresses.
+    pub fn exploit_bytecode(&self) -> Result<Bytes, Error> {
+        // the layout only depends on the number of instructions, so a first pass with dummy
+        // offsets gives the size of the program
+        let program_size = self.program(0, 0)?.len() as u32;
+        let revert_dest = program_size;
+        let mut code = self.program(revert_dest, revert_dest + REVERT_BLOCK_SIZE)?;
+        code.extend([JUMPDEST, PUSH1, 0, DUP1, REVERT]);
+        for entry in &self.transactions {
+            code.extend_from_slice(&entry.transaction.data);
+        }
+        Ok(Bytes::from(code))
+    }
+
+    fn program(&self, revert_dest: u32, data_start: u32) -> Result<Vec<u8>, Error> {
+        let created = self
+            .transactions
+            .iter()
+            .filter(|entry| entry.transaction.to.is_none())
+            .filter_map(|entry| entry.contract_address)
+            .collect::<Vec<_>>();
+        // the created addresses are kept in memory words 0..n, calldata is copied after them
+        let slot = |address: &Address| {
+            created
+                .iter()
+                .position(|created| created == address)
+                .map(|index| index as u32 * 32)
+        };
+        let calldata_offset = created.len() as u32 * 32;
+
+        let mut code = vec![];
+        let mut data_offset = data_start;
+        let mut num_created = 0;
+        for (index, entry) in self.transactions.iter().enumerate() {
+            let tx = &entry.transaction;
+            let size = tx.data.len() as u32;
+            push4(&mut code, size);
+            push4(&mut code, data_offset);
+            push4(&mut code, calldata_offset);
+            code.push(CODECOPY);
+            data_offset += size;
+
+            for (position, address) in created.iter().enumerate() {
+                for start in find(&tx.data, address.as_bytes()) {
+                    if start < 12 || tx.data[start - 12..start].iter().any(|byte| *byte != 0) {
+                        return Err(Error::InvalidArgument(format!(
+                            "transaction {index} uses the address of a created contract outside of an ABI encoded argument"
+                        )));
+                    }
+                    if position >= num_created {
+                        return Err(Error::InvalidArgument(format!(
+                            "transaction {index} uses the address of a contract created later"
+                        )));
+                    }
+                    push4(&mut code, position as u32 * 32);
+                    code.push(MLOAD);
+                    push4(&mut code, calldata_offset + start as u32 - 12);
+                    code.push(MSTORE);
+                }
+            }
+
+            let value = tx.value.unwrap_or_default();
+            match tx.to {
+                Some(to) => {
+                    // CALL takes gas, address, value, args offset, args size, ret offset, ret size
+                    code.extend([PUSH1, 0, PUSH1, 0]);
+                    push4(&mut code, size);
+                    push4(&mut code, calldata_offset);
+                    push32(&mut code, value);
+                    match slot(&to) {
+                        Some(slot) => {
+                            push4(&mut code, slot);
+                            code.push(MLOAD);
+                        }
+                        None => {
+                            code.push(PUSH20);
+                            code.extend_from_slice(to.as_bytes());
+                        }
+                    }
+                    code.extend([GAS, CALL, ISZERO]);
+                }
+                None => {
+                    // CREATE takes value, offset, size and returns the address or zero
+                    push4(&mut code, size);
+                    push4(&mut code, calldata_offset);
+                    push32(&mut code, value);
+                    code.push(CREATE);
+                    if entry.contract_address.is_some() {
+                        code.push(DUP1);
+                        push4(&mut code, num_created as u32 * 32);
+                        code.push(MSTORE);
+                        num_created += 1;
+                    }
+                    code.push(ISZERO);
+                }
+            }
+            push4(&mut code, revert_dest);
+            code.push(JUMPI);
+        }
+        code.push(STOP);
+        Ok(code)
+    }
+}
+
+fn push4(code: &mut Vec<u8>, value: u32) {
+    code.push(PUSH4);
+    code.extend_from_slice(&value.to_be_bytes());
+}
+
+fn push32(code: &mut Vec<u8>, value: U256) {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    code.push(PUSH32);
+    code.extend_from_slice(&bytes);
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Vec<usize> {
+    haystack
+        .windows(needle.len())
+        .enumerate()
+        .filter(|(_, window)| *window == needle)
+        .map(|(start, _)| start)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ForgeBroadcast, JUMPDEST, REVERT, STOP};
+
+    #[test]
+    fn test_exploit_bytecode_layout() {
+        let broadcast: ForgeBroadcast = serde_json::from_str(
+            r#"{"transactions": [
+                {"contractAddress": "0x1111111111111111111111111111111111111111",
+                 "transaction": {"to": null, "input": "0x6000"}},
+                {"transaction": {"to": "0x1111111111111111111111111111111111111111", "value": "0x1",
+                 "input": "0xaabbccdd0000000000000000000000001111111111111111111111111111111111111111"}}
+            ]}"#,
+        )
+        .unwrap();
+        let code = broadcast.exploit_bytecode().unwrap();
+        let data = [
+            broadcast.transactions[0].transaction.data.to_vec(),
+            broadcast.transactions[1].transaction.data.to_vec(),
+        ]
+        .concat();
+        assert!(code.ends_with(&data));
+        let program_size = code.len() - data.len() - 5;
+        assert_eq!(code[program_size - 1], STOP);
+        assert_eq!(code[program_size], JUMPDEST);
+        assert_eq!(code[program_size + 4], REVERT);
+
+        // an address which is not left padded to a word cannot be redirected
+        let mut misplaced = broadcast.clone();
+        misplaced.transactions[1].transaction.data =
+            [vec![0xaa; 4], vec![0x11; 20]].concat().into();
+        assert!(misplaced.exploit_bytecode().is_err());
+    }
+}
@@ -1,19 +1,40 @@
-use super::{anvil::conversion::ConversionReverse, helpers::hashmap};
+use super::{
+    anvil::conversion::ConversionReverse,
+    helpers::hashmap,
+    watchdog::{watch, DEFAULT_RPC_TIMEOUT},
+};
 use crate::error::Error;
 use anvil_core::eth::transaction::EthTransactionRequest;
 use bus_mapping::{POX_CHALLENGE_ADDRESS, POX_EXPLOIT_ADDRESS};
-use eth_types::{Bytes, GethExecTrace, Transaction, U256, U64};
+use eth_types::{Block, Bytes, GethExecTrace, Transaction, H256, U256, U64};
 use ethers::{
-    providers::{Http, Provider},
+    providers::{Http, Middleware, Provider, RpcError},
     utils::hex,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
+
+/// Methods witness generation calls on a node which is not anvil, anvil forks it with the eth
+/// methods and the traces come from the debug methods.
+pub const REQUIRED_RPC_METHODS: [&str; 8] = [
+    "eth_chainId",
+    "eth_getBlockByNumber",
+    "eth_getBalance",
+    "eth_getCode",
+    "eth_getStorageAt",
+    "eth_getTransactionCount",
+    "debug_traceTransaction",
+    "debug_traceCall",
+];
+
+/// JSON-RPC error code of a method the node does not serve.
+const METHOD_NOT_FOUND: i64 = -32601;
 
 #[derive(Clone)]
 pub struct GethClient {
     provider: Provider<Http>,
+    timeout: Duration,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
@@ -42,10 +63,78 @@ struct StateOverrides {
 }
 
 impl GethClient {
-    pub fn new(url: String) -> Self {
-        GethClient {
-            provider: Provider::<Http>::try_from(&url).unwrap(),
+    pub fn new(url: String) -> Result<Self, Error> {
+        Ok(GethClient {
+            provider: Provider::<Http>::try_from(&url)
+                .map_err(|_| Error::InvalidArgument("invalid geth rpc url".to_string()))?,
+            timeout: DEFAULT_RPC_TIMEOUT,
+        })
+    }
+
+    /// Sets the time after which a pending request fails with `Error::Timeout`.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Fails with `Error::UnsupportedRpcMethod` naming the first of `REQUIRED_RPC_METHODS` the node
+    /// does not serve. Each method is called without params, a node serving it answers with an
+    /// invalid params error at most.
+    pub async fn check_capabilities(&self) -> Result<(), Error> {
+        for method in REQUIRED_RPC_METHODS {
+            let params: [(); 0] = [];
+            let result = watch(
+                method,
+                params,
+                self.timeout,
+                self.provider.request::<_, Value>(method, params),
+            )
+            .await;
+            let Err(err) = result else {
+                continue;
+            };
+            // an error response of the node other than method not found means that it serves the
+            // method, only the params were wrong
+            let missing = match &err {
+                Error::EthersProviderError(err) => err.as_error_response().map(|response| {
+                    response.code == METHOD_NOT_FOUND || response.message.contains("does not exist")
+                }),
+                _ => None,
+            };
+            match missing {
+                Some(true) => {
+                    return Err(Error::UnsupportedRpcMethod(format!(
+                        "the node does not serve {method}, witness generation needs {}",
+                        REQUIRED_RPC_METHODS.join(", ")
+                    )))
+                }
+                Some(false) => {}
+                None => return Err(err),
+            }
         }
+        Ok(())
+    }
+
+    pub async fn transaction_by_hash(&self, hash: H256) -> Result<Option<Transaction>, Error> {
+        watch(
+            "eth_getTransactionByHash",
+            hash,
+            self.timeout,
+            self.provider.get_transaction(hash),
+        )
+        .await
+    }
+
+    pub async fn block_by_number_full(
+        &self,
+        block_number: usize,
+    ) -> Result<Option<Block<Transaction>>, Error> {
+        watch(
+            "eth_getBlockByNumber",
+            (block_number, true),
+            self.timeout,
+            self.provider.get_block_with_txs(block_number as u64),
+        )
+        .await
     }
 
     pub async fn simulate_exploit(
@@ -55,47 +144,50 @@ impl GethClient {
         exploit_bytecode: Bytes,
         exploit_balance: U256,
     ) -> Result<GethExecTrace, Error> {
-        Ok(self
-            .provider
-            .request::<_, GethExecTrace>(
-                "debug_traceCall",
-                [
-                    serde_json::to_value(EthTransactionRequest {
-                        from: Some(tx.from),
-                        to: Some(POX_CHALLENGE_ADDRESS),
-                        gas_price: tx.gas_price,
-                        max_fee_per_gas: tx.max_fee_per_gas,
-                        max_priority_fee_per_gas: tx.max_priority_fee_per_gas,
-                        gas: Some(tx.gas),
-                        value: Some(tx.value),
-                        data: Some(tx.input.to_anvil_type()),
-                        nonce: Some(tx.nonce),
-                        chain_id: tx.chain_id.map(|c| U64::from(c.as_u64())),
-                        access_list: None, // TODO tx.access_list,
-                        transaction_type: tx.transaction_type.map(|c| U256::from(c.as_u64())),
-                    })
-                    .unwrap(),
-                    Value::String("latest".to_string()), // node not support archive trace - Value::String(format!("0x{block_number:x}")),
-                    serde_json::to_value(GethDebugTracingOptions {
-                        enable_memory: None,
-                        disable_stack: None,
-                        disable_storage: None,
-                        enable_return_data: None,
-                        timeout: None,
-                        state_overrides: Some(hashmap![
-                            format!("{POX_CHALLENGE_ADDRESS:?}") => StateOverrides {
-                                code: Some(hex::encode_prefixed(challenge_bytecode)),
-                                balance: None,
-                            },
-                            format!("{POX_EXPLOIT_ADDRESS:?}") => StateOverrides {
-                                code: Some(hex::encode_prefixed(exploit_bytecode)),
-                                balance: Some(format!("0x{exploit_balance:x}")),
-                            }
-                        ]),
-                    })
-                    .unwrap(),
-                ],
-            )
-            .await?)
+        let params = [
+            serde_json::to_value(EthTransactionRequest {
+                from: Some(tx.from),
+                to: Some(POX_CHALLENGE_ADDRESS),
+                gas_price: tx.gas_price,
+                max_fee_per_gas: tx.max_fee_per_gas,
+                max_priority_fee_per_gas: tx.max_priority_fee_per_gas,
+                gas: Some(tx.gas),
+                value: Some(tx.value),
+                data: Some(tx.input.to_anvil_type()),
+                nonce: Some(tx.nonce),
+                chain_id: tx.chain_id.map(|c| U64::from(c.as_u64())),
+                access_list: tx
+                    .access_list
+                    .as_ref()
+                    .map(|access_list| access_list.to_anvil_type().0),
+                transaction_type: tx.transaction_type.map(|c| U256::from(c.as_u64())),
+            })?,
+            Value::String("latest".to_string()), // node not support archive trace - Value::String(format!("0x{block_number:x}")),
+            serde_json::to_value(GethDebugTracingOptions {
+                enable_memory: None,
+                disable_stack: None,
+                disable_storage: None,
+                enable_return_data: None,
+                timeout: None,
+                state_overrides: Some(hashmap![
+                    format!("{POX_CHALLENGE_ADDRESS:?}") => StateOverrides {
+                        code: Some(hex::encode_prefixed(challenge_bytecode)),
+                        balance: None,
+                    },
+                    format!("{POX_EXPLOIT_ADDRESS:?}") => StateOverrides {
+                        code: Some(hex::encode_prefixed(exploit_bytecode)),
+                        balance: Some(format!("0x{exploit_balance:x}")),
+                    }
+                ]),
+            })?,
+        ];
+        watch(
+            "debug_traceCall",
+            &params,
+            self.timeout,
+            self.provider
+                .request::<_, GethExecTrace>("debug_traceCall", params.clone()),
+        )
+        .await
     }
 }
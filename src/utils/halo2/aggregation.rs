@@ -0,0 +1,971 @@
+use super::{
+    artifact_manifest::Artifact,
+    ceremony,
+    helpers::FrWrapper,
+    proof::{self, PcsScheme, Proof, ProofTranscript},
+    real_verifier::{self, verify_with_transcript, PoseidonTranscript, RATE, R_F, R_P, T},
+    srs::{self, ArtifactChecks, VerifierSRS},
+};
+use crate::{error::Error, witness::circuit::ExploitCircuit};
+use eth_types::{keccak256, U256};
+use ethers::{types::Bytes, utils::hex};
+use halo2_proofs::{
+    arithmetic::Field,
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    halo2curves::{
+        bn256::{Bn256, Fq, Fr, G1Affine},
+        CurveAffine,
+    },
+    plonk::{
+        self, create_proof, keygen_pk, keygen_vk, Circuit, ConstraintSystem, ProvingKey,
+        VerifyingKey,
+    },
+    poly::{
+        commitment::ParamsProver,
+        kzg::{
+            commitment::{KZGCommitmentScheme, ParamsKZG},
+            multiopen::{ProverSHPLONK, VerifierSHPLONK},
+            strategy::SingleStrategy,
+        },
+    },
+    transcript::{
+        Blake2bRead, Blake2bWrite, Challenge255, EncodedChallenge, TranscriptReadBuffer,
+        TranscriptWriterBuffer,
+    },
+    SerdeFormat,
+};
+use rand_chacha::{rand_core::SeedableRng, ChaChaRng};
+use rand_core::{OsRng, RngCore};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use snark_verifier::{
+    loader::{
+        self,
+        evm::EvmLoader,
+        halo2::halo2_wrong_ecc::{
+            self,
+            integer::rns::Rns,
+            maingate::{
+                MainGate, MainGateConfig, MainGateInstructions, RangeChip, RangeConfig,
+                RangeInstructions, RegionCtx,
+            },
+            EccConfig,
+        },
+        native::NativeLoader,
+    },
+    pcs::{
+        kzg::{
+            Bdfg21, KzgAccumulator, KzgAs, KzgDecidingKey, KzgSuccinctVerifyingKey, LimbsEncoding,
+            LimbsEncodingInstructions,
+        },
+        AccumulationDecider, AccumulationScheme, AccumulationSchemeProver,
+    },
+    system::halo2::{compile, transcript::evm::EvmTranscript, Config},
+    util::{
+        arithmetic::{fe_from_limbs, fe_to_limbs},
+        hash::Poseidon,
+    },
+    verifier::{self, plonk::PlonkProtocol, SnarkVerifier},
+};
+use std::{
+    fs::File,
+    io::{Read, Write},
+    iter,
+    path::{Path, PathBuf},
+    rc::Rc,
+    str::FromStr,
+};
+use tracing::debug;
+use zkevm_circuits::super_circuit::SuperCircuit;
+
+const LIMBS: usize = 4;
+const BITS: usize = 68;
+
+/// Number of instances taken by the accumulator at the start of the aggregation instance column.
+pub const ACCUMULATOR_INSTANCES: usize = 4 * LIMBS;
+
+/// Degree used for the aggregation circuit when none is provided.
+pub const DEFAULT_AGGREGATION_DEGREE: u32 = 22;
+
+/// Degree of the outer circuit wrapping a single proof, which is enough to verify one SuperCircuit
+/// proof in-circuit.
+pub const DEFAULT_WRAPPER_DEGREE: u32 = 21;
+
+const SERDE_FORMAT: SerdeFormat = SerdeFormat::RawBytes;
+
+/// Name of the aggregation circuit in the manifests of its keys.
+const AGGREGATION_CIRCUIT_NAME: &str = "AggregationCircuit";
+
+/// Inner proofs are created with SHPLONK, which snark-verifier calls Bdfg21.
+type As = KzgAs<Bn256, Bdfg21>;
+type PlonkSuccinctVerifier = verifier::plonk::PlonkSuccinctVerifier<As, LimbsEncoding<LIMBS, BITS>>;
+/// Verifier of the aggregation proof itself, which decodes the accumulator from the instances and
+/// checks its pairing along with the proof's own.
+type EvmPlonkVerifier = verifier::plonk::PlonkVerifier<As, LimbsEncoding<LIMBS, BITS>>;
+type Svk = KzgSuccinctVerifyingKey<G1Affine>;
+type BaseFieldEccChip = halo2_wrong_ecc::BaseFieldEccChip<G1Affine, LIMBS, BITS>;
+type Halo2Loader<'a> = loader::halo2::Halo2Loader<'a, G1Affine, BaseFieldEccChip>;
+type LoadedScalar<'a> = loader::halo2::Scalar<'a, G1Affine, BaseFieldEccChip>;
+
+/// Accumulator of the proofs verified in an aggregation circuit, the proofs are only valid once
+/// its pairing is checked with `decide_batch`.
+pub type Accumulator = KzgAccumulator<G1Affine, NativeLoader>;
+
+/// How the aggregation circuit exposes the instances of the proofs it aggregates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstanceEncoding {
+    /// Every instance is exposed after the accumulator.
+    #[default]
+    Plain,
+    /// Only a Poseidon digest of all the instances is exposed, hashed in-circuit. The calldata no
+    /// longer grows with the number of proofs and an outer circuit recomputes the digest cheaply.
+    Poseidon,
+}
+
+impl InstanceEncoding {
+    /// The encoding changes the circuit, so its keys are cached under a different id.
+    fn key_id(&self, key_id: &str) -> String {
+        match self {
+            InstanceEncoding::Plain => key_id.to_string(),
+            InstanceEncoding::Poseidon => format!("{key_id}_poseidon"),
+        }
+    }
+
+    fn num_instance(&self, num_inner_instances: usize) -> usize {
+        match self {
+            InstanceEncoding::Plain => num_inner_instances,
+            InstanceEncoding::Poseidon => 1,
+        }
+    }
+}
+
+/// Poseidon digest of the instances, computed natively the same way the aggregation circuit does.
+pub fn instances_digest(instances: &[Fr]) -> Fr {
+    let mut hasher = Poseidon::<Fr, Fr, T, RATE>::new(&NativeLoader, R_F, R_P);
+    hasher.update(instances);
+    hasher.squeeze()
+}
+
+/// A proof along with the protocol compiled from the verifying key it verifies against.
+#[derive(Clone)]
+pub struct Snark {
+    protocol: PlonkProtocol<G1Affine>,
+    instances: Vec<Vec<Fr>>,
+    proof: Vec<u8>,
+}
+
+impl Snark {
+    pub fn from(
+        params: &ParamsKZG<Bn256>,
+        vk: &VerifyingKey<G1Affine>,
+        proof: &Proof,
+    ) -> Result<Self, Error> {
+        if proof.transcript != ProofTranscript::Poseidon {
+            return Err(Error::InvalidArgument(
+                "only proofs generated with --for-aggregation can be aggregated".to_string(),
+            ));
+        }
+        if proof.pcs_scheme != PcsScheme::Shplonk {
+            return Err(Error::InvalidArgument(
+                "only SHPLONK proofs can be aggregated".to_string(),
+            ));
+        }
+        let protocol = compile(
+            params,
+            vk,
+            Config::kzg().with_num_instance(proof.num_instances()),
+        );
+        Ok(Self {
+            protocol,
+            instances: proof.instances(),
+            proof: proof.data.to_vec(),
+        })
+    }
+}
+
+#[derive(Clone)]
+struct SnarkWitness {
+    protocol: PlonkProtocol<G1Affine>,
+    instances: Vec<Vec<Value<Fr>>>,
+    proof: Value<Vec<u8>>,
+}
+
+impl From<Snark> for SnarkWitness {
+    fn from(snark: Snark) -> Self {
+        Self {
+            protocol: snark.protocol,
+            instances: snark
+                .instances
+                .into_iter()
+                .map(|column| column.into_iter().map(Value::known).collect())
+                .collect(),
+            proof: Value::known(snark.proof),
+        }
+    }
+}
+
+impl SnarkWitness {
+    fn without_witnesses(&self) -> Self {
+        Self {
+            protocol: self.protocol.clone(),
+            instances: self
+                .instances
+                .iter()
+                .map(|column| vec![Value::unknown(); column.len()])
+                .collect(),
+            proof: Value::unknown(),
+        }
+    }
+
+    fn proof(&self) -> Value<&[u8]> {
+        self.proof.as_ref().map(Vec::as_slice)
+    }
+}
+
+/// Verifies the snarks inside the circuit and folds their accumulators into a single one, the
+/// loaded instances of every snark are returned so that they can be exposed as public inputs.
+#[allow(clippy::type_complexity)]
+fn aggregate<'a>(
+    svk: &Svk,
+    loader: &Rc<Halo2Loader<'a>>,
+    snarks: &[SnarkWitness],
+    as_proof: Value<&'_ [u8]>,
+) -> Result<
+    (
+        KzgAccumulator<G1Affine, Rc<Halo2Loader<'a>>>,
+        Vec<Vec<Vec<LoadedScalar<'a>>>>,
+    ),
+    snark_verifier::Error,
+> {
+    let mut accumulators = vec![];
+    let mut loaded_instances = vec![];
+    for snark in snarks {
+        let protocol = snark.protocol.loaded(loader);
+        let instances = snark
+            .instances
+            .iter()
+            .map(|column| {
+                column
+                    .iter()
+                    .map(|instance| loader.assign_scalar(*instance))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let mut transcript = PoseidonTranscript::<Rc<Halo2Loader>, _>::new(loader, snark.proof());
+        let proof = PlonkSuccinctVerifier::read_proof(svk, &protocol, &instances, &mut transcript)?;
+        accumulators.extend(PlonkSuccinctVerifier::verify(
+            svk, &protocol, &instances, &proof,
+        )?);
+        loaded_instances.push(instances);
+    }
+
+    let mut transcript = PoseidonTranscript::<Rc<Halo2Loader>, _>::new(loader, as_proof);
+    let proof = As::read_proof(&Default::default(), &accumulators, &mut transcript)?;
+    let accumulator = As::verify(&Default::default(), &accumulators, &proof)?;
+    Ok((accumulator, loaded_instances))
+}
+
+#[derive(Clone)]
+pub struct AggregationConfig {
+    main_gate_config: MainGateConfig,
+    range_config: RangeConfig,
+}
+
+impl AggregationConfig {
+    fn main_gate(&self) -> MainGate<Fr> {
+        MainGate::new(self.main_gate_config.clone())
+    }
+
+    fn range_chip(&self) -> RangeChip<Fr> {
+        RangeChip::new(self.range_config.clone())
+    }
+
+    fn ecc_chip(&self) -> BaseFieldEccChip {
+        BaseFieldEccChip::new(EccConfig::new(
+            self.range_config.clone(),
+            self.main_gate_config.clone(),
+        ))
+    }
+}
+
+/// Circuit verifying several SuperCircuit proofs. Its instance column holds the limbs of the
+/// folded KZG accumulator followed by the instances of every aggregated proof, in order, or by
+/// their digest with `InstanceEncoding::Poseidon`.
+#[derive(Clone)]
+pub struct AggregationCircuit {
+    svk: Svk,
+    snarks: Vec<SnarkWitness>,
+    instances: Vec<Fr>,
+    instance_encoding: InstanceEncoding,
+    as_proof: Value<Vec<u8>>,
+}
+
+impl AggregationCircuit {
+    pub fn new(
+        params: &ParamsKZG<Bn256>,
+        snarks: Vec<Snark>,
+        instance_encoding: InstanceEncoding,
+    ) -> Result<Self, Error> {
+        if snarks.is_empty() {
+            return Err(Error::InvalidArgument(
+                "at least one proof is needed for aggregation".to_string(),
+            ));
+        }
+        let svk: Svk = params.get_g()[0].into();
+
+        let mut accumulators = vec![];
+        for snark in &snarks {
+            let mut transcript = PoseidonTranscript::<NativeLoader, _>::new(snark.proof.as_slice());
+            let proof = PlonkSuccinctVerifier::read_proof(
+                &svk,
+                &snark.protocol,
+                &snark.instances,
+                &mut transcript,
+            )?;
+            accumulators.extend(PlonkSuccinctVerifier::verify(
+                &svk,
+                &snark.protocol,
+                &snark.instances,
+                &proof,
+            )?);
+        }
+
+        let mut transcript = PoseidonTranscript::<NativeLoader, _>::new(Vec::new());
+        let KzgAccumulator { lhs, rhs } = As::create_proof(
+            &Default::default(),
+            &accumulators,
+            &mut transcript,
+            ChaChaRng::seed_from_u64(2),
+        )?;
+        let as_proof = transcript.finalize();
+
+        let mut instances = [lhs.x, lhs.y, rhs.x, rhs.y]
+            .map(fe_to_limbs::<_, _, LIMBS, BITS>)
+            .concat();
+        let inner_instances = snarks
+            .iter()
+            .flat_map(|snark| snark.instances.iter().flatten().copied())
+            .collect::<Vec<_>>();
+        match instance_encoding {
+            InstanceEncoding::Plain => instances.extend(inner_instances),
+            InstanceEncoding::Poseidon => instances.push(instances_digest(&inner_instances)),
+        }
+
+        Ok(Self {
+            svk,
+            snarks: snarks.into_iter().map(SnarkWitness::from).collect(),
+            instances,
+            instance_encoding,
+            as_proof: Value::known(as_proof),
+        })
+    }
+
+    pub fn instances(&self) -> Vec<Vec<Fr>> {
+        vec![self.instances.clone()]
+    }
+
+    /// Positions of the accumulator limbs in the instances, a circuit verifying this circuit's
+    /// proof compiles it with these so that the accumulator is folded into its own.
+    pub fn accumulator_indices() -> Vec<(usize, usize)> {
+        (0..ACCUMULATOR_INSTANCES).map(|row| (0, row)).collect()
+    }
+
+    pub fn num_instance(&self) -> Vec<usize> {
+        let num_inner_instances = self
+            .snarks
+            .iter()
+            .map(|snark| snark.instances.iter().map(Vec::len).sum::<usize>())
+            .sum::<usize>();
+        vec![ACCUMULATOR_INSTANCES + self.instance_encoding.num_instance(num_inner_instances)]
+    }
+
+    fn as_proof(&self) -> Value<&[u8]> {
+        self.as_proof.as_ref().map(Vec::as_slice)
+    }
+}
+
+impl Circuit<Fr> for AggregationCircuit {
+    type Config = AggregationConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            svk: self.svk,
+            snarks: self
+                .snarks
+                .iter()
+                .map(SnarkWitness::without_witnesses)
+                .collect(),
+            instances: Vec::new(),
+            instance_encoding: self.instance_encoding,
+            as_proof: Value::unknown(),
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let main_gate_config = MainGate::<Fr>::configure(meta);
+        let range_config = RangeChip::<Fr>::configure(
+            meta,
+            &main_gate_config,
+            vec![BITS / LIMBS],
+            Rns::<Fq, Fr, LIMBS, BITS>::construct().overflow_lengths(),
+        );
+        AggregationConfig {
+            main_gate_config,
+            range_config,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), plonk::Error> {
+        let main_gate = config.main_gate();
+        let range_chip = config.range_chip();
+        range_chip.load_table(&mut layouter)?;
+
+        let (accumulator_limbs, instances) = layouter.assign_region(
+            || "aggregation",
+            |region| {
+                let ctx = RegionCtx::new(region, 0);
+                let loader = Halo2Loader::new(config.ecc_chip(), ctx);
+                let (accumulator, instances) =
+                    aggregate(&self.svk, &loader, &self.snarks, self.as_proof())
+                        .map_err(|_| plonk::Error::Synthesis)?;
+
+                let accumulator_limbs = [accumulator.lhs, accumulator.rhs]
+                    .iter()
+                    .map(|ec_point| {
+                        loader
+                            .ecc_chip()
+                            .assign_ec_point_to_limbs(&mut loader.ctx_mut(), ec_point.assigned())
+                    })
+                    .collect::<Result<Vec<_>, plonk::Error>>()?
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<_>>();
+                let instances = instances
+                    .into_iter()
+                    .flatten()
+                    .flatten()
+                    .collect::<Vec<_>>();
+                let instances = match self.instance_encoding {
+                    InstanceEncoding::Plain => instances,
+                    InstanceEncoding::Poseidon => {
+                        let mut hasher =
+                            Poseidon::<Fr, LoadedScalar, T, RATE>::new(&loader, R_F, R_P);
+                        hasher.update(&instances);
+                        vec![hasher.squeeze()]
+                    }
+                };
+                let instances = instances
+                    .into_iter()
+                    .map(|instance| instance.into_assigned())
+                    .collect::<Vec<_>>();
+                Ok((accumulator_limbs, instances))
+            },
+        )?;
+
+        for (row, cell) in accumulator_limbs.into_iter().chain(instances).enumerate() {
+            main_gate.expose_public(layouter.namespace(|| "instance"), cell, row)?;
+        }
+        Ok(())
+    }
+}
+
+/// Proof of several exploit proofs, along with the proofs it aggregates so that a verifier can
+/// recompute their verifying keys and check their public data.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AggregatedProof {
+    pub version: Version,
+    pub degree: u32,
+    pub data: Bytes,
+    instances: Vec<Vec<FrWrapper>>,
+    #[serde(default)]
+    pub instance_encoding: InstanceEncoding,
+    /// Transcript of the aggregation proof, the aggregated proofs always use Poseidon.
+    #[serde(default)]
+    pub transcript: ProofTranscript,
+    pub proofs: Vec<Proof>,
+}
+
+impl AggregatedProof {
+    pub fn instances(&self) -> Vec<Vec<Fr>> {
+        self.instances
+            .iter()
+            .map(|column| column.iter().map(|element| element.0).collect())
+            .collect()
+    }
+
+    /// The accumulator exposed in the instances, which defers the pairing check of the
+    /// aggregated proofs.
+    pub fn accumulator(&self) -> Result<Accumulator, Error> {
+        let instances = self.instances();
+        let limbs = instances
+            .first()
+            .filter(|column| column.len() >= ACCUMULATOR_INSTANCES)
+            .ok_or(Error::InvalidArgument(
+                "aggregated proof has no accumulator in its instances".to_string(),
+            ))?;
+        accumulator_from_limbs(&limbs[..ACCUMULATOR_INSTANCES])
+    }
+
+    /// Calldata of the solidity verifier generated by `generate_solidity`.
+    pub fn evm_calldata(&self) -> Vec<u8> {
+        proof::evm_calldata(&self.instances(), &self.data)
+    }
+
+    pub fn write_to_file(&self, path: &PathBuf) -> Result<(), Error> {
+        let mut file = File::create(path)?;
+        file.write_all(serde_json::to_string(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn read_from_file(path: &PathBuf) -> Result<Self, Error> {
+        let mut file = File::open(path)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+}
+
+pub struct AggregationProver {
+    circuit: AggregationCircuit,
+    degree: u32,
+    general_params: ParamsKZG<Bn256>,
+    proving_key: ProvingKey<G1Affine>,
+    proofs: Vec<Proof>,
+    transcript: ProofTranscript,
+    /// Randomness of the blinding factors, the OS randomness when there is none.
+    rng: Option<ChaChaRng>,
+}
+
+impl AggregationProver {
+    /// Loads the verifying keys of the proofs and the keys of the aggregation circuit from
+    /// `srs_path`, downloading the params and generating and caching the aggregation keys if
+    /// they are missing.
+    pub async fn from(
+        srs_path: PathBuf,
+        proofs: Vec<Proof>,
+        degree: u32,
+        instance_encoding: InstanceEncoding,
+    ) -> Result<Self, Error> {
+        let (snarks, key_id) = load_snarks(&srs_path, &proofs).await?;
+        ceremony::provision(&srs_path, degree).await?;
+        Self::from_snarks(
+            &srs_path,
+            snarks,
+            &key_id,
+            proofs,
+            degree,
+            instance_encoding,
+        )
+    }
+
+    /// Same as `from` for snarks whose verifying keys are already loaded, `key_id` identifies
+    /// those keys in the file names of the cached aggregation keys.
+    pub fn from_snarks(
+        srs_path: &Path,
+        snarks: Vec<Snark>,
+        key_id: &str,
+        proofs: Vec<Proof>,
+        degree: u32,
+        instance_encoding: InstanceEncoding,
+    ) -> Result<Self, Error> {
+        let general_params = srs::load_general_params(srs_path.to_path_buf(), degree)?;
+        let circuit = AggregationCircuit::new(&general_params, snarks, instance_encoding)?;
+        let key_id = instance_encoding.key_id(key_id);
+        let verifying_key =
+            load_verifying_key(srs_path, degree, &key_id, &general_params, &circuit)?;
+        let proving_key = srs::read_or_gen(
+            Artifact {
+                label: "aggregation proving key",
+                circuit: Some(AGGREGATION_CIRCUIT_NAME),
+                degree,
+                checks: ArtifactChecks::default(),
+            },
+            srs_path.join(proving_key_file_name(degree, &key_id)),
+            |file| {
+                Ok(ProvingKey::<G1Affine>::read::<File, AggregationCircuit>(
+                    file,
+                    SERDE_FORMAT,
+                    (),
+                )?)
+            },
+            |mut file| {
+                let pk = keygen_pk(&general_params, verifying_key.clone(), &circuit)?;
+                pk.write(&mut file, SERDE_FORMAT)?;
+                Ok(pk)
+            },
+        )?;
+        Ok(Self {
+            circuit,
+            degree,
+            general_params,
+            proving_key,
+            proofs,
+            transcript: ProofTranscript::default(),
+            rng: None,
+        })
+    }
+
+    /// The aggregation proof is created with a Blake2b transcript by default, a Keccak one is
+    /// needed for the solidity verifier of `generate_solidity`.
+    pub fn set_transcript(&mut self, transcript: ProofTranscript) {
+        self.transcript = transcript;
+    }
+
+    /// Blinds the proof with `rng` instead of the OS randomness, see `RealProver::set_rng`.
+    pub fn set_rng(&mut self, rng: ChaChaRng) {
+        self.rng = Some(rng);
+    }
+
+    pub fn prove(mut self) -> Result<AggregatedProof, Error> {
+        let instances = self.circuit.instances();
+        let data = match self.transcript {
+            ProofTranscript::Blake2b => {
+                self.create_proof::<_, Blake2bWrite<_, G1Affine, Challenge255<_>>>(&instances)?
+            }
+            ProofTranscript::Poseidon => {
+                self.create_proof::<_, PoseidonTranscript<NativeLoader, _>>(&instances)?
+            }
+            ProofTranscript::Keccak => {
+                self.create_proof::<_, EvmTranscript<G1Affine, NativeLoader, _, _>>(&instances)?
+            }
+        };
+
+        Ok(AggregatedProof {
+            // cargo guarantees that the package version is valid semver
+            version: Version::from_str(env!("CARGO_PKG_VERSION")).unwrap(),
+            degree: self.degree,
+            data: Bytes::from(data),
+            instances: instances
+                .iter()
+                .map(|column| column.iter().map(|element| FrWrapper(*element)).collect())
+                .collect(),
+            instance_encoding: self.circuit.instance_encoding,
+            transcript: self.transcript,
+            proofs: self.proofs,
+        })
+    }
+
+    fn create_proof<E, T>(&mut self, instances: &[Vec<Fr>]) -> Result<Vec<u8>, Error>
+    where
+        E: EncodedChallenge<G1Affine>,
+        T: TranscriptWriterBuffer<Vec<u8>, G1Affine, E>,
+    {
+        let instance_refs = instances.iter().map(|v| &v[..]).collect::<Vec<&[Fr]>>();
+        let mut transcript = T::init(vec![]);
+        let mut os_rng = OsRng;
+        let rng: &mut dyn RngCore = match self.rng.as_mut() {
+            Some(rng) => rng,
+            None => &mut os_rng,
+        };
+        create_proof::<KZGCommitmentScheme<Bn256>, ProverSHPLONK<'_, Bn256>, E, _, T, _>(
+            &self.general_params,
+            &self.proving_key,
+            &[self.circuit.clone()],
+            &[&instance_refs],
+            rng,
+            &mut transcript,
+        )?;
+        Ok(transcript.finalize())
+    }
+}
+
+/// Verifies the aggregated proof, decides its accumulator and checks that the instances of every
+/// aggregated proof are the image of its public data.
+pub async fn verify(srs_path: PathBuf, aggregated: &AggregatedProof) -> Result<(), Error> {
+    let (general_params, accumulator) = verify_without_pairing(srs_path, aggregated).await?;
+    decide_batch(&general_params, &[accumulator])?;
+    debug!("Accumulator of the aggregated proofs is valid");
+    Ok(())
+}
+
+/// Same as `verify` except for the pairing check of the accumulator, which is returned so that
+/// the accumulators of many aggregated proofs can be decided at once with `decide_batch`.
+pub async fn verify_deferred(
+    srs_path: PathBuf,
+    aggregated: &AggregatedProof,
+) -> Result<Accumulator, Error> {
+    Ok(verify_without_pairing(srs_path, aggregated).await?.1)
+}
+
+/// Solidity source of a contract verifying aggregated proofs of the same proofs' circuits as
+/// `aggregated` on-chain, the verification itself is generated Yul. It checks the pairing of the
+/// accumulator in the instances along with the aggregation proof, which has to be created with a
+/// Keccak transcript. The instances of the aggregated proofs are not checked against their public
+/// data, the calling contract does that.
+pub async fn generate_solidity(
+    srs_path: PathBuf,
+    aggregated: &AggregatedProof,
+) -> Result<String, Error> {
+    let (general_params, circuit, verifying_key) =
+        load_aggregation_key(srs_path, aggregated).await?;
+    let num_instance = circuit.num_instance();
+    let protocol = compile(
+        &general_params,
+        &verifying_key,
+        Config::kzg()
+            .with_num_instance(num_instance.clone())
+            .with_accumulator_indices(Some(AggregationCircuit::accumulator_indices())),
+    );
+    let dk: KzgDecidingKey<Bn256> = (
+        general_params.get_g()[0],
+        general_params.g2(),
+        general_params.s_g2(),
+    )
+        .into();
+
+    let loader = EvmLoader::new::<Fq, Fr>();
+    let protocol = protocol.loaded(&loader);
+    let mut transcript = EvmTranscript::<_, Rc<EvmLoader>, _, _>::new(&loader);
+    let instances = transcript.load_instances(num_instance);
+    let proof = EvmPlonkVerifier::read_proof(&dk, &protocol, &instances, &mut transcript)?;
+    EvmPlonkVerifier::verify(&dk, &protocol, &instances, &proof)?;
+    Ok(real_verifier::with_wrapper(loader.solidity_code()))
+}
+
+/// Calls the solidity verifier written by `generate_solidity` with the aggregated proof in a local
+/// anvil and returns the gas the verification costs.
+pub async fn verify_on_evm(
+    source_path: &Path,
+    aggregated: &AggregatedProof,
+) -> Result<U256, Error> {
+    if aggregated.transcript != ProofTranscript::Keccak {
+        return Err(Error::InvalidArgument(format!(
+            "the solidity verifier only accepts aggregated proofs created with --transcript keccak, the proof has a {:?} transcript",
+            aggregated.transcript
+        )));
+    }
+    real_verifier::call_evm_verifier(source_path, aggregated.evm_calldata()).await
+}
+
+async fn verify_without_pairing(
+    srs_path: PathBuf,
+    aggregated: &AggregatedProof,
+) -> Result<(ParamsKZG<Bn256>, Accumulator), Error> {
+    let (general_params, circuit, verifying_key) =
+        load_aggregation_key(srs_path, aggregated).await?;
+
+    let instances = aggregated.instances();
+    let instance_refs = instances.iter().map(|v| &v[..]).collect::<Vec<&[Fr]>>();
+    let data = &aggregated.data[..];
+    match aggregated.transcript {
+        ProofTranscript::Blake2b => verify_with_transcript::<VerifierSHPLONK<'_, Bn256>, _, _, _>(
+            general_params.verifier_params(),
+            &verifying_key,
+            SingleStrategy::new(&general_params),
+            &instance_refs,
+            &mut Blake2bRead::<_, G1Affine, Challenge255<_>>::init(data),
+        )?,
+        ProofTranscript::Poseidon => verify_with_transcript::<VerifierSHPLONK<'_, Bn256>, _, _, _>(
+            general_params.verifier_params(),
+            &verifying_key,
+            SingleStrategy::new(&general_params),
+            &instance_refs,
+            &mut PoseidonTranscript::<NativeLoader, _>::new(data),
+        )?,
+        ProofTranscript::Keccak => verify_with_transcript::<VerifierSHPLONK<'_, Bn256>, _, _, _>(
+            general_params.verifier_params(),
+            &verifying_key,
+            SingleStrategy::new(&general_params),
+            &instance_refs,
+            &mut EvmTranscript::<G1Affine, NativeLoader, _, _>::new(data),
+        )?,
+    }
+    debug!("Aggregation proof verifies");
+
+    let column = instances
+        .first()
+        .ok_or(Error::InternalError("aggregated proof has no instances"))?;
+    if column.len() != circuit.num_instance()[0] {
+        return Err(Error::InvalidArgument(
+            "aggregated proof instances do not match the aggregated proofs".to_string(),
+        ));
+    }
+    let (accumulator_limbs, inner_instances) = column.split_at(ACCUMULATOR_INSTANCES);
+    let accumulator = accumulator_from_limbs(accumulator_limbs)?;
+
+    let proofs_instances = aggregated
+        .proofs
+        .iter()
+        .flat_map(|proof| proof.instances().into_iter().flatten())
+        .collect::<Vec<_>>();
+    let matches = match aggregated.instance_encoding {
+        InstanceEncoding::Plain => inner_instances == &proofs_instances[..],
+        InstanceEncoding::Poseidon => inner_instances == [instances_digest(&proofs_instances)],
+    };
+    if !matches {
+        return Err(Error::InvalidArgument(
+            "instances of the aggregated proofs do not match the aggregation instances".to_string(),
+        ));
+    }
+    for (index, proof) in aggregated.proofs.iter().enumerate() {
+        let expected = SuperCircuit::<Fr>::expected_instances(&proof.public_data);
+        if proof.instances().first() != expected.first() {
+            return Err(Error::InvalidArgument(format!(
+                "public data of aggregated proof {index} does not match its instances"
+            )));
+        }
+    }
+    debug!("Public data of the aggregated proofs matches the instances");
+    Ok((general_params, accumulator))
+}
+
+/// Folds the accumulators with powers of a challenge derived from all of them and checks the
+/// pairing of the result, so that any number of accumulators cost a single pairing check.
+pub fn decide_batch(params: &ParamsKZG<Bn256>, accumulators: &[Accumulator]) -> Result<(), Error> {
+    let seed = keccak256(
+        accumulators
+            .iter()
+            .flat_map(|accumulator| [accumulator.lhs, accumulator.rhs])
+            .flat_map(|point| [point.x.to_bytes(), point.y.to_bytes()])
+            .flatten()
+            .collect::<Vec<u8>>(),
+    );
+    let challenge = Fr::random(ChaChaRng::from_seed(seed));
+    let powers = iter::successors(Some(Fr::from(1)), |power| Some(*power * challenge));
+    let fold = |points: Vec<G1Affine>| {
+        points
+            .iter()
+            .zip(powers.clone())
+            .map(|(point, power)| point * power)
+            .reduce(|acc, point| acc + point)
+            .map(G1Affine::from)
+            .ok_or(Error::InvalidArgument(
+                "no accumulators to decide".to_string(),
+            ))
+    };
+    let accumulator = KzgAccumulator::new(
+        fold(
+            accumulators
+                .iter()
+                .map(|accumulator| accumulator.lhs)
+                .collect(),
+        )?,
+        fold(
+            accumulators
+                .iter()
+                .map(|accumulator| accumulator.rhs)
+                .collect(),
+        )?,
+    );
+    let dk: KzgDecidingKey<Bn256> = (params.get_g()[0], params.g2(), params.s_g2()).into();
+    Ok(As::decide(&dk, accumulator)?)
+}
+
+/// Decodes the KZG accumulator from the limbs of its coordinates.
+fn accumulator_from_limbs(limbs: &[Fr]) -> Result<Accumulator, Error> {
+    let coordinates = limbs
+        .chunks(LIMBS)
+        .map(|chunk| {
+            // chunks of LIMBS always fit the array
+            fe_from_limbs::<Fr, Fq, LIMBS, BITS>(chunk.try_into().unwrap())
+        })
+        .collect::<Vec<_>>();
+    let point = |x: Fq, y: Fq| {
+        Option::<G1Affine>::from(G1Affine::from_xy(x, y)).ok_or(Error::InvalidArgument(
+            "accumulator is not on the curve".to_string(),
+        ))
+    };
+    Ok(KzgAccumulator::new(
+        point(coordinates[0], coordinates[1])?,
+        point(coordinates[2], coordinates[3])?,
+    ))
+}
+
+/// Params, circuit shape and verifying key of the aggregation circuit of `aggregated`.
+async fn load_aggregation_key(
+    srs_path: PathBuf,
+    aggregated: &AggregatedProof,
+) -> Result<(ParamsKZG<Bn256>, AggregationCircuit, VerifyingKey<G1Affine>), Error> {
+    let (snarks, key_id) = load_snarks(&srs_path, &aggregated.proofs).await?;
+    ceremony::provision(&srs_path, aggregated.degree).await?;
+    let general_params = srs::load_general_params(srs_path.clone(), aggregated.degree)?;
+    // only the shape of the circuit is needed for the verifying key
+    let circuit = AggregationCircuit::new(&general_params, snarks, aggregated.instance_encoding)?
+        .without_witnesses();
+    let verifying_key = load_verifying_key(
+        &srs_path,
+        aggregated.degree,
+        &aggregated.instance_encoding.key_id(&key_id),
+        &general_params,
+        &circuit,
+    )?;
+    Ok((general_params, circuit, verifying_key))
+}
+
+/// Loads the verifying keys of the proofs and compiles them to snarks.
+async fn load_snarks(srs_path: &Path, proofs: &[Proof]) -> Result<(Vec<Snark>, String), Error> {
+    let mut snarks = vec![];
+    let mut verifying_keys = vec![];
+    for proof in proofs {
+        let inner = VerifierSRS::load(
+            srs_path.to_path_buf(),
+            proof.degree,
+            proof.circuit_params()?,
+            proof.fixed_circuit_params,
+        )
+        .await?;
+        snarks.push(Snark::from(
+            &inner.general_params,
+            &inner.circuit_verifying_key,
+            proof,
+        )?);
+        verifying_keys.push(inner.circuit_verifying_key);
+    }
+    let key_id = key_id(&verifying_keys);
+    Ok((snarks, key_id))
+}
+
+/// Short digest of the aggregated verifying keys, the aggregation circuit and thus its keys differ
+/// for every set of aggregated verifying keys.
+pub fn key_id(verifying_keys: &[VerifyingKey<G1Affine>]) -> String {
+    let reprs = verifying_keys
+        .iter()
+        .flat_map(|vk| vk.transcript_repr().to_bytes())
+        .collect::<Vec<u8>>();
+    hex::encode(&keccak256(reprs)[..8])
+}
+
+fn load_verifying_key(
+    srs_path: &Path,
+    degree: u32,
+    key_id: &str,
+    general_params: &ParamsKZG<Bn256>,
+    circuit: &AggregationCircuit,
+) -> Result<VerifyingKey<G1Affine>, Error> {
+    srs::read_or_gen(
+        Artifact {
+            label: "aggregation verifying key",
+            circuit: Some(AGGREGATION_CIRCUIT_NAME),
+            degree,
+            checks: ArtifactChecks::default(),
+        },
+        srs_path.join(verifying_key_file_name(degree, key_id)),
+        |file| {
+            Ok(VerifyingKey::<G1Affine>::read::<File, AggregationCircuit>(
+                file,
+                SERDE_FORMAT,
+                (),
+            )?)
+        },
+        |mut file| {
+            let vk = keygen_vk(general_params, &circuit.without_witnesses())?;
+            vk.write(&mut file, SERDE_FORMAT)?;
+            Ok(vk)
+        },
+    )
+}
+
+fn verifying_key_file_name(degree: u32, key_id: &str) -> String {
+    format!("PoX_aggregation_verifying_key_{degree}_{key_id}")
+}
+
+fn proving_key_file_name(degree: u32, key_id: &str) -> String {
+    format!("PoX_aggregation_proving_key_{degree}_{key_id}")
+}
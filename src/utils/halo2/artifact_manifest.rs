@@ -0,0 +1,278 @@
+//! Sidecar of every params and key file in the srs dir, written with the file and validated before
+//! it is read, so that a truncated file or one built for another degree or circuit fails with an
+//! error saying which file to delete instead of a deserialization error. The `manifest.json` of
+//! the dir collects the sidecars and the proofs created with the keys of the dir.
+
+use super::{
+    proof::Proof,
+    srs::{lock_artifact, partial_path, ArtifactChecks},
+};
+use crate::{constants::ZKEVM_CIRCUITS_COMMIT, error::Error};
+use eth_types::H256;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tiny_keccak::{Hasher, Keccak};
+use tracing::debug;
+
+const SIDECAR_EXTENSION: &str = ".manifest.json";
+
+const DIR_MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// What a file of the srs dir is expected to hold.
+#[derive(Clone, Copy, Debug)]
+pub struct Artifact<'a> {
+    pub label: &'a str,
+    /// Circuit of a key, params are shared by every circuit of their degree.
+    pub circuit: Option<&'a str>,
+    pub degree: u32,
+    /// The digest of the file is only checked along with its points.
+    pub checks: ArtifactChecks,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactManifest {
+    pub label: String,
+    pub circuit: Option<String>,
+    pub degree: u32,
+    pub size: u64,
+    pub digest: H256,
+    pub zkevm_circuits_commit: String,
+    pub version: String,
+    /// Unix time the file was written at.
+    pub created_at: u64,
+}
+
+impl ArtifactManifest {
+    /// `<file>.manifest.json` next to the artifact.
+    pub fn path(artifact_path: &Path) -> PathBuf {
+        let mut file_name = artifact_path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(SIDECAR_EXTENSION);
+        artifact_path.with_file_name(file_name)
+    }
+
+    pub fn from(artifact: &Artifact, artifact_path: &Path) -> Result<Self, Error> {
+        Ok(Self {
+            label: artifact.label.to_string(),
+            circuit: artifact.circuit.map(str::to_string),
+            degree: artifact.degree,
+            size: fs::metadata(artifact_path)?.len(),
+            digest: file_digest(artifact_path)?,
+            zkevm_circuits_commit: ZKEVM_CIRCUITS_COMMIT.to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: now(),
+        })
+    }
+
+    pub fn read_from_file(path: &Path) -> Result<Self, Error> {
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    pub fn write_to_file(&self, path: &Path) -> Result<(), Error> {
+        Ok(fs::write(path, serde_json::to_string_pretty(self)?)?)
+    }
+}
+
+impl Artifact<'_> {
+    /// Writes the manifest of the file at `path`, once it is complete.
+    pub fn record(&self, path: &Path) -> Result<(), Error> {
+        ArtifactManifest::from(self, path)?.write_to_file(&ArtifactManifest::path(path))?;
+        match path.parent() {
+            Some(dir) => DirManifest::update(dir, |_| {}),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks the file at `path` against its manifest. Files of older versions have no manifest
+    /// and are read as they are.
+    pub fn check(&self, path: &Path) -> Result<(), Error> {
+        let manifest_path = ArtifactManifest::path(path);
+        if !manifest_path.exists() {
+            debug!("{} has no manifest", path.display());
+            return Ok(());
+        }
+        let display = path.display();
+        let manifest = ArtifactManifest::read_from_file(&manifest_path).map_err(|err| {
+            Error::SrsInvalid(format!(
+                "cannot read {}: {err:?}, delete it and {display} to regenerate",
+                manifest_path.display()
+            ))
+        })?;
+        if manifest.degree != self.degree {
+            return Err(Error::SrsInvalid(format!(
+                "{display} was built for degree {} but degree {} is needed, delete it to regenerate",
+                manifest.degree, self.degree
+            )));
+        }
+        if self.circuit.is_some()
+            && (manifest.circuit.as_deref() != self.circuit
+                || manifest.zkevm_circuits_commit != ZKEVM_CIRCUITS_COMMIT)
+        {
+            return Err(Error::VkMismatch(format!(
+                "{display} was built for a different circuit layout ({} of zkevm-circuits {} by version {}), delete it or rerun with --regenerate-keys",
+                manifest.circuit.unwrap_or_default(),
+                manifest.zkevm_circuits_commit,
+                manifest.version
+            )));
+        }
+        let size = fs::metadata(path)?.len();
+        if size != manifest.size {
+            return Err(Error::SrsInvalid(format!(
+                "{display} has {size} bytes but was written with {}, it is truncated or modified, delete it to regenerate",
+                manifest.size
+            )));
+        }
+        if self.checks == ArtifactChecks::Checked && file_digest(path)? != manifest.digest {
+            return Err(Error::SrsInvalid(format!(
+                "{display} does not match the digest of its manifest, it was modified after it was written, delete it to regenerate"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Proof created with the keys of an srs dir.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofEntry {
+    pub degree: u32,
+    pub digest: H256,
+    pub vk_digest: Option<H256>,
+    /// keccak256 of the witness and the verifying key the proof was created from, see
+    /// `Witness::digest`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub witness_hash: Option<H256>,
+    pub zkevm_circuits_commit: String,
+    pub created_at: u64,
+}
+
+/// `manifest.json` of an srs dir: the manifests of its params and keys by file name, and the
+/// proofs created with them by path, for tooling which looks for stale or mismatched files.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirManifest {
+    pub artifacts: BTreeMap<String, ArtifactManifest>,
+    pub proofs: BTreeMap<String, ProofEntry>,
+}
+
+impl DirManifest {
+    pub fn path(srs_path: &Path) -> PathBuf {
+        srs_path.join(DIR_MANIFEST_FILE_NAME)
+    }
+
+    /// Manifest of `srs_path`, an empty one if nothing was written to the dir yet.
+    pub fn read_from_file(srs_path: &Path) -> Result<Self, Error> {
+        let path = Self::path(srs_path);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    /// Collects the sidecars of the files in `srs_path` again, applies `update` and writes the
+    /// manifest, locked against the other provers of the dir.
+    pub fn update(srs_path: &Path, update: impl FnOnce(&mut Self)) -> Result<(), Error> {
+        let path = Self::path(srs_path);
+        let _lock = lock_artifact(&path, true)?;
+        let mut manifest = Self::read_from_file(srs_path)?;
+        manifest.artifacts.clear();
+        for entry in fs::read_dir(srs_path)? {
+            let file_name = entry?.file_name().to_string_lossy().to_string();
+            if let Some(artifact_name) = file_name.strip_suffix(SIDECAR_EXTENSION) {
+                if srs_path.join(artifact_name).exists() {
+                    let sidecar = ArtifactManifest::read_from_file(&srs_path.join(&file_name))?;
+                    manifest
+                        .artifacts
+                        .insert(artifact_name.to_string(), sidecar);
+                }
+            }
+        }
+        update(&mut manifest);
+
+        let partial_path = partial_path(&path);
+        fs::write(&partial_path, serde_json::to_string_pretty(&manifest)?)?;
+        Ok(fs::rename(&partial_path, &path)?)
+    }
+
+    /// Adds the proof written to `proof_path` with the keys of `srs_path`.
+    pub fn record_proof(
+        srs_path: &Path,
+        proof_path: &Path,
+        proof: &Proof,
+        witness_hash: Option<H256>,
+    ) -> Result<(), Error> {
+        let entry = ProofEntry {
+            degree: proof.degree,
+            digest: file_digest(proof_path)?,
+            vk_digest: proof.vk_digest,
+            witness_hash,
+            zkevm_circuits_commit: ZKEVM_CIRCUITS_COMMIT.to_string(),
+            created_at: now(),
+        };
+        let proof_path = fs::canonicalize(proof_path)?;
+        Self::update(srs_path, |manifest| {
+            manifest
+                .proofs
+                .insert(proof_path.to_string_lossy().to_string(), entry);
+        })
+    }
+
+    /// Path of a proof of `witness_hash` created with the keys of `srs_path`, if its file is still
+    /// the one which was written.
+    pub fn cached_proof(srs_path: &Path, witness_hash: H256) -> Result<Option<PathBuf>, Error> {
+        let manifest = Self::read_from_file(srs_path)?;
+        Ok(manifest.proofs.iter().find_map(|(path, entry)| {
+            let cached = entry.witness_hash == Some(witness_hash)
+                && entry.zkevm_circuits_commit == ZKEVM_CIRCUITS_COMMIT
+                && file_digest(Path::new(path)).ok() == Some(entry.digest);
+            cached.then(|| PathBuf::from(path))
+        }))
+    }
+
+    /// Names of the artifacts and paths of the proofs which were built against another
+    /// zkevm-circuits commit, or whose file is missing or no longer matches its digest.
+    pub fn stale(&self, srs_path: &Path) -> Vec<String> {
+        let artifacts = self.artifacts.iter().filter_map(|(name, manifest)| {
+            let path = srs_path.join(name);
+            let stale = (manifest.circuit.is_some()
+                && manifest.zkevm_circuits_commit != ZKEVM_CIRCUITS_COMMIT)
+                || fs::metadata(&path).map(|m| m.len()).ok() != Some(manifest.size);
+            stale.then(|| name.clone())
+        });
+        let proofs = self.proofs.iter().filter_map(|(path, entry)| {
+            let stale = entry.zkevm_circuits_commit != ZKEVM_CIRCUITS_COMMIT
+                || file_digest(Path::new(path)).ok() != Some(entry.digest);
+            stale.then(|| path.clone())
+        });
+        artifacts.chain(proofs).collect()
+    }
+}
+
+/// keccak256 of the file at `path`, streamed so that a proving key of several GB is never held in
+/// memory.
+fn file_digest(path: &Path) -> Result<H256, Error> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut hasher = Keccak::v256();
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+        hasher.update(buf);
+        let len = buf.len();
+        reader.consume(len);
+    }
+    let mut digest = [0u8; 32];
+    hasher.finalize(&mut digest);
+    Ok(H256::from(digest))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
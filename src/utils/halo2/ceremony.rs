@@ -0,0 +1,160 @@
+//! KZG params of the perpetual powers of tau ceremony. Unlike params generated from a seed, their
+//! secret is unknown as long as a single participant of the ceremony discarded theirs.
+
+use super::{
+    artifact_manifest::Artifact,
+    srs::{general_params_file_name, lock_artifact, partial_path, ArtifactChecks, SERDE_FORMAT},
+};
+use crate::{error::Error, utils::shutdown};
+use eth_types::H256;
+use halo2_proofs::{
+    arithmetic::{best_multiexp, Field},
+    halo2curves::{
+        bn256::{Bn256, Fr, G1Affine},
+        pairing::Engine,
+    },
+    poly::{
+        commitment::{Params, ParamsProver},
+        kzg::commitment::ParamsKZG,
+    },
+};
+use rand_chacha::{rand_core::SeedableRng, ChaChaRng};
+use std::{
+    env,
+    fs::{remove_file, rename, File},
+    io::{BufReader, BufWriter, Write},
+    iter,
+    path::Path,
+    str::FromStr,
+};
+use tiny_keccak::{Hasher, Keccak};
+use tracing::info;
+
+/// Bucket with the ceremony params converted to the halo2 format, one file per degree.
+pub const CEREMONY_URL: &str = "https://trusted-setup-halo2kzg.s3.eu-central-1.amazonaws.com";
+
+/// Largest degree the ceremony params are published for.
+pub const MAX_CEREMONY_DEGREE: u32 = 26;
+
+/// keccak256 digests of the ceremony params of each degree, which a download must match. A degree
+/// which is not pinned here needs the digest the ceremony published for it in the
+/// `SRS_DIGEST_<degree>` env var.
+const CEREMONY_DIGESTS: &[(u32, &str)] = &[];
+
+fn ceremony_file_name(degree: u32) -> String {
+    format!("perpetual-powers-of-tau-raw-{degree}")
+}
+
+/// Downloads the ceremony params of `degree` into the srs dir unless they are already there, so
+/// that `load_general_params` can read them. The download must match the digest pinned for the
+/// degree, or the one in the `SRS_DIGEST_<degree>` env var, and is checked to be powers of the
+/// secret of its G2 point. It is streamed to disk, the params of degree 26 take several GB.
+pub async fn provision(srs_path: &Path, degree: u32) -> Result<(), Error> {
+    let path = srs_path.join(general_params_file_name(degree));
+    if path.exists() {
+        return Ok(());
+    }
+    // another prover may be downloading the same params, which are there once it is done
+    let _lock = lock_artifact(&path, true)?;
+    if path.exists() {
+        return Ok(());
+    }
+    if degree > MAX_CEREMONY_DEGREE {
+        return Err(Error::SrsMissing(format!(
+            "the ceremony params are published up to degree {MAX_CEREMONY_DEGREE}, degree {degree} is not available"
+        )));
+    }
+
+    let file_name = ceremony_file_name(degree);
+    let expected = pinned_digest(degree)?.ok_or(Error::SrsInvalid(format!(
+        "no digest is pinned for the ceremony params of degree {degree}, set SRS_DIGEST_{degree} to the keccak256 digest the ceremony published for {file_name}"
+    )))?;
+
+    let url = format!("{CEREMONY_URL}/{file_name}");
+    info!("Downloading ceremony params from {url}");
+    let partial_path = partial_path(&path);
+    shutdown::register_temp_file(&partial_path);
+    let result = fetch(&url, &partial_path, degree, expected)
+        .await
+        .and_then(|_| Ok(rename(&partial_path, &path)?));
+    if result.is_err() {
+        remove_file(&partial_path).ok();
+    }
+    shutdown::unregister_temp_file(&partial_path);
+    result?;
+    Artifact {
+        label: "general params",
+        circuit: None,
+        degree,
+        checks: ArtifactChecks::default(),
+    }
+    .record(&path)
+}
+
+/// Streams the ceremony params of `degree` at `url` to `path`, checking that they have the
+/// `expected` digest and are powers of a single secret.
+async fn fetch(url: &str, path: &Path, degree: u32, expected: H256) -> Result<(), Error> {
+    let mut response = reqwest::get(url).await?.error_for_status()?;
+    let mut file = BufWriter::new(File::create(path)?);
+    let mut hasher = Keccak::v256();
+    while let Some(chunk) = response.chunk().await? {
+        hasher.update(&chunk);
+        file.write_all(&chunk)?;
+    }
+    file.flush()?;
+    let mut digest = [0u8; 32];
+    hasher.finalize(&mut digest);
+    let digest = H256::from(digest);
+    if digest != expected {
+        return Err(Error::SrsInvalid(format!(
+            "{url} has digest {digest:?} but {expected:?} is pinned"
+        )));
+    }
+    let params =
+        ParamsKZG::<Bn256>::read_custom(&mut BufReader::new(File::open(path)?), SERDE_FORMAT)?;
+    check_powers_of_tau(&params, degree, digest)
+}
+
+/// Digest the params of `degree` must have, the one of `CEREMONY_DIGESTS` unless
+/// `SRS_DIGEST_<degree>` is set.
+fn pinned_digest(degree: u32) -> Result<Option<H256>, Error> {
+    let var = format!("SRS_DIGEST_{degree}");
+    if let Ok(digest) = env::var(&var) {
+        return H256::from_str(&digest)
+            .map(Some)
+            .map_err(|_| Error::InvalidArgument(format!("invalid value for env var {var}")));
+    }
+    CEREMONY_DIGESTS
+        .iter()
+        .find(|(pinned_degree, _)| *pinned_degree == degree)
+        .map(|(_, digest)| {
+            H256::from_str(digest)
+                .map_err(|_| Error::InternalError("invalid pinned ceremony digest"))
+        })
+        .transpose()
+}
+
+/// Checks that every point is the previous one times the secret of `s_g2` with a random linear
+/// combination, e(Σ r^i g_{i+1}, g2) = e(Σ r^i g_i, s_g2), the challenge being derived from the
+/// digest of the file.
+fn check_powers_of_tau(params: &ParamsKZG<Bn256>, degree: u32, digest: H256) -> Result<(), Error> {
+    if params.k() != degree {
+        return Err(Error::SrsInvalid(format!(
+            "ceremony params have degree {} instead of {degree}",
+            params.k()
+        )));
+    }
+    let g = params.get_g();
+    let challenge = Fr::random(ChaChaRng::from_seed(digest.0));
+    let powers = iter::successors(Some(Fr::from(1)), |power| Some(*power * challenge))
+        .take(g.len() - 1)
+        .collect::<Vec<_>>();
+    let shifted = G1Affine::from(best_multiexp(&powers, &g[1..]));
+    let unshifted = G1Affine::from(best_multiexp(&powers, &g[..g.len() - 1]));
+    if Bn256::pairing(&shifted, &params.g2()) != Bn256::pairing(&unshifted, &params.s_g2()) {
+        return Err(Error::SrsInvalid(format!(
+            "ceremony params of degree {degree} are not powers of a single secret"
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,138 @@
+use super::vk_registry::vk_digest;
+use crate::{constants::ZKEVM_CIRCUITS_COMMIT, error::Error};
+use eth_types::H256;
+use halo2_proofs::{
+    halo2curves::bn256::{Fr, G1Affine},
+    plonk::{Circuit, ConstraintSystem, VerifyingKey},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+use zkevm_circuits::super_circuit::{SuperCircuit, SuperCircuitParams};
+
+const LOCK_FILE_NAME: &str = "circuit.lock";
+
+/// Shape of the constraint system, any change to the circuit which changes its keys changes at
+/// least one of these or the zkevm-circuits commit.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CircuitConfig {
+    pub zkevm_circuits_commit: String,
+    pub num_fixed_columns: usize,
+    pub num_advice_columns: usize,
+    pub num_instance_columns: usize,
+    pub num_selectors: usize,
+    pub num_challenges: usize,
+    pub num_gates: usize,
+    pub num_lookups: usize,
+    pub num_permutation_columns: usize,
+    pub constraint_degree: usize,
+}
+
+impl CircuitConfig {
+    pub fn from(circuit_params: SuperCircuitParams<Fr>) -> Self {
+        let mut cs = ConstraintSystem::default();
+        SuperCircuit::<Fr>::configure_with_params(&mut cs, circuit_params);
+        Self {
+            zkevm_circuits_commit: ZKEVM_CIRCUITS_COMMIT.to_string(),
+            num_fixed_columns: cs.num_fixed_columns(),
+            num_advice_columns: cs.num_advice_columns(),
+            num_instance_columns: cs.num_instance_columns(),
+            num_selectors: cs.num_selectors(),
+            num_challenges: cs.num_challenges(),
+            num_gates: cs.gates().len(),
+            num_lookups: cs.lookups().len(),
+            num_permutation_columns: cs.permutation().get_columns().len(),
+            constraint_degree: cs.degree(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LockEntry {
+    pub config: CircuitConfig,
+    pub vk_digest: H256,
+}
+
+/// Configuration of the circuit every cached verifying key in the srs dir was generated for,
+/// keyed by the file name of the key. Keys and the on-chain verifiers generated from them only
+/// verify proofs of the exact same circuit, so a build whose circuit differs is stopped before
+/// it silently regenerates keys or produces proofs nobody can verify.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CircuitLock {
+    pub entries: BTreeMap<String, LockEntry>,
+}
+
+impl CircuitLock {
+    pub fn path(srs_path: &Path) -> PathBuf {
+        srs_path.join(LOCK_FILE_NAME)
+    }
+
+    /// Reads the lockfile in `srs_path`, an empty lock is returned if there is none yet.
+    pub fn load(srs_path: &Path) -> Result<Self, Error> {
+        let path = Self::path(srs_path);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    pub fn save(&self, srs_path: &Path) -> Result<(), Error> {
+        let mut file = File::create(Self::path(srs_path))?;
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    /// Fails if the keys in `vk_file_name` were generated for a circuit configured differently.
+    pub fn check_config(&self, vk_file_name: &str, config: &CircuitConfig) -> Result<(), Error> {
+        match self.entries.get(vk_file_name) {
+            Some(entry) if &entry.config != config => Err(Error::VkMismatch(format!(
+                "{vk_file_name} was generated for the circuit {:?} but this build has {config:?}, its proofs would not verify against existing keys and on-chain verifiers. Rerun with --regenerate-keys to replace the keys and the lock entry",
+                entry.config
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Records the configuration and vk of `vk_file_name`, or checks them against the recorded
+    /// ones if there are any.
+    pub fn check_or_record(
+        &mut self,
+        srs_path: &Path,
+        vk_file_name: &str,
+        config: CircuitConfig,
+        vk: &VerifyingKey<G1Affine>,
+    ) -> Result<(), Error> {
+        self.check_config(vk_file_name, &config)?;
+        let digest = vk_digest(vk);
+        if let Some(entry) = self.entries.get(vk_file_name) {
+            if entry.vk_digest != digest {
+                return Err(Error::VkMismatch(format!(
+                    "{vk_file_name} has digest {digest:?} but {:?} is locked, rerun with --regenerate-keys to replace the keys and the lock entry",
+                    entry.vk_digest
+                )));
+            }
+            return Ok(());
+        }
+        self.entries.insert(
+            vk_file_name.to_string(),
+            LockEntry {
+                config,
+                vk_digest: digest,
+            },
+        );
+        self.save(srs_path)
+    }
+
+    /// Forgets the entry of `vk_file_name` so that regenerated keys are recorded again.
+    pub fn remove(srs_path: &Path, vk_file_name: &str) -> Result<(), Error> {
+        let mut lock = Self::load(srs_path)?;
+        if lock.entries.remove(vk_file_name).is_some() {
+            lock.save(srs_path)?;
+        }
+        Ok(())
+    }
+}
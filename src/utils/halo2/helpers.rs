@@ -10,6 +10,7 @@ use std::{
 };
 use zkevm_circuits::super_circuit::SuperCircuitParams;
 
+/// Uses the struct name from the `Debug` output, falls back to the full output for unit structs.
 pub fn derive_circuit_name<ConcreteCircuit>(circuit: ConcreteCircuit) -> String
 where
     ConcreteCircuit: Debug,
@@ -17,10 +18,8 @@ where
     let mut circuit_format = format!("{:?}", circuit);
     if let Some(index) = circuit_format.find(' ') {
         circuit_format.truncate(index);
-        circuit_format
-    } else {
-        panic!("no space found in '{}'", circuit_format);
     }
+    circuit_format
 }
 
 #[derive(Clone, Debug)]
@@ -59,10 +58,12 @@ impl<'de> Visitor<'de> for FrVisitor {
     where
         E: de::Error,
     {
-        let bytes = H256::from_str(v).unwrap();
+        let bytes = H256::from_str(v).map_err(E::custom)?;
         let mut bytes = bytes.as_fixed_bytes().to_owned();
         bytes.reverse();
-        Ok(FrWrapper(Fr::from_bytes(&bytes).unwrap()))
+        Option::from(Fr::from_bytes(&bytes))
+            .map(FrWrapper)
+            .ok_or_else(|| E::custom("value is not a canonical field element"))
     }
 }
 
@@ -0,0 +1,263 @@
+//! IPA backend for local development. Its params are derived by hashing to the curve, so nothing
+//! has to be downloaded and there is no trusted setup, at the cost of proofs which are larger and
+//! slower to verify. Proofs from this backend are not accepted where KZG proofs are expected.
+
+use super::{
+    artifact_manifest::{Artifact, ArtifactManifest},
+    proof::{Proof, ProofBackend, ProofTranscript},
+    real_verifier::verify_public_inputs,
+    srs::{
+        self, ipa_params_file_name, ipa_proving_key_file_name, ipa_verifying_key_file_name,
+        ArtifactChecks, SERDE_FORMAT, SUPER_CIRCUIT_NAME,
+    },
+    vk_registry::vk_digest,
+};
+use crate::{error::Error, witness::circuit::ExploitCircuit};
+use bus_mapping::circuit_input_builder::FixedCParams;
+use eth_types::H256;
+use halo2_proofs::{
+    halo2curves::bn256::{Fr, G1Affine},
+    plonk::{create_proof, keygen_pk, keygen_vk, verify_proof, Circuit, ProvingKey, VerifyingKey},
+    poly::{
+        commitment::Params,
+        ipa::{
+            commitment::{IPACommitmentScheme, ParamsIPA},
+            multiopen::{ProverIPA, VerifierIPA},
+            strategy::SingleStrategy,
+        },
+    },
+    transcript::{
+        Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+    },
+};
+use rand_core::OsRng;
+use std::{
+    fs::{remove_file, File},
+    path::{Path, PathBuf},
+};
+use tracing::info;
+use zkevm_circuits::super_circuit::SuperCircuit;
+
+#[derive(Clone)]
+pub struct IpaSRS {
+    pub params: ParamsIPA<G1Affine>,
+    pub circuit_verifying_key: VerifyingKey<G1Affine>,
+    pub circuit_proving_key: ProvingKey<G1Affine>,
+}
+
+impl IpaSRS {
+    /// Reads the params and keys from `srs_path`, generating and caching any that are missing.
+    pub fn load(circuit: &SuperCircuit<Fr>, degree: u32, srs_path: PathBuf) -> Result<Self, Error> {
+        let fcp = circuit.fixed_params();
+        let params = srs::read_or_gen(
+            Artifact {
+                label: "ipa params",
+                circuit: None,
+                degree,
+                checks: ArtifactChecks::default(),
+            },
+            srs_path.join(ipa_params_file_name(degree)),
+            |file| Ok(ParamsIPA::<G1Affine>::read(file)?),
+            |file| {
+                let params = ParamsIPA::<G1Affine>::new(degree);
+                params.write(file)?;
+                Ok(params)
+            },
+        )?;
+        let circuit_verifying_key = srs::read_or_gen(
+            Artifact {
+                label: "ipa circuit verifying key",
+                circuit: Some(SUPER_CIRCUIT_NAME),
+                degree,
+                checks: ArtifactChecks::default(),
+            },
+            srs_path.join(ipa_verifying_key_file_name(degree, fcp)),
+            |file| {
+                Ok(VerifyingKey::<G1Affine>::read::<File, SuperCircuit<Fr>>(
+                    file,
+                    SERDE_FORMAT,
+                    circuit.params(),
+                )?)
+            },
+            |mut file| {
+                let cvk = keygen_vk(&params, circuit)?;
+                cvk.write(&mut file, SERDE_FORMAT)?;
+                Ok(cvk)
+            },
+        )?;
+        let circuit_proving_key = srs::read_or_gen(
+            Artifact {
+                label: "ipa circuit proving key",
+                circuit: Some(SUPER_CIRCUIT_NAME),
+                degree,
+                checks: ArtifactChecks::default(),
+            },
+            srs_path.join(ipa_proving_key_file_name(degree, fcp)),
+            |file| {
+                Ok(ProvingKey::<G1Affine>::read::<File, SuperCircuit<Fr>>(
+                    file,
+                    SERDE_FORMAT,
+                    circuit.params(),
+                )?)
+            },
+            |mut file| {
+                let cpk = keygen_pk(&params, circuit_verifying_key.clone(), circuit)?;
+                cpk.write(&mut file, SERDE_FORMAT)?;
+                Ok(cpk)
+            },
+        )?;
+
+        if circuit_proving_key.get_vk().transcript_repr() != circuit_verifying_key.transcript_repr()
+        {
+            return Err(Error::VkMismatch(format!(
+                "{} was generated against a different verifying key or circuit params than {}, rerun with --regenerate-keys to rebuild both",
+                ipa_proving_key_file_name(degree, fcp),
+                ipa_verifying_key_file_name(degree, fcp),
+            )));
+        }
+
+        Ok(Self {
+            params,
+            circuit_verifying_key,
+            circuit_proving_key,
+        })
+    }
+
+    /// Deletes the cached vk and pk for the circuit params so that the next load regenerates them.
+    pub fn remove_keys(srs_path: &Path, degree: u32, fcp: FixedCParams) -> Result<(), Error> {
+        for path in [
+            srs_path.join(ipa_verifying_key_file_name(degree, fcp)),
+            srs_path.join(ipa_proving_key_file_name(degree, fcp)),
+        ] {
+            if path.exists() {
+                info!("Removing {}", path.display());
+                remove_file(&path)?;
+            }
+            remove_file(ArtifactManifest::path(&path)).ok();
+        }
+        Ok(())
+    }
+}
+
+pub struct IpaProver {
+    circuit: SuperCircuit<Fr>,
+    degree: u32,
+    srs: IpaSRS,
+}
+
+impl IpaProver {
+    pub fn from(circuit: SuperCircuit<Fr>, degree: u32, srs_path: PathBuf) -> Result<Self, Error> {
+        let srs = IpaSRS::load(&circuit, degree, srs_path)?;
+        Ok(Self {
+            circuit,
+            degree,
+            srs,
+        })
+    }
+
+    pub fn prove(&mut self) -> Result<Proof, Error> {
+        let block = self
+            .circuit
+            .block()
+            .ok_or(Error::InternalError("circuit is missing the witness block"))?;
+        let public_data = SuperCircuit::<Fr>::public_data(block);
+        let instances = self.circuit.instances();
+        let instances_refs = instances.iter().map(|v| &v[..]).collect::<Vec<&[Fr]>>();
+
+        let mut transcript = Blake2bWrite::<_, G1Affine, Challenge255<_>>::init(vec![]);
+        create_proof::<IPACommitmentScheme<G1Affine>, ProverIPA<'_, G1Affine>, _, _, _, _>(
+            &self.srs.params,
+            &self.srs.circuit_proving_key,
+            &[self.circuit.clone()],
+            &[&instances_refs],
+            OsRng,
+            &mut transcript,
+        )?;
+
+        let mut proof = Proof::from(
+            self.degree,
+            transcript.finalize(),
+            instances,
+            self.circuit.params(),
+            self.circuit.fixed_params(),
+            public_data,
+            None,
+            None,
+            ProofTranscript::Blake2b,
+        );
+        proof.backend = ProofBackend::Ipa;
+        Ok(proof)
+    }
+
+    pub fn verifier(&self) -> IpaVerifier {
+        IpaVerifier {
+            params: self.srs.params.clone(),
+            circuit_verifying_key: self.srs.circuit_verifying_key.clone(),
+        }
+    }
+}
+
+pub struct IpaVerifier {
+    pub params: ParamsIPA<G1Affine>,
+    pub circuit_verifying_key: VerifyingKey<G1Affine>,
+}
+
+impl IpaVerifier {
+    /// The vk has to be generated by the prove command, the params are regenerated if missing
+    /// since they do not depend on a setup.
+    pub async fn load_srs(srs_path: PathBuf, proof: &Proof) -> Result<Self, Error> {
+        let params_path = srs_path.join(ipa_params_file_name(proof.degree));
+        let params = if params_path.exists() {
+            ParamsIPA::<G1Affine>::read(&mut File::open(params_path)?)?
+        } else {
+            ParamsIPA::<G1Affine>::new(proof.degree)
+        };
+        let circuit_params = proof.circuit_params()?;
+        let circuit_verifying_key = srs::read(
+            Artifact {
+                label: "ipa circuit verifying key",
+                circuit: Some(SUPER_CIRCUIT_NAME),
+                degree: proof.degree,
+                checks: ArtifactChecks::default(),
+            },
+            srs_path,
+            ipa_verifying_key_file_name(proof.degree, proof.fixed_circuit_params),
+            |file| {
+                Ok(VerifyingKey::<G1Affine>::read::<File, SuperCircuit<Fr>>(
+                    file,
+                    SERDE_FORMAT,
+                    circuit_params.clone(),
+                )?)
+            },
+        )
+        .await?;
+        Ok(Self {
+            params,
+            circuit_verifying_key,
+        })
+    }
+
+    /// Digest of the verifying key the approved-vk registry checks, as `RealVerifier::vk_hash`.
+    pub fn vk_hash(&self) -> H256 {
+        vk_digest(&self.circuit_verifying_key)
+    }
+
+    pub async fn verify(&self, proof: &Proof) -> Result<(), Error> {
+        if proof.backend != ProofBackend::Ipa {
+            return Err(Error::InvalidArgument(format!(
+                "proof was created with the {:?} backend, not IPA",
+                proof.backend
+            )));
+        }
+        let instances = proof.instances();
+        let instances_refs = instances.iter().map(|v| &v[..]).collect::<Vec<&[Fr]>>();
+        verify_proof::<IPACommitmentScheme<G1Affine>, VerifierIPA<'_, G1Affine>, _, _, _>(
+            &self.params,
+            &self.circuit_verifying_key,
+            SingleStrategy::new(&self.params),
+            &[&instances_refs],
+            &mut Blake2bRead::<_, G1Affine, Challenge255<_>>::init(&proof.data[..]),
+        )?;
+        verify_public_inputs(proof).await
+    }
+}
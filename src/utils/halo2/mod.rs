@@ -1,5 +1,26 @@
+#[cfg(feature = "nowasm")]
+pub mod aggregation;
+pub mod artifact_manifest;
+#[cfg(feature = "nowasm")]
+pub mod ceremony;
+pub mod circuit_lock;
 pub mod helpers;
+#[cfg(feature = "nowasm")]
+pub mod ipa;
+#[cfg(feature = "nowasm")]
+pub mod preflight;
+#[cfg(feature = "nowasm")]
+pub mod preset;
 pub mod proof;
+#[cfg(feature = "nowasm")]
+pub mod prover;
+#[cfg(feature = "nowasm")]
 pub mod real_prover;
 pub mod real_verifier;
+#[cfg(feature = "nowasm")]
+pub mod split_verifier;
 pub mod srs;
+#[cfg(feature = "dev-circuits")]
+pub mod sub_circuit;
+pub mod trajectory;
+pub mod vk_registry;
@@ -0,0 +1,223 @@
+use super::srs::{
+    circuit_proving_key_file_name, circuit_verifying_key_file_name, general_params_file_name,
+};
+use crate::error::Error;
+use halo2_proofs::{
+    halo2curves::bn256::Fr,
+    plonk::{Circuit, ConstraintSystem},
+};
+use std::{
+    fs::{self, File},
+    io::Read,
+    path::Path,
+};
+use tracing::debug;
+use zkevm_circuits::super_circuit::SuperCircuit;
+
+const FIELD_BYTES: u64 = 32;
+const G1_BYTES: u64 = 64;
+
+/// Approximate sizes in bytes of the artifacts written for a circuit at a degree.
+#[derive(Clone, Copy, Debug)]
+pub struct ArtifactSizes {
+    pub general_params: u64,
+    pub proving_key: u64,
+}
+
+/// Approximate memory in bytes a proof of a circuit at a degree takes at its peak, when the
+/// params and the proving key are loaded and the polynomials of the proof are being committed.
+#[derive(Clone, Copy, Debug)]
+pub struct MemoryEstimate {
+    pub general_params: u64,
+    pub proving_key: u64,
+    pub proof: u64,
+}
+
+impl MemoryEstimate {
+    pub fn total(&self) -> u64 {
+        self.general_params + self.proving_key + self.proof
+    }
+}
+
+fn constraint_system(circuit: &SuperCircuit<Fr>) -> ConstraintSystem<Fr> {
+    let mut cs = ConstraintSystem::default();
+    SuperCircuit::<Fr>::configure_with_params(&mut cs, circuit.params());
+    cs
+}
+
+/// Size of the extended domain the quotient polynomial is evaluated on, sized by the constraint
+/// degree.
+fn extended_size(cs: &ConstraintSystem<Fr>, n: u64) -> u64 {
+    n * (cs.degree() as u64 - 1).next_power_of_two()
+}
+
+pub fn estimate_artifact_sizes(circuit: &SuperCircuit<Fr>, degree: u32) -> ArtifactSizes {
+    let cs = constraint_system(circuit);
+
+    let n = 1u64 << degree;
+    let extended_n = extended_size(&cs, n);
+    let num_fixed = cs.num_fixed_columns() as u64;
+    let num_permutation = cs.permutation().get_columns().len() as u64;
+
+    ArtifactSizes {
+        // g and g_lagrange
+        general_params: 2 * n * G1_BYTES,
+        // values and polys in the base domain, cosets in the extended domain, plus l0, l_last
+        // and l_active_row cosets
+        proving_key: FIELD_BYTES
+            * (2 * n * (num_fixed + num_permutation)
+                + extended_n * (num_fixed + num_permutation + 3)),
+    }
+}
+
+/// The params and the proving key take as much memory as their files, the proof holds the values,
+/// polys and extended cosets of the advice and instance columns, of the permuted columns and
+/// products of the lookups and of the permutation products, and the quotient polynomial.
+pub fn estimate_memory(circuit: &SuperCircuit<Fr>, degree: u32) -> MemoryEstimate {
+    let sizes = estimate_artifact_sizes(circuit, degree);
+    let cs = constraint_system(circuit);
+
+    let n = 1u64 << degree;
+    let extended_n = extended_size(&cs, n);
+    let column = 2 * n + extended_n;
+    let num_columns = (cs.num_advice_columns() + cs.num_instance_columns()) as u64;
+    let num_lookups = cs.lookups().len() as u64;
+    // the permutation argument splits its columns into chunks of the constraint degree minus 2
+    let chunk_len = (cs.degree() as u64).saturating_sub(2).max(1);
+    let num_permutation_chunks =
+        (cs.permutation().get_columns().len() as u64 + chunk_len - 1) / chunk_len;
+
+    MemoryEstimate {
+        general_params: sizes.general_params,
+        proving_key: sizes.proving_key,
+        proof: FIELD_BYTES
+            * (num_columns * column
+                + num_lookups * 3 * column
+                + num_permutation_chunks * column
+                + extended_n),
+    }
+}
+
+/// Fails before keygen and proving start if the estimated peak memory exceeds the memory the OS
+/// reports as available, rather than being killed for running out of it hours later. Nothing is
+/// checked on systems without `/proc/meminfo`.
+pub fn check_memory(circuit: &SuperCircuit<Fr>, degree: u32) -> Result<(), Error> {
+    let estimate = estimate_memory(circuit, degree);
+    debug!("Preflight: {estimate:?}");
+    let Some(available_memory) = available_memory() else {
+        return Ok(());
+    };
+    if available_memory < estimate.total() {
+        return Err(Error::PreflightFailed(format!(
+            "not enough memory to prove at degree {degree}: ~{} MB required ({} MB params, {} MB proving key, {} MB proof), {} MB available, use --skip-memory-check to try anyway",
+            estimate.total() / 1_000_000,
+            estimate.general_params / 1_000_000,
+            estimate.proving_key / 1_000_000,
+            estimate.proof / 1_000_000,
+            available_memory / 1_000_000,
+        )));
+    }
+    Ok(())
+}
+
+/// `MemAvailable` of `/proc/meminfo` in bytes.
+fn available_memory() -> Option<u64> {
+    fs::read_to_string("/proc/meminfo")
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("MemAvailable:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(|kb| kb * 1024)
+}
+
+/// Resets the peak resident memory of the process which `peak_memory` reads, on Linux only.
+pub fn reset_peak_memory() {
+    let _ = fs::write("/proc/self/clear_refs", "5");
+}
+
+/// `VmHWM` of `/proc/self/status` in bytes, the peak resident memory of the process since it
+/// started or since `reset_peak_memory`.
+pub fn peak_memory() -> Option<u64> {
+    fs::read_to_string("/proc/self/status")
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(|kb| kb * 1024)
+}
+
+/// Validates the artifact dir before any long running work starts, so that a full disk or a
+/// read-only mount fails in seconds rather than after keygen.
+pub fn check(srs_path: &Path, circuit: &SuperCircuit<Fr>, degree: u32) -> Result<(), Error> {
+    check_writable(srs_path)?;
+
+    let sizes = estimate_artifact_sizes(circuit, degree);
+    let params_path = srs_path.join(general_params_file_name(degree));
+    let vk_path = srs_path.join(circuit_verifying_key_file_name(
+        degree,
+        circuit.circuits_params,
+    ));
+    let pk_path = srs_path.join(circuit_proving_key_file_name(
+        degree,
+        circuit.circuits_params,
+    ));
+
+    let mut required_space = 0;
+    for (path, size) in [
+        (&params_path, sizes.general_params),
+        (&vk_path, 0),
+        (&pk_path, sizes.proving_key),
+    ] {
+        if path.exists() {
+            check_readable(path)?;
+        } else {
+            required_space += size;
+        }
+    }
+
+    let available_space = fs2::available_space(srs_path)?;
+    debug!("Preflight: {required_space} bytes required, {available_space} bytes available");
+    if available_space < required_space {
+        return Err(Error::PreflightFailed(format!(
+            "not enough free space in {}: ~{} MB required for params and proving key at degree {degree}, {} MB available",
+            srs_path.display(),
+            required_space / 1_000_000,
+            available_space / 1_000_000,
+        )));
+    }
+    Ok(())
+}
+
+pub fn check_writable(dir: &Path) -> Result<(), Error> {
+    fs::create_dir_all(dir)
+        .map_err(|e| Error::PreflightFailed(format!("cannot create dir {}: {e}", dir.display())))?;
+    let probe = dir.join(".pox_write_check");
+    File::create(&probe)
+        .and_then(|_| fs::remove_file(&probe))
+        .map_err(|e| Error::PreflightFailed(format!("{} is not writable: {e}", dir.display())))
+}
+
+fn check_readable(path: &Path) -> Result<(), Error> {
+    let mut buf = [0u8; 1];
+    File::open(path)
+        .and_then(|mut file| file.read(&mut buf))
+        .map_err(|e| Error::PreflightFailed(format!("cannot read {}: {e}", path.display())))
+        .and_then(|read| {
+            if read == 0 {
+                Err(Error::PreflightFailed(format!(
+                    "{} is empty, delete it to regenerate",
+                    path.display()
+                )))
+            } else {
+                Ok(())
+            }
+        })
+}
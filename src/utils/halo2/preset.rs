@@ -0,0 +1,65 @@
+use crate::{
+    constants::{MAX_CALLDATA, MAX_TXS},
+    error::Error,
+};
+use bus_mapping::circuit_input_builder::FixedCParams;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Named circuit sizes to start from instead of picking each `--max-*` value. The degree is the
+/// one the params usually end up with, the actual degree is still computed from the witness.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CircuitPreset {
+    /// A transfer or a call with little storage access.
+    Small,
+    /// A typical DeFi interaction, a few calls and token transfers. These are the params whose
+    /// keys are published on IPFS.
+    Medium,
+    /// An exploit with many nested calls, e.g. a flash loan moving through several protocols.
+    Large,
+}
+
+impl CircuitPreset {
+    pub fn params(&self) -> FixedCParams {
+        let (max_rws, max_copy_rows, max_exp_steps, max_bytecode, max_evm_rows, max_keccak_rows) =
+            match self {
+                CircuitPreset::Small => (5000, 5000, 1000, 2000, 5000, 10000),
+                CircuitPreset::Medium => (40000, 40000, 40000, 10000, 20000, 50000),
+                CircuitPreset::Large => (120000, 120000, 60000, 40000, 100000, 150000),
+            };
+        FixedCParams {
+            max_rws,
+            max_txs: MAX_TXS,
+            max_calldata: MAX_CALLDATA,
+            max_copy_rows,
+            max_exp_steps,
+            max_bytecode,
+            max_evm_rows,
+            max_keccak_rows,
+        }
+    }
+
+    pub fn degree(&self) -> u32 {
+        match self {
+            CircuitPreset::Small => 18,
+            CircuitPreset::Medium => 19,
+            CircuitPreset::Large => 20,
+        }
+    }
+}
+
+impl FromStr for CircuitPreset {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "small" => Ok(CircuitPreset::Small),
+            "medium" => Ok(CircuitPreset::Medium),
+            "large" => Ok(CircuitPreset::Large),
+            _ => Err(Error::InvalidArgument(format!(
+                "unknown preset {s}, expected small, medium or large"
+            ))),
+        }
+    }
+}
@@ -1,24 +1,159 @@
-use super::{super::solidity::Artifact, helpers::FrWrapper, helpers::SuperCircuitParamsWrapper};
-use crate::error::Error;
+use super::{
+    super::solc_artifact::Artifact, helpers::FrWrapper, helpers::SuperCircuitParamsWrapper,
+    real_verifier::RealVerifier,
+};
+use crate::{constants::RANDOMNESS, error::Error};
 use bus_mapping::circuit_input_builder::FixedCParams;
-use ethers::types::Bytes;
-use halo2_proofs::halo2curves::bn256::Fr;
+use eth_types::H256;
+use ethers::types::{Address, Bytes, TransactionRequest};
+use halo2_proofs::halo2curves::{bn256::Fr, group::ff::PrimeField};
 use semver::Version;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use std::{
     fmt::Debug,
-    fs::File,
+    fs::{self, File},
     io::{Read, Write},
     path::PathBuf,
     str::FromStr,
 };
 use zkevm_circuits::{instance::PublicData, super_circuit::SuperCircuitParams};
 
+/// Transcript the proof was created with. Blake2b is cheapest to verify natively while Poseidon
+/// proofs can be verified inside an aggregation circuit and Keccak proofs by the solidity verifier
+/// of `gen-verifier`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofTranscript {
+    #[default]
+    Blake2b,
+    Poseidon,
+    Keccak,
+}
+
+/// Commitment scheme the proof was created with. KZG is used for real proofs, IPA needs no
+/// trusted setup and is meant for local development only. Mock proofs have no proof data, only
+/// the MockProver checked the constraints.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofBackend {
+    #[default]
+    Kzg,
+    Ipa,
+    Mock,
+}
+
+/// Multiopen scheme of the KZG openings of the proof, which the prover, the native verifiers and
+/// the solidity verifier must agree on. SHPLONK proofs are smaller and the only ones the
+/// aggregation circuit verifies, snark-verifier calls it Bdfg21 and GWC Gwc19.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PcsScheme {
+    #[default]
+    Shplonk,
+    Gwc,
+}
+
+impl PcsScheme {
+    pub fn name(self) -> &'static str {
+        match self {
+            PcsScheme::Shplonk => "SHPLONK",
+            PcsScheme::Gwc => "GWC",
+        }
+    }
+}
+
+impl FromStr for PcsScheme {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "shplonk" => Ok(PcsScheme::Shplonk),
+            "gwc" => Ok(PcsScheme::Gwc),
+            _ => Err(Error::InvalidArgument(format!(
+                "unknown multiopen scheme {s}, expected shplonk or gwc"
+            ))),
+        }
+    }
+}
+
+impl FromStr for ProofTranscript {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "blake2b" => Ok(ProofTranscript::Blake2b),
+            "poseidon" => Ok(ProofTranscript::Poseidon),
+            "keccak" => Ok(ProofTranscript::Keccak),
+            _ => Err(Error::InvalidArgument(format!(
+                "unknown transcript {s}, expected blake2b, poseidon or keccak"
+            ))),
+        }
+    }
+}
+
+/// Version of the layout of proof files, bumped whenever a field changes meaning so that a file is
+/// never misread by another version. Files written before it existed are format 0.
+pub const PROOF_FORMAT_VERSION: u32 = 1;
+
+/// Name of the circuit every proof of this crate proves.
+pub const SUPER_CIRCUIT_NAME: &str = "super_circuit";
+
+/// Calldata of a solidity verifier generated by snark-verifier, every instance as a big endian
+/// word followed by the proof data.
+pub fn evm_calldata(instances: &[Vec<Fr>], proof: &[u8]) -> Vec<u8> {
+    instances
+        .iter()
+        .flatten()
+        .flat_map(|value| {
+            value
+                .to_repr()
+                .as_ref()
+                .iter()
+                .rev()
+                .cloned()
+                .collect::<Vec<_>>()
+        })
+        .chain(proof.iter().cloned())
+        .collect()
+}
+
+/// JSON-RPC `eth_sendTransaction` request calling the contract at `to` with `calldata`, sent from
+/// `from` or else from the default account of the node.
+pub fn send_transaction_request(to: Address, from: Option<Address>, calldata: Bytes) -> Value {
+    let mut transaction = TransactionRequest::new().to(to).data(calldata);
+    transaction.from = from;
+    json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "eth_sendTransaction",
+        "params": [transaction],
+    })
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Proof {
+    #[serde(default)]
+    pub format: u32,
     pub version: Version,
+    #[serde(default = "default_circuit")]
+    pub circuit: String,
     pub degree: u32,
     pub data: Bytes,
+    /// keccak256 of the verifying key the proof was created against, a verifier whose key differs
+    /// rejects the proof before verifying it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vk_digest: Option<H256>,
+    /// Calldata of the solidity verifier of `gen-verifier`, for Keccak transcript proofs only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calldata: Option<Bytes>,
+    #[serde(default)]
+    pub transcript: ProofTranscript,
+    #[serde(default)]
+    pub backend: ProofBackend,
+    /// Proofs written before the scheme was configurable are SHPLONK.
+    #[serde(default)]
+    pub pcs_scheme: PcsScheme,
     instances: Vec<Vec<FrWrapper>>,
     circuit_params: SuperCircuitParamsWrapper,
     pub fixed_circuit_params: FixedCParams,
@@ -26,9 +161,14 @@ pub struct Proof {
     pub challenge_artifact: Option<Artifact>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub summary: Option<String>,
+    /// Whether the exploit transaction reverted, proven with `--allow-revert`. The prover records
+    /// it, the public data does not tell a reverting execution apart.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub reverted: bool,
 }
 
 impl Proof {
+    /// Panics only if `CARGO_PKG_VERSION` is not a valid semver, which cargo guarantees it is.
     #[allow(clippy::too_many_arguments)]
     pub fn from(
         degree: u32,
@@ -39,11 +179,21 @@ impl Proof {
         public_data: PublicData,
         challenge_artifact: Option<Artifact>,
         summary: Option<String>,
+        transcript: ProofTranscript,
     ) -> Self {
+        let calldata = (transcript == ProofTranscript::Keccak)
+            .then(|| Bytes::from(evm_calldata(&instances, &proof)));
         Self {
+            format: PROOF_FORMAT_VERSION,
             version: Version::from_str(env!("CARGO_PKG_VERSION")).unwrap(),
+            circuit: default_circuit(),
             degree,
             data: Bytes::from(proof),
+            vk_digest: None,
+            calldata,
+            transcript,
+            backend: ProofBackend::default(),
+            pcs_scheme: PcsScheme::default(),
             instances: instances
                 .iter()
                 .map(|column| column.iter().map(|element| FrWrapper(*element)).collect())
@@ -53,6 +203,7 @@ impl Proof {
             public_data,
             challenge_artifact,
             summary,
+            reverted: false,
         }
     }
 
@@ -63,38 +214,200 @@ impl Proof {
             .collect()
     }
 
+    /// Calldata of the solidity verifier.
+    pub fn evm_calldata(&self) -> Vec<u8> {
+        evm_calldata(&self.instances(), &self.data)
+    }
+
+    /// Calldata of the solidity verifier, which only accepts KZG proofs with a Keccak transcript.
+    pub fn to_onchain_calldata(&self) -> Result<Bytes, Error> {
+        if self.backend != ProofBackend::Kzg || self.transcript != ProofTranscript::Keccak {
+            return Err(Error::InvalidArgument(format!(
+                "the solidity verifier only accepts KZG proofs created with --transcript keccak, the proof is a {:?} proof with a {:?} transcript",
+                self.backend, self.transcript
+            )));
+        }
+        Ok(Bytes::from(self.evm_calldata()))
+    }
+
+    /// `eth_sendTransaction` request verifying the proof with the solidity verifier deployed at
+    /// `verifier`, see `send_transaction_request`.
+    pub fn to_send_transaction(
+        &self,
+        verifier: Address,
+        from: Option<Address>,
+    ) -> Result<Value, Error> {
+        Ok(send_transaction_request(
+            verifier,
+            from,
+            self.to_onchain_calldata()?,
+        ))
+    }
+
     pub fn num_instances(&self) -> Vec<usize> {
         self.instances.iter().map(|column| column.len()).collect()
     }
 
-    pub fn circuit_params(&self) -> SuperCircuitParams<Fr> {
-        self.circuit_params.clone().unwrap()
+    /// Fails for proofs created with another witness randomness than the one the keys are
+    /// generated with.
+    pub fn circuit_params(&self) -> Result<SuperCircuitParams<Fr>, Error> {
+        let circuit_params = self.circuit_params.clone().unwrap();
+        if circuit_params.mock_randomness != Fr::from(RANDOMNESS) {
+            return Err(Error::InvalidArgument(format!(
+                "proof was created with the witness randomness {:?} but the keys use {RANDOMNESS:#x}",
+                circuit_params.mock_randomness
+            )));
+        }
+        Ok(circuit_params)
     }
 
-    pub fn unpack(&self) -> (u32, Bytes, Vec<Vec<Fr>>, PublicData, SuperCircuitParams<Fr>) {
+    #[allow(clippy::type_complexity)]
+    pub fn unpack(
+        &self,
+    ) -> Result<(u32, Bytes, Vec<Vec<Fr>>, PublicData, SuperCircuitParams<Fr>), Error> {
         let instances = self.instances();
-        let circuit_params = self.circuit_params();
-        (
+        let circuit_params = self.circuit_params()?;
+        Ok((
             self.degree,
             self.data.clone(),
             instances,
             self.public_data.clone(),
             circuit_params,
-        )
+        ))
     }
 
-    pub fn write_to_file(&self, path: &PathBuf) -> Result<(), Error> {
-        // TODO ensure that parent dir exists
-        let mut file = File::create(path)?;
-        file.write_all(serde_json::to_string(self)?.as_bytes())
-            .unwrap();
+    /// Verifies the proof with the keys `verifier` was loaded with, along with the public inputs.
+    pub async fn verify(&self, verifier: &RealVerifier) -> Result<(), Error> {
+        verifier.verify(self).await
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
+        writer.write_all(serde_json::to_string(self)?.as_bytes())?;
         Ok(())
     }
 
-    pub fn read_from_file(path: &PathBuf) -> Result<Self, Error> {
-        let mut file = File::open(path)?;
+    /// Fails for files of a newer format than this version reads.
+    pub fn read<R: Read>(reader: &mut R) -> Result<Self, Error> {
         let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
-        Ok(serde_json::from_str(&contents)?)
+        reader.read_to_string(&mut contents)?;
+        let proof: Self = serde_json::from_str(&contents)?;
+        if proof.format > PROOF_FORMAT_VERSION {
+            return Err(Error::InvalidArgument(format!(
+                "proof file is of format {} but version {} reads up to format {PROOF_FORMAT_VERSION}, upgrade to read it",
+                proof.format,
+                env!("CARGO_PKG_VERSION")
+            )));
+        }
+        Ok(proof)
+    }
+
+    pub fn write_to_file(&self, path: &PathBuf) -> Result<(), Error> {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)?;
+        }
+        self.write(&mut File::create(path)?)
+    }
+
+    pub fn read_from_file(path: &PathBuf) -> Result<Self, Error> {
+        Self::read(&mut File::open(path)?)
+    }
+}
+
+fn default_circuit() -> String {
+    SUPER_CIRCUIT_NAME.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{evm_calldata, Proof, ProofTranscript, PROOF_FORMAT_VERSION};
+    use crate::{constants::RANDOMNESS, error::Error};
+    use bus_mapping::circuit_input_builder::FixedCParams;
+    use ethers::types::Address;
+    use halo2_proofs::halo2curves::bn256::Fr;
+    use serde_json::json;
+    use zkevm_circuits::{instance::PublicData, super_circuit::SuperCircuitParams};
+
+    fn proof(transcript: ProofTranscript) -> Proof {
+        Proof::from(
+            10,
+            vec![0xaa, 0xbb],
+            vec![vec![Fr::from(1)], vec![Fr::from(0x0102)]],
+            SuperCircuitParams {
+                mock_randomness: Fr::from(RANDOMNESS),
+            },
+            FixedCParams::default(),
+            PublicData::default(),
+            None,
+            None,
+            transcript,
+        )
+    }
+
+    #[test]
+    fn test_evm_calldata() {
+        let mut expected = vec![0u8; 64 + 2];
+        expected[31] = 0x01;
+        expected[62] = 0x01;
+        expected[63] = 0x02;
+        expected[64..].copy_from_slice(&[0xaa, 0xbb]);
+        assert_eq!(
+            evm_calldata(&[vec![Fr::from(1)], vec![Fr::from(0x0102)]], &[0xaa, 0xbb]),
+            expected
+        );
+        assert_eq!(proof(ProofTranscript::Keccak).evm_calldata(), expected);
+    }
+
+    #[test]
+    fn test_onchain_calldata() {
+        let keccak = proof(ProofTranscript::Keccak);
+        let calldata = keccak.to_onchain_calldata().unwrap();
+        assert_eq!(calldata.to_vec(), keccak.evm_calldata());
+        assert_eq!(keccak.calldata, Some(calldata.clone()));
+
+        let verifier = Address::repeat_byte(1);
+        let request = keccak.to_send_transaction(verifier, None).unwrap();
+        assert_eq!(request["method"], json!("eth_sendTransaction"));
+        assert_eq!(request["params"][0]["to"], json!(verifier));
+        assert_eq!(request["params"][0]["data"], json!(calldata));
+
+        let blake2b = proof(ProofTranscript::Blake2b);
+        assert!(blake2b.calldata.is_none());
+        assert!(matches!(
+            blake2b.to_onchain_calldata(),
+            Err(Error::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            blake2b.to_send_transaction(verifier, None),
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_format_round_trip() {
+        let proof = proof(ProofTranscript::Keccak);
+        let mut file = vec![];
+        proof.write(&mut file).unwrap();
+        let read = Proof::read(&mut file.as_slice()).unwrap();
+        assert_eq!(read.format, PROOF_FORMAT_VERSION);
+        assert_eq!(
+            serde_json::to_value(&read).unwrap(),
+            serde_json::to_value(&proof).unwrap()
+        );
+        assert_eq!(read.instances(), proof.instances());
+
+        let mut newer = serde_json::to_value(&proof).unwrap();
+        newer["format"] = json!(PROOF_FORMAT_VERSION + 1);
+        assert!(matches!(
+            Proof::read(&mut newer.to_string().as_bytes()),
+            Err(Error::InvalidArgument(_))
+        ));
+
+        let mut unversioned = serde_json::to_value(&proof).unwrap();
+        unversioned.as_object_mut().unwrap().remove("format");
+        let read = Proof::read(&mut unversioned.to_string().as_bytes()).unwrap();
+        assert_eq!(read.format, 0);
     }
 }
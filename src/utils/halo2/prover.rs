@@ -0,0 +1,79 @@
+use super::{
+    ipa::IpaProver,
+    proof::{Proof, ProofBackend, ProofTranscript},
+    real_prover::RealProver,
+};
+use crate::{error::Error, witness::circuit::ExploitCircuit};
+use halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+use zkevm_circuits::super_circuit::SuperCircuit;
+
+/// Common interface of the provers, so that the code calling them stays the same when an exploit
+/// author switches from the stub prover to real proofs.
+pub trait Prover {
+    fn prove(&mut self) -> Result<Proof, Error>;
+}
+
+impl Prover for RealProver {
+    fn prove(&mut self) -> Result<Proof, Error> {
+        RealProver::prove(self)
+    }
+}
+
+impl Prover for IpaProver {
+    fn prove(&mut self) -> Result<Proof, Error> {
+        IpaProver::prove(self)
+    }
+}
+
+/// Checks the constraints with the MockProver and returns a proof without proof data, which has
+/// the instances and public data of a real one. It needs no keys or params and runs in seconds,
+/// the stub proves nothing and every verifier rejects it.
+pub struct StubProver {
+    circuit: SuperCircuit<Fr>,
+    degree: u32,
+}
+
+impl StubProver {
+    /// The degree is the smallest one the witness block fits in.
+    pub fn from(circuit: SuperCircuit<Fr>) -> Result<Self, Error> {
+        let block = circuit
+            .block()
+            .ok_or(Error::InternalError("circuit is missing the witness block"))?;
+        let degree = SuperCircuit::<Fr>::degree_for_block(block);
+        Ok(Self { circuit, degree })
+    }
+}
+
+impl Prover for StubProver {
+    fn prove(&mut self) -> Result<Proof, Error> {
+        let block = self
+            .circuit
+            .block()
+            .ok_or(Error::InternalError("circuit is missing the witness block"))?;
+        let public_data = SuperCircuit::<Fr>::public_data(block);
+        let instances = self.circuit.instances();
+        MockProver::run(self.degree, &self.circuit, instances.clone())?
+            .verify_par()
+            .map_err(|failures| {
+                Error::InvalidWitness(format!(
+                    "{} constraints are not satisfied, the first one: {}",
+                    failures.len(),
+                    failures[0]
+                ))
+            })?;
+
+        let mut proof = Proof::from(
+            self.degree,
+            vec![],
+            instances,
+            self.circuit.params(),
+            self.circuit.fixed_params(),
+            public_data,
+            None,
+            None,
+            ProofTranscript::Blake2b,
+        );
+        proof.backend = ProofBackend::Mock;
+        Ok(proof)
+    }
+}
@@ -1,80 +1,494 @@
+//! The KZG prover, the only one of this crate for real proofs. The params and keys it proves with
+//! are cached by `SRS`, and `RealVerifier` verifies its proofs whichever transcript they use.
+//! Other circuits go through the `ExploitCircuit` seam rather than a prover of their own, the
+//! sub-circuits of the SuperCircuit are only proven on their own in the dev mode of `sub_circuit`.
+
+#[cfg(feature = "dev-circuits")]
+use super::sub_circuit::{self, CircuitSelection, SubCircuitProof};
 use super::{
-    proof::Proof,
-    real_verifier::RealVerifier,
-    srs::{VerifierSRS, SRS},
+    aggregation::{
+        self, AggregatedProof, AggregationProver, InstanceEncoding, Snark, DEFAULT_WRAPPER_DEGREE,
+    },
+    ceremony,
+    preflight::{self, MemoryEstimate},
+    proof::{PcsScheme, Proof, ProofTranscript},
+    real_verifier::{PoseidonTranscript, RealVerifier},
+    srs::{ArtifactChecks, VerifierSRS, SRS},
+    trajectory,
+    vk_registry::vk_digest,
+};
+use crate::{
+    config::Config,
+    error::Error,
+    utils::progress::{NoProgress, ProofPhase, ProverProgress},
+    witness::{circuit::ExploitCircuit, repro::Repro},
 };
-use crate::error::Error;
+use eth_types::H256;
 use halo2_proofs::{
+    dev::MockProver,
     halo2curves::bn256::{Bn256, Fr, G1Affine},
     plonk::{create_proof, Circuit},
-    poly::kzg::{commitment::KZGCommitmentScheme, multiopen::ProverSHPLONK},
-    transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer},
+    poly::kzg::{
+        commitment::KZGCommitmentScheme,
+        multiopen::{ProverGWC, ProverSHPLONK},
+    },
+    transcript::{Blake2bWrite, Challenge255, EncodedChallenge, TranscriptWriterBuffer},
 };
 use rand_chacha::{rand_core::SeedableRng, ChaChaRng};
-use std::path::PathBuf;
-use zkevm_circuits::{
-    instance::public_data_convert, super_circuit::SuperCircuit, util::SubCircuit,
+use rand_core::{OsRng, RngCore};
+use rayon::ThreadPoolBuilder;
+use snark_verifier::{loader::native::NativeLoader, system::halo2::transcript::evm::EvmTranscript};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
 };
+use tracing::{info, instrument};
+use zkevm_circuits::{super_circuit::SuperCircuit, witness::Block};
 
 #[derive(Clone)]
 pub struct RealProver {
     circuit: SuperCircuit<Fr>,
     degree: u32,
-    srs: SRS,
+    /// Shared by the provers of a long-running service, which load the keys only once.
+    srs: Arc<SRS>,
+    srs_path: PathBuf,
+    transcript: ProofTranscript,
+    pcs_scheme: PcsScheme,
+    /// Randomness of the blinding factors, the OS randomness when there is none.
+    rng: Option<ChaChaRng>,
+    options: ProverOptions,
+    /// Observer of the phases of `prove`.
+    progress: Arc<dyn ProverProgress>,
+}
+
+/// Seed of the rng of `set_deterministic`, which every proof used before the rng was an option.
+pub const DETERMINISTIC_SEED: u64 = 2;
+
+/// Resources keygen and proving may take, so that a prover can share a machine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProverOptions {
+    /// Threads of the rayon pool `keygen_pk` and `create_proof` run in, every core when there is
+    /// none.
+    pub num_threads: Option<usize>,
+    /// Releases the witness of the circuit once its proof is created, before a wrapper proof
+    /// loads its own keys. The prover then proves only once.
+    pub low_memory: bool,
+    /// Writes a generated proving key to the srs dir for the next provers of the circuit. An
+    /// ephemeral prover which proves once skips the slow write and the disk it takes.
+    pub persist_proving_key: bool,
+    /// Checks of the params and keys read from the srs dir.
+    pub artifact_checks: ArtifactChecks,
+    /// Runs `mock_check` before the keys are loaded, a witness which does not satisfy the
+    /// constraints then fails before keygen and proving rather than with an invalid proof.
+    pub mock_check: bool,
+}
+
+impl Default for ProverOptions {
+    fn default() -> Self {
+        Self {
+            num_threads: None,
+            low_memory: false,
+            persist_proving_key: true,
+            artifact_checks: ArtifactChecks::default(),
+            mock_check: false,
+        }
+    }
+}
+
+impl ProverOptions {
+    /// Runs `op` in a pool of `num_threads` threads, in the global pool if there is no limit.
+    pub fn install<T: Send>(
+        &self,
+        op: impl FnOnce() -> Result<T, Error> + Send,
+    ) -> Result<T, Error> {
+        match self.num_threads {
+            Some(num_threads) => ThreadPoolBuilder::new()
+                .num_threads(num_threads)
+                .build()
+                .map_err(|err| {
+                    Error::InvalidArgument(format!(
+                        "cannot start a pool of {num_threads} prover threads: {err}"
+                    ))
+                })?
+                .install(op),
+            None => op(),
+        }
+    }
 }
 
 impl RealProver {
-    pub fn from(circuit: SuperCircuit<Fr>, degree: u32, srs_path: PathBuf) -> Self {
-        let srs = SRS::load(&circuit, degree, srs_path);
+    pub fn from(circuit: SuperCircuit<Fr>, degree: u32, srs_path: PathBuf) -> Result<Self, Error> {
+        Self::from_with_options(circuit, degree, srs_path, ProverOptions::default())
+    }
+
+    /// Prover whose keys are generated, if they are not cached yet, and whose proofs are created
+    /// within the limits of `options`.
+    pub fn from_with_options(
+        circuit: SuperCircuit<Fr>,
+        degree: u32,
+        srs_path: PathBuf,
+        options: ProverOptions,
+    ) -> Result<Self, Error> {
+        Self::from_with_progress(circuit, degree, srs_path, options, Arc::new(NoProgress))
+    }
+
+    /// Prover which reports the loading of its keys and then the phases of `prove` to
+    /// `progress`.
+    pub fn from_with_progress(
+        circuit: SuperCircuit<Fr>,
+        degree: u32,
+        srs_path: PathBuf,
+        options: ProverOptions,
+        progress: Arc<dyn ProverProgress>,
+    ) -> Result<Self, Error> {
+        if options.mock_check {
+            options.install(|| Self::mock_check(&circuit, degree))?;
+        }
+        progress.on_phase(ProofPhase::Keygen, ProofPhase::Keygen.percent());
+        let srs = options.install(|| {
+            SRS::load_with(
+                &circuit,
+                degree,
+                srs_path.clone(),
+                options.persist_proving_key,
+                options.artifact_checks,
+            )
+        })?;
+        let mut prover = Self::from_srs(circuit, degree, srs_path, Arc::new(srs), options);
+        prover.set_progress(progress);
+        Ok(prover)
+    }
+
+    /// Runs the MockProver on the circuit and its instances at `degree`, which fails in seconds
+    /// to minutes with the constraints the witness does not satisfy, where a real proof of an
+    /// invalid witness only fails verification after keygen and proving.
+    pub fn mock_check(circuit: &SuperCircuit<Fr>, degree: u32) -> Result<(), Error> {
+        info!("Checking the constraints with the MockProver");
+        let prover = MockProver::run(degree, circuit, circuit.instances())?;
+        prover.verify_par().map_err(|failures| {
+            Error::InvalidWitness(format!(
+                "{} constraints are not satisfied, the first one: {}",
+                failures.len(),
+                failures[0]
+            ))
+        })
+    }
+
+    /// Proves only the sub-circuit `selection` of the witness `block`, for iterating on an
+    /// exploit without waiting for the keys and proof of the whole SuperCircuit. The keys are
+    /// generated in memory and the proof is verified before it is returned.
+    #[cfg(feature = "dev-circuits")]
+    pub fn prove_sub_circuit(
+        block: &Block<Fr>,
+        selection: CircuitSelection,
+        srs_path: &Path,
+        options: ProverOptions,
+    ) -> Result<SubCircuitProof, Error> {
+        options.install(|| sub_circuit::prove(selection, block, srs_path))
+    }
+
+    /// Prover with params and keys which are already loaded, `srs` must have been loaded for the
+    /// circuit params of `circuit` at `degree`.
+    pub fn from_srs(
+        circuit: SuperCircuit<Fr>,
+        degree: u32,
+        srs_path: PathBuf,
+        srs: Arc<SRS>,
+        options: ProverOptions,
+    ) -> Self {
         Self {
             circuit,
             degree,
             srs,
+            srs_path,
+            transcript: ProofTranscript::default(),
+            pcs_scheme: PcsScheme::default(),
+            rng: None,
+            options,
+            progress: Arc::new(NoProgress),
+        }
+    }
+
+    /// Approximate memory proving `circuit` at `degree` takes at its peak, to be checked before
+    /// the keys are generated.
+    pub fn estimate_memory(circuit: &SuperCircuit<Fr>, degree: u32) -> MemoryEstimate {
+        preflight::estimate_memory(circuit, degree)
+    }
+
+    /// Proofs of the witnesses of consecutive blocks from `BuilderClient::gen_witnesses`, one per
+    /// block, all at the degree of the largest block so that they share the keys. Proofs with a
+    /// Poseidon transcript can be aggregated into one proof with `AggregationProver`, and
+    /// `verify --trajectory` checks the chain of state roots again.
+    pub fn prove_blocks(
+        blocks: &[Block<Fr>],
+        srs_path: PathBuf,
+        transcript: ProofTranscript,
+    ) -> Result<Vec<Proof>, Error> {
+        let degree = blocks
+            .iter()
+            .map(SuperCircuit::<Fr>::degree_for_block)
+            .max()
+            .ok_or(Error::InvalidArgument("no blocks to prove".to_string()))?;
+        let options = ProverOptions::default();
+        let srs = Arc::new(SRS::load_with(
+            &SuperCircuit::<Fr>::from_block(&blocks[0]),
+            degree,
+            srs_path.clone(),
+            options.persist_proving_key,
+            options.artifact_checks,
+        )?);
+        let mut proofs = vec![];
+        for block in blocks {
+            let circuit = SuperCircuit::<Fr>::from_block(block);
+            let mut prover =
+                Self::from_srs(circuit, degree, srs_path.clone(), Arc::clone(&srs), options);
+            prover.set_transcript(transcript);
+            proofs.push(prover.prove()?);
+        }
+        trajectory::check_continuity(&proofs)?;
+        Ok(proofs)
+    }
+
+    /// Prover with the degree and srs dir of a parameter profile. Without a degree the circuit is
+    /// proven at the smallest degree its witness block fits in.
+    pub fn from_profile(circuit: SuperCircuit<Fr>, config: &Config) -> Result<Self, Error> {
+        let block = circuit
+            .block()
+            .ok_or(Error::InternalError("circuit is missing the witness block"))?;
+        let min_degree = SuperCircuit::<Fr>::degree_for_block(block);
+        let degree = config.degree().unwrap_or(min_degree);
+        if degree < min_degree {
+            return Err(Error::InvalidArgument(format!(
+                "the witness block needs degree {min_degree} but the config sets {degree}"
+            )));
         }
+        let srs_path = config.srs_path.clone().ok_or(Error::InvalidArgument(
+            "the config has no srs_path for the params and keys".to_string(),
+        ))?;
+        Self::from(circuit, degree, srs_path)
     }
 
+    /// Prover of a witness file written with `--repro-out` on a machine with chain access, so
+    /// that the proof is generated without any RPC. The circuit params and randomness are the
+    /// file's and the degree is the smallest the replayed block fits in.
+    pub async fn from_witness_file(path: &Path, srs_path: PathBuf) -> Result<Self, Error> {
+        let witness = Repro::read_from_file(path)?.replay()?;
+        let degree = witness.degree();
+        ceremony::provision(&srs_path, degree).await?;
+        Self::from(witness.into_circuit(), degree, srs_path)
+    }
+
+    /// Identity of the circuit, the keccak256 digest of the serialized verifying key, which a
+    /// bounty contract registers to accept proofs of this circuit only.
+    pub fn vk_hash(&self) -> H256 {
+        vk_digest(&self.srs.circuit_verifying_key)
+    }
+
+    /// Dir the params and keys of the prover are read from and cached in.
+    pub fn dir_path(&self) -> &Path {
+        &self.srs_path
+    }
+
+    /// Prover whose params and keys are the ones of `dir_path`, see `set_dir_path`.
+    pub fn with_dir_path(mut self, dir_path: PathBuf) -> Result<Self, Error> {
+        self.set_dir_path(dir_path)?;
+        Ok(self)
+    }
+
+    /// Loads the params and keys again from `dir_path`, generating and caching the ones it does
+    /// not have yet, and caches the keys of later wrapper proofs there.
+    pub fn set_dir_path(&mut self, dir_path: PathBuf) -> Result<(), Error> {
+        let options = self.options;
+        let srs = options.install(|| {
+            SRS::load_with(
+                &self.circuit,
+                self.degree,
+                dir_path.clone(),
+                options.persist_proving_key,
+                options.artifact_checks,
+            )
+        })?;
+        self.srs = Arc::new(srs);
+        self.srs_path = dir_path;
+        Ok(())
+    }
+
+    /// Blinds the proofs with `rng` instead of the OS randomness.
+    pub fn set_rng(&mut self, rng: ChaChaRng) {
+        self.rng = Some(rng);
+    }
+
+    /// Blinds the proofs with a fixed seed so that the same witness always gives the same proof,
+    /// for tests only since the blinding factors are then public.
+    pub fn set_deterministic(&mut self) {
+        self.set_rng(ChaChaRng::seed_from_u64(DETERMINISTIC_SEED));
+    }
+
+    /// Proofs are created with a Blake2b transcript by default, a Poseidon transcript is needed
+    /// for proofs which are going to be aggregated and a Keccak one for the solidity verifier.
+    pub fn set_transcript(&mut self, transcript: ProofTranscript) {
+        self.transcript = transcript;
+    }
+
+    /// Proofs are created with SHPLONK by default, which wrapped and aggregated proofs need.
+    pub fn set_pcs_scheme(&mut self, pcs_scheme: PcsScheme) {
+        self.pcs_scheme = pcs_scheme;
+    }
+
+    pub fn set_progress(&mut self, progress: Arc<dyn ProverProgress>) {
+        self.progress = progress;
+    }
+
+    /// With `low_memory` the witness is dropped once the proof is created, and proving again
+    /// fails with `Error::InvalidArgument`.
+    #[instrument(name = "prove", skip_all, fields(degree = self.degree))]
     pub fn prove(&mut self) -> Result<Proof, Error> {
-        let public_data = public_data_convert(&self.circuit.evm_circuit.block.clone().unwrap());
-        let instances = self.circuit.instance();
-        let instances_refs_intermediate = instances.iter().map(|v| &v[..]).collect::<Vec<&[Fr]>>();
-        let mut transcript = Blake2bWrite::<_, G1Affine, Challenge255<_>>::init(vec![]);
-        create_proof::<
-            KZGCommitmentScheme<Bn256>,
-            ProverSHPLONK<'_, Bn256>,
-            Challenge255<G1Affine>,
-            ChaChaRng,
-            Blake2bWrite<Vec<u8>, G1Affine, Challenge255<G1Affine>>,
-            _,
-        >(
-            &self.srs.general_params,
-            &self.srs.circuit_proving_key,
-            &[self.circuit.clone()],
-            &[&instances_refs_intermediate],
-            ChaChaRng::seed_from_u64(2),
-            &mut transcript,
-        )
-        .unwrap();
-
-        let proof = transcript.finalize();
-        Ok(Proof::from(
+        let block = self.circuit.block().ok_or_else(|| {
+            Error::InvalidArgument(match self.options.low_memory {
+                true => "the witness was dropped after the previous proof to save memory, create a new prover to prove again".to_string(),
+                false => "the circuit has no witness to prove".to_string(),
+            })
+        })?;
+        let public_data = SuperCircuit::<Fr>::public_data(block);
+        let instances = self.circuit.instances();
+        let options = self.options;
+        self.progress
+            .on_phase(ProofPhase::Proving, ProofPhase::Proving.percent());
+        let proof = options.install(|| match self.transcript {
+            ProofTranscript::Blake2b => {
+                self.create_proof::<_, Blake2bWrite<_, G1Affine, Challenge255<_>>>(&instances)
+            }
+            ProofTranscript::Poseidon => {
+                self.create_proof::<_, PoseidonTranscript<NativeLoader, _>>(&instances)
+            }
+            ProofTranscript::Keccak => {
+                self.create_proof::<_, EvmTranscript<G1Affine, NativeLoader, _, _>>(&instances)
+            }
+        })?;
+        let circuit_params = self.circuit.params();
+        let circuits_params = self.circuit.circuits_params;
+        if self.options.low_memory {
+            self.circuit = self.circuit.without_witnesses();
+        }
+        let mut proof = Proof::from(
             self.degree,
             proof,
             instances,
-            self.circuit.params(),
-            self.circuit.circuits_params,
+            circuit_params,
+            circuits_params,
             public_data,
             None,
             None,
-        ))
+            self.transcript,
+        );
+        proof.vk_digest = Some(self.vk_hash());
+        proof.pcs_scheme = self.pcs_scheme;
+        self.progress
+            .on_phase(ProofPhase::Done, ProofPhase::Done.percent());
+        Ok(proof)
+    }
+
+    /// Proves the circuit with a Poseidon transcript and verifies that proof inside a small outer
+    /// circuit of `wrapper_degree`, whose proof is far cheaper to verify on-chain. The outer proof
+    /// is created with the transcript set with `set_transcript`. The returned proof aggregates
+    /// only the SuperCircuit proof, which it carries along.
+    pub fn prove_wrapped(
+        &mut self,
+        wrapper_degree: u32,
+        instance_encoding: InstanceEncoding,
+    ) -> Result<AggregatedProof, Error> {
+        // the aggregation circuit verifies SHPLONK proofs with a Poseidon transcript
+        let transcript = self.transcript;
+        let pcs_scheme = self.pcs_scheme;
+        self.transcript = ProofTranscript::Poseidon;
+        self.pcs_scheme = PcsScheme::Shplonk;
+        let proof = self.prove();
+        self.transcript = transcript;
+        self.pcs_scheme = pcs_scheme;
+        let proof = proof?;
+
+        let snark = Snark::from(
+            &self.srs.general_params,
+            &self.srs.circuit_verifying_key,
+            &proof,
+        )?;
+        let key_id = aggregation::key_id(&[self.srs.circuit_verifying_key.clone()]);
+        let mut prover = self.options.install(|| {
+            AggregationProver::from_snarks(
+                &self.srs_path,
+                vec![snark],
+                &key_id,
+                vec![proof],
+                wrapper_degree,
+                instance_encoding,
+            )
+        })?;
+        prover.set_transcript(self.transcript);
+        if let Some(rng) = self.rng.as_mut() {
+            prover.set_rng(ChaChaRng::seed_from_u64(rng.next_u64()));
+        }
+        self.options.install(|| prover.prove())
+    }
+
+    /// Compresses the SuperCircuit proof into an outer proof with a Keccak transcript, which the
+    /// solidity verifier of `aggregation::generate_solidity` accepts.
+    pub fn prove_aggregated(
+        &mut self,
+        instance_encoding: InstanceEncoding,
+    ) -> Result<AggregatedProof, Error> {
+        let transcript = self.transcript;
+        self.transcript = ProofTranscript::Keccak;
+        let aggregated = self.prove_wrapped(DEFAULT_WRAPPER_DEGREE, instance_encoding);
+        self.transcript = transcript;
+        aggregated
+    }
+
+    fn create_proof<E, T>(&mut self, instances: &[Vec<Fr>]) -> Result<Vec<u8>, Error>
+    where
+        E: EncodedChallenge<G1Affine>,
+        T: TranscriptWriterBuffer<Vec<u8>, G1Affine, E>,
+    {
+        let instances_refs_intermediate = instances.iter().map(|v| &v[..]).collect::<Vec<&[Fr]>>();
+        let mut transcript = T::init(vec![]);
+        let mut os_rng = OsRng;
+        let rng: &mut dyn RngCore = match self.rng.as_mut() {
+            Some(rng) => rng,
+            None => &mut os_rng,
+        };
+        match self.pcs_scheme {
+            PcsScheme::Shplonk => {
+                create_proof::<KZGCommitmentScheme<Bn256>, ProverSHPLONK<'_, Bn256>, E, _, T, _>(
+                    &self.srs.general_params,
+                    &self.srs.circuit_proving_key,
+                    std::slice::from_ref(&self.circuit),
+                    &[&instances_refs_intermediate],
+                    rng,
+                    &mut transcript,
+                )
+            }
+            PcsScheme::Gwc => {
+                create_proof::<KZGCommitmentScheme<Bn256>, ProverGWC<'_, Bn256>, E, _, T, _>(
+                    &self.srs.general_params,
+                    &self.srs.circuit_proving_key,
+                    std::slice::from_ref(&self.circuit),
+                    &[&instances_refs_intermediate],
+                    rng,
+                    &mut transcript,
+                )
+            }
+        }?;
+        Ok(transcript.finalize())
     }
 
-    pub fn verifier(&mut self) -> RealVerifier {
+    /// Builds a verifier from the already loaded keys, this does not touch the disk and cannot fail.
+    pub fn verifier(&self) -> RealVerifier {
         RealVerifier {
             srs: VerifierSRS {
                 general_params: self.srs.general_params.clone(),
                 verifier_params: self.srs.verifier_params.clone(),
                 circuit_verifying_key: self.srs.circuit_verifying_key.clone(),
             },
+            pcs_scheme: self.pcs_scheme,
         }
     }
 }
@@ -1,119 +1,827 @@
-use super::{proof::Proof, srs::VerifierSRS};
-use crate::error::Error;
+#[cfg(feature = "nowasm")]
+use super::split_verifier;
+use super::{
+    artifact_manifest::Artifact,
+    proof::{PcsScheme, Proof, ProofBackend, ProofTranscript},
+    srs::{
+        self, circuit_verifying_key_file_name, light_verifier_key_file_name, ArtifactChecks,
+        LightVerifierKey, VerifierSRS, SUPER_CIRCUIT_NAME,
+    },
+    vk_registry::vk_digest,
+};
+#[cfg(feature = "nowasm")]
+use crate::utils::{
+    anvil::{
+        conversion::ConversionReverse,
+        types::{anvil_types, zkevm_types::Address},
+        AnvilClient,
+    },
+    hardfork::Hardfork,
+    solidity,
+};
+use crate::{error::Error, witness::circuit::ExploitCircuit};
+use bus_mapping::circuit_input_builder::FixedCParams;
 use core::slice::SlicePattern;
+#[cfg(feature = "nowasm")]
+use eth_types::U256;
 use eth_types::{keccak256, H256};
 use halo2_proofs::{
-    halo2curves::bn256::{Bn256, Fr, G1Affine},
-    plonk::verify_proof,
-    poly::kzg::{
-        commitment::KZGCommitmentScheme, multiopen::VerifierSHPLONK, strategy::SingleStrategy,
+    halo2curves::bn256::{Bn256, Fq, Fr, G1Affine},
+    plonk::{self, verify_proof, Circuit, VerifyingKey},
+    poly::{
+        commitment::{VerificationStrategy, Verifier},
+        kzg::{
+            commitment::{KZGCommitmentScheme, ParamsKZG},
+            multiopen::{VerifierGWC, VerifierSHPLONK},
+            strategy::{AccumulatorStrategy, SingleStrategy},
+        },
+    },
+    transcript::{
+        Blake2bRead, Challenge255, EncodedChallenge, TranscriptRead, TranscriptReadBuffer,
     },
-    transcript::{Blake2bRead, Challenge255, TranscriptReadBuffer},
 };
-use std::path::PathBuf;
+use snark_verifier::{
+    loader::{evm::EvmLoader, native::NativeLoader},
+    pcs::kzg::{Bdfg21, Gwc19, KzgAs, KzgDecidingKey},
+    system::halo2::{
+        compile,
+        transcript::{evm::EvmTranscript, halo2},
+        Config,
+    },
+    util::transcript::TranscriptRead as SnarkTranscriptRead,
+    verifier::{self, plonk::PlonkProtocol, SnarkVerifier},
+};
+use std::{fmt, path::PathBuf, rc::Rc};
+#[cfg(feature = "nowasm")]
+use std::{fs, path::Path};
+use tracing::{debug, warn};
+use zkevm_circuits::super_circuit::SuperCircuit;
+
+/// Name of the contract `RealVerifier::generate_solidity` generates.
+pub const SOLIDITY_VERIFIER_CONTRACT: &str = "Halo2Verifier";
+
+/// Name of the contract appended to the generated verifier, which takes ABI encoded arguments.
+pub const SOLIDITY_WRAPPER_CONTRACT: &str = "PoXVerifier";
+
+/// The generated verifier takes the instances followed by the proof as raw calldata, the wrapper
+/// exposes it with an ABI so that other contracts can call it. The verifier exceeds the contract
+/// size limit on some chains, so it is deployed on its own and the wrapper calls it. A call to an
+/// address without code succeeds, so the wrapper refuses to be deployed with one.
+const SOLIDITY_WRAPPER: &str = r#"
+contract PoXVerifier {
+    address public immutable verifier;
+
+    constructor(address verifier_) {
+        require(verifier_.code.length > 0, "verifier has no code");
+        verifier = verifier_;
+    }
+
+    /// Returns whether the verifier accepts the proof for the instances, the number of instances
+    /// must match the circuit.
+    function verify(bytes calldata proof, uint256[] calldata instances) external view returns (bool) {
+        (bool success, ) = verifier.staticcall(abi.encodePacked(instances, proof));
+        return success;
+    }
+}
+"#;
+
+/// Address the solidity verifier is deployed at in the local anvil of `verify_on_evm`.
+#[cfg(feature = "nowasm")]
+const EVM_VERIFIER_ADDRESS: Address = Address::repeat_byte(0x5e);
+
+pub(crate) const T: usize = 5;
+pub(crate) const RATE: usize = 4;
+pub(crate) const R_F: usize = 8;
+pub(crate) const R_P: usize = 60;
 
-// type PlonkVerifier = verifier::plonk::PlonkVerifier<KzgAs<Bn256, Gwc19>>;
+/// Transcript of the proofs created for aggregation, the aggregation circuit hashes with the same
+/// Poseidon spec.
+pub type PoseidonTranscript<L, S> = halo2::PoseidonTranscript<G1Affine, L, S, T, RATE, R_F, R_P>;
+
+/// snark-verifier calls SHPLONK Bdfg21 and GWC Gwc19, see `PcsScheme`.
+type PlonkVerifier<PCS> = verifier::plonk::PlonkVerifier<KzgAs<Bn256, PCS>>;
 
 pub struct RealVerifier {
     pub srs: VerifierSRS,
+    /// Multiopen scheme of the proofs this verifier accepts and of its solidity verifier.
+    pub pcs_scheme: PcsScheme,
+}
+
+/// Most likely cause of a verification failure, as found by `RealVerifier::diagnose`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Diagnosis {
+    /// The instances are not the digest of the public data, or the challenge artifact does not
+    /// compile to the codehash in it.
+    InvalidPublicInputs(String),
+    DegreeMismatch {
+        vk_degree: u32,
+        proof_degree: u32,
+    },
+    InstanceShapeMismatch {
+        expected: Vec<usize>,
+        actual: Vec<usize>,
+    },
+    InstanceMismatch {
+        column: usize,
+        row: usize,
+        expected: Fr,
+        actual: Fr,
+    },
+    MultiopenSchemeMismatch {
+        expected: &'static str,
+        actual: &'static str,
+    },
+    WrongVkOrInvalidProof,
+    /// The proof verifies with this verifying key, but it records another key or scheme.
+    Rejected(String),
+}
+
+impl fmt::Display for Diagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Diagnosis::InvalidPublicInputs(reason) => write!(f, "public inputs are invalid: {reason}"),
+            Diagnosis::DegreeMismatch {
+                vk_degree,
+                proof_degree,
+            } => write!(
+                f,
+                "verifying key is for degree {vk_degree} but the proof is for degree {proof_degree}"
+            ),
+            Diagnosis::InstanceShapeMismatch { expected, actual } => write!(
+                f,
+                "verifying key expects instance columns of lengths {expected:?} but the proof has {actual:?}"
+            ),
+            Diagnosis::InstanceMismatch {
+                column,
+                row,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "instance [{column}][{row}] is {actual:?} but the public data recomputes to {expected:?}"
+            ),
+            Diagnosis::MultiopenSchemeMismatch { expected, actual } => write!(
+                f,
+                "proof was created with the {actual} multiopen scheme but {expected} was expected"
+            ),
+            Diagnosis::WrongVkOrInvalidProof => write!(
+                f,
+                "instances are consistent but the proof does not verify with this verifying key, either the key is from a different circuit or the proof is invalid"
+            ),
+            Diagnosis::Rejected(reason) => write!(f, "proof verifies but is rejected: {reason}"),
+        }
+    }
 }
 
 impl RealVerifier {
-    pub async fn load_srs(srs_path: PathBuf, proof: &Proof) -> Self {
-        Self {
-            srs: VerifierSRS::load(
+    pub async fn load_srs(srs_path: PathBuf, proof: &Proof) -> Result<Self, Error> {
+        Self::load_srs_with(srs_path, proof, ArtifactChecks::default()).await
+    }
+
+    /// Verifier whose params and verifying key are read with the checks of `checks`.
+    pub async fn load_srs_with(
+        srs_path: PathBuf,
+        proof: &Proof,
+        checks: ArtifactChecks,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            srs: VerifierSRS::load_with(
                 srs_path,
                 proof.degree,
-                proof.circuit_params(),
+                proof.circuit_params()?,
                 proof.fixed_circuit_params,
+                checks,
             )
-            .await,
-        }
+            .await?,
+            pcs_scheme: proof.pcs_scheme,
+        })
+    }
+
+    /// Verifier of the params and verifying key files' bytes, see `VerifierSRS::from_bytes`.
+    pub fn from_bytes(
+        general_params: &[u8],
+        circuit_verifying_key: &[u8],
+        proof: &Proof,
+        checks: ArtifactChecks,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            srs: VerifierSRS::from_bytes(
+                general_params,
+                circuit_verifying_key,
+                proof.circuit_params()?,
+                checks,
+            )?,
+            pcs_scheme: proof.pcs_scheme,
+        })
+    }
+
+    /// Verifier of the circuit for `fcp` whose keys `setup` generated, before any proof exists.
+    pub async fn from_setup(
+        srs_path: PathBuf,
+        degree: u32,
+        fcp: FixedCParams,
+        pcs_scheme: PcsScheme,
+    ) -> Result<Self, Error> {
+        let circuit = SuperCircuit::<Fr>::without_witness(fcp);
+        Ok(Self {
+            srs: VerifierSRS::load(srs_path, degree, circuit.params(), fcp).await?,
+            pcs_scheme,
+        })
+    }
+
+    /// Solidity verifier of the circuit for `fcp`, whose keys `setup` generated, so that the
+    /// contract can be deployed before any proof exists. It accepts proofs of `pcs_scheme`.
+    pub async fn gen_evm_verifier(
+        srs_path: PathBuf,
+        degree: u32,
+        fcp: FixedCParams,
+        pcs_scheme: PcsScheme,
+    ) -> Result<String, Error> {
+        Self::from_setup(srs_path, degree, fcp, pcs_scheme)
+            .await?
+            .generate_setup_solidity(fcp)
+    }
+
+    /// Solidity verifier of this verifier's circuit, loaded with `from_setup` for `fcp`.
+    pub fn generate_setup_solidity(&self, fcp: FixedCParams) -> Result<String, Error> {
+        let circuit = SuperCircuit::<Fr>::without_witness(fcp);
+        self.generate_solidity(circuit.instances().iter().map(Vec::len).collect())
     }
 
     pub async fn verify(&self, proof: &Proof) -> Result<(), Error> {
-        let (_, proof_data, instances, public_data, _) = proof.unpack();
-        let strategy = SingleStrategy::new(&self.srs.general_params);
-        let instance_refs_intermediate = instances.iter().map(|v| &v[..]).collect::<Vec<&[Fr]>>();
-        let mut verifier_transcript =
-            Blake2bRead::<_, G1Affine, Challenge255<_>>::init(&proof_data[..]);
+        self.check_vk_digest(proof)?;
+        self.check_pcs_scheme(proof)?;
+        let (_, proof_data, instances, _, _) = proof.unpack()?;
+        self.verify_with_scheme(self.pcs_scheme, proof.transcript, &proof_data, &instances)?;
+        debug!("ZK proof verifies");
+
+        verify_public_inputs(proof).await
+    }
+
+    /// Verifies proofs of the circuit this verifier was loaded for in one go. The openings of all
+    /// the proofs are folded into a single MSM, so the batch costs one pairing check instead of
+    /// one per proof.
+    pub async fn verify_batch(&self, proofs: &[Proof]) -> Result<(), Error> {
+        let vk_degree = self.srs.circuit_verifying_key.get_domain().k();
+        let mut vk_file_name = None;
+        for (index, proof) in proofs.iter().enumerate() {
+            if proof.backend != ProofBackend::Kzg {
+                return Err(Error::InvalidArgument(format!(
+                    "proof {index} was not created with KZG and cannot be batched"
+                )));
+            }
+            // vks are identified by their file name, every proof needs the same one
+            let file_name =
+                circuit_verifying_key_file_name(proof.degree, proof.fixed_circuit_params);
+            if proof.degree != vk_degree
+                || *vk_file_name.get_or_insert_with(|| file_name.clone()) != file_name
+            {
+                return Err(Error::InvalidArgument(format!(
+                    "proof {index} is for {file_name}, batched proofs must share the verifying key"
+                )));
+            }
+            self.check_vk_digest(proof)?;
+            self.check_pcs_scheme(proof)?;
+        }
+        let holds = match self.pcs_scheme {
+            PcsScheme::Shplonk => self.batch_holds::<VerifierSHPLONK<'_, Bn256>>(proofs)?,
+            PcsScheme::Gwc => self.batch_holds::<VerifierGWC<'_, Bn256>>(proofs)?,
+        };
+        if !holds {
+            return Err(plonk::Error::ConstraintSystemFailure.into());
+        }
+        debug!("ZK proofs of the batch verify");
+
+        for proof in proofs {
+            verify_public_inputs(proof).await?;
+        }
+        Ok(())
+    }
 
-        verify_proof::<
+    /// Folds the openings of the proofs into one MSM and checks it.
+    fn batch_holds<'a, V>(&'a self, proofs: &[Proof]) -> Result<bool, Error>
+    where
+        V: Verifier<'a, KZGCommitmentScheme<Bn256>>,
+        AccumulatorStrategy<'a, Bn256>: VerificationStrategy<
+            'a,
             KZGCommitmentScheme<Bn256>,
-            VerifierSHPLONK<'_, Bn256>,
-            Challenge255<G1Affine>,
-            Blake2bRead<&[u8], G1Affine, Challenge255<G1Affine>>,
-            SingleStrategy<'_, Bn256>,
-        >(
+            V,
+            Output = AccumulatorStrategy<'a, Bn256>,
+        >,
+    {
+        let mut strategy = AccumulatorStrategy::new(&self.srs.general_params);
+        for proof in proofs {
+            let instances = proof.instances();
+            let instances = instances.iter().map(|v| &v[..]).collect::<Vec<&[Fr]>>();
+            strategy = match proof.transcript {
+                ProofTranscript::Blake2b => verify_with_transcript::<V, _, _, _>(
+                    &self.srs.verifier_params,
+                    &self.srs.circuit_verifying_key,
+                    strategy,
+                    &instances,
+                    &mut Blake2bRead::<_, G1Affine, Challenge255<_>>::init(&proof.data[..]),
+                )?,
+                ProofTranscript::Poseidon => verify_with_transcript::<V, _, _, _>(
+                    &self.srs.verifier_params,
+                    &self.srs.circuit_verifying_key,
+                    strategy,
+                    &instances,
+                    &mut PoseidonTranscript::<NativeLoader, _>::new(&proof.data[..]),
+                )?,
+                ProofTranscript::Keccak => verify_with_transcript::<V, _, _, _>(
+                    &self.srs.verifier_params,
+                    &self.srs.circuit_verifying_key,
+                    strategy,
+                    &instances,
+                    &mut EvmTranscript::<G1Affine, NativeLoader, _, _>::new(&proof.data[..]),
+                )?,
+            };
+        }
+        Ok(VerificationStrategy::<_, V>::finalize(strategy))
+    }
+
+    /// Same digest of the verifying key as `RealProver::vk_hash`.
+    pub fn vk_hash(&self) -> H256 {
+        vk_digest(&self.srs.circuit_verifying_key)
+    }
+
+    /// Fails if the proof was created with another multiopen scheme than this verifier's.
+    fn check_pcs_scheme(&self, proof: &Proof) -> Result<(), Error> {
+        match proof.pcs_scheme == self.pcs_scheme {
+            true => Ok(()),
+            false => Err(Error::InvalidArgument(format!(
+                "proof was created with the {} multiopen scheme but the verifier expects {}",
+                proof.pcs_scheme.name(),
+                self.pcs_scheme.name()
+            ))),
+        }
+    }
+
+    /// Fails if the proof records the digest of another verifying key than this verifier's.
+    fn check_vk_digest(&self, proof: &Proof) -> Result<(), Error> {
+        let digest = self.vk_hash();
+        match proof.vk_digest {
+            Some(proof_digest) if proof_digest != digest => Err(Error::VkMismatch(format!(
+                "proof was created against the verifying key {proof_digest:?} but {digest:?} is loaded"
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Finds out why a proof fails to verify: invalid public inputs, a vk for another degree,
+    /// instances which are not the image of the public data, a proof made with another multiopen
+    /// scheme, or otherwise a wrong vk or a bad proof. Only meant for a proof `verify` rejected.
+    pub async fn diagnose(&self, proof: &Proof) -> Diagnosis {
+        if let Err(error) = verify_public_inputs(proof).await {
+            return Diagnosis::InvalidPublicInputs(format!("{error:?}"));
+        }
+        let instances = proof.instances();
+
+        let vk_degree = self.srs.circuit_verifying_key.get_domain().k();
+        if vk_degree != proof.degree {
+            return Diagnosis::DegreeMismatch {
+                vk_degree,
+                proof_degree: proof.degree,
+            };
+        }
+
+        let expected = SuperCircuit::<Fr>::expected_instances(&proof.public_data);
+        let expected_shape = expected
+            .iter()
+            .map(|column| column.len())
+            .collect::<Vec<_>>();
+        let actual_shape = proof.num_instances();
+        if self.srs.circuit_verifying_key.cs().num_instance_columns() != expected.len()
+            || expected_shape != actual_shape
+        {
+            return Diagnosis::InstanceShapeMismatch {
+                expected: expected_shape,
+                actual: actual_shape,
+            };
+        }
+        for (column, (expected_column, actual_column)) in
+            expected.iter().zip(&instances).enumerate()
+        {
+            for (row, (expected, actual)) in expected_column.iter().zip(actual_column).enumerate() {
+                if expected != actual {
+                    return Diagnosis::InstanceMismatch {
+                        column,
+                        row,
+                        expected: *expected,
+                        actual: *actual,
+                    };
+                }
+            }
+        }
+
+        let expected = self.pcs_scheme;
+        if self
+            .verify_with_scheme(expected, proof.transcript, &proof.data, &instances)
+            .is_ok()
+        {
+            // the proof itself verifies, so `verify` failed on what the proof records
+            let error = self
+                .check_vk_digest(proof)
+                .and_then(|_| self.check_pcs_scheme(proof))
+                .err();
+            return Diagnosis::Rejected(match error {
+                Some(error) => format!("{error:?}"),
+                None => "the proof data does not unpack".to_string(),
+            });
+        }
+        let actual = match expected {
+            PcsScheme::Shplonk => PcsScheme::Gwc,
+            PcsScheme::Gwc => PcsScheme::Shplonk,
+        };
+        if self
+            .verify_with_scheme(actual, proof.transcript, &proof.data, &instances)
+            .is_ok()
+        {
+            return Diagnosis::MultiopenSchemeMismatch {
+                expected: expected.name(),
+                actual: actual.name(),
+            };
+        }
+        Diagnosis::WrongVkOrInvalidProof
+    }
+
+    fn verify_with_scheme(
+        &self,
+        pcs_scheme: PcsScheme,
+        transcript: ProofTranscript,
+        proof_data: &[u8],
+        instances: &[Vec<Fr>],
+    ) -> Result<(), Error> {
+        match pcs_scheme {
+            PcsScheme::Shplonk => self
+                .verify_proof_data::<VerifierSHPLONK<'_, Bn256>>(transcript, proof_data, instances),
+            PcsScheme::Gwc => {
+                self.verify_proof_data::<VerifierGWC<'_, Bn256>>(transcript, proof_data, instances)
+            }
+        }
+    }
+
+    fn verify_proof_data<'a, V>(
+        &'a self,
+        transcript: ProofTranscript,
+        proof_data: &[u8],
+        instances: &[Vec<Fr>],
+    ) -> Result<(), Error>
+    where
+        V: Verifier<'a, KZGCommitmentScheme<Bn256>>,
+        SingleStrategy<'a, Bn256>:
+            VerificationStrategy<'a, KZGCommitmentScheme<Bn256>, V, Output = ()>,
+    {
+        let instances = instances.iter().map(|v| &v[..]).collect::<Vec<&[Fr]>>();
+        match transcript {
+            ProofTranscript::Blake2b => verify_with_transcript::<V, _, _, _>(
+                &self.srs.verifier_params,
+                &self.srs.circuit_verifying_key,
+                SingleStrategy::new(&self.srs.general_params),
+                &instances,
+                &mut Blake2bRead::<_, G1Affine, Challenge255<_>>::init(proof_data),
+            ),
+            ProofTranscript::Poseidon => verify_with_transcript::<V, _, _, _>(
+                &self.srs.verifier_params,
+                &self.srs.circuit_verifying_key,
+                SingleStrategy::new(&self.srs.general_params),
+                &instances,
+                &mut PoseidonTranscript::<NativeLoader, _>::new(proof_data),
+            ),
+            ProofTranscript::Keccak => verify_with_transcript::<V, _, _, _>(
+                &self.srs.verifier_params,
+                &self.srs.circuit_verifying_key,
+                SingleStrategy::new(&self.srs.general_params),
+                &instances,
+                &mut EvmTranscript::<G1Affine, NativeLoader, _, _>::new(proof_data),
+            ),
+        }
+    }
+
+    /// Solidity source of a contract verifying the proofs of this verifier's circuit on-chain,
+    /// the verification itself is generated Yul. It accepts proofs of the multiopen scheme of the
+    /// verifier created with a Keccak transcript and takes the instances followed by the proof as
+    /// calldata. The source also has the `PoXVerifier` wrapper with an ABI, see `with_wrapper`.
+    pub fn generate_solidity(&self, num_instance: Vec<usize>) -> Result<String, Error> {
+        let protocol = compile(
             &self.srs.verifier_params,
             &self.srs.circuit_verifying_key,
-            strategy,
-            &[&instance_refs_intermediate],
-            &mut verifier_transcript,
-        )?;
-        // println!("- ZK proof verifies");
-
-        // verify public data to be image of instance
-        let digest = public_data.get_rpi_digest_word::<Fr>();
-        if !(instances[0][0] == digest.lo() && instances[0][1] == digest.hi()) {
-            return Err(Error::InternalError("digest mismatch"));
-        }
-        // println!("- Public inputs digest matches with instance");
-
-        if let Some(challenge_artifact) = proof.challenge_artifact.clone() {
-            // verify compilation
-            challenge_artifact.verify_compilation().await?;
-            // println!("- Challenge contract compiles to POX codehash in public inputs");
-
-            // ensure that challenge codehash is same as the codehash in public inputs
-            let bytecode = challenge_artifact
-                .get_deployed_bytecode("Challenge".to_string())
-                .unwrap();
-            let compiled_codehash = H256::from(keccak256(bytecode.as_slice()));
-            if compiled_codehash != proof.public_data.pox_challenge_codehash {
-                return Err(Error::InternalError(
-                    "compiled codehash does not match public inputs",
-                ));
+            Config::kzg().with_num_instance(num_instance.clone()),
+        );
+        let params = &self.srs.general_params;
+        let vk: KzgDecidingKey<Bn256> = (params.get_g()[0], params.g2(), params.s_g2()).into();
+
+        let loader = EvmLoader::new::<Fq, Fr>();
+        let protocol = protocol.loaded(&loader);
+        let mut transcript = EvmTranscript::<_, Rc<EvmLoader>, _, _>::new(&loader);
+        let instances = transcript.load_instances(num_instance);
+        match self.pcs_scheme {
+            PcsScheme::Shplonk => {
+                let proof = PlonkVerifier::<Bdfg21>::read_proof(
+                    &vk,
+                    &protocol,
+                    &instances,
+                    &mut transcript,
+                )?;
+                PlonkVerifier::<Bdfg21>::verify(&vk, &protocol, &instances, &proof)?;
+            }
+            PcsScheme::Gwc => {
+                let proof = PlonkVerifier::<Gwc19>::read_proof(
+                    &vk,
+                    &protocol,
+                    &instances,
+                    &mut transcript,
+                )?;
+                PlonkVerifier::<Gwc19>::verify(&vk, &protocol, &instances, &proof)?;
             }
-            // println!("- Compiled codehash verified with public inputs");
-            // println!("- Challenge codehash in public inputs");
-        } else {
-            println!("Warning: Challenge artifact is not present in the proof");
         }
+        Ok(with_wrapper(loader.solidity_code()))
+    }
 
-        Ok(())
+    /// Writes the solidity verifier of this verifier's circuit to `source_path` and runs the proof
+    /// through it in a local anvil, so that a verifier and a proof transcript which do not match
+    /// show up right after proving rather than after deploying. Returns the gas of the call.
+    #[cfg(feature = "nowasm")]
+    pub async fn check_on_evm(&self, proof: &Proof, source_path: &Path) -> Result<U256, Error> {
+        fs::write(source_path, self.generate_solidity(proof.num_instances())?)?;
+        verify_on_evm(source_path, proof).await
+    }
+}
+
+/// Verifier which only reads the light verifier key of the srs dir, written along with the
+/// verifying key, so that proofs can be verified on machines which never download the params.
+pub struct LightVerifier {
+    pub key: LightVerifierKey,
+}
+
+impl LightVerifier {
+    pub async fn load_srs(srs_path: PathBuf, proof: &Proof) -> Result<Self, Error> {
+        let key = srs::read(
+            Artifact {
+                label: "light verifier key",
+                circuit: Some(SUPER_CIRCUIT_NAME),
+                degree: proof.degree,
+                checks: ArtifactChecks::Checked,
+            },
+            srs_path,
+            light_verifier_key_file_name(proof.degree, proof.fixed_circuit_params),
+            |file| Ok(serde_json::from_reader(file)?),
+        )
+        .await?;
+        Ok(Self { key })
+    }
+
+    /// Digest of the verifying key the key file claims, as `RealVerifier::vk_hash`. The protocol
+    /// does not have the verifying key to recompute it from, so it only tells proofs of another
+    /// key apart and must not be checked against the approved keys.
+    pub fn vk_hash(&self) -> H256 {
+        self.key.vk_digest
+    }
+
+    pub async fn verify(&self, proof: &Proof) -> Result<(), Error> {
+        if let Some(proof_digest) = proof.vk_digest.filter(|digest| *digest != self.vk_hash()) {
+            return Err(Error::VkMismatch(format!(
+                "proof was created against the verifying key {proof_digest:?} but {:?} is loaded",
+                self.vk_hash()
+            )));
+        }
+        let dk = self.key.deciding_key()?;
+        let protocol = &self.key.protocol;
+        let instances = proof.instances();
+        match proof.transcript {
+            ProofTranscript::Blake2b => verify_native(
+                proof.pcs_scheme,
+                &dk,
+                protocol,
+                &instances,
+                &mut Blake2bRead::<_, G1Affine, Challenge255<_>>::init(&proof.data[..]),
+            ),
+            ProofTranscript::Poseidon => verify_native(
+                proof.pcs_scheme,
+                &dk,
+                protocol,
+                &instances,
+                &mut PoseidonTranscript::<NativeLoader, _>::new(&proof.data[..]),
+            ),
+            ProofTranscript::Keccak => verify_native(
+                proof.pcs_scheme,
+                &dk,
+                protocol,
+                &instances,
+                &mut EvmTranscript::<G1Affine, NativeLoader, _, _>::new(&proof.data[..]),
+            ),
+        }?;
+        debug!("ZK proof verifies");
+
+        verify_public_inputs(proof).await
+    }
+}
+
+fn verify_native<T>(
+    pcs_scheme: PcsScheme,
+    dk: &KzgDecidingKey<Bn256>,
+    protocol: &PlonkProtocol<G1Affine>,
+    instances: &[Vec<Fr>],
+    transcript: &mut T,
+) -> Result<(), Error>
+where
+    T: SnarkTranscriptRead<G1Affine, NativeLoader>,
+{
+    match pcs_scheme {
+        PcsScheme::Shplonk => {
+            let proof = PlonkVerifier::<Bdfg21>::read_proof(dk, protocol, instances, transcript)?;
+            PlonkVerifier::<Bdfg21>::verify(dk, protocol, instances, &proof)?;
+        }
+        PcsScheme::Gwc => {
+            let proof = PlonkVerifier::<Gwc19>::read_proof(dk, protocol, instances, transcript)?;
+            PlonkVerifier::<Gwc19>::verify(dk, protocol, instances, &proof)?;
+        }
+    }
+    Ok(())
+}
+
+/// Appends the `PoXVerifier` wrapper exposing `verify(bytes proof, uint256[] instances)` to the
+/// source of a generated verifier.
+pub(crate) fn with_wrapper(verifier_source: String) -> String {
+    verifier_source + SOLIDITY_WRAPPER
+}
+
+/// Deploys a solidity verifier written by `gen-verifier` in a local anvil and calls it with the
+/// proof, so that a proof and a contract which do not match show up before the contract is
+/// deployed on-chain. Returns the gas the verification costs.
+#[cfg(feature = "nowasm")]
+pub async fn verify_on_evm(source_path: &Path, proof: &Proof) -> Result<U256, Error> {
+    if proof.transcript != ProofTranscript::Keccak {
+        return Err(Error::InvalidArgument(format!(
+            "the solidity verifier only accepts proofs created with --transcript keccak, the proof has a {:?} transcript",
+            proof.transcript
+        )));
+    }
+    call_evm_verifier(source_path, proof.evm_calldata()).await
+}
+
+/// Sets the compiled solidity verifier in a local anvil and estimates a call with `calldata`,
+/// which fails if the verifier reverts.
+#[cfg(feature = "nowasm")]
+pub(crate) async fn call_evm_verifier(
+    source_path: &Path,
+    calldata: Vec<u8>,
+) -> Result<U256, Error> {
+    let mut contracts =
+        solidity::compile_solidity_contracts(source_path.to_string_lossy().to_string())?;
+    let code = contracts
+        .remove(SOLIDITY_VERIFIER_CONTRACT)
+        .ok_or(Error::CompilationError(format!(
+            "Could not find a {SOLIDITY_VERIFIER_CONTRACT} solidity contract"
+        )))?;
+    let anvil = AnvilClient::setup(None, None, Hardfork::default()).await?;
+    // the verifier may be larger than the contract size limit, so it is set instead of deployed
+    anvil.set_code(EVM_VERIFIER_ADDRESS, code).await?;
+    split_verifier::install(&anvil, contracts, EVM_VERIFIER_ADDRESS).await?;
+    let request = evm_request(Some(EVM_VERIFIER_ADDRESS), calldata);
+    // the verifier reverts on a proof which does not verify, which fails the estimation
+    anvil.estimate_gas(request, None).await.map_err(|error| {
+        debug!("Solidity verifier reverted: {error:?}");
+        plonk::Error::ConstraintSystemFailure.into()
+    })
+}
+
+#[cfg(feature = "nowasm")]
+pub(crate) fn evm_request(
+    to: Option<Address>,
+    data: Vec<u8>,
+) -> anvil_types::EthTransactionRequest {
+    anvil_types::EthTransactionRequest {
+        from: None,
+        to: to.map(|to| to.to_anvil_type()),
+        gas_price: Some(anvil_types::U256::zero()),
+        max_fee_per_gas: None,
+        max_priority_fee_per_gas: None,
+        gas: None,
+        value: None,
+        data: Some(anvil_types::Bytes::from(data)),
+        nonce: None,
+        chain_id: None,
+        access_list: None,
+        transaction_type: None,
+    }
+}
+
+/// Runtime code size above which EIP-170 rejects the deployment of a contract.
+#[cfg(feature = "nowasm")]
+pub const CONTRACT_SIZE_LIMIT: usize = 24576;
+
+/// On-chain cost of a solidity verifier, measured in a local anvil before anything is deployed.
+#[cfg(feature = "nowasm")]
+#[derive(Clone, Copy, Debug)]
+pub struct EvmGasReport {
+    /// Size of the runtime code of the largest contract of the verifier in bytes.
+    pub code_size: usize,
+    /// Number of parts of a verifier split by `split_verifier`, zero if it is not split.
+    pub parts: usize,
+    /// Gas of the transactions creating the verifier, none if its code exceeds
+    /// `CONTRACT_SIZE_LIMIT`, as it is only deployable on chains without the limit.
+    pub deployment_gas: Option<U256>,
+    /// Gas of a transaction verifying a sample proof, see `verify_on_evm`.
+    pub verification_gas: U256,
+}
+
+#[cfg(feature = "nowasm")]
+impl EvmGasReport {
+    /// Estimates the creation of the verifier at `source_path` in a local anvil, along with the
+    /// `verification_gas` of a sample proof the caller ran through it.
+    pub async fn new(source_path: &Path, verification_gas: U256) -> Result<Self, Error> {
+        let code_sizes = split_verifier::code_sizes(source_path)?;
+        let code_size = code_sizes.values().max().copied().unwrap_or_default();
+        let deployment_gas = match code_size > CONTRACT_SIZE_LIMIT {
+            true => None,
+            false => Some(split_verifier::deployment_gas(source_path).await?),
+        };
+        Ok(Self {
+            code_size,
+            parts: code_sizes.len().saturating_sub(1),
+            deployment_gas,
+            verification_gas,
+        })
+    }
+}
+
+impl fmt::Display for EvmGasReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.deployment_gas {
+            Some(gas) if self.parts > 0 => writeln!(
+                f,
+                "Deploying the {} parts of the verifier (at most {} bytes each) and its dispatcher costs {gas} gas",
+                self.parts, self.code_size
+            ),
+            Some(gas) => writeln!(
+                f,
+                "Deploying the verifier ({} bytes) costs {gas} gas",
+                self.code_size
+            ),
+            None => writeln!(
+                f,
+                "The verifier ({} bytes) exceeds the contract size limit of {CONTRACT_SIZE_LIMIT} bytes, it is only deployable on chains without the limit",
+                self.code_size
+            ),
+        }?;
+        write!(f, "Verifying a proof costs {} gas", self.verification_gas)
+    }
+}
+
+/// Checks the parts of a proof which do not depend on the commitment scheme: the instances must be
+/// the image of the public data and the challenge artifact must compile to the codehash in it.
+pub(crate) async fn verify_public_inputs(proof: &Proof) -> Result<(), Error> {
+    // verify public data to be image of instance
+    if proof.instances().first()
+        != SuperCircuit::<Fr>::expected_instances(&proof.public_data).first()
+    {
+        return Err(Error::InvalidArgument(
+            "the instances of the proof are not the digest of its public data".to_string(),
+        ));
+    }
+    debug!("Public inputs digest matches with instance");
+
+    if let Some(challenge_artifact) = proof.challenge_artifact.clone() {
+        // verify compilation
+        challenge_artifact.verify_compilation().await?;
+        debug!("Challenge contract compiles to POX codehash in public inputs");
+
+        // ensure that challenge codehash is same as the codehash in public inputs
+        let bytecode = challenge_artifact.get_deployed_bytecode("Challenge".to_string())?;
+        let compiled_codehash = H256::from(keccak256(bytecode.as_slice()));
+        if compiled_codehash != proof.public_data.pox_challenge_codehash {
+            return Err(Error::InvalidArgument(
+                "the challenge artifact does not compile to the codehash in the public inputs"
+                    .to_string(),
+            ));
+        }
+        debug!("Compiled codehash verified with public inputs");
+    } else {
+        warn!("Challenge artifact is not present in the proof");
     }
 
-    // pub fn generate_yul(&self, write_to_file: bool) -> Result<String, Error> {
-    //     let protocol = compile(
-    //         &self.verifier_params,
-    //         &self.circuit_verifying_key,
-    //         Config::kzg().with_num_instance(self.num_instance.clone()),
-    //     );
-    //     let vk: KzgDecidingKey<Bn256> = (
-    //         self.verifier_params.get_g()[0],
-    //         self.verifier_params.g2(),
-    //         self.verifier_params.s_g2(),
-    //     )
-    //         .into();
-
-    //     let loader = EvmLoader::new::<Fq, Fr>();
-    //     let protocol = protocol.loaded(&loader);
-    //     let mut transcript = EvmTranscript::<_, Rc<EvmLoader>, _, _>::new(&loader);
-
-    //     let instances = transcript.load_instances(self.num_instance.clone());
-    //     let proof = PlonkVerifier::read_proof(&vk, &protocol, &instances, &mut transcript).unwrap();
-    //     PlonkVerifier::verify(&vk, &protocol, &instances, &proof).unwrap();
-
-    //     let source = loader.solidity_code();
-    //     if write_to_file {
-    //         let proof_path = self
-    //             .dir_path
-    //             .join(Path::new(&format!("{}_verifier.yul", self.circuit_name)));
-
-    //         let mut file = File::create(proof_path)?;
-    //         file.write_all(source.as_bytes())?;
-    //     }
-    //     Ok(source)
-    // }
+    Ok(())
+}
+
+pub(crate) fn verify_with_transcript<'a, V, E, T, S>(
+    params: &'a ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    strategy: S,
+    instances: &[&[Fr]],
+    transcript: &mut T,
+) -> Result<S::Output, Error>
+where
+    V: Verifier<'a, KZGCommitmentScheme<Bn256>>,
+    E: EncodedChallenge<G1Affine>,
+    T: TranscriptRead<G1Affine, E>,
+    S: VerificationStrategy<'a, KZGCommitmentScheme<Bn256>, V>,
+{
+    Ok(verify_proof::<KZGCommitmentScheme<Bn256>, V, E, T, S>(
+        params,
+        vk,
+        strategy,
+        &[instances],
+        transcript,
+    )?)
 }
@@ -0,0 +1,358 @@
+//! Splitting of a generated solidity verifier whose runtime code exceeds the contract size limit.
+//! The statements of the Yul verification are cut into `Halo2VerifierPart<i>` contracts which run
+//! one after the other, each part returning its memory to the `Halo2Verifier` dispatcher which
+//! hands it to the next part after the calldata. The dispatcher keeps the name and the calldata of
+//! the unsplit verifier, so the `PoXVerifier` wrapper and `verify_on_evm` work with either.
+
+use super::real_verifier::{evm_request, CONTRACT_SIZE_LIMIT, SOLIDITY_VERIFIER_CONTRACT};
+use crate::{
+    error::Error,
+    utils::{
+        anvil::{types::zkevm_types::Address, AnvilClient},
+        hardfork::Hardfork,
+        solidity,
+    },
+};
+use eth_types::{keccak256, Bytes, H256, U256};
+use ethers::abi::{encode, Token};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+};
+use tracing::info;
+
+/// Most parts a verifier is split in before giving up.
+const MAX_PARTS: usize = 16;
+
+/// First address of the parts in the local anvil of `install`.
+const PART_ADDRESS_BASE: u64 = 0x5e0000;
+
+const DISPATCHER: &str = r#"
+contract Halo2Verifier {
+    address[] public parts;
+
+    constructor(address[] memory parts_) {
+        parts = parts_;
+    }
+
+    /// Runs the parts in order, the state each part returns is appended to the calldata of the
+    /// next one along with its length. Reverts if a part reverts, like the unsplit verifier.
+    fallback(bytes calldata input) external returns (bytes memory) {
+        (bool success, bytes memory state) = parts[0].staticcall(input);
+        require(success);
+        for (uint256 i = 1; i < parts.length; i++) {
+            (success, state) = parts[i].staticcall(abi.encodePacked(input, state, state.length));
+            require(success);
+        }
+        assembly {
+            return(0, 0)
+        }
+    }
+}
+"#;
+
+/// Memory of the previous part, which the dispatcher appends to the calldata.
+const RESTORE_STATE: &str = "{
+                let state_size := calldataload(sub(calldatasize(), 0x20))
+                calldatacopy(0, sub(sub(calldatasize(), 0x20), state_size), state_size)
+            }";
+
+/// End of every part but the last one, whose memory is the state of the next part.
+const RETURN_STATE: &str = "if not(success) { revert(0, 0) }
+            return(0, msize())";
+
+fn part_name(index: usize) -> String {
+    format!("{SOLIDITY_VERIFIER_CONTRACT}Part{index}")
+}
+
+/// Size of the runtime code of every contract of the verifier at `source_path`, the verifier and
+/// its parts if it is split.
+pub fn code_sizes(source_path: &Path) -> Result<BTreeMap<String, usize>, Error> {
+    Ok(
+        solidity::compile_solidity_contracts(source_path.to_string_lossy().to_string())?
+            .into_iter()
+            .filter(|(name, _)| name.starts_with(SOLIDITY_VERIFIER_CONTRACT))
+            .map(|(name, code)| (name, code.len()))
+            .collect(),
+    )
+}
+
+/// Rewrites the verifier at `source_path` into as few parts as keep every contract under the
+/// contract size limit. Returns the number of parts, zero if the verifier already fits.
+pub fn split_to_fit(source_path: &Path) -> Result<usize, Error> {
+    let verifier_size = code_sizes(source_path)?
+        .get(SOLIDITY_VERIFIER_CONTRACT)
+        .copied()
+        .ok_or(Error::CompilationError(format!(
+            "{} has no {SOLIDITY_VERIFIER_CONTRACT} contract",
+            source_path.display()
+        )))?;
+    if verifier_size <= CONTRACT_SIZE_LIMIT {
+        return Ok(0);
+    }
+
+    let source = fs::read_to_string(source_path)?;
+    let first_guess = (verifier_size + CONTRACT_SIZE_LIMIT - 1) / CONTRACT_SIZE_LIMIT;
+    for parts in first_guess.max(2)..=MAX_PARTS {
+        info!("Splitting the verifier of {verifier_size} bytes in {parts} parts");
+        fs::write(source_path, split(&source, parts)?)?;
+        let sizes = code_sizes(source_path)?;
+        if sizes.values().all(|size| *size <= CONTRACT_SIZE_LIMIT) {
+            return Ok(parts);
+        }
+    }
+    fs::write(source_path, source)?;
+    Err(Error::CompilationError(format!(
+        "the verifier of {verifier_size} bytes does not fit the contract size limit in {MAX_PARTS} parts"
+    )))
+}
+
+/// Source with the `Halo2Verifier` contract of a generated verifier replaced by `parts` parts and
+/// the dispatcher. The declarations and checks before the first statement of the verification,
+/// `success`, the moduli and the helper functions, are repeated in every part.
+pub fn split(source: &str, parts: usize) -> Result<String, Error> {
+    let contract_start = source
+        .find(&format!("contract {SOLIDITY_VERIFIER_CONTRACT} {{"))
+        .ok_or(unsplittable("the verifier contract is missing"))?;
+    let contract_end = matching_brace(source, contract_start)?;
+    let assembly_start = source[contract_start..contract_end]
+        .find("assembly")
+        .map(|offset| contract_start + offset)
+        .ok_or(unsplittable("the verifier has no assembly block"))?;
+    let assembly_end = matching_brace(source, assembly_start)?;
+    let body_start = assembly_start
+        + source[assembly_start..]
+            .find('{')
+            .ok_or(unsplittable("the assembly block has no body"))?
+        + 1;
+
+    let statements = statements(&source[body_start..assembly_end])?;
+    let prologue_len = statements
+        .iter()
+        .take_while(|statement| {
+            ["let ", "function ", "if "]
+                .iter()
+                .any(|keyword| statement.starts_with(keyword))
+        })
+        .count();
+    let (prologue, verification) = statements.split_at(prologue_len);
+    if let Some(statement) = verification.iter().find(|s| s.starts_with("let ")) {
+        return Err(unsplittable(&format!(
+            "the verification declares a variable shared by its statements: {statement}"
+        )));
+    }
+    if verification.len() < parts {
+        return Err(unsplittable(
+            "the verification has fewer statements than parts",
+        ));
+    }
+
+    let mut contracts = vec![];
+    let chunk_len = verification.iter().map(String::len).sum::<usize>() / parts;
+    let mut chunk: Vec<&str> = vec![];
+    let mut len = 0;
+    for (index, statement) in verification.iter().enumerate() {
+        chunk.push(statement);
+        len += statement.len();
+        let remaining = verification.len() - index - 1;
+        let parts_left = parts - contracts.len() - 1;
+        if parts_left > 0 && (len >= chunk_len || remaining == parts_left) {
+            contracts.push(part(contracts.len(), prologue, &chunk, false));
+            chunk.clear();
+            len = 0;
+        }
+    }
+    contracts.push(part(contracts.len(), prologue, &chunk, true));
+
+    Ok(format!(
+        "{}{}\n{DISPATCHER}{}",
+        &source[..contract_start],
+        contracts.join("\n"),
+        &source[contract_end + 1..]
+    ))
+}
+
+fn part(index: usize, prologue: &[String], statements: &[&str], last: bool) -> String {
+    let mut body = prologue.to_vec();
+    if index > 0 {
+        body.push(RESTORE_STATE.to_string());
+    }
+    body.extend(statements.iter().map(|statement| statement.to_string()));
+    if !last {
+        body.push(RETURN_STATE.to_string());
+    }
+    format!(
+        "contract {} {{
+    fallback(bytes calldata) external returns (bytes memory) {{
+        assembly {{
+            {}
+        }}
+    }}
+}}
+",
+        part_name(index),
+        body.join("\n            ")
+    )
+}
+
+/// Top level statements of a Yul block, a statement ending on the line its braces are closed.
+fn statements(body: &str) -> Result<Vec<String>, Error> {
+    let mut statements = vec![];
+    let mut statement = String::new();
+    let mut depth = 0i64;
+    for line in body.lines() {
+        let line = line.trim();
+        if line.is_empty() || (depth == 0 && line.starts_with("//")) {
+            continue;
+        }
+        if !statement.is_empty() {
+            statement.push('\n');
+        }
+        statement.push_str(line);
+        depth += line.matches('{').count() as i64 - line.matches('}').count() as i64;
+        if depth < 0 {
+            return Err(unsplittable("the assembly block has unbalanced braces"));
+        }
+        if depth == 0 {
+            statements.push(std::mem::take(&mut statement));
+        }
+    }
+    match depth {
+        0 => Ok(statements),
+        _ => Err(unsplittable("the assembly block has unbalanced braces")),
+    }
+}
+
+/// Offset of the brace closing the first brace after `start`.
+fn matching_brace(source: &str, start: usize) -> Result<usize, Error> {
+    let mut depth = 0;
+    for (offset, c) in source[start..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 1 => return Ok(start + offset),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    Err(unsplittable("the verifier has unbalanced braces"))
+}
+
+fn unsplittable(reason: &str) -> Error {
+    Error::CompilationError(format!("cannot split the verifier, {reason}"))
+}
+
+fn part_address(index: usize) -> Address {
+    Address::from_low_u64_be(PART_ADDRESS_BASE + index as u64)
+}
+
+/// Sets the parts among the runtime code of `contracts` in anvil and points the dispatcher at
+/// `dispatcher_address` to them, nothing is done for a verifier which is not split.
+pub(crate) async fn install(
+    anvil: &AnvilClient,
+    mut contracts: HashMap<String, Bytes>,
+    dispatcher_address: Address,
+) -> Result<(), Error> {
+    let mut parts = vec![];
+    while let Some(code) = contracts.remove(&part_name(parts.len())) {
+        let address = part_address(parts.len());
+        anvil.set_code(address, code).await?;
+        parts.push(address);
+    }
+    if parts.is_empty() {
+        return Ok(());
+    }
+    // `parts` is the dynamic array of slot 0, its elements start at keccak256(0)
+    anvil
+        .set_storage_at(
+            dispatcher_address,
+            U256::zero(),
+            H256::from_low_u64_be(parts.len() as u64),
+        )
+        .await?;
+    let elements = U256::from_big_endian(&keccak256(H256::zero()));
+    for (index, address) in parts.into_iter().enumerate() {
+        anvil
+            .set_storage_at(dispatcher_address, elements + index, H256::from(address))
+            .await?;
+    }
+    Ok(())
+}
+
+/// Gas of the transactions creating the verifier at `source_path` in a local anvil, the parts and
+/// then the dispatcher if it is split.
+pub(crate) async fn deployment_gas(source_path: &Path) -> Result<U256, Error> {
+    let mut contracts =
+        solidity::compile_solidity_creation_contracts(source_path.to_string_lossy().to_string())?;
+    let anvil = AnvilClient::setup(None, None, Hardfork::default()).await?;
+    let mut gas = U256::zero();
+    let mut parts = vec![];
+    while let Some(code) = contracts.remove(&part_name(parts.len())) {
+        gas += anvil
+            .estimate_gas(evm_request(None, code.to_vec()), None)
+            .await?;
+        parts.push(Token::Address(part_address(parts.len())));
+    }
+    let mut creation_code = contracts
+        .remove(SOLIDITY_VERIFIER_CONTRACT)
+        .ok_or(Error::CompilationError(format!(
+            "{} has no {SOLIDITY_VERIFIER_CONTRACT} contract",
+            source_path.display()
+        )))?
+        .to_vec();
+    if !parts.is_empty() {
+        creation_code.extend(encode(&[Token::Array(parts)]));
+    }
+    gas += anvil
+        .estimate_gas(evm_request(None, creation_code), None)
+        .await?;
+    Ok(gas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split;
+
+    const VERIFIER: &str = "pragma solidity ^0.8.0;
+
+contract Halo2Verifier {
+    fallback(bytes calldata) external returns (bytes memory) {
+        assembly {
+            let success := true
+            let f_q := 0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001
+            function validate_ec_point(x, y) -> valid {
+                valid := lt(x, y)
+            }
+            mstore(0x0, mod(calldataload(0x0), f_q))
+            {
+                let x := calldataload(0x20)
+                mstore(0x20, x)
+            }
+            success := and(success, staticcall(gas(), 0x7, 0x0, 0x60, 0x40, 0x40))
+            mstore(0x60, mload(0x40))
+            if not(success) { revert(0, 0) }
+            return(0, 0)
+        }
+    }
+}
+
+contract PoXVerifier {
+}
+";
+
+    #[test]
+    fn test_split() {
+        let source = split(VERIFIER, 3).unwrap();
+        for index in 0..3 {
+            assert!(source.contains(&format!("contract Halo2VerifierPart{index} {{")));
+        }
+        assert_eq!(source.matches("function validate_ec_point").count(), 3);
+        assert_eq!(source.matches("let state_size :=").count(), 2);
+        assert_eq!(source.matches("return(0, msize())").count(), 2);
+        assert_eq!(source.matches("calldataload(0x20)").count(), 1);
+        assert!(source.contains("contract Halo2Verifier {\n    address[] public parts;"));
+        assert!(source.contains("contract PoXVerifier {"));
+
+        assert!(split(VERIFIER, 7).is_err());
+    }
+}
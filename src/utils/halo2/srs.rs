@@ -1,20 +1,75 @@
-use crate::{error::Error, utils::ipfs};
+use super::{
+    artifact_manifest::Artifact,
+    circuit_lock::{CircuitConfig, CircuitLock},
+    vk_registry::vk_digest,
+};
+#[cfg(feature = "nowasm")]
+use super::{artifact_manifest::ArtifactManifest, ceremony};
+use crate::error::Error;
+#[cfg(feature = "nowasm")]
+use crate::{
+    utils::{ipfs, shutdown},
+    witness::circuit::ExploitCircuit,
+};
 use bus_mapping::circuit_input_builder::FixedCParams;
+use eth_types::H256;
+use ethers::types::Bytes;
+use fs2::FileExt;
+#[cfg(feature = "nowasm")]
+use halo2_proofs::plonk::{keygen_pk, keygen_vk, Circuit, ProvingKey};
 use halo2_proofs::{
-    halo2curves::bn256::{Bn256, Fr, G1Affine},
-    plonk::{keygen_pk, keygen_vk, Circuit, ProvingKey, VerifyingKey},
-    poly::{commitment::ParamsProver, kzg::commitment::ParamsKZG},
+    halo2curves::{
+        bn256::{Bn256, Fr, G1Affine, G2Affine},
+        group::GroupEncoding,
+    },
+    plonk::VerifyingKey,
+    poly::{
+        commitment::{Params, ParamsProver},
+        kzg::commitment::ParamsKZG,
+    },
     SerdeFormat,
 };
-use rand_chacha::{rand_core::SeedableRng, ChaChaRng};
+use serde::{Deserialize, Serialize};
+use snark_verifier::{
+    pcs::kzg::KzgDecidingKey,
+    system::halo2::{compile, Config},
+    verifier::plonk::PlonkProtocol,
+};
 use std::{
-    fs::{remove_file, File},
-    path::PathBuf,
+    fs::{read_dir, remove_file, rename, File, OpenOptions},
+    io::BufReader,
+    path::{Path, PathBuf},
 };
+use tracing::{debug, info, instrument, warn};
 use zkevm_circuits::super_circuit::{SuperCircuit, SuperCircuitParams};
 
-const SERDE_FORMAT: SerdeFormat = SerdeFormat::RawBytes;
+pub(crate) const SERDE_FORMAT: SerdeFormat = SerdeFormat::RawBytes;
+
+/// Name of the SuperCircuit in the manifests of its keys.
+pub(crate) const SUPER_CIRCUIT_NAME: &str = "SuperCircuit";
+
+/// Checks of the points of the params and keys read from the srs dir. The files are always
+/// written in `SERDE_FORMAT`, the raw bytes both variants read.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ArtifactChecks {
+    /// Every point is checked to be on the curve while it is read.
+    #[default]
+    Checked,
+    /// Points are read without checks, several times faster for a large proving key, only for
+    /// an srs dir nobody else writes to.
+    Unchecked,
+}
 
+impl ArtifactChecks {
+    pub fn serde_format(self) -> SerdeFormat {
+        match self {
+            ArtifactChecks::Checked => SERDE_FORMAT,
+            ArtifactChecks::Unchecked => SerdeFormat::RawBytesUnchecked,
+        }
+    }
+}
+
+#[cfg(feature = "nowasm")]
 #[derive(Clone)]
 pub struct SRS {
     pub general_params: ParamsKZG<Bn256>,
@@ -23,25 +78,115 @@ pub struct SRS {
     pub circuit_proving_key: ProvingKey<G1Affine>,
 }
 
+#[cfg(feature = "nowasm")]
 impl SRS {
-    pub fn load(circuit: &SuperCircuit<Fr>, degree: u32, srs_path: PathBuf) -> Self {
-        let general_params = load_general_params(srs_path.clone(), degree);
+    /// Reads the params and keys from `srs_path`, generating and caching any that are missing.
+    pub fn load(circuit: &SuperCircuit<Fr>, degree: u32, srs_path: PathBuf) -> Result<Self, Error> {
+        Self::load_with(circuit, degree, srs_path, true, ArtifactChecks::default())
+    }
+
+    /// Same as `load`, but a proving key which is not cached yet is only generated in memory
+    /// unless `persist_proving_key` is set. A cached proving key is read either way, with the
+    /// checks of `checks` like the params and the verifying key.
+    #[instrument(name = "keygen", skip_all, fields(degree = degree))]
+    pub fn load_with(
+        circuit: &SuperCircuit<Fr>,
+        degree: u32,
+        srs_path: PathBuf,
+        persist_proving_key: bool,
+        checks: ArtifactChecks,
+    ) -> Result<Self, Error> {
+        // checked before the keys are read, a key which fails to read would be regenerated
+        let mut lock = CircuitLock::load(&srs_path)?;
+        let vk_file_name = circuit_verifying_key_file_name(degree, circuit.circuits_params);
+        let config = CircuitConfig::from(circuit.params());
+        lock.check_config(&vk_file_name, &config)?;
+
+        let general_params = load_general_params_with(srs_path.clone(), degree, checks)?;
         let verifier_params = general_params.verifier_params().clone();
-        let circuit_verifying_key =
-            load_circuit_verifying_key(srs_path.clone(), degree, circuit, &general_params);
+        let locked = lock.entries.contains_key(&vk_file_name);
+        let circuit_verifying_key = load_circuit_verifying_key(
+            srs_path.clone(),
+            degree,
+            circuit,
+            &general_params,
+            checks,
+            locked,
+        )?;
+        lock.check_or_record(&srs_path, &vk_file_name, config, &circuit_verifying_key)?;
+        load_light_verifier_key(
+            srs_path.clone(),
+            degree,
+            circuit,
+            &general_params,
+            &circuit_verifying_key,
+        )?;
         let circuit_proving_key = load_circuit_proving_key(
             srs_path,
             degree,
             circuit,
             &general_params,
             &circuit_verifying_key,
-        );
-        Self {
+            persist_proving_key,
+            checks,
+        )?;
+
+        // the pk embeds the vk it was generated against, a different digest means one of the
+        // cached files is stale and proofs from this pk would never verify against the vk
+        if circuit_proving_key.get_vk().transcript_repr() != circuit_verifying_key.transcript_repr()
+        {
+            return Err(Error::VkMismatch(format!(
+                "{} was generated against a different verifying key or circuit params than {}, rerun with --regenerate-keys to rebuild both",
+                circuit_proving_key_file_name(degree, circuit.circuits_params),
+                circuit_verifying_key_file_name(degree, circuit.circuits_params),
+            )));
+        }
+
+        Ok(Self {
             general_params,
             verifier_params,
             circuit_verifying_key,
             circuit_proving_key,
+        })
+    }
+
+    /// Downloads the params and generates the keys of the circuit for `fcp` ahead of the first
+    /// proof, which would otherwise do it while proving.
+    pub async fn setup(srs_path: PathBuf, degree: u32, fcp: FixedCParams) -> Result<Self, Error> {
+        ceremony::provision(&srs_path, degree).await?;
+        Self::load(&SuperCircuit::<Fr>::without_witness(fcp), degree, srs_path)
+    }
+
+    /// Digest of the verifying key cached for `fcp` at `degree`, read from its light verifier key
+    /// of a few hundred KB rather than from the keys. `None` if the keys were not generated yet.
+    pub fn cached_vk_digest(
+        srs_path: &Path,
+        degree: u32,
+        fcp: FixedCParams,
+    ) -> Result<Option<H256>, Error> {
+        let path = srs_path.join(light_verifier_key_file_name(degree, fcp));
+        if !path.exists() {
+            return Ok(None);
+        }
+        let key: LightVerifierKey = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        Ok(Some(key.vk_digest))
+    }
+
+    /// Deletes the cached vk and pk for the circuit params so that the next load regenerates them.
+    pub fn remove_keys(srs_path: &Path, degree: u32, fcp: FixedCParams) -> Result<(), Error> {
+        CircuitLock::remove(srs_path, &circuit_verifying_key_file_name(degree, fcp))?;
+        for path in [
+            srs_path.join(circuit_verifying_key_file_name(degree, fcp)),
+            srs_path.join(circuit_proving_key_file_name(degree, fcp)),
+            srs_path.join(light_verifier_key_file_name(degree, fcp)),
+        ] {
+            if path.exists() {
+                info!("Removing {}", path.display());
+                remove_file(&path)?;
+            }
+            remove_file(ArtifactManifest::path(&path)).ok();
         }
+        Ok(())
     }
 }
 
@@ -57,14 +202,44 @@ impl VerifierSRS {
         degree: u32,
         circuit_params: SuperCircuitParams<Fr>,
         fcp: FixedCParams,
-    ) -> Self {
+    ) -> Result<Self, Error> {
+        Self::load_with(
+            srs_path,
+            degree,
+            circuit_params,
+            fcp,
+            ArtifactChecks::default(),
+        )
+        .await
+    }
+
+    pub async fn load_with(
+        srs_path: PathBuf,
+        degree: u32,
+        circuit_params: SuperCircuitParams<Fr>,
+        fcp: FixedCParams,
+        checks: ArtifactChecks,
+    ) -> Result<Self, Error> {
+        // a verifier-only build reads the params already in the srs dir
+        #[cfg(feature = "nowasm")]
+        ceremony::provision(&srs_path, degree).await?;
         let general_params = read(
+            Artifact {
+                label: "general params",
+                circuit: None,
+                degree,
+                checks,
+            },
             srs_path.clone(),
             general_params_file_name(degree),
-            |mut file| Ok(ParamsKZG::<Bn256>::read_custom(&mut file, SERDE_FORMAT)?),
+            |mut file| {
+                Ok(ParamsKZG::<Bn256>::read_custom(
+                    &mut file,
+                    checks.serde_format(),
+                )?)
+            },
         )
-        .await
-        .unwrap();
+        .await?;
         let verifier_params = general_params.verifier_params().clone();
         // let verifier_params = read(
         //     srs_path.clone(),
@@ -73,56 +248,230 @@ impl VerifierSRS {
         // )
         // .await
         // .unwrap();
+        let mut lock = CircuitLock::load(&srs_path)?;
+        let vk_file_name = circuit_verifying_key_file_name(degree, fcp);
+        let config = CircuitConfig::from(circuit_params.clone());
+        lock.check_config(&vk_file_name, &config)?;
         let circuit_verifying_key = read(
-            srs_path,
-            circuit_verifying_key_file_name(degree, fcp),
+            Artifact {
+                label: "circuit verifying key",
+                circuit: Some(SUPER_CIRCUIT_NAME),
+                degree,
+                checks,
+            },
+            srs_path.clone(),
+            vk_file_name.clone(),
             |file| {
                 Ok(VerifyingKey::<G1Affine>::read::<File, SuperCircuit<Fr>>(
                     file,
-                    SERDE_FORMAT,
+                    checks.serde_format(),
                     circuit_params.clone(),
                 )?)
             },
         )
-        .await
-        .unwrap();
-        Self {
+        .await?;
+        lock.check_or_record(&srs_path, &vk_file_name, config, &circuit_verifying_key)?;
+        Ok(Self {
             general_params,
             verifier_params,
             circuit_verifying_key,
+        })
+    }
+
+    /// Params and verifying key from the bytes of their files rather than from an srs dir, for
+    /// verifiers which are handed the files, without the manifest and lock checks of the dir.
+    pub fn from_bytes(
+        mut general_params: &[u8],
+        mut circuit_verifying_key: &[u8],
+        circuit_params: SuperCircuitParams<Fr>,
+        checks: ArtifactChecks,
+    ) -> Result<Self, Error> {
+        let general_params =
+            ParamsKZG::<Bn256>::read_custom(&mut general_params, checks.serde_format())?;
+        let verifier_params = general_params.verifier_params().clone();
+        let circuit_verifying_key = VerifyingKey::<G1Affine>::read::<_, SuperCircuit<Fr>>(
+            &mut circuit_verifying_key,
+            checks.serde_format(),
+            circuit_params,
+        )?;
+        Ok(Self {
+            general_params,
+            verifier_params,
+            circuit_verifying_key,
+        })
+    }
+}
+
+/// What verifying a proof needs of the params and the verifying key: the protocol snark-verifier
+/// compiles from the vk and the three points of the params the final pairing is checked with. It
+/// is a few hundred KB, while the params of a high degree take hundreds of MB.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LightVerifierKey {
+    pub degree: u32,
+    pub vk_digest: H256,
+    pub protocol: PlonkProtocol<G1Affine>,
+    g: Bytes,
+    g2: Bytes,
+    s_g2: Bytes,
+}
+
+impl LightVerifierKey {
+    pub fn from(
+        general_params: &ParamsKZG<Bn256>,
+        vk: &VerifyingKey<G1Affine>,
+        num_instance: Vec<usize>,
+    ) -> Self {
+        Self {
+            degree: general_params.k(),
+            vk_digest: vk_digest(vk),
+            protocol: compile(
+                general_params,
+                vk,
+                Config::kzg().with_num_instance(num_instance),
+            ),
+            g: Bytes::from(general_params.get_g()[0].to_bytes().as_ref().to_vec()),
+            g2: Bytes::from(general_params.g2().to_bytes().as_ref().to_vec()),
+            s_g2: Bytes::from(general_params.s_g2().to_bytes().as_ref().to_vec()),
         }
     }
+
+    pub fn deciding_key(&self) -> Result<KzgDecidingKey<Bn256>, Error> {
+        Ok((
+            decode_point::<G1Affine>(&self.g)?,
+            decode_point::<G2Affine>(&self.g2)?,
+            decode_point::<G2Affine>(&self.s_g2)?,
+        )
+            .into())
+    }
+}
+
+fn decode_point<C: GroupEncoding>(bytes: &[u8]) -> Result<C, Error> {
+    let mut repr = C::Repr::default();
+    if repr.as_ref().len() != bytes.len() {
+        return Err(Error::SrsInvalid(
+            "light verifier key has a point of the wrong length".to_string(),
+        ));
+    }
+    repr.as_mut().copy_from_slice(bytes);
+    Option::from(C::from_bytes(&repr)).ok_or(Error::SrsInvalid(
+        "light verifier key has a point which is not on the curve".to_string(),
+    ))
+}
+
+const GENERAL_PARAMS_PREFIX: &str = "kzg_general_params_";
+
+pub(crate) fn general_params_file_name(degree: u32) -> String {
+    format!("{GENERAL_PARAMS_PREFIX}{}", degree)
+}
+
+#[cfg(feature = "nowasm")]
+pub(crate) fn ipa_params_file_name(degree: u32) -> String {
+    format!("ipa_params_{}", degree)
+}
+
+#[cfg(feature = "nowasm")]
+pub(crate) fn ipa_verifying_key_file_name(degree: u32, fcp: FixedCParams) -> String {
+    format!(
+        "PoX_ipa_verifying_key_{}_{}",
+        degree,
+        circuit_params_str(fcp)
+    )
 }
 
-fn general_params_file_name(degree: u32) -> String {
-    format!("kzg_general_params_{}", degree)
+#[cfg(feature = "nowasm")]
+pub(crate) fn ipa_proving_key_file_name(degree: u32, fcp: FixedCParams) -> String {
+    format!("PoX_ipa_proving_key_{}_{}", degree, circuit_params_str(fcp))
 }
 
 // fn verifier_params_file_name(degree: u32) -> String {
 //     format!("kzg_verifier_params_{}", degree)
 // }
 
-fn circuit_verifying_key_file_name(degree: u32, fcp: FixedCParams) -> String {
+pub(crate) fn circuit_verifying_key_file_name(degree: u32, fcp: FixedCParams) -> String {
     format!("PoX_verifying_key_{}_{}", degree, circuit_params_str(fcp))
 }
 
-fn circuit_proving_key_file_name(degree: u32, fcp: FixedCParams) -> String {
+#[cfg(feature = "nowasm")]
+pub(crate) fn circuit_proving_key_file_name(degree: u32, fcp: FixedCParams) -> String {
     format!("PoX_proving_key_{}_{}", degree, circuit_params_str(fcp),)
 }
 
-fn load_general_params(srs_path: PathBuf, degree: u32) -> ParamsKZG<Bn256> {
+pub(crate) fn light_verifier_key_file_name(degree: u32, fcp: FixedCParams) -> String {
+    format!(
+        "PoX_light_verifier_key_{}_{}",
+        degree,
+        circuit_params_str(fcp)
+    )
+}
+
+/// Reads the params of `degree`, which `ceremony::provision` downloads. Missing params are
+/// trimmed from the params of a larger degree in the srs dir if there are some. Params are never
+/// generated, since anyone who knows their secret can forge proofs.
+pub fn load_general_params(srs_path: PathBuf, degree: u32) -> Result<ParamsKZG<Bn256>, Error> {
+    load_general_params_with(srs_path, degree, ArtifactChecks::default())
+}
+
+pub fn load_general_params_with(
+    srs_path: PathBuf,
+    degree: u32,
+    checks: ArtifactChecks,
+) -> Result<ParamsKZG<Bn256>, Error> {
     read_or_gen(
-        "general params",
+        Artifact {
+            label: "general params",
+            circuit: None,
+            degree,
+            checks,
+        },
         srs_path.join(general_params_file_name(degree)),
-        |mut file| Ok(ParamsKZG::<Bn256>::read_custom(&mut file, SERDE_FORMAT)?),
         |mut file| {
-            let rng = ChaChaRng::seed_from_u64(2);
-            let general_params = ParamsKZG::<Bn256>::setup(degree, rng);
+            Ok(ParamsKZG::<Bn256>::read_custom(
+                &mut file,
+                checks.serde_format(),
+            )?)
+        },
+        |mut file| {
+            let general_params = downsize_cached(&srs_path, degree, checks)?.ok_or(
+                Error::SrsMissing(format!(
+                    "{} not found, download the ceremony params with `ceremony::provision` or the setup command",
+                    general_params_file_name(degree)
+                )),
+            )?;
             general_params.write_custom(&mut file, SERDE_FORMAT)?;
             Ok(general_params)
         },
     )
-    .expect("load_general_params should not fail")
+}
+
+/// Params of `degree` trimmed from the cached params of the smallest larger degree, which are
+/// powers of the same secret, so that they come from the same setup as the params already used.
+fn downsize_cached(
+    srs_path: &Path,
+    degree: u32,
+    checks: ArtifactChecks,
+) -> Result<Option<ParamsKZG<Bn256>>, Error> {
+    let Some(cached_degree) = larger_cached_degree(srs_path, degree) else {
+        return Ok(None);
+    };
+    let mut general_params =
+        load_general_params_with(srs_path.to_path_buf(), cached_degree, checks)?;
+    info!("Downsizing the general params of degree {cached_degree} to degree {degree}");
+    general_params.downsize(degree);
+    Ok(Some(general_params))
+}
+
+/// Smallest degree above `degree` whose params are in the srs dir, the cheapest to read.
+fn larger_cached_degree(srs_path: &Path, degree: u32) -> Option<u32> {
+    read_dir(srs_path)
+        .ok()?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter_map(|name| {
+            name.strip_prefix(GENERAL_PARAMS_PREFIX)?
+                .parse::<u32>()
+                .ok()
+        })
+        .filter(|cached_degree| *cached_degree > degree)
+        .min()
 }
 
 // fn load_verifier_params(
@@ -143,51 +492,137 @@ fn load_general_params(srs_path: PathBuf, degree: u32) -> ParamsKZG<Bn256> {
 //     .expect("load_verifier_params should not fail")
 // }
 
+/// A cached vk whose file has an entry in the circuit lock, `locked`, is checked against the
+/// digest the lock recorded for the circuit config by `SRS::load_with`. A vk without one is of an
+/// unknown circuit, it is compared once with the vk derived from the circuit before it is locked.
+#[cfg(feature = "nowasm")]
 fn load_circuit_verifying_key(
     srs_path: PathBuf,
     degree: u32,
     circuit: &SuperCircuit<Fr>,
     general_params: &ParamsKZG<Bn256>,
-) -> VerifyingKey<G1Affine> {
+    checks: ArtifactChecks,
+    locked: bool,
+) -> Result<VerifyingKey<G1Affine>, Error> {
+    // the reader runs under the shared and again under the exclusive lock
+    let mut circuit_digest = None;
     read_or_gen(
-        "circuit verifying key",
+        Artifact {
+            label: "circuit verifying key",
+            circuit: Some(SUPER_CIRCUIT_NAME),
+            degree,
+            checks,
+        },
         srs_path.join(circuit_verifying_key_file_name(
             degree,
             circuit.circuits_params,
         )),
         |file| {
-            Ok(VerifyingKey::<G1Affine>::read::<File, SuperCircuit<Fr>>(
+            let cvk = VerifyingKey::<G1Affine>::read::<File, SuperCircuit<Fr>>(
                 file,
-                SERDE_FORMAT,
+                checks.serde_format(),
                 circuit.params(),
-            )?)
+            )?;
+            if locked {
+                return Ok(cvk);
+            }
+            // a cached vk and pk pair of another circuit agree with each other, only the vk of
+            // the current circuit tells that they are stale. A stale vk is regenerated, and the
+            // pk is then found to be of another vk by `SRS::load_with`
+            let expected = match circuit_digest {
+                Some(digest) => digest,
+                None => *circuit_digest.insert(vk_digest(&keygen_vk(general_params, circuit)?)),
+            };
+            if vk_digest(&cvk) != expected {
+                return Err(Error::VkMismatch(format!(
+                    "cached verifying key has digest {:?} but the circuit's is {expected:?}",
+                    vk_digest(&cvk),
+                )));
+            }
+            Ok(cvk)
         },
         |mut file| {
-            let cvk = keygen_vk(&general_params.clone(), circuit)?;
+            let cvk = keygen_vk(general_params, circuit)?;
             cvk.write(&mut file, SERDE_FORMAT)?;
             Ok(cvk)
         },
     )
-    .expect("load_circuit_verifying_key should not fail")
 }
 
-fn load_circuit_proving_key(
+/// The light verifier key is derived from the vk, one which was derived from another vk is stale and
+/// is regenerated.
+#[cfg(feature = "nowasm")]
+fn load_light_verifier_key(
     srs_path: PathBuf,
     degree: u32,
     circuit: &SuperCircuit<Fr>,
     general_params: &ParamsKZG<Bn256>,
     circuit_verifying_key: &VerifyingKey<G1Affine>,
-) -> ProvingKey<G1Affine> {
+) -> Result<LightVerifierKey, Error> {
+    let digest = vk_digest(circuit_verifying_key);
     read_or_gen(
-        "circuit proving key",
-        srs_path.join(circuit_proving_key_file_name(
+        Artifact {
+            label: "light verifier key",
+            circuit: Some(SUPER_CIRCUIT_NAME),
+            degree,
+            checks: ArtifactChecks::Checked,
+        },
+        srs_path.join(light_verifier_key_file_name(
             degree,
             circuit.circuits_params,
         )),
+        |file| {
+            let key: LightVerifierKey = serde_json::from_reader(file)?;
+            if key.vk_digest != digest {
+                return Err(Error::SrsInvalid(
+                    "light verifier key of another verifying key".to_string(),
+                ));
+            }
+            Ok(key)
+        },
+        |file| {
+            let num_instance = circuit.instances().iter().map(Vec::len).collect();
+            let key = LightVerifierKey::from(general_params, circuit_verifying_key, num_instance);
+            serde_json::to_writer(file, &key)?;
+            Ok(key)
+        },
+    )
+}
+
+#[cfg(feature = "nowasm")]
+fn load_circuit_proving_key(
+    srs_path: PathBuf,
+    degree: u32,
+    circuit: &SuperCircuit<Fr>,
+    general_params: &ParamsKZG<Bn256>,
+    circuit_verifying_key: &VerifyingKey<G1Affine>,
+    persist: bool,
+    checks: ArtifactChecks,
+) -> Result<ProvingKey<G1Affine>, Error> {
+    let path = srs_path.join(circuit_proving_key_file_name(
+        degree,
+        circuit.circuits_params,
+    ));
+    if !persist && !path.exists() {
+        info!("Generating circuit proving key in memory...");
+        return Ok(keygen_pk(
+            general_params,
+            circuit_verifying_key.clone(),
+            circuit,
+        )?);
+    }
+    read_or_gen(
+        Artifact {
+            label: "circuit proving key",
+            circuit: Some(SUPER_CIRCUIT_NAME),
+            degree,
+            checks,
+        },
+        path,
         |file| {
             Ok(ProvingKey::<G1Affine>::read::<File, SuperCircuit<Fr>>(
                 file,
-                SERDE_FORMAT,
+                checks.serde_format(),
                 circuit.params(),
             )?)
         },
@@ -197,48 +632,178 @@ fn load_circuit_proving_key(
             Ok(cpk)
         },
     )
-    .expect("load_circuit_proving_key should not fail")
 }
 
-async fn read<T, F>(srs_path: PathBuf, file_name: String, mut read: F) -> Result<T, Error>
+/// Dir of the srs dir the files of the IPFS setup are downloaded to, apart from the ceremony params.
+#[cfg(feature = "nowasm")]
+pub const IPFS_SETUP_DIR: &str = "ipfs_setup";
+
+/// IPFS hashes of the params and verifying key of the setup the first proofs were published with,
+/// before the ceremony params were used. Its params were generated from a fixed seed.
+#[cfg(feature = "nowasm")]
+const IPFS_SETUP: [(&str, &str); 2] = [
+    (
+        "kzg_general_params_19",
+        "QmeJngu5KuP4NjCimnkZjoGHt5xUY2eSmoADiZTf6WUwHG",
+    ),
+    (
+        "PoX_verifying_key_19_40000_1_256_40000_40000_10000_20000_50000",
+        "QmWGqxjCWrReL3WQy86g56dJ1hKY9miB91rnjLzHeeGivo",
+    ),
+];
+
+/// Downloads the params and verifying key of the IPFS setup, which proofs published before the
+/// ceremony params were used are verified with, and returns the dir to verify them from. Fails
+/// unless the proof is of the degree and circuit params of that setup.
+#[cfg(feature = "nowasm")]
+pub async fn provision_ipfs_setup(
+    srs_path: &Path,
+    degree: u32,
+    fcp: FixedCParams,
+) -> Result<PathBuf, Error> {
+    let vk_file_name = circuit_verifying_key_file_name(degree, fcp);
+    if !IPFS_SETUP
+        .iter()
+        .any(|(file_name, _)| *file_name == vk_file_name)
+    {
+        return Err(Error::InvalidArgument(format!(
+            "the IPFS setup only has {}, not {vk_file_name}",
+            IPFS_SETUP[1].0
+        )));
+    }
+    let dir = srs_path.join(IPFS_SETUP_DIR);
+    std::fs::create_dir_all(&dir)?;
+    for (file_name, hash) in IPFS_SETUP {
+        let path = dir.join(file_name);
+        if !path.exists() {
+            info!("Downloading {file_name} from IPFS");
+            ipfs::download_file(hash.to_string(), path.to_string_lossy().to_string()).await?;
+        }
+    }
+    Ok(dir)
+}
+
+pub(crate) async fn read<T, F>(
+    artifact: Artifact<'_>,
+    srs_path: PathBuf,
+    file_name: String,
+    mut read: F,
+) -> Result<T, Error>
 where
     F: FnMut(&mut File) -> Result<T, Error>,
 {
-    let path = srs_path.join(file_name.clone());
+    let path = srs_path.join(file_name);
     if !path.exists() {
-        if let Some(ipfs_hash) = get_ipfs_hash(file_name.clone()) {
-            println!("Downloading {file_name} from IPFS");
-            ipfs::download_file(ipfs_hash, path.to_string_lossy().to_string()).await
-        }
+        return Err(Error::SrsMissing(format!(
+            "{} not found, please generate it using the prove command or provide --srs",
+            path.display()
+        )));
     }
+    // the manifest is written after the file, both are only complete outside an exclusive lock,
+    // which a read-only srs dir never has
+    let _lock = lock_artifact(&path, false).ok();
+    artifact.check(&path)?;
     let mut file = File::open(path)?;
     read(&mut file)
 }
 
-fn read_or_gen<T, F1, F2>(label: &str, path: PathBuf, mut read: F1, mut gen: F2) -> Result<T, Error>
+/// Reads the file at `path` once it is checked against its manifest, otherwise generates it and
+/// writes its manifest. A file which fails to read is regenerated. Provers sharing the srs dir
+/// read a file together, but only one of them generates a missing file while the others wait for
+/// it and then read it.
+pub(crate) fn read_or_gen<T, F1, F2>(
+    artifact: Artifact<'_>,
+    path: PathBuf,
+    mut read: F1,
+    mut gen: F2,
+) -> Result<T, Error>
 where
     F1: FnMut(&mut File) -> Result<T, Error>,
     F2: FnMut(&mut File) -> Result<T, Error>,
 {
+    let label = artifact.label;
+    debug!("Using {label} at {}", path.display());
+    {
+        let _lock = lock_artifact(&path, false)?;
+        if let Ok(mut file) = File::open(&path) {
+            artifact.check(&path)?;
+            info!("Reading {label}...");
+            if let Ok(result) = read(&mut file) {
+                return Ok(result);
+            }
+        }
+    }
+
+    // the file is missing or unreadable, it may have been generated while the lock was awaited
+    let _lock = lock_artifact(&path, true)?;
     let file = File::open(path.clone());
     if let Ok(mut file) = file {
-        println!("Reading {label}...");
+        artifact.check(&path)?;
+        info!("Reading {label}...");
         match read(&mut file) {
             Ok(result) => {
                 return Ok(result);
             }
             Err(e) => {
                 // Remove file and freshly create it in next step
-                println!("Failed {e:?}");
-                remove_file(path.clone())
-                    .unwrap_or_else(|_| panic!("Failed to remove file: {}", path.display()));
+                warn!("Failed to read {label}, regenerating it: {e:?}");
+                remove_file(path.clone())?;
             }
         }
     }
 
-    println!("Generating {label}...");
-    let result = gen(&mut File::create(path)?)?;
-    Ok(result)
+    info!("Generating {label}...");
+    // write to a temp file first so that an interrupted or failed generation never leaves a
+    // truncated file at the cached path
+    let partial_path = partial_path(&path);
+    #[cfg(feature = "nowasm")]
+    shutdown::register_temp_file(&partial_path);
+    let result = File::create(&partial_path)
+        .map_err(Error::from)
+        .and_then(|mut file| gen(&mut file))
+        .and_then(|result| {
+            rename(&partial_path, &path)?;
+            artifact.record(&path)?;
+            Ok(result)
+        });
+    if result.is_err() {
+        remove_file(&partial_path).ok();
+    }
+    #[cfg(feature = "nowasm")]
+    shutdown::unregister_temp_file(&partial_path);
+    result
+}
+
+/// Advisory lock of the artifact at `path`, shared by readers and exclusive to the process which
+/// writes it, held until the returned file is dropped. The lock is on `<file>.lock` rather than
+/// on the artifact, which is replaced by a rename once it is written.
+pub(crate) fn lock_artifact(path: &Path, exclusive: bool) -> Result<File, Error> {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".lock");
+    let lock = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .open(path.with_file_name(file_name))?;
+    let acquired = if exclusive {
+        lock.try_lock_exclusive()
+    } else {
+        lock.try_lock_shared()
+    };
+    if acquired.is_err() {
+        info!("Waiting for another process using {}...", path.display());
+        if exclusive {
+            lock.lock_exclusive()?;
+        } else {
+            lock.lock_shared()?;
+        }
+    }
+    Ok(lock)
+}
+
+pub(crate) fn partial_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".partial");
+    path.with_file_name(file_name)
 }
 
 fn circuit_params_str(fcp: FixedCParams) -> String {
@@ -254,16 +819,3 @@ fn circuit_params_str(fcp: FixedCParams) -> String {
         fcp.max_keccak_rows,
     )
 }
-
-fn get_ipfs_hash(file_name: String) -> Option<String> {
-    // TODO improve this code
-    if file_name == *"kzg_general_params_19" {
-        Some("QmeJngu5KuP4NjCimnkZjoGHt5xUY2eSmoADiZTf6WUwHG".to_string())
-    } else if file_name
-        == *"PoX_verifying_key_19_40000_1_256_40000_40000_10000_20000_50000".to_string()
-    {
-        Some("QmWGqxjCWrReL3WQy86g56dJ1hKY9miB91rnjLzHeeGivo".to_string())
-    } else {
-        None
-    }
-}
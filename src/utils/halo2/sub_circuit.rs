@@ -0,0 +1,156 @@
+//! Dev mode proving a single sub-circuit of the SuperCircuit, built from the same witness block,
+//! for iterating on an exploit whose evm steps or state accesses are the part that changes. The
+//! keys of the sub-circuit are generated in memory at the degree it needs and its proof is
+//! verified right away. The proof covers that sub-circuit only, no verifier of the cli accepts it.
+
+use super::{helpers::FrWrapper, real_verifier::verify_with_transcript, srs::load_general_params};
+use crate::{error::Error, witness::circuit::RESERVED_ROWS};
+use halo2_proofs::{
+    halo2curves::bn256::{Bn256, Fr, G1Affine},
+    plonk::{create_proof, keygen_pk, keygen_vk, Circuit},
+    poly::kzg::{
+        commitment::KZGCommitmentScheme,
+        multiopen::{ProverSHPLONK, VerifierSHPLONK},
+        strategy::SingleStrategy,
+    },
+    transcript::{
+        Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+    },
+};
+use rand_core::OsRng;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path, str::FromStr, time::Instant};
+use tracing::info;
+use zkevm_circuits::{
+    copy_circuit::CopyCircuit,
+    evm_circuit::EvmCircuit,
+    exp_circuit::ExpCircuit,
+    keccak_circuit::KeccakCircuit,
+    state_circuit::StateCircuit,
+    tx_circuit::TxCircuit,
+    util::{log2_ceil, SubCircuit},
+    witness::Block,
+};
+
+/// Circuit a prover proves, the SuperCircuit unless a sub-circuit is selected for development.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CircuitSelection {
+    #[default]
+    Super,
+    Evm,
+    State,
+    Copy,
+    Keccak,
+    Exp,
+    Tx,
+}
+
+impl FromStr for CircuitSelection {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "super" => Ok(CircuitSelection::Super),
+            "evm" => Ok(CircuitSelection::Evm),
+            "state" => Ok(CircuitSelection::State),
+            "copy" => Ok(CircuitSelection::Copy),
+            "keccak" => Ok(CircuitSelection::Keccak),
+            "exp" => Ok(CircuitSelection::Exp),
+            "tx" => Ok(CircuitSelection::Tx),
+            _ => Err(Error::InvalidArgument(format!(
+                "unknown circuit {s}, expected super, evm, state, copy, keccak, exp or tx"
+            ))),
+        }
+    }
+}
+
+/// Proof of a sub-circuit with the time its keys and proof took.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SubCircuitProof {
+    pub circuit: CircuitSelection,
+    pub degree: u32,
+    pub data: Vec<u8>,
+    pub instances: Vec<Vec<FrWrapper>>,
+    pub keygen_seconds: f64,
+    pub proving_seconds: f64,
+}
+
+impl SubCircuitProof {
+    pub fn write_to_file(&self, path: &Path) -> Result<(), Error> {
+        Ok(fs::write(path, serde_json::to_string_pretty(self)?)?)
+    }
+}
+
+/// Proves the sub-circuit `selection` of `block` with the general params of the srs dir at the
+/// degree the sub-circuit needs, see `RealProver::prove_sub_circuit`.
+pub(crate) fn prove(
+    selection: CircuitSelection,
+    block: &Block<Fr>,
+    srs_path: &Path,
+) -> Result<SubCircuitProof, Error> {
+    match selection {
+        CircuitSelection::Super => Err(Error::InvalidArgument(
+            "the SuperCircuit is proven by RealProver::prove".to_string(),
+        )),
+        CircuitSelection::Evm => prove_circuit::<EvmCircuit<Fr>>(selection, block, srs_path),
+        CircuitSelection::State => prove_circuit::<StateCircuit<Fr>>(selection, block, srs_path),
+        CircuitSelection::Copy => prove_circuit::<CopyCircuit<Fr>>(selection, block, srs_path),
+        CircuitSelection::Keccak => prove_circuit::<KeccakCircuit<Fr>>(selection, block, srs_path),
+        CircuitSelection::Exp => prove_circuit::<ExpCircuit<Fr>>(selection, block, srs_path),
+        CircuitSelection::Tx => prove_circuit::<TxCircuit<Fr>>(selection, block, srs_path),
+    }
+}
+
+fn prove_circuit<C: SubCircuit<Fr> + Circuit<Fr>>(
+    selection: CircuitSelection,
+    block: &Block<Fr>,
+    srs_path: &Path,
+) -> Result<SubCircuitProof, Error> {
+    let circuit = C::new_from_block(block);
+    let (_, rows) = C::min_num_rows_block(block);
+    let degree = log2_ceil(RESERVED_ROWS + rows);
+    let general_params = load_general_params(srs_path.to_path_buf(), degree)?;
+
+    info!("Generating the keys of the {selection:?} circuit at degree {degree}");
+    let start = Instant::now();
+    let verifying_key = keygen_vk(&general_params, &circuit)?;
+    let proving_key = keygen_pk(&general_params, verifying_key, &circuit)?;
+    let keygen_seconds = start.elapsed().as_secs_f64();
+
+    info!("Proving the {selection:?} circuit");
+    let instances = circuit.instance();
+    let instances_refs = instances.iter().map(|v| &v[..]).collect::<Vec<&[Fr]>>();
+    let start = Instant::now();
+    let mut transcript = Blake2bWrite::<_, G1Affine, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<Bn256>, ProverSHPLONK<'_, Bn256>, _, _, _, _>(
+        &general_params,
+        &proving_key,
+        &[circuit],
+        &[&instances_refs],
+        OsRng,
+        &mut transcript,
+    )?;
+    let data = transcript.finalize();
+    let proving_seconds = start.elapsed().as_secs_f64();
+
+    verify_with_transcript::<VerifierSHPLONK<'_, Bn256>, _, _, _>(
+        general_params.verifier_params(),
+        proving_key.get_vk(),
+        SingleStrategy::new(&general_params),
+        &instances_refs,
+        &mut Blake2bRead::<_, G1Affine, Challenge255<_>>::init(&data[..]),
+    )?;
+
+    Ok(SubCircuitProof {
+        circuit: selection,
+        degree,
+        data,
+        instances: instances
+            .into_iter()
+            .map(|column| column.into_iter().map(FrWrapper).collect())
+            .collect(),
+        keygen_seconds,
+        proving_seconds,
+    })
+}
@@ -0,0 +1,62 @@
+use super::proof::Proof;
+use crate::error::Error;
+use eth_types::U256;
+use zkevm_circuits::instance::PublicData;
+
+/// Checks that the public data of the proofs claims consecutive blocks of one chain exploiting the
+/// same challenge, each block starting from the state root the previous one ended with. The state
+/// roots are claimed by the prover and not constrained by the circuit, which has no MPT circuit,
+/// so this is a consistency check of the claims and does not prove that the blocks ran on chained
+/// state. The public data has to be verified against the instances of every proof separately.
+pub fn check_continuity(proofs: &[Proof]) -> Result<(), Error> {
+    let public_data = proofs
+        .iter()
+        .map(|proof| &proof.public_data)
+        .collect::<Vec<_>>();
+    check_sequence(&public_data, "proof")
+}
+
+/// Same checks as `check_continuity` on the public data of the witnesses of consecutive blocks,
+/// before they are proven.
+pub fn check_witness_continuity(public_data: &[&PublicData]) -> Result<(), Error> {
+    check_sequence(public_data, "witness")
+}
+
+fn check_sequence(public_data: &[&PublicData], item: &str) -> Result<(), Error> {
+    for (index, pair) in public_data.windows(2).enumerate() {
+        let (prev, next) = (pair[0], pair[1]);
+        if next.chain_id != prev.chain_id {
+            return Err(Error::InvalidArgument(format!(
+                "{item} {} is for chain {} but {item} {index} is for chain {}",
+                index + 1,
+                next.chain_id,
+                prev.chain_id
+            )));
+        }
+        if next.pox_challenge_codehash != prev.pox_challenge_codehash {
+            return Err(Error::InvalidArgument(format!(
+                "{item} {} exploits challenge {:?} but {item} {index} exploits {:?}",
+                index + 1,
+                next.pox_challenge_codehash,
+                prev.pox_challenge_codehash
+            )));
+        }
+        if next.block_constants.number != prev.block_constants.number + U256::one() {
+            return Err(Error::InvalidArgument(format!(
+                "{item} {} is for block {} which does not follow block {} of {item} {index}",
+                index + 1,
+                next.block_constants.number,
+                prev.block_constants.number
+            )));
+        }
+        if next.prev_state_root != prev.state_root {
+            return Err(Error::InvalidArgument(format!(
+                "{item} {} starts from state root {:?} but {item} {index} ends with {:?}",
+                index + 1,
+                next.prev_state_root,
+                prev.state_root
+            )));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,147 @@
+use super::srs::SERDE_FORMAT;
+use crate::error::Error;
+use eth_types::{keccak256, H256};
+use halo2_proofs::{halo2curves::bn256::G1Affine, plonk::VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+const REGISTRY_FILE_NAME: &str = "approved_verifying_keys.json";
+
+/// Digests of the verifying keys whose proofs are accepted. Keeping the keys of previous circuit
+/// versions approved lets claims proven before an upgrade still verify. The set is committed to
+/// by a Merkle root so that a contract can store the root only and check a membership proof.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct VkRegistry {
+    pub approved: BTreeSet<H256>,
+}
+
+impl VkRegistry {
+    pub fn path(srs_path: &Path) -> PathBuf {
+        srs_path.join(REGISTRY_FILE_NAME)
+    }
+
+    /// Reads the registry in `srs_path`, an empty registry is returned if there is none yet.
+    pub fn load(srs_path: &Path) -> Result<Self, Error> {
+        let path = Self::path(srs_path);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    pub fn save(&self, srs_path: &Path) -> Result<(), Error> {
+        let mut file = File::create(Self::path(srs_path))?;
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    /// Returns false if the digest was already approved.
+    pub fn approve(&mut self, digest: H256) -> bool {
+        self.approved.insert(digest)
+    }
+
+    /// Returns false if the digest was not approved.
+    pub fn revoke(&mut self, digest: H256) -> bool {
+        self.approved.remove(&digest)
+    }
+
+    pub fn contains(&self, digest: &H256) -> bool {
+        self.approved.contains(digest)
+    }
+
+    /// Root of the Merkle tree over the approved digests, zero for an empty registry.
+    pub fn root(&self) -> H256 {
+        let mut layer = self.approved.iter().copied().collect::<Vec<_>>();
+        while layer.len() > 1 {
+            layer = next_layer(&layer);
+        }
+        layer.first().copied().unwrap_or_default()
+    }
+
+    /// Sibling hashes from the leaf of `digest` up to the root, or None if it is not approved.
+    pub fn membership_proof(&self, digest: &H256) -> Option<Vec<H256>> {
+        let mut layer = self.approved.iter().copied().collect::<Vec<_>>();
+        let mut index = layer.iter().position(|leaf| leaf == digest)?;
+        let mut proof = vec![];
+        while layer.len() > 1 {
+            if let Some(sibling) = layer.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            layer = next_layer(&layer);
+            index /= 2;
+        }
+        Some(proof)
+    }
+}
+
+/// Checks a membership proof the same way OpenZeppelin's `MerkleProof.verify` does, pairs are
+/// hashed in sorted order so that the proof does not need the position of the leaf.
+pub fn verify_membership(root: H256, digest: H256, proof: &[H256]) -> bool {
+    proof
+        .iter()
+        .fold(digest, |node, sibling| hash_pair(node, *sibling))
+        == root
+}
+
+/// Digest of a verifying key, the keccak of its serialization which is also the digest of its
+/// cached file.
+pub fn vk_digest(vk: &VerifyingKey<G1Affine>) -> H256 {
+    H256::from(keccak256(vk.to_bytes(SERDE_FORMAT)))
+}
+
+/// Parses a verifying key given either as its 0x prefixed digest or as the path of its file.
+pub fn parse_vk_digest(input: &str) -> Result<H256, Error> {
+    if input.starts_with("0x") {
+        return H256::from_str(input)
+            .map_err(|_| Error::InvalidArgument(format!("invalid verifying key digest {input}")));
+    }
+    Ok(H256::from(keccak256(fs::read(input)?)))
+}
+
+/// Hashes the nodes of a layer in pairs, an odd node out is carried up as is.
+fn next_layer(layer: &[H256]) -> Vec<H256> {
+    layer
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => hash_pair(*left, *right),
+            _ => pair[0],
+        })
+        .collect()
+}
+
+fn hash_pair(a: H256, b: H256) -> H256 {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    H256::from(keccak256([first.as_bytes(), second.as_bytes()].concat()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{verify_membership, VkRegistry};
+    use eth_types::H256;
+
+    #[test]
+    fn test_membership_proofs() {
+        let mut registry = VkRegistry::default();
+        assert_eq!(registry.root(), H256::zero());
+        for byte in 1..=5u8 {
+            registry.approve(H256::repeat_byte(byte));
+        }
+        let root = registry.root();
+        for byte in 1..=5u8 {
+            let digest = H256::repeat_byte(byte);
+            let proof = registry.membership_proof(&digest).unwrap();
+            assert!(verify_membership(root, digest, &proof));
+            assert!(!verify_membership(root, H256::repeat_byte(9), &proof));
+        }
+        assert!(registry.membership_proof(&H256::repeat_byte(9)).is_none());
+
+        registry.revoke(H256::repeat_byte(3));
+        assert_ne!(registry.root(), root);
+    }
+}
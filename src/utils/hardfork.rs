@@ -0,0 +1,175 @@
+use super::{anvil::types::zkevm_types::GethExecTrace, watchdog::watch};
+use crate::error::Error;
+use ethers::{
+    providers::{Http, Middleware, Provider},
+    types::{Block, BlockId, BlockNumber},
+};
+use serde::{Deserialize, Serialize};
+use std::{str::FromStr, time::Duration};
+
+/// Hardfork whose rules the zkevm-circuits build has compiled in, bus_mapping and the circuits
+/// charge gas by them and cannot be configured for another one. The fork constrains PUSH0 but
+/// none of the opcodes introduced by Cancun.
+pub const CIRCUIT_HARDFORK: Hardfork = Hardfork::Shanghai;
+
+/// Opcodes introduced after London, with the hardfork which introduced them.
+const OPCODES: [(u8, &str, Hardfork); 4] = [
+    (0x5c, "TLOAD", Hardfork::Cancun),
+    (0x5d, "TSTORE", Hardfork::Cancun),
+    (0x5e, "MCOPY", Hardfork::Cancun),
+    (0x5f, "PUSH0", Hardfork::Shanghai),
+];
+
+/// Hardforks which changed the opcodes the circuits have to constrain. Earlier ones only changed
+/// gas costs, which the circuits take from the trace.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Hardfork {
+    London,
+    Shanghai,
+    Cancun,
+}
+
+impl Default for Hardfork {
+    fn default() -> Self {
+        CIRCUIT_HARDFORK
+    }
+}
+
+impl Hardfork {
+    /// Hardfork a header belongs to, told apart by the header fields each hardfork added.
+    pub fn of_block<T>(header: &Block<T>) -> Self {
+        if header.parent_beacon_block_root.is_some() {
+            Hardfork::Cancun
+        } else if header.withdrawals_root.is_some() {
+            Hardfork::Shanghai
+        } else {
+            Hardfork::London
+        }
+    }
+
+    /// Hardfork of the block `rpc` is forked from, the latest block if there is no block number.
+    pub async fn of_fork_block(
+        rpc: &str,
+        block: Option<usize>,
+        timeout: Duration,
+    ) -> Result<Self, Error> {
+        let provider = Provider::<Http>::try_from(rpc)
+            .map_err(|_| Error::InvalidArgument(format!("invalid rpc url {rpc}")))?;
+        let id = BlockId::Number(block.map_or(BlockNumber::Latest, |number| {
+            BlockNumber::Number(number.into())
+        }));
+        let header = watch("eth_getBlockByNumber", id, timeout, provider.get_block(id))
+            .await?
+            .ok_or(Error::InvalidArgument(format!(
+                "fork block {id:?} not found"
+            )))?;
+        Ok(Self::of_block(&header))
+    }
+
+    /// Fails unless anvil, bus_mapping and the circuits can all apply the rules of the hardfork,
+    /// and unless they are the rules of the forked chain, so that the gas charged in the proof
+    /// is the gas the chain being claimed about charges.
+    pub fn check_supported(&self, chain: Option<Hardfork>) -> Result<(), Error> {
+        if *self > CIRCUIT_HARDFORK {
+            return Err(Error::InvalidArgument(format!(
+                "the circuits do not support {self:?} yet, only {CIRCUIT_HARDFORK:?} blocks can be proven, fork a {CIRCUIT_HARDFORK:?} block with --block"
+            )));
+        }
+        if *self < CIRCUIT_HARDFORK {
+            return Err(Error::InvalidArgument(format!(
+                "bus_mapping and the circuits charge gas by the rules of {CIRCUIT_HARDFORK:?}, an execution under {self:?} would not satisfy them, only {CIRCUIT_HARDFORK:?} blocks can be proven"
+            )));
+        }
+        match chain {
+            Some(chain) if chain != *self => Err(Error::InvalidArgument(format!(
+                "the fork block is a {chain:?} block, proving it under {self:?} would not match the gas rules of the chain"
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Hardfork anvil executes the exploit with. The anvil build predates Cancun, so Cancun is
+    /// its latest hardfork.
+    pub fn anvil_hardfork(&self) -> anvil::Hardfork {
+        match self {
+            Hardfork::London => anvil::Hardfork::London,
+            Hardfork::Shanghai => anvil::Hardfork::Shanghai,
+            Hardfork::Cancun => anvil::Hardfork::Latest,
+        }
+    }
+
+    /// Scans the traces before they reach the circuit input builder, which would otherwise fail
+    /// deep in the witness generation on an opcode this hardfork does not enable.
+    pub fn check_traces(&self, traces: &[GethExecTrace]) -> Result<(), Error> {
+        for (index, trace) in traces.iter().enumerate() {
+            for step in &trace.struct_logs {
+                let op = step.op.as_u8();
+                if let Some((_, name, hardfork)) = OPCODES
+                    .iter()
+                    .find(|(opcode, _, hardfork)| *opcode == op && hardfork > self)
+                {
+                    return Err(Error::UnsupportedOpcode(format!(
+                        "{name} at pc {} of tx {index} was introduced in {hardfork:?} but the circuits are configured for {self:?}",
+                        step.pc
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Hardfork {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "london" => Ok(Hardfork::London),
+            "shanghai" => Ok(Hardfork::Shanghai),
+            "cancun" => Ok(Hardfork::Cancun),
+            _ => Err(Error::InvalidArgument(format!(
+                "unknown hardfork {s}, expected london, shanghai or cancun"
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Hardfork;
+    use crate::{error::ErrorCode, utils::anvil::types::zkevm_types::*};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_check_traces() {
+        let trace = GethExecTrace {
+            gas: 0,
+            failed: false,
+            return_value: String::new(),
+            struct_logs: vec![GethExecStep {
+                pc: 0,
+                op: OpcodeId::from(0x5f),
+                gas: 100,
+                gas_cost: 2,
+                refund: 0,
+                depth: 1,
+                error: None,
+                stack: Stack(vec![]),
+                memory: Memory(vec![]),
+                storage: Storage(HashMap::new()),
+            }],
+        };
+        assert!(Hardfork::Shanghai.check_traces(&[trace.clone()]).is_ok());
+        assert_eq!(
+            Hardfork::London.check_traces(&[trace]).unwrap_err().code(),
+            ErrorCode::UnsupportedOpcode
+        );
+        assert!(Hardfork::Shanghai.check_supported(None).is_ok());
+        assert!(Hardfork::Cancun.check_supported(None).is_err());
+        assert!(Hardfork::London.check_supported(None).is_err());
+        assert!(Hardfork::Shanghai
+            .check_supported(Some(Hardfork::Cancun))
+            .is_err());
+    }
+}
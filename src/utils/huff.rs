@@ -1,20 +1,29 @@
+use crate::error::Error;
 use eth_types::Bytes;
 use std::{process, str::FromStr};
 
-pub fn compile_huff(source_path_string: String) -> Bytes {
+pub fn compile_huff(source_path_string: String) -> Result<Bytes, Error> {
+    run_huffc(source_path_string, "-r")
+}
+
+/// Creation bytecode of the program, which runs its `CONSTRUCTOR` macro.
+pub fn compile_huff_creation(source_path_string: String) -> Result<Bytes, Error> {
+    run_huffc(source_path_string, "-b")
+}
+
+fn run_huffc(source_path_string: String, output_flag: &str) -> Result<Bytes, Error> {
     let mut cmd = process::Command::new("huffc");
     cmd.arg(source_path_string);
-    cmd.arg("-r");
+    cmd.arg(output_flag);
     cmd.args(["-e", "paris"]); // TODO put this behind a flag somehow
-    let output = cmd.output().unwrap();
+    let output = cmd.output()?;
     if !output.stderr.is_empty() {
-        println!(
+        return Err(Error::CompilationError(format!(
             "huffc error: {:?}",
-            String::from_utf8(output.stderr).unwrap()
-        );
-        process::exit(1);
+            String::from_utf8_lossy(&output.stderr)
+        )));
     }
 
-    let stdout = String::from_utf8(output.stdout).unwrap();
-    Bytes::from_str(stdout.as_str()).unwrap()
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Bytes::from_str(&stdout).map_err(|_| Error::InternalError("huffc returned invalid bytecode"))
 }
@@ -1,10 +1,18 @@
-use std::{fs::File, io::Write};
+use std::{
+    fs::{remove_file, rename, File},
+    io::Write,
+    path::PathBuf,
+};
 
-use super::halo2::proof::Proof;
+use super::{
+    halo2::{proof::Proof, srs::partial_path},
+    shutdown,
+};
 use crate::error::Error;
 use pinata_sdk::{PinByFile, PinByJson, PinataApi};
 use reqwest;
 
+// Panics only if the hardcoded api credentials are empty, which they never are.
 fn pinata() -> PinataApi {
     PinataApi::new(
         // temp api key only allows pinning json, TODO allow passing own api key
@@ -26,10 +34,9 @@ pub async fn get(hash: String) -> Result<Proof, Error> {
     let res = client
         .get(gateway.to_owned() + hash.as_str())
         .send()
-        .await
-        .unwrap();
+        .await?;
 
-    let str = res.text().await.unwrap();
+    let str = res.text().await?;
     Ok(serde_json::from_str(str.as_str())?)
 }
 
@@ -39,17 +46,25 @@ pub async fn publish_file(path: String) -> Result<String, Error> {
     Ok(hash)
 }
 
-pub async fn download_file(hash: String, path: String) {
+pub async fn download_file(hash: String, path: String) -> Result<(), Error> {
     let gateway = "https://gateway.pinata.cloud/ipfs/";
 
     let client = reqwest::Client::new();
     let res = client
         .get(gateway.to_owned() + hash.as_str())
         .send()
-        .await
-        .unwrap();
+        .await?;
 
-    let data = res.bytes().await.unwrap();
-    let mut file = File::create(path).unwrap();
-    file.write_all(&data).unwrap();
+    let data = res.bytes().await?;
+    let path = PathBuf::from(path);
+    let partial_path = partial_path(&path);
+    shutdown::register_temp_file(&partial_path);
+    let result = File::create(&partial_path)
+        .and_then(|mut file| file.write_all(&data))
+        .and_then(|_| rename(&partial_path, &path));
+    if result.is_err() {
+        remove_file(&partial_path).ok();
+    }
+    shutdown::unregister_temp_file(&partial_path);
+    Ok(result?)
 }
@@ -0,0 +1,268 @@
+//! Jobs of `serve`, one JSON file per job in `<srs_path>/jobs` next to the proofs of the finished
+//! ones, so that a restarted server proves the jobs which were queued and still answers for the
+//! ones it proved before.
+
+use crate::{
+    error::{Error, ErrorBody},
+    service::ProveRequest,
+    utils::{
+        halo2::{proof::Proof, srs::partial_path},
+        progress::ProofPhase,
+    },
+};
+use eth_types::{keccak256, H256};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+pub(crate) const JOBS_DIR: &str = "jobs";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Proving,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JobRecord {
+    pub id: u64,
+    pub status: JobStatus,
+    /// keccak256 of the JSON of the request.
+    pub inputs_hash: H256,
+    pub request: ProveRequest,
+    /// Phase a proving job is at, with the estimated share of the proof done.
+    #[serde(default)]
+    pub phase: Option<ProofPhase>,
+    #[serde(default)]
+    pub percent: u8,
+    /// Proof of a done job.
+    pub proof_path: Option<PathBuf>,
+    pub error: Option<ErrorBody>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+pub struct JobStore {
+    dir: PathBuf,
+    /// Held while a job file is read and written again, the server updates jobs from its
+    /// requests and from the prover thread.
+    lock: Mutex<()>,
+}
+
+impl JobStore {
+    pub fn open(srs_path: &Path) -> Result<Self, Error> {
+        let dir = srs_path.join(JOBS_DIR);
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            lock: Mutex::new(()),
+        })
+    }
+
+    pub fn enqueue(&self, request: ProveRequest) -> Result<JobRecord, Error> {
+        let _lock = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+        let id = self.list()?.last().map_or(1, |job| job.id + 1);
+        let now = now();
+        let job = JobRecord {
+            id,
+            status: JobStatus::Queued,
+            inputs_hash: H256::from(keccak256(serde_json::to_vec(&request)?)),
+            request,
+            phase: None,
+            percent: 0,
+            proof_path: None,
+            error: None,
+            created_at: now,
+            updated_at: now,
+        };
+        self.write(&job)?;
+        Ok(job)
+    }
+
+    pub fn get(&self, id: u64) -> Result<Option<JobRecord>, Error> {
+        let path = self.job_path(id);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_slice(&fs::read(path)?)?))
+    }
+
+    /// Every job of the store, oldest first.
+    pub fn list(&self) -> Result<Vec<JobRecord>, Error> {
+        let mut jobs = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let file_name = entry?.file_name().to_string_lossy().to_string();
+            let id = file_name
+                .strip_prefix("job_")
+                .and_then(|name| name.strip_suffix(".json"))
+                .and_then(|id| id.parse().ok());
+            if let Some(job) = id.map(|id| self.get(id)).transpose()?.flatten() {
+                jobs.push(job);
+            }
+        }
+        jobs.sort_by_key(|job| job.id);
+        Ok(jobs)
+    }
+
+    /// Cancels a queued job, a job which is proving already runs to its end.
+    pub fn cancel(&self, id: u64) -> Result<JobRecord, Error> {
+        self.update(id, |job| match job.status {
+            JobStatus::Queued => {
+                job.status = JobStatus::Cancelled;
+                Ok(())
+            }
+            status => Err(Error::InvalidArgument(format!(
+                "job {id} is {status:?} and can no longer be cancelled"
+            ))),
+        })
+    }
+
+    /// Marks a queued job as proving, false if it was cancelled in the meantime.
+    pub fn start(&self, id: u64) -> Result<bool, Error> {
+        let mut started = false;
+        self.update(id, |job| {
+            if job.status == JobStatus::Queued {
+                job.status = JobStatus::Proving;
+                started = true;
+            }
+            Ok(())
+        })?;
+        Ok(started)
+    }
+
+    pub fn set_phase(&self, id: u64, phase: ProofPhase, percent: u8) -> Result<(), Error> {
+        self.update(id, |job| {
+            job.phase = Some(phase);
+            job.percent = percent;
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    /// Records the outcome of a job, the proof is written next to the job file.
+    pub fn finish(&self, id: u64, result: Result<Proof, Error>) -> Result<JobRecord, Error> {
+        let outcome = match result {
+            Ok(proof) => {
+                let proof_path = self.dir.join(format!("proof_{id}.json"));
+                proof.write_to_file(&proof_path)?;
+                Ok(proof_path)
+            }
+            Err(error) => Err(error.to_body()),
+        };
+        self.update(id, |job| {
+            match outcome {
+                Ok(proof_path) => {
+                    job.status = JobStatus::Done;
+                    job.proof_path = Some(proof_path);
+                }
+                Err(error) => {
+                    job.status = JobStatus::Failed;
+                    job.error = Some(error);
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Ids of the jobs to prove after a restart, oldest first. Jobs which were proving when the
+    /// server stopped are queued again.
+    pub fn pending(&self) -> Result<Vec<u64>, Error> {
+        let mut pending = vec![];
+        for job in self.list()? {
+            if job.status == JobStatus::Proving {
+                self.update(job.id, |job| {
+                    job.status = JobStatus::Queued;
+                    Ok(())
+                })?;
+            }
+            if matches!(job.status, JobStatus::Queued | JobStatus::Proving) {
+                pending.push(job.id);
+            }
+        }
+        Ok(pending)
+    }
+
+    fn update(
+        &self,
+        id: u64,
+        update: impl FnOnce(&mut JobRecord) -> Result<(), Error>,
+    ) -> Result<JobRecord, Error> {
+        let _lock = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut job = self
+            .get(id)?
+            .ok_or(Error::InvalidArgument(format!("no job {id}")))?;
+        update(&mut job)?;
+        job.updated_at = now();
+        self.write(&job)?;
+        Ok(job)
+    }
+
+    fn write(&self, job: &JobRecord) -> Result<(), Error> {
+        let path = self.job_path(job.id);
+        let partial_path = partial_path(&path);
+        fs::write(&partial_path, serde_json::to_string_pretty(job)?)?;
+        Ok(fs::rename(&partial_path, &path)?)
+    }
+
+    fn job_path(&self, id: u64) -> PathBuf {
+        self.dir.join(format!("job_{id}.json"))
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JobStatus, JobStore};
+    use crate::{service::ProveRequest, utils::halo2::proof::ProofTranscript};
+
+    fn request() -> ProveRequest {
+        ProveRequest {
+            block: Some(1),
+            challenge_source: "contract Challenge {}".to_string(),
+            exploit_bytecode: vec![0x00].into(),
+            exploit_balance: 0.into(),
+            gas: None,
+            transcript: ProofTranscript::Blake2b,
+        }
+    }
+
+    #[test]
+    fn test_jobs_survive_restart() {
+        let dir = std::env::temp_dir().join(format!("pox_job_store_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let store = JobStore::open(&dir).unwrap();
+        let first = store.enqueue(request()).unwrap();
+        let second = store.enqueue(request()).unwrap();
+        let third = store.enqueue(request()).unwrap();
+        assert_eq!((first.id, second.id, third.id), (1, 2, 3));
+        assert!(store.start(first.id).unwrap());
+        store.cancel(second.id).unwrap();
+        assert!(store.cancel(first.id).is_err());
+        assert!(!store.start(second.id).unwrap());
+
+        let store = JobStore::open(&dir).unwrap();
+        assert_eq!(store.pending().unwrap(), vec![1, 3]);
+        assert_eq!(store.get(1).unwrap().unwrap().status, JobStatus::Queued);
+        assert_eq!(store.get(2).unwrap().unwrap().status, JobStatus::Cancelled);
+        assert_eq!(
+            store.get(3).unwrap().unwrap().inputs_hash,
+            first.inputs_hash
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,99 @@
+use crate::error::Error;
+use std::{fs::File, path::PathBuf, str::FromStr, sync::Arc};
+use tracing_subscriber::{
+    filter::{LevelFilter, Targets},
+    fmt::{self, format::FmtSpan},
+    prelude::*,
+};
+
+const CRATE_TARGET: &str = "proof_of_exploit";
+
+/// Format of the console output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    /// One JSON object per event with the spans it is in, for log collectors.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(Error::InvalidArgument(format!(
+                "unknown log format {s}, expected text or json"
+            ))),
+        }
+    }
+}
+
+/// Console verbosity for the number of `-v` flags passed.
+pub fn console_level(verbosity: u8) -> LevelFilter {
+    match verbosity {
+        0 => LevelFilter::INFO,
+        1 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    }
+}
+
+/// Installs the global logger. Console output is filtered by `level` and kept short in the text
+/// format, while the optional log file always receives everything this crate logs along with
+/// timestamps. The JSON output and the log file also record when the spans of the anvil calls,
+/// witness, keygen and proving close, with their duration.
+pub fn init(level: LevelFilter, format: LogFormat, log_file: Option<PathBuf>) -> Result<(), Error> {
+    let console_filter = Targets::new()
+        .with_target(CRATE_TARGET, level)
+        .with_default(LevelFilter::WARN);
+    let (text_layer, json_layer) = match format {
+        LogFormat::Text => (
+            Some(
+                fmt::layer()
+                    .without_time()
+                    .with_target(false)
+                    .with_filter(console_filter),
+            ),
+            None,
+        ),
+        LogFormat::Json => (
+            None,
+            Some(
+                fmt::layer()
+                    .json()
+                    .with_current_span(true)
+                    .with_span_list(true)
+                    .with_span_events(FmtSpan::CLOSE)
+                    .with_filter(console_filter),
+            ),
+        ),
+    };
+
+    let file_layer = match log_file {
+        Some(path) => {
+            let file = File::create(path)?;
+            Some(
+                fmt::layer()
+                    .with_ansi(false)
+                    .with_thread_ids(true)
+                    .with_span_events(FmtSpan::CLOSE)
+                    .with_writer(Arc::new(file))
+                    .with_filter(
+                        Targets::new()
+                            .with_target(CRATE_TARGET, LevelFilter::TRACE)
+                            .with_default(LevelFilter::INFO),
+                    ),
+            )
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(text_layer)
+        .with(json_layer)
+        .with(file_layer)
+        .try_init()
+        .map_err(|_| Error::InternalError("logger is already initialized"))
+}
@@ -1,9 +1,49 @@
+#[cfg(feature = "alloy")]
+pub mod alloy;
+#[cfg(feature = "nowasm")]
+pub mod ancestry;
+#[cfg(feature = "nowasm")]
 pub mod anvil;
+#[cfg(feature = "nowasm")]
+pub mod audit;
+#[cfg(feature = "nowasm")]
+pub mod bench;
+#[cfg(feature = "nowasm")]
+pub mod corpus;
+#[cfg(feature = "nowasm")]
+pub mod eip3155;
+#[cfg(feature = "nowasm")]
 pub mod etherscan;
+#[cfg(feature = "nowasm")]
+pub mod fixture;
+#[cfg(feature = "nowasm")]
+pub mod forge;
+#[cfg(feature = "nowasm")]
 pub mod geth;
 pub mod halo2;
+#[cfg(feature = "nowasm")]
+pub mod hardfork;
 pub mod helpers;
+#[cfg(feature = "nowasm")]
 pub mod huff;
+#[cfg(feature = "nowasm")]
 pub mod ipfs;
+#[cfg(feature = "nowasm")]
+pub mod job_store;
+#[cfg(feature = "nowasm")]
+pub mod logger;
+#[cfg(feature = "nowasm")]
+pub mod progress;
+#[cfg(feature = "nowasm")]
+pub mod run_manifest;
+#[cfg(feature = "nowasm")]
 pub mod scaffold;
+#[cfg(feature = "nowasm")]
+pub mod shutdown;
+#[cfg(feature = "nowasm")]
+pub mod signer;
+pub mod solc_artifact;
+#[cfg(feature = "nowasm")]
 pub mod solidity;
+#[cfg(feature = "nowasm")]
+pub mod watchdog;
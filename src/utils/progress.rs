@@ -0,0 +1,71 @@
+//! Progress of a proof from the exploit block to the proof, which `BuilderClient` and `RealProver`
+//! report to a `ProverProgress` so that the cli and the services show where a proof of half an
+//! hour is at.
+//!
+//! The percentages are estimates of the share of the time a proof of a large circuit spends
+//! before each phase. `create_proof` does not report the progress of its own rounds, so proving
+//! is a single step from 30% to done.
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofPhase {
+    /// Tracing the transactions of the block.
+    Trace,
+    /// Fetching the accounts and storage the block touches.
+    State,
+    /// Building the circuit inputs and the witness block.
+    Witness,
+    /// Loading the params and keys, and generating the ones which are missing.
+    Keygen,
+    Proving,
+    Done,
+}
+
+impl ProofPhase {
+    /// Estimated share of the proof done when the phase starts.
+    pub fn percent(&self) -> u8 {
+        match self {
+            ProofPhase::Trace => 0,
+            ProofPhase::State => 5,
+            ProofPhase::Witness => 10,
+            ProofPhase::Keygen => 15,
+            ProofPhase::Proving => 30,
+            ProofPhase::Done => 100,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            ProofPhase::Trace => "Tracing the exploit block",
+            ProofPhase::State => "Fetching the state the block touches",
+            ProofPhase::Witness => "Generating the witness",
+            ProofPhase::Keygen => "Loading the params and keys",
+            ProofPhase::Proving => "Generating the proof",
+            ProofPhase::Done => "Proof generated",
+        }
+    }
+}
+
+/// Observer of the phases of a proof, called when each phase starts.
+pub trait ProverProgress: Send + Sync {
+    fn on_phase(&self, phase: ProofPhase, percent: u8);
+}
+
+/// Reports nothing, the observer of the builders and provers nobody watches.
+pub struct NoProgress;
+
+impl ProverProgress for NoProgress {
+    fn on_phase(&self, _phase: ProofPhase, _percent: u8) {}
+}
+
+/// Logs the phases, the observer of the cli.
+pub struct ConsoleProgress;
+
+impl ProverProgress for ConsoleProgress {
+    fn on_phase(&self, phase: ProofPhase, percent: u8) {
+        info!(?phase, percent, "[{percent:>3}%] {}", phase.description());
+    }
+}
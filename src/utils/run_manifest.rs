@@ -0,0 +1,208 @@
+use crate::{cli::ProveArgs, error::Error, utils::halo2::srs};
+use bus_mapping::circuit_input_builder::FixedCParams;
+use eth_types::{keccak256, H256, U256};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+pub(crate) const RUNS_DIR: &str = "runs";
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunOutcome {
+    Running,
+    Success,
+    Failed,
+    Interrupted,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunInputs {
+    pub rpc: String,
+    pub block: Option<usize>,
+    pub challenge_codehash: H256,
+    pub exploit_codehash: H256,
+    pub exploit_balance: U256,
+    pub gas: Option<usize>,
+}
+
+impl RunInputs {
+    pub fn from(args: &ProveArgs) -> Result<Self, Error> {
+        let challenge_bytecode = args
+            .challenge_artifact
+            .get_deployed_bytecode("Challenge".to_string())?;
+        Ok(Self {
+            rpc: args.rpc.clone(),
+            block: args.block,
+            challenge_codehash: H256::from(keccak256(&challenge_bytecode)),
+            exploit_codehash: H256::from(keccak256(&args.exploit_bytecode)),
+            exploit_balance: args.exploit_balance,
+            gas: args.gas,
+        })
+    }
+}
+
+/// Record of a single pipeline execution, written to `<srs_path>/runs/<id>.json`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunManifest {
+    pub id: String,
+    pub command: String,
+    pub version: Version,
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+    pub inputs: Option<RunInputs>,
+    pub circuit_params: Option<FixedCParams>,
+    pub circuit_params_digest: Option<H256>,
+    pub degree: Option<u32>,
+    pub verifying_key_digest: Option<H256>,
+    pub artifacts: Vec<PathBuf>,
+    /// Wall time of each phase in milliseconds.
+    pub timings: BTreeMap<String, u128>,
+    pub outcome: RunOutcome,
+    pub error: Option<String>,
+}
+
+impl RunManifest {
+    pub fn new(command: &str) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Self {
+            id: format!("{}_{}", now.as_millis(), command),
+            command: command.to_string(),
+            // cargo guarantees that the package version is valid semver
+            version: Version::from_str(env!("CARGO_PKG_VERSION")).unwrap(),
+            started_at: now.as_secs(),
+            finished_at: None,
+            inputs: None,
+            circuit_params: None,
+            circuit_params_digest: None,
+            degree: None,
+            verifying_key_digest: None,
+            artifacts: vec![],
+            timings: BTreeMap::new(),
+            outcome: RunOutcome::Running,
+            error: None,
+        }
+    }
+
+    pub fn set_circuit(&mut self, circuit_params: FixedCParams, degree: u32) -> Result<(), Error> {
+        self.circuit_params_digest =
+            Some(H256::from(keccak256(serde_json::to_vec(&circuit_params)?)));
+        self.circuit_params = Some(circuit_params);
+        self.degree = Some(degree);
+        Ok(())
+    }
+
+    /// Records the digest of the cached verifying key and the artifacts used from `srs_path`.
+    pub fn set_keys(&mut self, srs_path: &Path) {
+        let (Some(circuit_params), Some(degree)) = (self.circuit_params, self.degree) else {
+            return;
+        };
+        let params_path = srs_path.join(srs::general_params_file_name(degree));
+        let vk_path = srs_path.join(srs::circuit_verifying_key_file_name(degree, circuit_params));
+        let pk_path = srs_path.join(srs::circuit_proving_key_file_name(degree, circuit_params));
+        if let Ok(vk_bytes) = fs::read(&vk_path) {
+            self.verifying_key_digest = Some(H256::from(keccak256(vk_bytes)));
+        }
+        for path in [params_path, vk_path, pk_path] {
+            if path.exists() {
+                self.artifacts.push(path);
+            }
+        }
+    }
+
+    pub fn record_timing(&mut self, phase: &str, duration: Duration) {
+        self.timings.insert(phase.to_string(), duration.as_millis());
+    }
+
+    pub fn finish_time(&mut self) {
+        self.finished_at = Some(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        );
+    }
+
+    pub fn finish<T>(&mut self, result: &Result<T, Error>) {
+        self.finish_time();
+        match result {
+            Ok(_) => self.outcome = RunOutcome::Success,
+            Err(error) => {
+                self.outcome = RunOutcome::Failed;
+                self.error = Some(format!("{error:?}"));
+            }
+        }
+    }
+
+    pub fn write(&self, srs_path: &Path) -> Result<PathBuf, Error> {
+        let dir = srs_path.join(RUNS_DIR);
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}.json", self.id));
+        let mut file = File::create(&path)?;
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(path)
+    }
+
+    pub fn read(srs_path: &Path, id: &str) -> Result<Self, Error> {
+        read_manifest(&srs_path.join(RUNS_DIR).join(format!("{id}.json")))
+    }
+
+    /// Lists past runs recorded in `srs_path`, oldest first.
+    pub fn list(srs_path: &Path) -> Result<Vec<Self>, Error> {
+        let dir = srs_path.join(RUNS_DIR);
+        if !dir.exists() {
+            return Ok(vec![]);
+        }
+        let mut runs = vec![];
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            // audit archives used to be written next to the manifests
+            let is_audit = path.to_string_lossy().ends_with(".audit.json");
+            if path.extension().map_or(false, |ext| ext == "json") && !is_audit {
+                runs.push(read_manifest(&path)?);
+            }
+        }
+        runs.sort_by_key(|run| (run.started_at, run.id.clone()));
+        Ok(runs)
+    }
+}
+
+fn read_manifest(path: &Path) -> Result<RunManifest, Error> {
+    let mut contents = String::new();
+    File::open(path)?.read_to_string(&mut contents)?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RunManifest, RunOutcome};
+    use crate::error::Error;
+    use std::time::Duration;
+
+    #[test]
+    fn test_write_and_list() {
+        let dir =
+            std::env::temp_dir().join(format!("pox_run_manifest_test_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut manifest = RunManifest::new("test");
+        manifest.record_timing("witness", Duration::from_millis(5));
+        manifest.finish::<()>(&Err(Error::InternalError("boom")));
+        manifest.write(&dir).unwrap();
+
+        let runs = RunManifest::list(&dir).unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].outcome, RunOutcome::Failed);
+        assert_eq!(runs[0].timings.get("witness"), Some(&5));
+        assert!(RunManifest::read(&dir, &manifest.id).is_ok());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::error::Error;
 use std::{
     env::current_dir,
     process::{Command, Stdio},
@@ -5,32 +6,34 @@ use std::{
 
 const HUFF_TEMPLATE: &str = "https://github.com/zemse/proof-of-exploit-huff-template";
 
-pub fn huff_template(project_name: &str) {
-    use_template(HUFF_TEMPLATE, project_name);
+pub fn huff_template(project_name: &str) -> Result<(), Error> {
+    use_template(HUFF_TEMPLATE, project_name)
 }
 
-pub fn use_template(git_url: &str, project_name: &str) {
-    let next_dir = current_dir().unwrap().join(project_name);
-    let next_dir = next_dir.to_str().unwrap();
-    run(format!("git clone --recursive {git_url} {project_name}",).as_str());
-    run_at(next_dir, "rm -rf .git");
-    run_at(next_dir, "git init");
-    run_at(next_dir, "git add .");
-    run_at(next_dir, "git commit -m init");
+pub fn use_template(git_url: &str, project_name: &str) -> Result<(), Error> {
+    let next_dir = current_dir()?.join(project_name);
+    let next_dir = next_dir.to_string_lossy();
+    run(format!("git clone --recursive {git_url} {project_name}",).as_str())?;
+    run_at(&next_dir, "rm -rf .git")?;
+    run_at(&next_dir, "git init")?;
+    run_at(&next_dir, "git add .")?;
+    run_at(&next_dir, "git commit -m init")?;
+    Ok(())
 }
 
-pub fn run(cmd: &str) {
+pub fn run(cmd: &str) -> Result<(), Error> {
     let split: Vec<&str> = cmd.split(' ').collect();
     let mut cmd = Command::new(split[0]);
     for arg in &split[1..] {
         cmd.arg(arg);
     }
     cmd.stdout(Stdio::piped()).stdout(Stdio::piped());
-    let mut child = cmd.spawn().unwrap();
-    child.wait().unwrap();
+    let mut child = cmd.spawn()?;
+    child.wait()?;
+    Ok(())
 }
 
-pub fn run_at(dir: &str, cmd: &str) {
+pub fn run_at(dir: &str, cmd: &str) -> Result<(), Error> {
     let split: Vec<&str> = cmd.split(' ').collect();
 
     let mut cmd = Command::new(split[0]);
@@ -39,6 +42,7 @@ pub fn run_at(dir: &str, cmd: &str) {
         cmd.arg(arg);
     }
     cmd.stdout(Stdio::piped()).stdout(Stdio::piped());
-    let mut child = cmd.spawn().unwrap();
-    child.wait().unwrap();
+    let mut child = cmd.spawn()?;
+    child.wait()?;
+    Ok(())
 }
@@ -0,0 +1,87 @@
+#[cfg(feature = "nowasm")]
+use super::run_manifest::{RunManifest, RunOutcome};
+#[cfg(feature = "nowasm")]
+use crate::error::Error;
+#[cfg(feature = "nowasm")]
+use std::{fs, process};
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+#[cfg(feature = "nowasm")]
+use tracing::{info, warn};
+
+struct ShutdownState {
+    temp_files: Vec<PathBuf>,
+    #[cfg(feature = "nowasm")]
+    run: Option<(PathBuf, RunManifest)>,
+}
+
+static STATE: Mutex<ShutdownState> = Mutex::new(ShutdownState {
+    temp_files: vec![],
+    #[cfg(feature = "nowasm")]
+    run: None,
+});
+
+#[cfg(feature = "nowasm")]
+const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// Installs a SIGINT/SIGTERM handler. Keygen and proving cannot be aborted midway, so the handler
+/// removes partially written files, records the interrupted run and exits the process. Rerunning
+/// the same command resumes from the artifacts which were completely written.
+#[cfg(feature = "nowasm")]
+pub fn install() -> Result<(), Error> {
+    ctrlc::set_handler(|| {
+        warn!("Interrupted, cleaning up...");
+        cleanup();
+        process::exit(INTERRUPTED_EXIT_CODE);
+    })
+    .map_err(|_| Error::InternalError("failed to install the interrupt handler"))
+}
+
+pub fn register_temp_file(path: &Path) {
+    if let Ok(mut state) = STATE.lock() {
+        state.temp_files.push(path.to_path_buf());
+    }
+}
+
+pub fn unregister_temp_file(path: &Path) {
+    if let Ok(mut state) = STATE.lock() {
+        state.temp_files.retain(|p| p != path);
+    }
+}
+
+/// Keeps the latest state of the current run, it is written with an interrupted outcome if a
+/// signal arrives before the run finishes.
+#[cfg(feature = "nowasm")]
+pub fn track_run(srs_path: &Path, manifest: &RunManifest) {
+    if let Ok(mut state) = STATE.lock() {
+        state.run = Some((srs_path.to_path_buf(), manifest.clone()));
+    }
+}
+
+#[cfg(feature = "nowasm")]
+pub fn untrack_run() {
+    if let Ok(mut state) = STATE.lock() {
+        state.run = None;
+    }
+}
+
+#[cfg(feature = "nowasm")]
+fn cleanup() {
+    let Ok(mut state) = STATE.lock() else {
+        return;
+    };
+    for path in state.temp_files.drain(..) {
+        if fs::remove_file(&path).is_ok() {
+            info!("Removed partially written {}", path.display());
+        }
+    }
+    if let Some((srs_path, mut manifest)) = state.run.take() {
+        manifest.outcome = RunOutcome::Interrupted;
+        manifest.finish_time();
+        if let Ok(path) = manifest.write(&srs_path) {
+            info!("Saved interrupted run to {}", path.display());
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use crate::error::Error;
+use ethers::signers::{coins_bip39::English, LocalWallet, MnemonicBuilder};
+use std::str::FromStr;
+
+/// Key of the account which sends the exploit transaction, the account has no funds on any
+/// chain and the transaction pays no gas on anvil.
+pub const DEFAULT_SIGNER_KEY: &str =
+    "0x0000000000000000000000000000000000000000000000000000000000000001";
+
+pub fn signer_from_key(key: &str) -> Result<LocalWallet, Error> {
+    LocalWallet::from_str(key)
+        .map_err(|_| Error::InvalidArgument("invalid signer private key".to_string()))
+}
+
+/// Account `index` of the standard derivation path of a BIP-39 mnemonic.
+pub fn signer_from_mnemonic(phrase: &str, index: u32) -> Result<LocalWallet, Error> {
+    MnemonicBuilder::<English>::default()
+        .phrase(phrase)
+        .index(index)
+        .and_then(|builder| builder.build())
+        .map_err(|err| Error::InvalidArgument(format!("invalid signer mnemonic: {err}")))
+}
+
+pub fn default_signer() -> LocalWallet {
+    // the key is a valid literal, parsing it cannot fail
+    signer_from_key(DEFAULT_SIGNER_KEY).unwrap()
+}
@@ -0,0 +1,108 @@
+//! Sources and solc output of the challenge contract, carried in proof files so that a verifier
+//! can compile the sources again and compare the bytecode, see `solidity`.
+
+use crate::error::Error;
+use eth_types::Bytes;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs, io::Write, path::Path};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct Input {
+    pub(crate) language: String,
+    pub(crate) sources: HashMap<String, InputSource>,
+    pub(crate) settings: InputSettings,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct InputSettings {
+    pub(crate) optimizer: InputSettingsOptimizer,
+    #[serde(rename = "evmVersion")]
+    pub(crate) evm_version: EvmVersion,
+    #[serde(rename = "outputSelection")]
+    pub(crate) output_selection: HashMap<String, HashMap<String, Vec<String>>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct InputSettingsOptimizer {
+    pub(crate) enabled: bool,
+    pub(crate) runs: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct InputSource {
+    pub(crate) content: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum EvmVersion {
+    Homestead,
+    TangerineWhistle,
+    SpuriousDragon,
+    Byzantium,
+    Constantinople,
+    Petersburg,
+    Istanbul,
+    Berlin,
+    London,
+    Paris,
+    Shanghai,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Output {
+    pub(crate) contracts: HashMap<String, HashMap<String, OutputContract>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct OutputContract {
+    pub(crate) evm: OutputContractEvm,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct OutputContractEvm {
+    #[serde(rename = "deployedBytecode")]
+    pub(crate) deployed_bytecode: OutputBytecode,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct OutputBytecode {
+    pub(crate) object: Bytes,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Artifact {
+    pub(crate) solc_version: Version,
+    pub(crate) input: Input,
+    pub(crate) output: Output,
+}
+
+impl Artifact {
+    pub fn get_deployed_bytecode(&self, search_contract_name: String) -> Result<Bytes, Error> {
+        for (_, contracts) in self.output.contracts.iter() {
+            for (contract_name, contract) in contracts.iter() {
+                if &search_contract_name == contract_name {
+                    return Ok(contract.evm.deployed_bytecode.object.clone());
+                }
+            }
+        }
+        Err(Error::CompilationError(
+            "could not find contract".to_string(),
+        ))
+    }
+
+    pub fn unpack(&self, unpack_dir: String) -> Result<(), Error> {
+        for (path, source) in self.input.sources.iter() {
+            let prefix = Path::new(&unpack_dir);
+            let path = Path::new(path);
+            let path = prefix.join(path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let mut file = fs::File::create(path)?;
+            file.write_all(source.content.as_bytes())?;
+        }
+        Ok(())
+    }
+}
@@ -1,129 +1,148 @@
-use crate::error::Error;
-use eth_types::Bytes;
+use crate::{
+    error::Error,
+    utils::huff::{compile_huff, compile_huff_creation},
+};
+use bus_mapping::circuit_input_builder::PoxInputs;
+use eth_types::{keccak256, Bytes, U256};
 use regex::Regex;
 use semver::Version;
-use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::{
     collections::HashMap,
     fs,
     io::Write,
-    path::{Path, PathBuf},
+    path::PathBuf,
     process::{self, Command, Stdio},
     str::FromStr,
 };
+use tracing::{info, warn};
 
 use super::helpers::hashmap;
+pub use super::solc_artifact::Artifact;
+use super::solc_artifact::{
+    EvmVersion, Input, InputSettings, InputSettingsOptimizer, InputSource, Output,
+};
 
-pub fn compile_solidity(source_path_string: String, match_contract_name: &str) -> Bytes {
-    let mut cmd = process::Command::new("solc");
-    cmd.arg(source_path_string);
-    cmd.arg("--combined-json");
-    cmd.arg("bin-runtime");
-    let output = cmd.output().unwrap();
-    let output = if !output.stdout.is_empty() {
-        String::from_utf8(output.stdout).unwrap()
-    } else {
-        String::from_utf8(output.stderr).unwrap()
-    };
-    let solc_json_output: Value = serde_json::from_str(output.as_str()).unwrap_or_else(|_| {
-        println!("solc error: {output}");
-        process::exit(1);
-    });
-    let compiled_bytecode = 'cb: {
-        let regx = Regex::new(r"(?m)^([^:]+):(.+)$").unwrap();
-        for (key, val) in solc_json_output
-            .as_object()
-            .unwrap()
-            .get("contracts")
-            .unwrap()
-            .as_object()
-            .unwrap()
-        {
-            let contract_name = regx
-                .captures(key.as_str())
-                .unwrap()
-                .get(2)
-                .unwrap()
-                .as_str();
-            if contract_name == match_contract_name {
-                break 'cb val
-                    .as_object()
-                    .unwrap()
-                    .get("bin-runtime")
-                    .unwrap()
-                    .as_str()
-                    .unwrap();
-            }
-        }
-        println!("Could not find a Challenge solidity contract");
-        process::exit(1);
-    };
-    Bytes::from_str(compiled_bytecode).unwrap()
-}
-
-#[derive(Clone, Debug, Serialize, Deserialize)]
-struct Input {
-    language: String,
-    sources: HashMap<String, InputSource>,
-    settings: InputSettings,
+pub fn compile_solidity(
+    source_path_string: String,
+    match_contract_name: &str,
+) -> Result<Bytes, Error> {
+    compile_solidity_output(source_path_string, match_contract_name, "bin-runtime")
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-struct InputSettings {
-    optimizer: InputSettingsOptimizer,
-    #[serde(rename = "evmVersion")]
-    evm_version: EvmVersion,
-    #[serde(rename = "outputSelection")]
-    output_selection: HashMap<String, HashMap<String, Vec<String>>>,
+/// Creation bytecode of the contract, which runs its constructor.
+pub fn compile_solidity_creation(
+    source_path_string: String,
+    match_contract_name: &str,
+) -> Result<Bytes, Error> {
+    compile_solidity_output(source_path_string, match_contract_name, "bin")
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-struct InputSettingsOptimizer {
-    enabled: bool,
-    runs: usize,
+/// Runtime bytecode of every contract of the source by name, from a single run of solc.
+pub fn compile_solidity_contracts(
+    source_path_string: String,
+) -> Result<HashMap<String, Bytes>, Error> {
+    compile_solidity_outputs(source_path_string, "bin-runtime")
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-struct InputSource {
-    content: String,
+/// Creation bytecode of every contract of the source by name, from a single run of solc.
+pub fn compile_solidity_creation_contracts(
+    source_path_string: String,
+) -> Result<HashMap<String, Bytes>, Error> {
+    compile_solidity_outputs(source_path_string, "bin")
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-enum EvmVersion {
-    Homestead,
-    TangerineWhistle,
-    SpuriousDragon,
-    Byzantium,
-    Constantinople,
-    Petersburg,
-    Istanbul,
-    Berlin,
-    London,
-    Paris,
-    Shanghai,
+fn compile_solidity_output(
+    source_path_string: String,
+    match_contract_name: &str,
+    output_kind: &str,
+) -> Result<Bytes, Error> {
+    compile_solidity_outputs(source_path_string, output_kind)?
+        .remove(match_contract_name)
+        .ok_or(Error::CompilationError(format!(
+            "Could not find a {match_contract_name} solidity contract"
+        )))
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
-struct Output {
-    contracts: HashMap<String, HashMap<String, OutputContract>>,
+fn compile_solidity_outputs(
+    source_path_string: String,
+    output_kind: &str,
+) -> Result<HashMap<String, Bytes>, Error> {
+    let mut cmd = process::Command::new("solc");
+    cmd.arg(source_path_string);
+    cmd.arg("--combined-json");
+    cmd.arg(output_kind);
+    let output = cmd.output()?;
+    let output = if !output.stdout.is_empty() {
+        String::from_utf8_lossy(&output.stdout).to_string()
+    } else {
+        String::from_utf8_lossy(&output.stderr).to_string()
+    };
+    let solc_json_output: Value = serde_json::from_str(output.as_str())
+        .map_err(|_| Error::CompilationError(format!("solc error: {output}")))?;
+    let contracts = solc_json_output
+        .get("contracts")
+        .and_then(|contracts| contracts.as_object())
+        .ok_or(Error::InternalError("unexpected solc output"))?;
+    // the regex is a literal, it is always valid
+    let regx = Regex::new(r"(?m)^([^:]+):(.+)$").unwrap();
+    let mut compiled = HashMap::new();
+    for (key, val) in contracts {
+        let Some(contract_name) = regx
+            .captures(key.as_str())
+            .and_then(|captures| captures.get(2))
+            .map(|m| m.as_str().to_string())
+        else {
+            continue;
+        };
+        let compiled_bytecode = val
+            .get(output_kind)
+            .and_then(|bin| bin.as_str())
+            .ok_or(Error::InternalError("unexpected solc output"))?;
+        let bytecode = Bytes::from_str(compiled_bytecode)
+            .map_err(|_| Error::InternalError("solc returned invalid bytecode"))?;
+        compiled.insert(contract_name, bytecode);
+    }
+    Ok(compiled)
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
-struct OutputContract {
-    evm: OutputContractEvm,
+/// Runtime bytecode of an exploit, the `Exploit` contract of a `.sol` source and the huff
+/// program of any other source.
+pub fn compile_exploit(source_path_string: String) -> Result<Bytes, Error> {
+    if source_path_string.ends_with(".sol") {
+        compile_solidity(source_path_string, "Exploit")
+    } else {
+        compile_huff(source_path_string)
+    }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
-struct OutputContractEvm {
-    #[serde(rename = "deployedBytecode")]
-    deployed_bytecode: OutputBytecode,
+/// Creation bytecode of an exploit, see `compile_exploit`.
+pub fn compile_exploit_creation(source_path_string: String) -> Result<Bytes, Error> {
+    if source_path_string.ends_with(".sol") {
+        compile_solidity_creation(source_path_string, "Exploit")
+    } else {
+        compile_huff_creation(source_path_string)
+    }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
-struct OutputBytecode {
-    object: Bytes,
+/// PoX inputs of the `Challenge` contract of a solidity source and of an exploit source, see
+/// `compile_exploit`. The balance of the exploit before is zero as on a new chain, witness
+/// generation sets it to the balance on the forked chain.
+pub fn compile_pox_inputs(
+    challenge_source_path_string: String,
+    exploit_source_path_string: String,
+    exploit_balance: U256,
+) -> Result<PoxInputs, Error> {
+    let challenge_bytecode = compile_solidity(challenge_source_path_string, "Challenge")?;
+    let exploit_bytecode = compile_exploit(exploit_source_path_string)?;
+    Ok(PoxInputs {
+        challenge_codehash: keccak256(&challenge_bytecode).into(),
+        challenge_bytecode,
+        exploit_codehash: keccak256(&exploit_bytecode).into(),
+        exploit_bytecode,
+        exploit_balance,
+        exploit_balance_before: U256::zero(),
+    })
 }
 
 // TODO support multiple files
@@ -133,15 +152,19 @@ fn file_to_artifact(source_path_string: String) -> Result<Input, Error> {
     let path = PathBuf::from(source_path_string.clone());
     let stripped_path = path
         .file_name()
-        .expect("solidity input should be a file")
+        .ok_or(Error::InternalError("solidity input should be a file"))?
         .to_string_lossy()
         .to_string();
+    Ok(source_to_input(
+        stripped_path,
+        fs::read_to_string(source_path_string)?,
+    ))
+}
 
-    Ok(Input {
+fn source_to_input(file_name: String, content: String) -> Input {
+    Input {
         language: "Solidity".to_string(),
-        sources: hashmap![stripped_path => InputSource {
-            content: fs::read_to_string(source_path_string)?,
-        }],
+        sources: hashmap![file_name => InputSource { content }],
         settings: InputSettings {
             optimizer: InputSettingsOptimizer {
                 enabled: true,
@@ -150,7 +173,7 @@ fn file_to_artifact(source_path_string: String) -> Result<Input, Error> {
             evm_version: EvmVersion::Paris,
             output_selection: hashmap!["*".into() => hashmap!["*".into() => vec!["evm.deployedBytecode.object".into()]]],
         },
-    })
+    }
 }
 
 fn compile_artifact(input: &Input) -> Result<Output, Error> {
@@ -167,87 +190,72 @@ fn compile_artifact(input: &Input) -> Result<Output, Error> {
     }
 
     let output = solc.wait_with_output()?;
-    if let Ok(output) = serde_json::from_str(std::str::from_utf8(&output.stdout).unwrap()) {
+    if let Ok(output) = serde_json::from_slice(&output.stdout) {
         Ok(output)
     } else {
         if !output.stdout.is_empty() {
-            println!(
-                "solc stdout: {:?}",
-                std::str::from_utf8(&output.stdout).unwrap()
-            );
+            warn!("solc stdout: {:?}", String::from_utf8_lossy(&output.stdout));
         }
         if !output.stderr.is_empty() {
-            println!(
-                "solc stderr {:?}",
-                std::str::from_utf8(&output.stderr).unwrap()
-            );
+            warn!("solc stderr: {:?}", String::from_utf8_lossy(&output.stderr));
         }
         Err(Error::InternalError("unexpected solc output"))
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct Artifact {
-    solc_version: Version,
-    input: Input,
-    output: Output,
-}
-
 impl Artifact {
-    pub fn from_source(source_path_string: String) -> Self {
-        let solc_version = svm_lib::current_version().unwrap().unwrap();
-        let input = file_to_artifact(source_path_string).unwrap();
-        let output = compile_artifact(&input).unwrap();
-        Artifact {
+    pub fn from_source(source_path_string: String) -> Result<Self, Error> {
+        let solc_version = current_solc_version()?;
+        let input = file_to_artifact(source_path_string)?;
+        let output = compile_artifact(&input)?;
+        Ok(Artifact {
             solc_version,
             input,
             output,
-        }
+        })
+    }
+
+    /// Artifact of a source sent rather than read from a file, `file_name` is the name the
+    /// source has in the artifact.
+    pub fn from_source_code(file_name: String, content: String) -> Result<Self, Error> {
+        let solc_version = current_solc_version()?;
+        let input = source_to_input(file_name, content);
+        let output = compile_artifact(&input)?;
+        Ok(Artifact {
+            solc_version,
+            input,
+            output,
+        })
     }
 
     pub async fn verify_compilation(&self) -> Result<(), Error> {
-        let user_version = svm_lib::current_version().unwrap().unwrap();
+        let user_version = current_solc_version()?;
         if user_version != self.solc_version {
-            let installed_versions = svm_lib::installed_versions().unwrap();
+            let installed_versions = svm_lib::installed_versions()?;
             if !installed_versions.contains(&self.solc_version) {
-                println!("Installing solc version {}...", self.solc_version);
-                svm_lib::install(&self.solc_version).await.unwrap();
+                info!("Installing solc version {}...", self.solc_version);
+                svm_lib::install(&self.solc_version).await?;
             }
             // switch solc to proof's solc version
-            svm_lib::use_version(&self.solc_version).unwrap();
+            svm_lib::use_version(&self.solc_version)?;
         }
-        let fresh_compilation_output = compile_artifact(&self.input)?;
+        let fresh_compilation_output = compile_artifact(&self.input);
         if user_version != self.solc_version {
             // switch solc to user's original solc version
-            svm_lib::use_version(&user_version).unwrap();
+            svm_lib::use_version(&user_version)?;
         }
+        let fresh_compilation_output = fresh_compilation_output?;
         if fresh_compilation_output != self.output {
-            return Err(Error::InternalError("compilation not matching"));
+            return Err(Error::CompilationError(
+                "compilation not matching".to_string(),
+            ));
         }
         Ok(())
     }
+}
 
-    pub fn get_deployed_bytecode(&self, search_contract_name: String) -> Result<Bytes, Error> {
-        for (_, contracts) in self.output.contracts.iter() {
-            for (contract_name, contract) in contracts.iter() {
-                if &search_contract_name == contract_name {
-                    return Ok(contract.evm.deployed_bytecode.object.clone());
-                }
-            }
-        }
-        Err(Error::InternalError("could not find contract"))
-    }
-
-    pub fn unpack(&self, unpack_dir: String) {
-        for (path, source) in self.input.sources.iter() {
-            let prefix = Path::new(&unpack_dir);
-            let path = Path::new(path);
-            let path = prefix.join(path);
-            if let Some(parent) = path.parent() {
-                fs::create_dir_all(parent).unwrap();
-            }
-            let mut file = fs::File::create(path).unwrap();
-            file.write_all(source.content.as_bytes()).unwrap();
-        }
-    }
+fn current_solc_version() -> Result<Version, Error> {
+    svm_lib::current_version()?.ok_or(Error::CompilationError(
+        "solc version is not set, please install solc using svm".to_string(),
+    ))
 }
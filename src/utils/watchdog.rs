@@ -0,0 +1,46 @@
+use crate::error::Error;
+use std::{
+    fmt::Debug,
+    future::Future,
+    time::{Duration, Instant},
+};
+use tracing::{instrument, warn};
+
+pub const DEFAULT_RPC_TIMEOUT: Duration = Duration::from_secs(300);
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Awaits `fut`, warning every few seconds about the call that is still pending and failing with
+/// `Error::Timeout` once `timeout` has passed, so a misbehaving node never hangs silently.
+#[instrument(level = "debug", skip_all, fields(method = %method))]
+pub async fn watch<T, E, F>(
+    method: &str,
+    params: impl Debug,
+    timeout: Duration,
+    fut: F,
+) -> Result<T, Error>
+where
+    F: Future<Output = Result<T, E>>,
+    Error: From<E>,
+{
+    tokio::pin!(fut);
+    let start = Instant::now();
+    loop {
+        let remaining = timeout.saturating_sub(start.elapsed());
+        match tokio::time::timeout(remaining.min(WATCHDOG_INTERVAL), &mut fut).await {
+            Ok(result) => return Ok(result?),
+            Err(_) => {
+                let elapsed = start.elapsed();
+                if elapsed >= timeout {
+                    return Err(Error::Timeout(format!(
+                        "{method} {params:?} did not respond within {}s",
+                        timeout.as_secs()
+                    )));
+                }
+                warn!(
+                    "Still waiting for {method} {params:?} after {}s",
+                    elapsed.as_secs()
+                );
+            }
+        }
+    }
+}
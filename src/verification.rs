@@ -1,11 +1,24 @@
 use crate::{
     cli::VerifyArgs,
-    utils::{self, halo2::real_verifier::RealVerifier},
+    error::Error,
+    utils::{
+        self, ancestry,
+        halo2::{
+            ipa::IpaVerifier,
+            proof::ProofBackend,
+            real_verifier::{self, LightVerifier, RealVerifier},
+            srs, trajectory,
+            vk_registry::VkRegistry,
+        },
+    },
 };
+use eth_types::H256;
 use semver::Version;
-use std::{process, str::FromStr};
+use std::str::FromStr;
+use tracing::{error, warn};
 
-pub async fn handle_verify(args: VerifyArgs) {
+pub async fn handle_verify(mut args: VerifyArgs) -> Result<(), Error> {
+    // cargo guarantees that the package version is valid semver
     let my_version = Version::from_str(env!("CARGO_PKG_VERSION")).unwrap();
     if my_version < args.proof.version {
         println!(
@@ -14,17 +27,108 @@ pub async fn handle_verify(args: VerifyArgs) {
         );
     }
 
-    let verifier = RealVerifier::load_srs(args.srs_path, &args.proof).await;
-    if let Err(error) = verifier.verify(&args.proof).await {
-        println!("Proof verification failed: {:?}", error);
-        process::exit(1);
+    let registry = VkRegistry::load(&args.srs_path)?;
+    if args.ipfs_setup {
+        println!(
+            "Warning: the IPFS setup was generated from a known seed, its proofs are forgeable\n"
+        );
+        args.srs_path = srs::provision_ipfs_setup(
+            &args.srs_path,
+            args.proof.degree,
+            args.proof.fixed_circuit_params,
+        )
+        .await?;
+    }
+    // an empty registry accepts any verifying key, as before keys could be approved
+    let check_approved = |digest: H256| {
+        if registry.approved.is_empty() || registry.contains(&digest) {
+            Ok(())
+        } else {
+            Err(Error::VkMismatch(format!(
+                "verifying key {digest:?} of the proof is not approved"
+            )))
+        }
+    };
+
+    if !args.batch.is_empty() {
+        let proofs = [vec![args.proof], args.batch].concat();
+        let verifier =
+            RealVerifier::load_srs_with(args.srs_path, &proofs[0], args.artifact_checks).await?;
+        check_approved(verifier.vk_hash())?;
+        verifier.verify_batch(&proofs).await?;
+        println!("Verification of {} proofs success!", proofs.len());
+        if args.trajectory {
+            trajectory::check_continuity(&proofs)?;
+            println!(
+                "The proofs claim consecutive blocks from block {} to block {}, their state roots are not constrained by the circuit",
+                proofs[0].public_data.block_constants.number - 1,
+                proofs[proofs.len() - 1].public_data.block_constants.number - 1
+            );
+        }
+        return Ok(());
+    }
+
+    let result = match args.proof.backend {
+        ProofBackend::Kzg if args.light => {
+            if !registry.approved.is_empty() {
+                return Err(Error::VkMismatch(
+                    "the verifying key digest of a light verifier key cannot be recomputed to check it is approved, verify without --light".to_string(),
+                ));
+            }
+            let verifier = LightVerifier::load_srs(args.srs_path, &args.proof).await?;
+            verifier.verify(&args.proof).await.map_err(|error| {
+                error!("Proof verification failed: {:?}", error);
+                error
+            })
+        }
+        ProofBackend::Kzg => {
+            let verifier =
+                RealVerifier::load_srs_with(args.srs_path, &args.proof, args.artifact_checks)
+                    .await?;
+            check_approved(verifier.vk_hash())?;
+            let result = verifier.verify(&args.proof).await;
+            if let Err(error) = &result {
+                error!("Proof verification failed: {:?}", error);
+                error!("Diagnosis: {}", verifier.diagnose(&args.proof).await);
+            }
+            result
+        }
+        ProofBackend::Ipa => {
+            warn!("This proof uses the IPA development backend");
+            let verifier = IpaVerifier::load_srs(args.srs_path, &args.proof).await?;
+            check_approved(verifier.vk_hash())?;
+            verifier.verify(&args.proof).await.map_err(|error| {
+                error!("Proof verification failed: {:?}", error);
+                error
+            })
+        }
+        ProofBackend::Mock => Err(Error::InvalidArgument(
+            "the proof is a stub of the mock prover and proves nothing".to_string(),
+        )),
+    };
+    if let Err(error) = result {
+        return Err(error);
     } else {
         println!("Proof verification success!\n");
 
+        if let Some(evm_verifier) = &args.evm_verifier {
+            let gas = real_verifier::verify_on_evm(evm_verifier, &args.proof).await?;
+            println!("Solidity verifier accepts the proof, verifying it costs {gas} gas\n");
+        }
+
+        if let Some((rpc, anchor)) = &args.anchor {
+            let depth = ancestry::check_ancestry(rpc, &args.proof, *anchor).await?;
+            println!("Proven block is the ancestor {depth} blocks before {anchor:?}\n");
+        }
+
         if let Some(summary) = args.proof.summary {
             println!("Summary: {}\n", summary);
         }
 
+        if args.proof.reverted {
+            println!("The exploit transaction reverted, the proof is of a reverting execution\n");
+        }
+
         println!("Public Inputs:");
         println!("  Chain Id: {:?}", args.proof.public_data.chain_id,);
         println!(
@@ -44,14 +148,14 @@ pub async fn handle_verify(args: VerifyArgs) {
             "  Exploit Stipend: {} ether",
             ethers::utils::format_ether(args.proof.public_data.pox_exploit_balance)
                 .parse::<f64>()
-                .unwrap()
+                .unwrap_or_default()
         );
     }
 
     if let Some(unpack_dir) = args.unpack_dir {
         if let Some(challenge_artifact) = args.proof.challenge_artifact {
             println!("\nUnpacking challenge source code...");
-            challenge_artifact.unpack(unpack_dir);
+            challenge_artifact.unpack(unpack_dir)?;
             println!("Done!");
         } else {
             println!("\nError: Proof does not contain challenge source code to unpack.");
@@ -59,4 +163,5 @@ pub async fn handle_verify(args: VerifyArgs) {
     } else {
         println!("\nTo view challenge source code, use --unpack flag.");
     }
+    Ok(())
 }
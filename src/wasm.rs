@@ -1,13 +1,17 @@
+use crate::constants::RANDOMNESS;
 use halo2_proofs::halo2curves::bn256::{Bn256, Fr, G1Affine};
 use halo2_proofs::plonk::{verify_proof, VerifyingKey};
+use halo2_proofs::poly::commitment::Params;
 use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
 use halo2_proofs::poly::kzg::multiopen::VerifierSHPLONK;
 use halo2_proofs::poly::kzg::strategy::SingleStrategy;
 use halo2_proofs::transcript::{Blake2bRead, Challenge255, TranscriptReadBuffer};
 use halo2_proofs::SerdeFormat;
 use js_sys::Uint8Array;
+use serde::Deserialize;
 use std::io::BufReader;
 use wasm_bindgen::prelude::*;
+use zkevm_circuits::instance::PublicData;
 use zkevm_circuits::super_circuit::{SuperCircuit, SuperCircuitParams};
 
 const SERDE_FORMAT: SerdeFormat = SerdeFormat::RawBytes;
@@ -19,52 +23,195 @@ pub fn verify(
     vk_js: JsValue,
     instance_0: JsValue,
     instance_1: JsValue,
-) -> bool {
+) -> Result<bool, JsError> {
     console_error_panic_hook::set_once();
 
     #[allow(deprecated)]
-    let proof_vec = proof_js.into_serde::<Vec<u8>>().unwrap();
+    let proof_vec = proof_js.into_serde::<Vec<u8>>()?;
     let params_vec = Uint8Array::new(&params_js).to_vec();
     let vk_vec = Uint8Array::new(&vk_js).to_vec();
-    let mut instance_0 = instance_0.into_serde::<[u8; 32]>().unwrap();
-    let mut instance_1 = instance_1.into_serde::<[u8; 32]>().unwrap();
+    #[allow(deprecated)]
+    let mut instance_0 = instance_0.into_serde::<[u8; 32]>()?;
+    #[allow(deprecated)]
+    let mut instance_1 = instance_1.into_serde::<[u8; 32]>()?;
 
-    let params =
-        ParamsKZG::<Bn256>::read_custom(&mut BufReader::new(&params_vec[..]), SERDE_FORMAT)
-            .unwrap();
+    instance_0.reverse();
+    instance_1.reverse();
 
-    let vk = VerifyingKey::<G1Affine>::read::<BufReader<&[u8]>, SuperCircuit<Fr>>(
-        &mut BufReader::new(&vk_vec[..]),
-        SERDE_FORMAT,
+    let instances = vec![
+        vec![field_element(instance_0)?, field_element(instance_1)?],
+        vec![],
+    ];
+    verify_kzg(
+        &proof_vec,
+        &params_vec,
+        &vk_vec,
         SuperCircuitParams {
-            mock_randomness: Fr::from(0x100),
+            mock_randomness: Fr::from(RANDOMNESS),
         },
+        None,
+        &instances,
     )
-    .unwrap();
+}
 
-    let strategy = SingleStrategy::new(&params);
-    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof_vec[..]);
+/// The fields of a proof file written by `exploit prove` which verifying it needs.
+#[derive(Deserialize)]
+struct ProofFile {
+    degree: u32,
+    data: String,
+    #[serde(default)]
+    transcript: Option<String>,
+    #[serde(default)]
+    backend: Option<String>,
+    #[serde(default)]
+    pcs_scheme: Option<String>,
+    instances: Vec<Vec<String>>,
+    circuit_params: ProofFileCircuitParams,
+    public_data: PublicData,
+}
 
-    instance_0.reverse();
-    instance_1.reverse();
+#[derive(Deserialize)]
+struct ProofFileCircuitParams {
+    mock_randomness: String,
+}
 
-    let instances = vec![
-        vec![
-            Fr::from_bytes(&instance_0).unwrap(),
-            Fr::from_bytes(&instance_1).unwrap(),
-        ],
-        vec![],
-    ];
+/// Verifies the bytes of a proof file written by `exploit prove` against the params and the
+/// verifying key of its circuit, and checks that its instances are the digest of its public data.
+/// Only SHPLONK proofs with the default blake2b transcript can be verified in the browser.
+#[wasm_bindgen]
+pub fn verify_proof_file(
+    proof_file_js: JsValue,
+    params_js: JsValue,
+    vk_js: JsValue,
+) -> Result<bool, JsError> {
+    console_error_panic_hook::set_once();
+
+    let proof_file_vec = Uint8Array::new(&proof_file_js).to_vec();
+    let params_vec = Uint8Array::new(&params_js).to_vec();
+    let vk_vec = Uint8Array::new(&vk_js).to_vec();
+    let proof: ProofFile = serde_json::from_slice(&proof_file_vec)?;
+
+    if let Some(backend) = proof.backend.filter(|backend| backend != "kzg") {
+        return Err(JsError::new(&format!(
+            "the proof was created with the {backend} backend, only kzg proofs can be verified"
+        )));
+    }
+    if let Some(transcript) = proof
+        .transcript
+        .filter(|transcript| transcript != "blake2b")
+    {
+        return Err(JsError::new(&format!(
+            "the proof has a {transcript} transcript, only blake2b proofs can be verified in the browser"
+        )));
+    }
+    if let Some(pcs_scheme) = proof
+        .pcs_scheme
+        .filter(|pcs_scheme| pcs_scheme != "shplonk")
+    {
+        return Err(JsError::new(&format!(
+            "the proof was opened with {pcs_scheme}, only shplonk proofs can be verified in the browser"
+        )));
+    }
+
+    let instances = proof
+        .instances
+        .iter()
+        .map(|column| {
+            column
+                .iter()
+                .map(|value| hex_field_element(value))
+                .collect()
+        })
+        .collect::<Result<Vec<Vec<Fr>>, JsError>>()?;
+    // the randomness is fixed by the circuit, a proof file cannot pick its own
+    let mock_randomness = Fr::from(RANDOMNESS);
+    if hex_field_element(&proof.circuit_params.mock_randomness)? != mock_randomness {
+        return Err(JsError::new(
+            "the proof was created with another witness randomness than the circuit keys",
+        ));
+    }
+
+    let digest = proof.public_data.get_rpi_digest_word::<Fr>();
+    if instances.first() != Some(&vec![digest.lo(), digest.hi()]) {
+        return Ok(false);
+    }
+
+    verify_kzg(
+        &decode_hex(&proof.data)?,
+        &params_vec,
+        &vk_vec,
+        SuperCircuitParams { mock_randomness },
+        Some(proof.degree),
+        &instances,
+    )
+}
+
+/// Verifies a SHPLONK proof with a blake2b transcript, the params and the vk must be of `degree`
+/// when it is known.
+fn verify_kzg(
+    proof: &[u8],
+    params: &[u8],
+    vk: &[u8],
+    circuit_params: SuperCircuitParams<Fr>,
+    degree: Option<u32>,
+    instances: &[Vec<Fr>],
+) -> Result<bool, JsError> {
+    let params = ParamsKZG::<Bn256>::read_custom(&mut BufReader::new(params), SERDE_FORMAT)?;
+    let vk = VerifyingKey::<G1Affine>::read::<BufReader<&[u8]>, SuperCircuit<Fr>>(
+        &mut BufReader::new(vk),
+        SERDE_FORMAT,
+        circuit_params,
+    )?;
+    if let Some(degree) = degree {
+        if params.k() != degree || vk.get_domain().k() != degree {
+            return Err(JsError::new(&format!(
+                "the proof is for degree {degree} but the params are for degree {} and the verifying key for degree {}",
+                params.k(),
+                vk.get_domain().k()
+            )));
+        }
+    }
+
+    let strategy = SingleStrategy::new(&params);
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(proof);
     let instances = instances.iter().map(|v| &v[..]).collect::<Vec<&[Fr]>>();
 
-    verify_proof::<
+    Ok(verify_proof::<
         KZGCommitmentScheme<Bn256>,
         VerifierSHPLONK<'_, Bn256>,
         Challenge255<G1Affine>,
         Blake2bRead<&[u8], G1Affine, Challenge255<G1Affine>>,
         SingleStrategy<'_, Bn256>,
     >(&params, &vk, strategy, &[&instances], &mut transcript)
-    .is_ok()
+    .is_ok())
+}
+
+fn decode_hex(value: &str) -> Result<Vec<u8>, JsError> {
+    let value = value.strip_prefix("0x").unwrap_or(value);
+    if !value.is_ascii() || value.len() % 2 != 0 {
+        return Err(JsError::new("value is not hex encoded"));
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&value[i..i + 2], 16)
+                .map_err(|_| JsError::new("value is not hex encoded"))
+        })
+        .collect()
+}
+
+/// Field element encoded as big endian hex, like the instances of a proof file.
+fn hex_field_element(value: &str) -> Result<Fr, JsError> {
+    let mut bytes: [u8; 32] = decode_hex(value)?
+        .try_into()
+        .map_err(|_| JsError::new("field element is not 32 bytes"))?;
+    bytes.reverse();
+    field_element(bytes)
+}
+
+fn field_element(bytes: [u8; 32]) -> Result<Fr, JsError> {
+    Option::from(Fr::from_bytes(&bytes))
+        .ok_or_else(|| JsError::new("instance is not a canonical field element"))
 }
 
 #[wasm_bindgen]
@@ -0,0 +1,201 @@
+//! Seam between this crate and the zkevm-circuits snapshot it is built against. Building the
+//! witness block, the circuit and its instances goes through `ExploitCircuit`, so that moving to
+//! another zkevm-circuits release means implementing this trait for its super circuit instead of
+//! touching the witness, prover and verifier code.
+//!
+//! Only the proof-of-exploit fork is implemented. Upstream zkevm-circuits has no `PoxInputs` nor
+//! PoX fields in its public data, so a release can only be supported once the fork is rebased on it.
+//! There is therefore no cargo feature selecting another release: it would resolve to a crate
+//! this one cannot build against.
+
+use crate::{constants::RANDOMNESS, error::Error};
+use bus_mapping::circuit_input_builder::{CircuitInputBuilder, FixedCParams};
+use eth_types::H256;
+use halo2_proofs::{halo2curves::bn256::Fr, plonk::Circuit};
+use serde::{Deserialize, Serialize};
+use zkevm_circuits::{
+    evm_circuit::EvmCircuit,
+    instance::{public_data_convert, PublicData},
+    keccak_circuit::KeccakCircuit,
+    super_circuit::SuperCircuit,
+    util::{log2_ceil, SubCircuit},
+    witness::{block_convert, Block},
+};
+
+/// Extra rows kept free for blinding and the rows halo2 reserves at the end of the circuit.
+pub(crate) const RESERVED_ROWS: usize = 64;
+
+/// Challenge codehash of the public data in which `challenge_codehash_offset` looks for it.
+const CODEHASH_PROBE: H256 = H256::repeat_byte(0xc0);
+
+/// Rows a block takes in a sub-circuit.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RowUsage {
+    pub circuit: String,
+    pub rows: usize,
+    /// Rows the circuit params reserve for the sub-circuit.
+    pub max_rows: usize,
+}
+
+pub trait ExploitCircuit: Circuit<Fr> + Clone + Sized {
+    /// Converts the inputs collected by bus-mapping into the witness block, with the randomness
+    /// the keys are generated with.
+    fn block_from_builder(builder: &CircuitInputBuilder<FixedCParams>) -> Result<Block<Fr>, Error>;
+
+    fn from_block(block: &Block<Fr>) -> Self;
+
+    /// Circuit of an empty block, enough to generate the keys of `circuits_params` before there
+    /// is an exploit to prove.
+    fn without_witness(circuits_params: FixedCParams) -> Self;
+
+    /// Smallest degree at which the circuit fits the block.
+    fn degree_for_block(block: &Block<Fr>) -> u32;
+
+    /// Smallest circuit params the block fits in, for a block built with params sized by
+    /// bus_mapping, which leaves the evm and keccak rows to the circuits.
+    fn params_for_block(block: &Block<Fr>) -> FixedCParams;
+
+    /// Rows the block of the circuit takes in the evm, state, copy, keccak, exp, bytecode and tx
+    /// circuits, empty for a circuit without a witness.
+    fn row_usage(&self) -> Vec<RowUsage>;
+
+    /// Smallest degree at which the rows of `usage` fit, with params shrunk to the block.
+    fn degree_for_rows(usage: &[RowUsage]) -> u32 {
+        let rows = usage
+            .iter()
+            .map(|usage| usage.rows)
+            .max()
+            .unwrap_or_default();
+        log2_ceil(RESERVED_ROWS + rows)
+    }
+
+    fn block(&self) -> Option<&Block<Fr>>;
+
+    fn fixed_params(&self) -> FixedCParams;
+
+    fn instances(&self) -> Vec<Vec<Fr>>;
+
+    /// Instances which a proof for `public_data` must have, in the layout of `instances`.
+    fn expected_instances(public_data: &PublicData) -> Vec<Vec<Fr>>;
+
+    fn public_data(block: &Block<Fr>) -> PublicData {
+        public_data_convert(block)
+    }
+
+    /// Bytes of the public data whose keccak256 digest the instances commit to.
+    fn public_data_bytes(public_data: &PublicData) -> Vec<u8>;
+
+    /// Offset of the PoX challenge codehash in `public_data_bytes`. The fields before it have a
+    /// fixed size, so a contract given the public data of an instance digest reads the challenge
+    /// the proof is about at this offset.
+    fn challenge_codehash_offset() -> Result<usize, Error> {
+        let public_data = PublicData {
+            pox_challenge_codehash: CODEHASH_PROBE,
+            ..Default::default()
+        };
+        Self::public_data_bytes(&public_data)
+            .windows(32)
+            .position(|window| window == CODEHASH_PROBE.as_bytes())
+            .ok_or(Error::InternalError(
+                "public data bytes do not contain the challenge codehash",
+            ))
+    }
+}
+
+impl ExploitCircuit for SuperCircuit<Fr> {
+    fn block_from_builder(builder: &CircuitInputBuilder<FixedCParams>) -> Result<Block<Fr>, Error> {
+        let mut block = block_convert::<Fr>(builder)?;
+        // the keys and every verifier are generated with this randomness
+        block.randomness = Fr::from(RANDOMNESS);
+        Ok(block)
+    }
+
+    fn from_block(block: &Block<Fr>) -> Self {
+        SuperCircuit::<Fr>::new_from_block(block)
+    }
+
+    fn without_witness(circuits_params: FixedCParams) -> Self {
+        let block = Block::<Fr> {
+            circuits_params,
+            randomness: Fr::from(RANDOMNESS),
+            ..Default::default()
+        };
+        SuperCircuit::<Fr>::new_from_block(&block)
+    }
+
+    fn degree_for_block(block: &Block<Fr>) -> u32 {
+        let (_, rows_needed) = SuperCircuit::<Fr>::min_num_rows_block(block);
+        log2_ceil(RESERVED_ROWS + rows_needed)
+    }
+
+    fn params_for_block(block: &Block<Fr>) -> FixedCParams {
+        // the second value covers the fixed tables, which are laid out in the evm circuit too
+        let (evm_rows, fixed_table_rows) = EvmCircuit::<Fr>::min_num_rows_block(block);
+        let (keccak_rows, _) = KeccakCircuit::<Fr>::min_num_rows_block(block);
+        FixedCParams {
+            max_evm_rows: evm_rows.max(fixed_table_rows),
+            max_keccak_rows: keccak_rows,
+            ..block.circuits_params
+        }
+    }
+
+    fn row_usage(&self) -> Vec<RowUsage> {
+        let Some(block) = self.block() else {
+            return vec![];
+        };
+        let (evm_rows, fixed_table_rows) = EvmCircuit::<Fr>::min_num_rows_block(block);
+        let evm_rows = evm_rows.max(fixed_table_rows);
+        // the evm circuit sizes itself to the block when max_evm_rows is 0
+        let max_evm_rows = match self.circuits_params.max_evm_rows {
+            0 => evm_rows,
+            max_evm_rows => max_evm_rows,
+        };
+        vec![
+            RowUsage {
+                circuit: "evm".to_string(),
+                rows: evm_rows,
+                max_rows: max_evm_rows,
+            },
+            row_usage("state", &self.state_circuit, block),
+            row_usage("copy", &self.copy_circuit, block),
+            row_usage("keccak", &self.keccak_circuit, block),
+            row_usage("exp", &self.exp_circuit, block),
+            row_usage("bytecode", &self.bytecode_circuit, block),
+            row_usage("tx", &self.tx_circuit, block),
+        ]
+    }
+
+    fn block(&self) -> Option<&Block<Fr>> {
+        self.evm_circuit.block.as_ref()
+    }
+
+    fn fixed_params(&self) -> FixedCParams {
+        self.circuits_params
+    }
+
+    fn instances(&self) -> Vec<Vec<Fr>> {
+        self.instance()
+    }
+
+    /// The first column is the keccak digest of the public data split into lo and hi limbs, the
+    /// second column is unused. The challenge codehash is committed to through the digest, see
+    /// `challenge_codehash_offset`, the instance columns are laid out by the circuit of the fork.
+    fn expected_instances(public_data: &PublicData) -> Vec<Vec<Fr>> {
+        let digest = public_data.get_rpi_digest_word::<Fr>();
+        vec![vec![digest.lo(), digest.hi()], vec![]]
+    }
+
+    fn public_data_bytes(public_data: &PublicData) -> Vec<u8> {
+        public_data.get_pi_bytes()
+    }
+}
+
+/// Rows of the sub-circuit of type `C`, the sub-circuit itself is only passed for its type.
+fn row_usage<C: SubCircuit<Fr>>(circuit: &str, _: &C, block: &Block<Fr>) -> RowUsage {
+    let (rows, max_rows) = C::min_num_rows_block(block);
+    RowUsage {
+        circuit: circuit.to_string(),
+        rows,
+        max_rows,
+    }
+}
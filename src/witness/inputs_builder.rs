@@ -1,14 +1,20 @@
 use crate::{
+    config::Config,
+    constants::DEFAULT_PARAMS_MARGIN,
     error::Error,
     utils::{
         anvil::{conversion::ConversionReverse, types::zkevm_types::*, AnvilClient},
         geth::GethClient,
+        halo2::trajectory::check_witness_continuity,
+        hardfork::Hardfork,
+        progress::{NoProgress, ProofPhase, ProverProgress},
+        watchdog::DEFAULT_RPC_TIMEOUT,
     },
 };
 pub use bus_mapping::{
     circuit_input_builder::{
         build_state_code_db, gen_state_access_trace, Access, AccessSet, AccessValue, Block,
-        CircuitInputBuilder, CircuitsParams, FixedCParams, PoxInputs,
+        CircuitInputBuilder, CircuitsParams, DynamicCParams, FixedCParams, PoxInputs,
     },
     operation::RW,
     state_db::{CodeDB, StateDB},
@@ -18,14 +24,27 @@ use eth_types::Fr;
 use ethers::utils::keccak256;
 use futures::future;
 use partial_mpt::StateTrie;
-use std::collections::HashMap;
-use zkevm_circuits::witness::block_convert;
+use std::{collections::HashMap, ops::RangeInclusive, sync::Arc, time::Duration};
+use zkevm_circuits::super_circuit::SuperCircuit;
+
+use super::{circuit::ExploitCircuit, repro::Repro};
+
+/// Transaction types the circuits constrain: legacy, EIP-2930 and EIP-1559. Blob transactions of
+/// EIP-4844 (type 3) would make bus_mapping panic deep in the witness generation.
+const SUPPORTED_TX_TYPES: [u64; 3] = [0, 1, 2];
 
 pub struct BuilderClient {
     pub anvil: AnvilClient,
     pub geth: Option<GethClient>,
     pub chain_id: eth_types::Word,
     pub circuits_params: FixedCParams,
+    /// Trace of the exploit tx produced by external tooling, used instead of tracing it.
+    pub imported_trace: Option<GethExecTrace>,
+    /// Hardfork anvil executes the transactions with, the traces are checked to only use its
+    /// opcodes.
+    pub hardfork: Hardfork,
+    /// Observer of the phases of the witness generation.
+    pub progress: Arc<dyn ProverProgress>,
 }
 
 pub fn get_state_accesses(
@@ -45,6 +64,27 @@ pub fn get_state_accesses(
         let geth_trace = &geth_traces[tx_index];
         let tx_access_trace = gen_state_access_trace(block, tx, geth_trace)?;
         block_access_trace.extend(tx_access_trace);
+        // the access list is warmed even when the tx never touches it, and a warm account or
+        // slot has to be in the state db for its proof
+        for item in tx.access_list.iter().flat_map(|access_list| &access_list.0) {
+            block_access_trace.push(Access::new(
+                Some(tx_index),
+                RW::READ,
+                AccessValue::Account {
+                    address: item.address,
+                },
+            ));
+            for key in &item.storage_keys {
+                block_access_trace.push(Access::new(
+                    Some(tx_index),
+                    RW::READ,
+                    AccessValue::Storage {
+                        address: item.address,
+                        key: Word::from_big_endian(key.as_bytes()),
+                    },
+                ));
+            }
+        }
     }
 
     // the Challenge address has no code on the mainnet, however in the private block we assign it
@@ -70,14 +110,98 @@ impl BuilderClient {
         eth_rpc_url: Option<String>,
         geth_rpc_url: Option<String>,
         fork_block_number: Option<usize>,
+        hardfork: Hardfork,
     ) -> Result<Self, Error> {
-        let anvil = AnvilClient::setup(eth_rpc_url.clone(), fork_block_number).await;
-        let geth = geth_rpc_url.or(eth_rpc_url).map(GethClient::new);
-        Self::new(anvil, geth, circuits_params)
+        let anvil = AnvilClient::setup(eth_rpc_url.clone(), fork_block_number, hardfork).await?;
+        let geth = geth_rpc_url
+            .or(eth_rpc_url)
+            .map(GethClient::new)
+            .transpose()?;
+        let mut builder = Self::new(anvil, geth, circuits_params)?;
+        builder.hardfork = hardfork;
+        Ok(builder)
+    }
+
+    /// Builder with anvil forking `block_number` of the chain of `eth_rpc_url`, the latest block if
+    /// there is none, under the hardfork of that block. The block anvil starts from is checked to
+    /// be the one of the chain, so that the witnesses are of the deployed contracts and balances.
+    pub async fn fork(eth_rpc_url: String, block_number: Option<usize>) -> Result<Self, Error> {
+        let hardfork =
+            Hardfork::of_fork_block(&eth_rpc_url, block_number, DEFAULT_RPC_TIMEOUT).await?;
+        hardfork.check_supported(None)?;
+        let builder = Self::from_config(
+            FixedCParams::default(),
+            Some(eth_rpc_url),
+            None,
+            block_number,
+            hardfork,
+        )
+        .await?;
+        let fork_block_number = builder.anvil.block_number().await?;
+        let forked = builder
+            .anvil
+            .block_by_number(fork_block_number)
+            .await?
+            .and_then(|block| block.hash);
+        let chain = builder
+            .geth
+            .as_ref()
+            .ok_or(Error::InternalError("fork has no rpc"))?
+            .block_by_number_full(fork_block_number)
+            .await?
+            .and_then(|block| block.hash);
+        if forked.is_none() || forked != chain {
+            return Err(Error::InternalError(
+                "anvil does not start from the fork block of the chain",
+            ));
+        }
+        Ok(builder)
+    }
+
+    /// Builder against a node already running elsewhere, geth, reth, erigon or another anvil. Its
+    /// latest block is forked by anvil, which executes the exploit on the node's state, and the
+    /// traces are the ones of its debug methods. Fails with `Error::UnsupportedRpcMethod` unless
+    /// the node serves the methods this needs.
+    pub async fn from_rpc_url(url: String, circuits_params: FixedCParams) -> Result<Self, Error> {
+        GethClient::new(url.clone())?.check_capabilities().await?;
+        let hardfork = Hardfork::of_fork_block(&url, None, DEFAULT_RPC_TIMEOUT).await?;
+        Self::from_config(
+            circuits_params,
+            Some(url.clone()),
+            Some(url),
+            None,
+            hardfork,
+        )
+        .await
+    }
+
+    /// Builder with the anvil node already running at `url`, which is not stopped with the builder.
+    pub async fn attach(url: String, circuits_params: FixedCParams) -> Result<Self, Error> {
+        let anvil = AnvilClient::attach(url).await?;
+        Self::new(anvil, None, circuits_params)
+    }
+
+    /// Builder for the circuit size of a parameter profile, with anvil forking the chain of its
+    /// anvil settings.
+    pub async fn from_profile(config: &Config) -> Result<Self, Error> {
+        let anvil = AnvilClient::from_config(&config.anvil).await?;
+        let geth = config
+            .anvil
+            .geth_rpc
+            .clone()
+            .or(config.anvil.rpc.clone())
+            .map(GethClient::new)
+            .transpose()?;
+        let mut builder = Self::new(anvil, geth, config.circuits_params())?;
+        builder.hardfork = config.anvil.hardfork.unwrap_or_default();
+        if let (Some(rpc_timeout), Some(geth)) = (config.anvil.rpc_timeout, builder.geth.as_mut()) {
+            geth.set_timeout(Duration::from_secs(rpc_timeout as u64));
+        }
+        Ok(builder)
     }
 
     pub async fn from_circuits_params(circuits_params: FixedCParams) -> Result<Self, Error> {
-        let anvil = AnvilClient::default().await;
+        let anvil = AnvilClient::default().await?;
         Self::new(anvil, None, circuits_params)
     }
 
@@ -92,6 +216,9 @@ impl BuilderClient {
                 geth,
                 chain_id: Word::from(chain_id.as_usize()),
                 circuits_params,
+                imported_trace: None,
+                hardfork: Hardfork::default(),
+                progress: Arc::new(NoProgress),
             })
         } else {
             Err(Error::InternalError(
@@ -100,6 +227,155 @@ impl BuilderClient {
         }
     }
 
+    /// Builder with anvil forking the block before the one of `tx_hash`, the mined tx of the chain
+    /// of `eth_rpc_url`, for `gen_witness_for_tx`.
+    pub async fn for_tx(
+        circuits_params: FixedCParams,
+        eth_rpc_url: String,
+        hardfork: Hardfork,
+        tx_hash: H256,
+    ) -> Result<Self, Error> {
+        let tx = GethClient::new(eth_rpc_url.clone())?
+            .transaction_by_hash(tx_hash)
+            .await?
+            .ok_or(Error::InvalidArgument(format!("tx {tx_hash:?} not found")))?;
+        let tx_block_number = tx
+            .block_number
+            .ok_or(Error::InvalidArgument(format!(
+                "tx {tx_hash:?} is not mined"
+            )))?
+            .as_usize();
+        Self::from_config(
+            circuits_params,
+            Some(eth_rpc_url),
+            None,
+            Some(tx_block_number - 1),
+            hardfork,
+        )
+        .await
+    }
+
+    /// Replays the mined tx `tx_hash` in anvil and returns the number of the anvil block it is
+    /// mined in. Anvil must fork the block before the tx's, the txs preceding it in its block are
+    /// replayed first, each in a block of its own so that the tx is alone in its block.
+    pub async fn replay_tx(&self, tx_hash: H256) -> Result<usize, Error> {
+        let geth = self.geth.as_ref().ok_or(Error::InvalidArgument(
+            "replaying a tx needs the rpc of its chain".to_string(),
+        ))?;
+        let tx = geth
+            .transaction_by_hash(tx_hash)
+            .await?
+            .ok_or(Error::InvalidArgument(format!("tx {tx_hash:?} not found")))?;
+        let tx_block_number = tx
+            .block_number
+            .ok_or(Error::InvalidArgument(format!(
+                "tx {tx_hash:?} is not mined"
+            )))?
+            .as_usize();
+        let fork_block_number = self.anvil.block_number().await?;
+        if fork_block_number + 1 != tx_block_number {
+            return Err(Error::InvalidArgument(format!(
+                "tx {tx_hash:?} is in block {tx_block_number}, anvil needs to fork block {} instead of {fork_block_number}",
+                tx_block_number - 1
+            )));
+        }
+        let block = geth
+            .block_by_number_full(tx_block_number)
+            .await?
+            .ok_or(Error::InternalError("block of the tx not found"))?;
+        for preceding_tx in block.transactions.iter().take_while(|t| t.hash != tx_hash) {
+            self.send_mined_tx(preceding_tx).await?;
+        }
+        self.send_mined_tx(&tx).await?;
+        let replayed = self
+            .anvil
+            .transaction_by_hash(tx_hash)
+            .await?
+            .ok_or(Error::InternalError("replayed tx not found"))?;
+        Ok(replayed
+            .block_number
+            .ok_or(Error::InternalError("replayed tx is not mined"))?
+            .as_usize())
+    }
+
+    /// Witness of the block of anvil in which the mined tx `tx_hash` is replayed, see `replay_tx`.
+    /// The trace is the one of anvil, a geth trace would be of the original block.
+    pub async fn gen_witness_for_tx(
+        &self,
+        tx_hash: H256,
+        pox_inputs: PoxInputs,
+    ) -> Result<zkevm_circuits::witness::Block<Fr>, Error> {
+        let block_number = self.replay_tx(tx_hash).await?;
+        self.gen_witness(block_number, pox_inputs, false).await
+    }
+
+    /// Witness of the anvil block of the txs `tx_hashes`, e.g. a setup tx and the exploit tx sent
+    /// with `TxBuilder::send_in_one_block`. The block must have these txs and no other ones, and
+    /// at most `max_txs` of the circuit params.
+    pub async fn gen_witness_for_txs(
+        &self,
+        tx_hashes: &[H256],
+        pox_inputs: PoxInputs,
+    ) -> Result<zkevm_circuits::witness::Block<Fr>, Error> {
+        if tx_hashes.len() > self.circuits_params.max_txs {
+            return Err(Error::WitnessOverflow(format!(
+                "{} txs do not fit in a circuit laid out for {}",
+                tx_hashes.len(),
+                self.circuits_params.max_txs
+            )));
+        }
+        let mut block_number = None;
+        for tx_hash in tx_hashes {
+            let tx = self
+                .anvil
+                .transaction_by_hash(*tx_hash)
+                .await?
+                .ok_or(Error::InvalidArgument(format!("tx {tx_hash:?} not found")))?;
+            let tx_block_number = tx
+                .block_number
+                .ok_or(Error::InvalidArgument(format!(
+                    "tx {tx_hash:?} is not mined"
+                )))?
+                .as_usize();
+            if *block_number.get_or_insert(tx_block_number) != tx_block_number {
+                return Err(Error::InvalidArgument(
+                    "the txs of a witness must be in the same block".to_string(),
+                ));
+            }
+        }
+        let block_number =
+            block_number.ok_or(Error::InvalidArgument("no txs for the witness".to_string()))?;
+        let block = self
+            .anvil
+            .block_by_number(block_number)
+            .await?
+            .ok_or(Error::InternalError("block of the txs not found"))?;
+        let mut mined = block.transactions.clone();
+        mined.sort();
+        let mut requested = tx_hashes.to_vec();
+        requested.sort();
+        if mined != requested {
+            return Err(Error::InvalidArgument(format!(
+                "block {block_number} has the txs {:?}, the witness would not be of the ones given",
+                block.transactions
+            )));
+        }
+        self.gen_witness(block_number, pox_inputs, false).await
+    }
+
+    async fn send_mined_tx(&self, tx: &Transaction) -> Result<(), Error> {
+        let hash = self.anvil.send_raw_transaction(tx.rlp()).await?;
+        self.anvil.wait_for_transaction(hash).await
+    }
+
+    pub fn set_imported_trace(&mut self, trace: GethExecTrace) {
+        self.imported_trace = Some(trace);
+    }
+
+    pub fn set_progress(&mut self, progress: Arc<dyn ProverProgress>) {
+        self.progress = progress;
+    }
+
     pub async fn gen_witness(
         &self,
         block_number: usize,
@@ -109,7 +385,33 @@ impl BuilderClient {
         let (circuit_input_builder, _) = self
             .gen_inputs(block_number, pox_inputs, use_geth_trace)
             .await?;
-        Ok(block_convert::<Fr>(&circuit_input_builder)?)
+        self.progress
+            .on_phase(ProofPhase::Witness, ProofPhase::Witness.percent());
+        SuperCircuit::<Fr>::block_from_builder(&circuit_input_builder)
+    }
+
+    /// Witnesses of the consecutive blocks `block_numbers` of anvil, for an exploit spanning
+    /// several blocks like a flashloan setup and its trigger. Each block must start from the state
+    /// root the block before it ended with, see `trajectory::check_witness_continuity`.
+    pub async fn gen_witnesses(
+        &self,
+        block_numbers: RangeInclusive<usize>,
+        pox_inputs: PoxInputs,
+        use_geth_trace: bool,
+    ) -> Result<Vec<zkevm_circuits::witness::Block<Fr>>, Error> {
+        let mut blocks = vec![];
+        for block_number in block_numbers {
+            blocks.push(
+                self.gen_witness(block_number, pox_inputs.clone(), use_geth_trace)
+                    .await?,
+            );
+        }
+        let public_data = blocks
+            .iter()
+            .map(SuperCircuit::<Fr>::public_data)
+            .collect::<Vec<_>>();
+        check_witness_continuity(&public_data.iter().collect::<Vec<_>>())?;
+        Ok(blocks)
     }
 
     pub async fn gen_inputs(
@@ -118,16 +420,71 @@ impl BuilderClient {
         pox_inputs: PoxInputs,
         use_geth_trace: bool,
     ) -> Result<(CircuitInputBuilder<FixedCParams>, EthBlockFull), Error> {
+        self.gen_inputs_with(
+            block_number,
+            pox_inputs,
+            use_geth_trace,
+            Some(self.circuits_params),
+        )
+        .await
+    }
+
+    /// Measures the already mined block by building its inputs once with params sized by
+    /// bus_mapping, and returns the smallest params it fits in with every capacity grown by
+    /// `margin_percent`. The tx and calldata capacity stay those of `circuits_params`, the PoX
+    /// circuit is laid out for them.
+    pub async fn derive_circuits_params(
+        &self,
+        block_number: usize,
+        pox_inputs: PoxInputs,
+        use_geth_trace: bool,
+        margin_percent: usize,
+    ) -> Result<FixedCParams, Error> {
+        let (builder, _) = self
+            .gen_inputs_with(block_number, pox_inputs, use_geth_trace, None)
+            .await?;
+        let block = SuperCircuit::<Fr>::block_from_builder(&builder)?;
+        let measured = SuperCircuit::<Fr>::params_for_block(&block);
+        let grow = |value: usize| value + (value * margin_percent + 99) / 100;
+        Ok(FixedCParams {
+            max_rws: grow(measured.max_rws),
+            max_txs: self.circuits_params.max_txs,
+            max_calldata: self.circuits_params.max_calldata,
+            max_copy_rows: grow(measured.max_copy_rows),
+            max_exp_steps: grow(measured.max_exp_steps),
+            max_bytecode: grow(measured.max_bytecode),
+            max_evm_rows: grow(measured.max_evm_rows),
+            max_keccak_rows: grow(measured.max_keccak_rows),
+        })
+    }
+
+    async fn gen_inputs_with(
+        &self,
+        block_number: usize,
+        pox_inputs: PoxInputs,
+        use_geth_trace: bool,
+        circuits_params: Option<FixedCParams>,
+    ) -> Result<(CircuitInputBuilder<FixedCParams>, EthBlockFull), Error> {
+        self.progress
+            .on_phase(ProofPhase::Trace, ProofPhase::Trace.percent());
         let (mut block, traces, history_hashes, prev_state_root) = self
             .get_block(block_number, pox_inputs.clone(), use_geth_trace)
             .await?;
+        self.hardfork.check_traces(&traces)?;
         let access_set = get_state_accesses(&block, &traces)?;
+        self.progress
+            .on_phase(ProofPhase::State, ProofPhase::State.percent());
         let (proofs, codes, new_state_root) = self.get_state(block_number, access_set).await?;
+        if let Some(circuits_params) = &circuits_params {
+            check_capacity(circuits_params, &block, &traces, &codes)?;
+        }
         if block.state_root.is_zero() {
             block.state_root = new_state_root;
         }
         let (state_db, code_db) = build_state_code_db(proofs, codes);
-        let builder = self.gen_inputs_from_state(
+        let builder = gen_inputs_from_state(
+            self.chain_id,
+            circuits_params,
             state_db,
             code_db,
             &block,
@@ -139,27 +496,30 @@ impl BuilderClient {
         Ok((builder, block))
     }
 
-    #[allow(clippy::too_many_arguments)]
-    fn gen_inputs_from_state(
+    /// Collects everything the circuit inputs of the block are built from, so that they can be
+    /// built again without access to the chain.
+    pub async fn gen_repro(
         &self,
-        sdb: StateDB,
-        code_db: CodeDB,
-        eth_block: &EthBlockFull,
-        geth_traces: &[GethExecTrace],
-        history_hashes: Vec<Word>,
-        prev_state_root: Word,
+        block_number: usize,
         pox_inputs: PoxInputs,
-    ) -> Result<CircuitInputBuilder<FixedCParams>, Error> {
-        let block = Block::new(
+        use_geth_trace: bool,
+    ) -> Result<Repro, Error> {
+        let (block, traces, history_hashes, prev_state_root) = self
+            .get_block(block_number, pox_inputs.clone(), use_geth_trace)
+            .await?;
+        let access_set = get_state_accesses(&block, &traces)?;
+        let (proofs, codes, _) = self.get_state(block_number, access_set).await?;
+        Ok(Repro::from(
             self.chain_id,
+            self.circuits_params,
+            block,
+            &traces,
             history_hashes,
             prev_state_root,
-            eth_block,
+            proofs,
+            codes,
             pox_inputs,
-        )?;
-        let mut builder = CircuitInputBuilder::new(sdb, code_db, block, self.circuits_params);
-        builder.handle_block(eth_block, geth_traces)?;
-        Ok(builder)
+        ))
     }
 
     async fn get_block(
@@ -184,7 +544,7 @@ impl BuilderClient {
         let mut history_hashes = Vec::default();
         let results = future::join_all(futures).await;
         for result in results {
-            let header = result?.expect("parent block not found");
+            let header = result?.ok_or(Error::InternalError("parent block not found"))?;
 
             // set the previous state root
             if prev_state_root.is_none() {
@@ -216,7 +576,18 @@ impl BuilderClient {
             .anvil
             .block_by_number_full(block_number)
             .await?
-            .expect("block not found");
+            .ok_or(Error::InternalError("block not found"))?;
+        check_tx_types(&block)?;
+
+        if let Some(trace) = &self.imported_trace {
+            if block.transactions.len() != 1 {
+                return Err(Error::InvalidArgument(format!(
+                    "an imported trace covers a single tx but block {block_number} has {}",
+                    block.transactions.len()
+                )));
+            }
+            return Ok((block, vec![trace.clone()]));
+        }
 
         let mut traces = Vec::default();
         for tx in &block.transactions {
@@ -238,7 +609,9 @@ impl BuilderClient {
             } else {
                 self.geth
                     .clone()
-                    .unwrap()
+                    .ok_or(Error::InvalidArgument(
+                        "geth trace requested without a geth client".to_string(),
+                    ))?
                     .simulate_exploit(
                         tx,
                         pox_inputs.challenge_bytecode.clone(),
@@ -265,17 +638,12 @@ impl BuilderClient {
             let proof = self
                 .anvil
                 .get_proof(address, keys, Some(block_number - 1))
-                .await
-                .unwrap();
+                .await?;
             proofs.push(proof);
         }
         let mut codes: HashMap<Address, Vec<u8>> = HashMap::default();
         for address in access_set.code.clone() {
-            let code = self
-                .anvil
-                .get_code(address, Some(block_number - 1))
-                .await
-                .unwrap();
+            let code = self.anvil.get_code(address, Some(block_number - 1)).await?;
             codes.insert(address, code.to_vec());
         }
 
@@ -319,11 +687,7 @@ impl BuilderClient {
             }
         }
         for address in addresses {
-            let new_code = self
-                .anvil
-                .get_code(address, Some(block_number))
-                .await
-                .unwrap();
+            let new_code = self.anvil.get_code(address, Some(block_number)).await?;
             let new_code_hash = H256::from(keccak256(new_code));
             let new_balance = self.anvil.get_balance(address, Some(block_number)).await?;
             let new_nonce = self.anvil.get_nonce(address, Some(block_number)).await?;
@@ -336,19 +700,124 @@ impl BuilderClient {
                 .set(address.to_anvil_type(), account_data)?;
         }
 
-        Ok(trie.root().unwrap())
+        Ok(trie.root()?)
+    }
+}
+
+fn check_tx_types(block: &EthBlockFull) -> Result<(), Error> {
+    for tx in &block.transactions {
+        let tx_type = tx.transaction_type.map_or(0, |tx_type| tx_type.as_u64());
+        if !SUPPORTED_TX_TYPES.contains(&tx_type) {
+            return Err(Error::UnsupportedTxType(format!(
+                "tx {:?} in block {} has type {tx_type}{}, only legacy, EIP-2930 and EIP-1559 transactions can be proven",
+                tx.hash,
+                block.number.unwrap_or_default(),
+                if tx_type == 3 { " (EIP-4844 blob transaction)" } else { "" }
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Fails with `Error::WitnessOverflow` naming the param and a value which fits when the block
+/// does not fit in `params`, instead of an error of the circuits once the inputs are built. The
+/// rws are estimated from below with one per execution step, the other counts are exact.
+fn check_capacity(
+    params: &FixedCParams,
+    block: &EthBlockFull,
+    traces: &[GethExecTrace],
+    codes: &HashMap<Address, Vec<u8>>,
+) -> Result<(), Error> {
+    let txs = block.transactions.len();
+    if txs > params.max_txs {
+        return Err(Error::WitnessOverflow(format!(
+            "the block has {txs} transactions, the circuit is laid out for {}",
+            params.max_txs
+        )));
+    }
+    let calldata = block
+        .transactions
+        .iter()
+        .map(|tx| tx.input.len())
+        .sum::<usize>();
+    if calldata > params.max_calldata {
+        return Err(Error::WitnessOverflow(format!(
+            "the transactions have {calldata} bytes of calldata, the circuit is laid out for {}",
+            params.max_calldata
+        )));
+    }
+    // the bytecode circuit has a row per byte and a header row per contract
+    let bytecode = codes.values().map(|code| code.len() + 1).sum::<usize>();
+    let rws = traces
+        .iter()
+        .map(|trace| trace.struct_logs.len())
+        .sum::<usize>();
+    for (option, needed, max) in [
+        ("--max-bytecode", bytecode, params.max_bytecode),
+        ("--max-rws", rws, params.max_rws),
+    ] {
+        if needed > max {
+            let suggested = needed + (needed * DEFAULT_PARAMS_MARGIN + 99) / 100;
+            return Err(Error::WitnessOverflow(format!(
+                "the block needs at least {needed} rows for {option} but it is {max}, try {option} {suggested} or --auto-params"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Builds the circuit inputs of a block, with params sized to the block by bus_mapping if there
+/// are no `circuits_params`.
+#[allow(clippy::too_many_arguments)]
+pub fn gen_inputs_from_state(
+    chain_id: Word,
+    circuits_params: Option<FixedCParams>,
+    sdb: StateDB,
+    code_db: CodeDB,
+    eth_block: &EthBlockFull,
+    geth_traces: &[GethExecTrace],
+    history_hashes: Vec<Word>,
+    prev_state_root: Word,
+    pox_inputs: PoxInputs,
+) -> Result<CircuitInputBuilder<FixedCParams>, Error> {
+    let block = Block::new(
+        chain_id,
+        history_hashes,
+        prev_state_root,
+        eth_block,
+        pox_inputs,
+    )?;
+    match circuits_params {
+        Some(circuits_params) => {
+            let mut builder = CircuitInputBuilder::new(sdb, code_db, block, circuits_params);
+            builder.handle_block(eth_block, geth_traces)?;
+            Ok(builder)
+        }
+        // bus_mapping sizes the params to the block while handling it
+        None => Ok(
+            CircuitInputBuilder::new(sdb, code_db, block, DynamicCParams::default())
+                .handle_block(eth_block, geth_traces)?,
+        ),
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::BuilderClient;
-    use crate::utils::anvil::AnvilClient;
+    use super::{check_tx_types, BuilderClient};
+    use crate::{
+        error::ErrorCode,
+        utils::{
+            anvil::{types::zkevm_types::*, AnvilClient},
+            hardfork::Hardfork,
+        },
+    };
     use bus_mapping::circuit_input_builder::{FixedCParams, PoxInputs};
 
     #[tokio::test]
     async fn test() {
-        let anvil = AnvilClient::setup(None, None).await;
+        let anvil = AnvilClient::setup(None, None, Hardfork::default())
+            .await
+            .unwrap();
         let bc = BuilderClient::new(anvil, None, FixedCParams::default()).unwrap();
         assert_eq!(bc.chain_id.as_usize(), 31337);
 
@@ -373,7 +842,7 @@ mod tests {
                     assert_eq!(traces.len(), 1);
                     break;
                 } else {
-                    bc.anvil.mine_one().await;
+                    bc.anvil.mine_one().await.unwrap();
                     std::thread::sleep(std::time::Duration::from_secs(1));
                 }
             } else {
@@ -381,4 +850,20 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_check_tx_types() {
+        let mut block = EthBlockFull {
+            transactions: vec![Transaction::default()],
+            ..Default::default()
+        };
+        assert!(check_tx_types(&block).is_ok());
+        block.transactions[0].transaction_type = Some(U64::from(2));
+        assert!(check_tx_types(&block).is_ok());
+        block.transactions[0].transaction_type = Some(U64::from(3));
+        assert_eq!(
+            check_tx_types(&block).unwrap_err().code(),
+            ErrorCode::UnsupportedTxType
+        );
+    }
 }
@@ -1,52 +1,90 @@
-mod inputs_builder;
+pub mod circuit;
+pub mod inputs_builder;
+pub mod repro;
+pub mod row_report;
+pub mod sanity;
+pub mod trace_diff;
 
+#[cfg(feature = "dev-circuits")]
+use crate::utils::halo2::sub_circuit::CircuitSelection;
 use crate::{
     cli::ProveArgs,
-    constants::{MAX_CALLDATA, MAX_TXS, RANDOMNESS},
+    constants::{MAX_CALLDATA, MAX_TXS},
+    error::Error,
     utils::{
-        anvil::{conversion::Conversion, types::anvil_types},
-        halo2::real_prover::RealProver,
+        anvil::{
+            tx_builder::{deploy_at, TxBuilder},
+            types::zkevm_types,
+        },
+        eip3155::Eip3155Trace,
+        halo2::{
+            aggregation::{self, DEFAULT_WRAPPER_DEGREE},
+            artifact_manifest::DirManifest,
+            ceremony,
+            ipa::{IpaProver, IpaSRS},
+            preflight,
+            proof::{PcsScheme, Proof, ProofBackend, ProofTranscript},
+            prover::{Prover, StubProver},
+            real_prover::RealProver,
+            real_verifier::RealVerifier,
+            srs::SRS,
+        },
+        hardfork::Hardfork,
         ipfs,
+        progress::{ConsoleProgress, ProverProgress},
+        solidity,
     },
-    witness::inputs_builder::BuilderClient,
+    witness::{circuit::ExploitCircuit, inputs_builder::BuilderClient, row_report::RowReport},
 };
 use bus_mapping::{
     circuit_input_builder::{FixedCParams, PoxInputs},
     POX_CHALLENGE_ADDRESS, POX_EXPLOIT_ADDRESS,
 };
 use core::slice::SlicePattern;
-use eth_types::{keccak256, Fr, U256, U64};
-use ethers::{
-    signers::{LocalWallet, Signer},
-    types::{transaction::eip2718::TypedTransaction, NameOrAddress, TransactionRequest},
-    utils::hex,
-};
-use halo2_proofs::dev::MockProver;
+use eth_types::{keccak256, Bytes, Fr, H256, U64};
+use ethers::utils::{format_ether, hex};
 use std::{
+    fs,
     path::PathBuf,
-    process,
-    str::FromStr,
+    sync::Arc,
     time::{SystemTime, UNIX_EPOCH},
 };
-use zkevm_circuits::{
-    super_circuit::SuperCircuit,
-    util::{log2_ceil, SubCircuit},
-};
+use tracing::{debug, info, instrument, trace, warn};
+use zkevm_circuits::{super_circuit::SuperCircuit, witness::Block};
+
+/// Calldata of the exploit tx, the selector of `entryPoint()` of the challenge.
+const EXPLOIT_SELECTOR: [u8; 4] = [0xb0, 0xd6, 0x91, 0xfe];
 
 pub struct Witness {
     k: u32,
     instance: Vec<Vec<Fr>>,
     circuit: SuperCircuit<Fr>,
+    /// The exploit transaction reverted, which its proof records.
+    reverted: bool,
 }
 
 impl Witness {
-    pub async fn gen(args: &ProveArgs) -> Witness {
+    pub async fn gen(args: &ProveArgs) -> Result<Witness, Error> {
+        Self::gen_with_progress(args, Arc::new(ConsoleProgress)).await
+    }
+
+    /// Witness of the exploit whose generation reports its phases to `progress`.
+    #[instrument(name = "witness", skip_all, fields(block = ?args.block))]
+    pub async fn gen_with_progress(
+        args: &ProveArgs,
+        progress: Arc<dyn ProverProgress>,
+    ) -> Result<Witness, Error> {
         let challenge_bytecode = args
             .challenge_artifact
-            .get_deployed_bytecode("Challenge".to_string())
-            .unwrap();
+            .get_deployed_bytecode("Challenge".to_string())?;
+
+        let chain_hardfork =
+            Hardfork::of_fork_block(&args.rpc, args.block, args.rpc_timeout).await?;
+        let hardfork = args.hardfork.unwrap_or(chain_hardfork);
+        hardfork.check_supported(Some(chain_hardfork))?;
+        debug!("Hardfork: {hardfork:?}");
 
-        let builder = BuilderClient::from_config(
+        let mut builder = BuilderClient::from_config(
             FixedCParams {
                 max_rws: args.max_rws,
                 max_txs: MAX_TXS,
@@ -60,190 +98,507 @@ impl Witness {
             Some(args.rpc.clone()),
             args.geth_rpc.clone(),
             args.block,
+            hardfork,
         )
-        .await
-        .unwrap();
+        .await?;
+        builder.anvil.set_timeout(args.rpc_timeout);
+        if let Some(geth) = builder.geth.as_mut() {
+            geth.set_timeout(args.rpc_timeout);
+        }
+        debug!("Circuit params: {:?}", builder.circuits_params);
 
-        let chain_id = builder.anvil.eth_chain_id().unwrap().unwrap();
-        let block_number = builder.anvil.block_number().unwrap();
-        println!("Anvil initialized with chain_id: {chain_id:?}, block_number: {block_number:?}");
+        let chain_id = builder.chain_id;
+        let block_number = builder.anvil.block_number().await?;
+        info!("Anvil initialized with chain_id: {chain_id:?}, block_number: {block_number:?}");
 
         // updating challenge bytecode in local mainnet fork chain
         builder
             .anvil
             .set_code(POX_CHALLENGE_ADDRESS, challenge_bytecode.clone())
-            .await
-            .unwrap();
-        // updating exploit bytecode in local mainnet fork chain
-        builder
-            .anvil
-            .set_code(POX_EXPLOIT_ADDRESS, args.exploit_bytecode.clone())
-            .await
-            .unwrap();
+            .await?;
+        // updating exploit bytecode in local mainnet fork chain, through its constructor if it
+        // has constructor args
+        let exploit_bytecode = match &args.exploit_init_code {
+            Some(init_code) => {
+                deploy_at(&builder.anvil, POX_EXPLOIT_ADDRESS, init_code.clone()).await?
+            }
+            None => {
+                builder
+                    .anvil
+                    .set_code(POX_EXPLOIT_ADDRESS, args.exploit_bytecode.clone())
+                    .await?;
+                args.exploit_bytecode.clone()
+            }
+        };
 
         let exploit_balance_before = builder
             .anvil
             .get_balance(POX_EXPLOIT_ADDRESS, args.block)
-            .await
-            .unwrap();
+            .await?;
         builder
             .anvil
             .set_balance(POX_EXPLOIT_ADDRESS, args.exploit_balance)
-            .await
-            .unwrap();
-
-        let signer = LocalWallet::from_str(
-            "0x0000000000000000000000000000000000000000000000000000000000000001",
-        )
-        .unwrap();
-
-        // generate transaction request
-        let tx_req_estimate = anvil_types::EthTransactionRequest {
-            from: Some(signer.address()),
-            to: Some(POX_CHALLENGE_ADDRESS),
-            gas_price: Some(U256::zero()),
-            max_fee_per_gas: None,
-            max_priority_fee_per_gas: None,
-            gas: args.gas.or(Some(1_000_000)).map(U256::from),
-            value: Some(U256::zero()),
-            data: Some(anvil_types::Bytes::from_str("0xb0d691fe").unwrap()),
-            nonce: Some(
-                builder
-                    .anvil
-                    .get_nonce(signer.address(), Some(block_number))
-                    .await
-                    .unwrap(),
-            ),
-            chain_id: Some(chain_id.as_u64().into()),
-            access_list: None,
-            transaction_type: None,
-        };
-        let mut tx_req_sign = TypedTransaction::Legacy(TransactionRequest {
-            from: tx_req_estimate.from,
-            to: tx_req_estimate.to.map(NameOrAddress::Address),
-            gas_price: tx_req_estimate.gas_price,
-            gas: tx_req_estimate.gas,
-            value: tx_req_estimate.value,
-            data: tx_req_estimate.data.clone(),
-            nonce: tx_req_estimate.nonce,
-            chain_id: tx_req_estimate.chain_id,
-        });
-
-        // check for reverts and panic out
-        let gas_estimate = builder
-            .anvil
-            .estimate_gas(tx_req_estimate, None)
-            .await
-            .unwrap();
-        tx_req_sign.set_gas(args.gas.map(U256::from).unwrap_or(gas_estimate));
-
-        let signature = signer.sign_transaction_sync(&tx_req_sign).unwrap();
-        let raw_tx = tx_req_sign.rlp_signed(&signature);
+            .await?;
+        if let Some(state_snapshot) = &args.state_snapshot {
+            state_snapshot.apply(&builder.anvil).await?;
+        }
+        if let Some(imported_trace) = &args.imported_trace {
+            builder.set_imported_trace(imported_trace.to_geth_trace());
+        }
 
-        // confirm the tx on the local block
-        let hash = builder
-            .anvil
-            .send_raw_transaction(raw_tx.to_zkevm_type())
-            .await
-            .unwrap();
-        builder.anvil.wait_for_transaction(hash).await.unwrap();
+        // confirm the exploit tx on the local block, a reverting one only with --allow-revert
+        let mut tx_builder = TxBuilder::new(POX_CHALLENGE_ADDRESS);
+        tx_builder.set_data(Bytes::from_static(&EXPLOIT_SELECTOR));
+        tx_builder.set_gas(args.gas.map(|gas| gas as u64));
+        tx_builder.set_tx_type(args.tx_type);
+        if let Some(reason) = tx_builder.revert_reason(&builder.anvil).await? {
+            if !args.allow_revert {
+                return Err(Error::ExploitFailedSimulation(format!(
+                    "Exploit transaction reverts: {reason}, use --allow-revert to prove the reverting execution"
+                )));
+            }
+            warn!("Exploit transaction reverts: {reason}");
+            tx_builder.set_allow_revert(true);
+        }
+        if let Some(timestamp) = args.block_timestamp {
+            builder.anvil.set_next_block_timestamp(timestamp).await?;
+        }
+        let hash = tx_builder.send(&builder.anvil).await?;
 
         let rc = builder
             .anvil
             .transaction_receipt(hash)
-            .await
-            .unwrap()
-            .unwrap();
+            .await?
+            .ok_or(Error::InternalError("receipt not found for exploit tx"))?;
 
-        println!("Gas consumed: {}", rc.gas_used.unwrap());
+        info!("Gas consumed: {}", rc.gas_used.unwrap_or_default());
 
-        if rc.status.unwrap() != U64::from(1) {
+        if rc.status != Some(U64::from(1)) && !args.allow_revert {
             // TODO make sure that storage is also updated and not just tx is successful
             // TODO make sure that storage update with reversion does not pass the lookup check
-            println!("Error: Exploit transaction is not successful.");
-            process::exit(1);
+            return Err(Error::ExploitFailedSimulation(format!(
+                "Exploit transaction {} is not successful",
+                hex::encode_prefixed(hash)
+            )));
         }
 
-        println!("Tx confirmed on Anvil: {}", hex::encode_prefixed(hash));
+        info!("Tx confirmed on Anvil: {}", hex::encode_prefixed(hash));
 
-        println!("Generating Witness...");
+        if let Some(min_balance_gain) = args.min_balance_gain {
+            let balance = builder.anvil.get_balance(POX_EXPLOIT_ADDRESS, None).await?;
+            let balance_gain = balance.saturating_sub(args.exploit_balance);
+            if balance_gain < min_balance_gain {
+                return Err(Error::ExploitFailedSimulation(format!(
+                    "the exploit gained {} ether, less than the claimed {} ether",
+                    format_ether(balance_gain),
+                    format_ether(min_balance_gain)
+                )));
+            }
+            info!(
+                "Balance gained by the exploit: {} ether",
+                format_ether(balance_gain)
+            );
+        }
 
         let tx = builder
             .anvil
             .transaction_by_hash(hash)
-            .await
-            .unwrap()
-            .unwrap();
-
-        let mut witness = builder
-            .gen_witness(
-                tx.block_number.unwrap().as_usize(),
-                PoxInputs {
-                    challenge_codehash: keccak256(challenge_bytecode.as_slice()).into(),
-                    challenge_bytecode,
-                    exploit_codehash: keccak256(args.exploit_bytecode.as_slice()).into(),
-                    exploit_bytecode: args.exploit_bytecode.clone(),
-                    exploit_balance: args.exploit_balance,
-                    exploit_balance_before,
-                },
-                args.geth_rpc.is_some(),
-            )
-            .await
-            .unwrap();
-        witness.randomness = Fr::from(RANDOMNESS);
-
-        println!("Witness generated!");
-
-        let (_, rows_needed) = SuperCircuit::<Fr>::min_num_rows_block(&witness);
-        let circuit = SuperCircuit::<Fr>::new_from_block(&witness);
-        let k = log2_ceil(64 + rows_needed);
-        let instance = circuit.instance();
-
-        // println!("Instances: {instance:?}");
+            .await?
+            .ok_or(Error::InternalError("exploit tx not found"))?;
+
+        let tx_block_number = tx
+            .block_number
+            .ok_or(Error::InternalError("exploit tx is not mined"))?
+            .as_usize();
+
+        if let Some(trace_out) = &args.trace_out {
+            let trace = builder
+                .anvil
+                .debug_trace_transaction(
+                    hash,
+                    zkevm_types::GethDebugTracingOptions {
+                        enable_memory: Some(true),
+                        disable_stack: Some(false),
+                        disable_storage: Some(true),
+                        enable_return_data: Some(true),
+                        tracer: None,
+                        tracer_config: None,
+                        timeout: None,
+                    },
+                )
+                .await?;
+            let state_root = builder
+                .anvil
+                .block_by_number(tx_block_number)
+                .await?
+                .map(|header| header.state_root);
+            Eip3155Trace::from(&trace, state_root).write_to_file(trace_out)?;
+            info!("Trace written to {}", trace_out.display());
+        }
+        let pox_inputs = PoxInputs {
+            challenge_codehash: keccak256(challenge_bytecode.as_slice()).into(),
+            challenge_bytecode,
+            exploit_codehash: keccak256(exploit_bytecode.as_slice()).into(),
+            exploit_bytecode,
+            exploit_balance: args.exploit_balance,
+            exploit_balance_before,
+        };
+        if let Some(params_margin) = args.params_margin {
+            builder.circuits_params = builder
+                .derive_circuits_params(
+                    tx_block_number,
+                    pox_inputs.clone(),
+                    args.geth_rpc.is_some(),
+                    params_margin,
+                )
+                .await?;
+            info!(
+                "Circuit params measured from the block: {:?}",
+                builder.circuits_params
+            );
+        }
+        if let Some(repro_out) = &args.repro_out {
+            builder
+                .gen_repro(tx_block_number, pox_inputs.clone(), args.geth_rpc.is_some())
+                .await?
+                .write_to_file(repro_out)?;
+            info!("Repro fixture written to {}", repro_out.display());
+        }
+        builder.set_progress(progress);
+        let witness = builder
+            .gen_witness(tx_block_number, pox_inputs.clone(), args.geth_rpc.is_some())
+            .await?;
+
+        info!("Witness generated!");
+
+        if !args.skip_sanity_check {
+            let eth_block = builder
+                .anvil
+                .block_by_number_full(tx_block_number)
+                .await?
+                .ok_or(Error::InternalError("block not found"))?;
+            sanity::check(&witness, &eth_block, &builder.anvil, tx_block_number).await?;
+            debug!("Witness sanity check passed");
+        }
+
+        if args.diff_trace {
+            let geth = builder.geth.as_ref().ok_or(Error::InvalidArgument(
+                "--diff-trace needs a geth rpc to trace the exploit".to_string(),
+            ))?;
+            let eth_block = builder
+                .anvil
+                .block_by_number_full(tx_block_number)
+                .await?
+                .ok_or(Error::InternalError("block not found"))?;
+            trace_diff::check(&witness, &eth_block, geth, &pox_inputs).await?;
+            info!("Circuit inputs match the geth trace");
+        }
+
+        let mut witness = Self::from_block(&witness);
+        witness.reverted = rc.status != Some(U64::from(1));
+        if let Some(degree) = args.degree {
+            witness.set_degree(degree)?;
+        }
+        Ok(witness)
+    }
+
+    pub fn from_block(block: &Block<Fr>) -> Self {
+        let circuit = SuperCircuit::<Fr>::from_block(block);
+        let k = SuperCircuit::<Fr>::degree_for_block(block);
+        let instance = circuit.instances();
+        debug!("Degree: {k}");
+        trace!("Instances: {instance:?}");
 
         Witness {
             k,
             instance,
             circuit,
+            reverted: false,
         }
     }
 
-    pub fn assert(self) {
-        println!("Running MockProver");
-        let prover = MockProver::run(self.k, &self.circuit, self.instance).unwrap();
-        println!("Verifying constraints");
-        prover.assert_satisfied_par();
-        println!("Success!");
+    pub fn degree(&self) -> u32 {
+        self.k
     }
 
-    pub async fn prove(self, args: ProveArgs) {
-        println!("Running RealProver");
-        let mut prover = RealProver::from(self.circuit, self.k, args.srs_path.clone());
+    /// Proves at `degree` instead of the degree the block needs, which must not be larger.
+    pub fn set_degree(&mut self, degree: u32) -> Result<(), Error> {
+        if degree < self.k {
+            return Err(Error::WitnessOverflow(format!(
+                "the exploit block needs degree {} but degree {degree} was given",
+                self.k
+            )));
+        }
+        self.k = degree;
+        Ok(())
+    }
 
-        println!("Generating proof...");
-        let mut proof = prover.prove().unwrap();
-        proof.challenge_artifact = Some(args.challenge_artifact);
+    /// Rows the block takes in each sub-circuit against the maximums of the params.
+    pub fn row_report(&self) -> RowReport {
+        RowReport::from_circuit(&self.circuit)
+    }
+
+    /// keccak256 of the public data, circuit params and degree of the witness along with the
+    /// transcript, multiopen scheme and challenge source of its proof. A proof of the same digest and verifying key
+    /// is the same proof, so it is reused unless `--force` is given.
+    pub fn digest(
+        &self,
+        transcript: ProofTranscript,
+        pcs_scheme: PcsScheme,
+        challenge_artifact: &solidity::Artifact,
+    ) -> Result<H256, Error> {
+        let block = self
+            .block()
+            .ok_or(Error::InternalError("circuit is missing the witness block"))?;
+        let preimage = serde_json::to_vec(&(
+            SuperCircuit::<Fr>::public_data(block),
+            self.circuits_params(),
+            self.k,
+            transcript,
+            pcs_scheme,
+            challenge_artifact,
+        ))?;
+        Ok(H256::from(keccak256(preimage)))
+    }
+
+    pub fn circuits_params(&self) -> FixedCParams {
+        self.circuit.fixed_params()
+    }
+
+    pub fn block(&self) -> Option<&Block<Fr>> {
+        self.circuit.block()
+    }
 
-        let proof_out_path = args.proof_out_path.unwrap_or(format!(
+    pub fn instances(&self) -> &Vec<Vec<Fr>> {
+        &self.instance
+    }
+
+    pub fn into_circuit(self) -> SuperCircuit<Fr> {
+        self.circuit
+    }
+
+    /// Runs the MockProver and fails with `Error::InvalidWitness` naming the first unsatisfied
+    /// constraint, see `RealProver::mock_check`.
+    pub fn assert(self) -> Result<(), Error> {
+        RealProver::mock_check(&self.circuit, self.k)?;
+        info!("Success!");
+        Ok(())
+    }
+
+    /// Runs the MockProver and returns the unsatisfied constraints as an error instead of
+    /// panicking, for callers which go on with other circuits after a failure.
+    pub fn verify(self) -> Result<(), Error> {
+        RealProver::mock_check(&self.circuit, self.k)
+    }
+
+    /// Generates the proof and returns the path it was written to.
+    pub async fn prove(self, args: ProveArgs) -> Result<PathBuf, Error> {
+        let proof_out_path = args.proof_out_path.clone().unwrap_or(format!(
             "proof_{}.json",
             SystemTime::now()
                 .duration_since(UNIX_EPOCH)
-                .unwrap()
+                .unwrap_or_default()
                 .as_secs()
         ));
-
         let proof_path = PathBuf::from(proof_out_path);
-        println!("Writing proof to {}", proof_path.display());
-        proof.write_to_file(&proof_path).unwrap();
-        println!("Success!");
 
-        // sanity check
-        let verifier = prover.verifier();
-        verifier.verify(&proof).await.unwrap();
+        match args.backend {
+            ProofBackend::Ipa => return self.prove_ipa(args, proof_path).await,
+            ProofBackend::Mock => return self.prove_mock(args, proof_path),
+            ProofBackend::Kzg => {}
+        }
+        #[cfg(feature = "dev-circuits")]
+        if args.circuit != CircuitSelection::Super {
+            return self.prove_sub_circuit(args, proof_path).await;
+        }
+
+        if args.regenerate_keys {
+            SRS::remove_keys(&args.srs_path, self.k, self.circuit.circuits_params)?;
+        }
+        preflight::check(&args.srs_path, &self.circuit, self.k)?;
+        if !args.skip_memory_check {
+            preflight::check_memory(&self.circuit, self.k)?;
+        }
+        if let Some(parent) = proof_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            preflight::check_writable(parent)?;
+        }
+
+        ceremony::provision(&args.srs_path, self.k).await?;
+        if args.wrap {
+            ceremony::provision(&args.srs_path, DEFAULT_WRAPPER_DEGREE).await?;
+        }
+
+        let digest = self.digest(args.transcript, args.pcs_scheme, &args.challenge_artifact)?;
+        // the vk digest is read from the light verifier key, so that a witness which was proven
+        // before does not load the proving key
+        let cached_vk_digest = match args.force || args.wrap {
+            true => None,
+            false => SRS::cached_vk_digest(&args.srs_path, self.k, self.circuits_params())?,
+        };
+        if let Some(vk_hash) = cached_vk_digest {
+            let witness_hash = proof_cache_key(digest, vk_hash);
+            if let Some(cached_path) = DirManifest::cached_proof(&args.srs_path, witness_hash)? {
+                info!(
+                    "The witness was proven before with the same keys, reusing {} (--force proves it again)",
+                    cached_path.display()
+                );
+                if fs::canonicalize(&proof_path).ok().as_ref() != Some(&cached_path) {
+                    fs::copy(&cached_path, &proof_path)?;
+                }
+                let proof = Proof::read_from_file(&proof_path)?;
+                let verifier = RealVerifier::load_srs(args.srs_path.clone(), &proof).await?;
+                return finish_proof(args, proof_path, proof, witness_hash, &verifier).await;
+            }
+        }
+
+        info!("Running RealProver");
+        let mut prover = RealProver::from_with_progress(
+            self.circuit,
+            self.k,
+            args.srs_path.clone(),
+            args.prover_options,
+            Arc::new(ConsoleProgress),
+        )?;
+        prover.set_transcript(args.transcript);
+        prover.set_pcs_scheme(args.pcs_scheme);
+        info!("Verifying key hash: {:?}", prover.vk_hash());
+
+        if args.wrap {
+            info!("Generating wrapped proof...");
+            let mut wrapped =
+                prover.prove_wrapped(DEFAULT_WRAPPER_DEGREE, args.instance_encoding)?;
+            if let Some(proof) = wrapped.proofs.first_mut() {
+                proof.challenge_artifact = Some(args.challenge_artifact);
+                proof.reverted = self.reverted;
+            }
+
+            info!("Writing wrapped proof to {}", proof_path.display());
+            wrapped.write_to_file(&proof_path)?;
+
+            // sanity check
+            aggregation::verify(args.srs_path, &wrapped).await?;
+            info!("Success!");
+            return Ok(proof_path);
+        }
+
+        let mut proof = prover.prove()?;
+        proof.challenge_artifact = Some(args.challenge_artifact.clone());
+        proof.reverted = self.reverted;
+        info!("Writing proof to {}", proof_path.display());
+        proof.write_to_file(&proof_path)?;
+        let witness_hash = proof_cache_key(digest, prover.vk_hash());
+        finish_proof(args, proof_path, proof, witness_hash, &prover.verifier()).await
+    }
+
+    fn prove_mock(self, args: ProveArgs, proof_path: PathBuf) -> Result<PathBuf, Error> {
+        info!("Running MockProver, the proof is a stub for local iteration only");
+        let mut prover = StubProver::from(self.circuit)?;
+        let mut proof = prover.prove()?;
+        proof.challenge_artifact = Some(args.challenge_artifact);
+        proof.reverted = self.reverted;
+
+        info!("Writing stub proof to {}", proof_path.display());
+        proof.write_to_file(&proof_path)?;
+        info!("Success!");
+        Ok(proof_path)
+    }
 
-        if args.ipfs {
-            let hash = ipfs::publish(&proof).await.unwrap();
-            println!("Published proof to ipfs: {}", hash);
+    #[cfg(feature = "dev-circuits")]
+    async fn prove_sub_circuit(
+        self,
+        args: ProveArgs,
+        proof_path: PathBuf,
+    ) -> Result<PathBuf, Error> {
+        let block = self
+            .block()
+            .ok_or(Error::InternalError("circuit is missing the witness block"))?;
+        // a sub-circuit needs at most the degree of the whole circuit, whose params are trimmed
+        ceremony::provision(&args.srs_path, self.k).await?;
+        let proof = RealProver::prove_sub_circuit(
+            block,
+            args.circuit,
+            &args.srs_path,
+            args.prover_options,
+        )?;
+        info!(
+            "{:?} circuit proven at degree {} and verified, keygen {:.1}s, proving {:.1}s",
+            proof.circuit, proof.degree, proof.keygen_seconds, proof.proving_seconds
+        );
+        info!(
+            "Writing {:?} circuit proof to {}, it is for local iteration only",
+            proof.circuit,
+            proof_path.display()
+        );
+        proof.write_to_file(&proof_path)?;
+        Ok(proof_path)
+    }
+
+    async fn prove_ipa(self, args: ProveArgs, proof_path: PathBuf) -> Result<PathBuf, Error> {
+        if args.regenerate_keys {
+            IpaSRS::remove_keys(&args.srs_path, self.k, self.circuit.circuits_params)?;
         }
+        preflight::check_writable(&args.srs_path)?;
+
+        info!("Running IpaProver, the proof is for local development only");
+        let mut prover = IpaProver::from(self.circuit, self.k, args.srs_path.clone())?;
+
+        info!("Generating proof...");
+        let mut proof = prover.prove()?;
+        proof.challenge_artifact = Some(args.challenge_artifact);
+        proof.reverted = self.reverted;
+
+        info!("Writing proof to {}", proof_path.display());
+        proof.write_to_file(&proof_path)?;
+        info!("Success!");
+
+        // sanity check
+        prover.verifier().verify(&proof).await?;
+        Ok(proof_path)
+    }
+}
+
+/// Key of a proof in the cache of the srs dir, the `Witness::digest` of the witness along with
+/// the digest of the verifying key it is proven with.
+fn proof_cache_key(digest: H256, vk_hash: H256) -> H256 {
+    H256::from(keccak256([digest.as_bytes(), vk_hash.as_bytes()].concat()))
+}
+
+/// Records a written or reused proof in the manifest of the srs dir, checks it with `verifier`
+/// and publishes it if `--ipfs` is given.
+async fn finish_proof(
+    args: ProveArgs,
+    proof_path: PathBuf,
+    proof: Proof,
+    witness_hash: H256,
+    verifier: &RealVerifier,
+) -> Result<PathBuf, Error> {
+    // the proof is written, a manifest which cannot be updated only misses it in the cache
+    if let Err(error) =
+        DirManifest::record_proof(&args.srs_path, &proof_path, &proof, Some(witness_hash))
+    {
+        warn!(
+            "Cannot record the proof in the manifest of {}: {}",
+            args.srs_path.display(),
+            error.message()
+        );
+    }
+    info!("Success!");
+
+    // sanity check
+    verifier.verify(&proof).await?;
+
+    if proof.transcript == ProofTranscript::Keccak {
+        let source_path = proof_path.with_extension("sol");
+        let gas = verifier.check_on_evm(&proof, &source_path).await?;
+        info!(
+            "Solidity verifier {} accepts the proof, verifying it costs {gas} gas",
+            source_path.display()
+        );
+    }
+
+    if args.ipfs {
+        let hash = ipfs::publish(&proof).await?;
+        info!("Published proof to ipfs: {}", hash);
     }
+    Ok(proof_path)
 }
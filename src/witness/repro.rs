@@ -0,0 +1,142 @@
+use super::{inputs_builder::gen_inputs_from_state, Witness};
+use crate::{
+    constants::ZKEVM_CIRCUITS_COMMIT,
+    error::Error,
+    utils::{anvil::types::zkevm_types::*, eip3155::Eip3155Trace},
+    witness::circuit::ExploitCircuit,
+};
+use bus_mapping::circuit_input_builder::{build_state_code_db, FixedCParams, PoxInputs};
+use eth_types::{keccak256, Fr};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File},
+    io::Write,
+    path::Path,
+};
+use tracing::warn;
+use zkevm_circuits::super_circuit::SuperCircuit;
+
+/// Self-contained inputs of a block whose witness generation or constraints fail, small enough
+/// to attach to an issue of zkevm-circuits. Only the accounts, storage slots and code the block
+/// touches are kept, without their merkle proofs, and replaying needs no RPC access. The same
+/// file is the witness of `RealProver::from_witness_file` for proving on another machine.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Repro {
+    pub zkevm_circuits_commit: String,
+    pub chain_id: Word,
+    pub circuits_params: FixedCParams,
+    pub block: EthBlockFull,
+    pub traces: Vec<Eip3155Trace>,
+    pub history_hashes: Vec<Word>,
+    pub prev_state_root: Word,
+    pub accounts: Vec<EIP1186ProofResponse>,
+    pub codes: BTreeMap<Address, Bytes>,
+    pub challenge_bytecode: Bytes,
+    pub exploit_bytecode: Bytes,
+    pub exploit_balance: U256,
+    pub exploit_balance_before: U256,
+}
+
+impl Repro {
+    #[allow(clippy::too_many_arguments)]
+    pub fn from(
+        chain_id: Word,
+        circuits_params: FixedCParams,
+        block: EthBlockFull,
+        traces: &[GethExecTrace],
+        history_hashes: Vec<Word>,
+        prev_state_root: Word,
+        proofs: Vec<EIP1186ProofResponse>,
+        codes: HashMap<Address, Vec<u8>>,
+        pox_inputs: PoxInputs,
+    ) -> Self {
+        let accounts = proofs
+            .into_iter()
+            .map(|mut account| {
+                // the state db is built from the values, the proofs are only for the state root
+                account.account_proof = vec![];
+                for storage in account.storage_proof.iter_mut() {
+                    storage.proof = vec![];
+                }
+                account
+            })
+            .collect();
+        Self {
+            zkevm_circuits_commit: ZKEVM_CIRCUITS_COMMIT.to_string(),
+            chain_id,
+            circuits_params,
+            block,
+            traces: traces
+                .iter()
+                .map(|trace| Eip3155Trace::from(trace, None))
+                .collect(),
+            history_hashes,
+            prev_state_root,
+            accounts,
+            codes: codes
+                .into_iter()
+                .map(|(address, code)| (address, Bytes::from(code)))
+                .collect(),
+            challenge_bytecode: pox_inputs.challenge_bytecode,
+            exploit_bytecode: pox_inputs.exploit_bytecode,
+            exploit_balance: pox_inputs.exploit_balance,
+            exploit_balance_before: pox_inputs.exploit_balance_before,
+        }
+    }
+
+    pub fn pox_inputs(&self) -> PoxInputs {
+        PoxInputs {
+            challenge_codehash: keccak256(&self.challenge_bytecode).into(),
+            challenge_bytecode: self.challenge_bytecode.clone(),
+            exploit_codehash: keccak256(&self.exploit_bytecode).into(),
+            exploit_bytecode: self.exploit_bytecode.clone(),
+            exploit_balance: self.exploit_balance,
+            exploit_balance_before: self.exploit_balance_before,
+        }
+    }
+
+    /// Builds the circuit inputs and the witness again from the recorded state.
+    pub fn replay(&self) -> Result<Witness, Error> {
+        if self.zkevm_circuits_commit != ZKEVM_CIRCUITS_COMMIT {
+            warn!(
+                "Fixture was exported with zkevm-circuits {} but this build has {ZKEVM_CIRCUITS_COMMIT}",
+                self.zkevm_circuits_commit
+            );
+        }
+        let codes = self
+            .codes
+            .iter()
+            .map(|(address, code)| (*address, code.to_vec()))
+            .collect();
+        let (state_db, code_db) = build_state_code_db(self.accounts.clone(), codes);
+        let traces = self
+            .traces
+            .iter()
+            .map(Eip3155Trace::to_geth_trace)
+            .collect::<Vec<_>>();
+        let builder = gen_inputs_from_state(
+            self.chain_id,
+            Some(self.circuits_params),
+            state_db,
+            code_db,
+            &self.block,
+            &traces,
+            self.history_hashes.clone(),
+            self.prev_state_root,
+            self.pox_inputs(),
+        )?;
+        let block = SuperCircuit::<Fr>::block_from_builder(&builder)?;
+        Ok(Witness::from_block(&block))
+    }
+
+    pub fn write_to_file(&self, path: &Path) -> Result<(), Error> {
+        let mut file = File::create(path)?;
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn read_from_file(path: &Path) -> Result<Self, Error> {
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+}
@@ -0,0 +1,55 @@
+//! Rows the witness of an exploit takes in each sub-circuit against the maximums of its params,
+//! to shrink the `--max-*` params and the degree to the block instead of guessing them.
+
+use super::circuit::{ExploitCircuit, RowUsage};
+use bus_mapping::circuit_input_builder::FixedCParams;
+use halo2_proofs::halo2curves::bn256::Fr;
+use serde::{Deserialize, Serialize};
+use zkevm_circuits::{super_circuit::SuperCircuit, witness::Block};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RowReport {
+    pub circuits_params: FixedCParams,
+    pub usage: Vec<RowUsage>,
+    /// Degree of the circuit with its params.
+    pub degree: u32,
+    /// Smallest degree at which the rows the block takes fit.
+    pub min_degree: u32,
+}
+
+impl RowReport {
+    pub fn from_circuit(circuit: &SuperCircuit<Fr>) -> Self {
+        let usage = circuit.row_usage();
+        Self {
+            circuits_params: circuit.fixed_params(),
+            degree: circuit
+                .block()
+                .map_or(0, SuperCircuit::<Fr>::degree_for_block),
+            min_degree: SuperCircuit::<Fr>::degree_for_rows(&usage),
+            usage,
+        }
+    }
+
+    pub fn print(&self) {
+        println!("Rows of the block per sub-circuit:");
+        for usage in &self.usage {
+            let percent = match usage.max_rows {
+                0 => 0,
+                max_rows => usage.rows * 100 / max_rows,
+            };
+            println!(
+                "  {:<8} {:>8} of {:>8} ({percent}%)",
+                usage.circuit, usage.rows, usage.max_rows
+            );
+        }
+        println!(
+            "Degree {} with the params, the rows of the block fit in degree {}",
+            self.degree, self.min_degree
+        );
+    }
+}
+
+/// Row usage of the sub-circuits of the circuit of `block`.
+pub fn witness_report(block: &Block<Fr>) -> RowReport {
+    RowReport::from_circuit(&SuperCircuit::<Fr>::from_block(block))
+}
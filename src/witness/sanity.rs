@@ -0,0 +1,133 @@
+use super::circuit::ExploitCircuit;
+use crate::{
+    error::Error,
+    utils::anvil::{types::zkevm_types::*, AnvilClient},
+};
+use bus_mapping::{operation::Target, POX_CHALLENGE_ADDRESS};
+use eth_types::{keccak256, Fr};
+use halo2_proofs::arithmetic::Field;
+use std::collections::HashMap;
+use zkevm_circuits::{
+    super_circuit::SuperCircuit,
+    table::AccountFieldTag,
+    witness::{Block, Rw},
+};
+
+/// Cross checks the witness against itself and against the chain it was generated from, a
+/// failure here is a witness generation bug which would otherwise surface as an unsatisfied
+/// constraint after a full proving run.
+pub async fn check(
+    block: &Block<Fr>,
+    eth_block: &EthBlockFull,
+    anvil: &AnvilClient,
+    block_number: usize,
+) -> Result<(), Error> {
+    let mut issues = vec![];
+
+    if block.randomness.is_zero_vartime() {
+        issues.push("witness randomness is zero".to_string());
+    }
+
+    if block.txs.len() != eth_block.transactions.len() {
+        issues.push(format!(
+            "witness has {} txs but block {block_number} has {}",
+            block.txs.len(),
+            eth_block.transactions.len()
+        ));
+    }
+    if block.txs.len() > block.circuits_params.max_txs {
+        issues.push(format!(
+            "witness has {} txs but max_txs is {}",
+            block.txs.len(),
+            block.circuits_params.max_txs
+        ));
+    }
+    for (index, (tx, eth_tx)) in block.txs.iter().zip(&eth_block.transactions).enumerate() {
+        if tx.tx.hash != eth_tx.hash
+            || tx.tx.from != eth_tx.from
+            || tx.tx.to != eth_tx.to
+            || tx.tx.value != eth_tx.value
+            || tx.tx.call_data != eth_tx.input
+        {
+            issues.push(format!(
+                "witness tx {index} does not match on-chain tx {:?}",
+                eth_tx.hash
+            ));
+        }
+    }
+
+    // the codehash exposed in the public inputs must be the one of the challenge which was
+    // executed, otherwise the proof would claim an exploit of another contract
+    let public_data = SuperCircuit::<Fr>::public_data(block);
+    let challenge_code = anvil
+        .get_code(POX_CHALLENGE_ADDRESS, Some(block_number))
+        .await?;
+    let challenge_codehash = H256::from(keccak256(&challenge_code));
+    if public_data.pox_challenge_codehash != challenge_codehash {
+        issues.push(format!(
+            "public inputs commit to challenge codehash {:?} but {POX_CHALLENGE_ADDRESS:?} has codehash {challenge_codehash:?}",
+            public_data.pox_challenge_codehash
+        ));
+    }
+    // and it must be where a contract checking the public data of the digest reads it
+    let offset = SuperCircuit::<Fr>::challenge_codehash_offset()?;
+    if SuperCircuit::<Fr>::public_data_bytes(&public_data).get(offset..offset + 32)
+        != Some(public_data.pox_challenge_codehash.as_bytes())
+    {
+        issues.push(format!(
+            "public data does not have the challenge codehash at byte {offset} of its digest preimage"
+        ));
+    }
+
+    // every account write must start from the value left by the previous write, and the last
+    // value must be the state anvil ended up with after mining the block
+    let mut account_rws: Vec<&Rw> = block
+        .rws
+        .0
+        .get(&Target::Account)
+        .map(|rws| rws.iter().collect())
+        .unwrap_or_default();
+    account_rws.sort_by_key(|rw| rw.rw_counter());
+    let mut latest: HashMap<(Address, u64), Word> = HashMap::new();
+    for rw in account_rws {
+        if let Rw::Account {
+            rw_counter,
+            account_address,
+            field_tag,
+            value,
+            value_prev,
+            ..
+        } = rw
+        {
+            let key = (*account_address, *field_tag as u64);
+            if let Some(prev) = latest.get(&key) {
+                if prev != value_prev {
+                    issues.push(format!(
+                        "rw {rw_counter}: {field_tag:?} of {account_address:?} has value_prev {value_prev} but previous value was {prev}"
+                    ));
+                }
+            }
+            latest.insert(key, *value);
+        }
+    }
+    for ((address, tag), value) in latest {
+        let chain_value = if tag == AccountFieldTag::Balance as u64 {
+            anvil.get_balance(address, Some(block_number)).await?
+        } else if tag == AccountFieldTag::Nonce as u64 {
+            anvil.get_nonce(address, Some(block_number)).await?
+        } else {
+            continue;
+        };
+        if chain_value != value {
+            issues.push(format!(
+                "{address:?} ends with {value} in the rw table but {chain_value} on chain"
+            ));
+        }
+    }
+
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::InvalidWitness(issues.join("\n")))
+    }
+}
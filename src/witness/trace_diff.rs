@@ -0,0 +1,152 @@
+use crate::{
+    error::Error,
+    utils::{anvil::types::zkevm_types::*, geth::GethClient},
+};
+use bus_mapping::circuit_input_builder::PoxInputs;
+use eth_types::{evm_types::OpcodeId, Fr};
+use std::fmt;
+use zkevm_circuits::witness::{Block, Transaction};
+
+/// Fields of an execution step which both the circuit inputs and a geth trace have.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceStep {
+    pub pc: u64,
+    pub opcode: OpcodeId,
+    pub gas: u64,
+    pub stack_size: usize,
+    pub depth: usize,
+}
+
+impl fmt::Display for TraceStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "pc {} {:?} gas {} stack size {} depth {}",
+            self.pc, self.opcode, self.gas, self.stack_size, self.depth
+        )
+    }
+}
+
+/// Opcode steps of a witness tx, the steps bus_mapping adds around them (begin and end of the
+/// tx, precompiles, padding) have no counterpart in a geth trace and are skipped.
+pub fn circuit_steps(tx: &Transaction) -> Vec<TraceStep> {
+    tx.steps()
+        .iter()
+        .filter_map(|step| {
+            Some(TraceStep {
+                pc: step.pc,
+                opcode: step.opcode()?,
+                gas: step.gas_left,
+                stack_size: step.stack_size,
+                depth: tx.calls()[step.call_index].depth,
+            })
+        })
+        .collect()
+}
+
+pub fn reference_steps(trace: &GethExecTrace) -> Vec<TraceStep> {
+    trace
+        .struct_logs
+        .iter()
+        .map(|step| TraceStep {
+            pc: step.pc,
+            opcode: step.op,
+            gas: step.gas,
+            stack_size: step.stack.0.len(),
+            depth: usize::from(step.depth),
+        })
+        .collect()
+}
+
+/// Index of the first step which differs, with the step on each side. A missing step means that
+/// one of the traces ended earlier.
+pub fn first_divergence(
+    circuit: &[TraceStep],
+    reference: &[TraceStep],
+) -> Option<(usize, Option<TraceStep>, Option<TraceStep>)> {
+    (0..circuit.len().max(reference.len())).find_map(|index| {
+        let (a, b) = (circuit.get(index), reference.get(index));
+        (a != b).then(|| (index, a.cloned(), b.cloned()))
+    })
+}
+
+/// Traces the exploit with geth and compares it step by step with the steps the witness was
+/// built from. A witness bug otherwise only shows up as an unsatisfied constraint, while the
+/// first divergent step usually points at the opcode which is handled differently.
+pub async fn check(
+    block: &Block<Fr>,
+    eth_block: &EthBlockFull,
+    geth: &GethClient,
+    pox_inputs: &PoxInputs,
+) -> Result<(), Error> {
+    let mut issues = vec![];
+    for (index, (tx, eth_tx)) in block.txs.iter().zip(&eth_block.transactions).enumerate() {
+        // the tx only exists on the local fork, geth replays it as a call with the same overrides
+        let trace = geth
+            .simulate_exploit(
+                eth_tx,
+                pox_inputs.challenge_bytecode.clone(),
+                pox_inputs.exploit_bytecode.clone(),
+                pox_inputs.exploit_balance,
+            )
+            .await?;
+        let circuit = circuit_steps(tx);
+        let reference = reference_steps(&trace);
+        if let Some((step, ours, theirs)) = first_divergence(&circuit, &reference) {
+            let describe = |step: Option<TraceStep>| {
+                step.map(|step| step.to_string())
+                    .unwrap_or("end of trace".to_string())
+            };
+            issues.push(format!(
+                "tx {index} diverges at step {step} of {}: circuit has {} but geth has {}",
+                reference.len(),
+                describe(ours),
+                describe(theirs)
+            ));
+        }
+    }
+
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::InvalidWitness(issues.join("\n")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{first_divergence, TraceStep};
+    use eth_types::evm_types::OpcodeId;
+
+    fn step(pc: u64, opcode: OpcodeId, gas: u64) -> TraceStep {
+        TraceStep {
+            pc,
+            opcode,
+            gas,
+            stack_size: 0,
+            depth: 1,
+        }
+    }
+
+    #[test]
+    fn test_first_divergence() {
+        let trace = vec![
+            step(0, OpcodeId::PUSH1, 100),
+            step(2, OpcodeId::PUSH1, 97),
+            step(4, OpcodeId::SSTORE, 94),
+        ];
+        assert_eq!(first_divergence(&trace, &trace), None);
+
+        let mut gas = trace.clone();
+        gas[1].gas = 98;
+        assert_eq!(
+            first_divergence(&gas, &trace),
+            Some((1, Some(gas[1].clone()), Some(trace[1].clone())))
+        );
+
+        assert_eq!(
+            first_divergence(&trace[..2], &trace),
+            Some((2, None, Some(trace[2].clone())))
+        );
+    }
+}